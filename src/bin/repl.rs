@@ -14,7 +14,8 @@ use std::time::Duration;
 
 use gumdrop::{Options, ParsingStyle};
 use ketos::{
-	complete_name, Builder, Context, Error, Interpreter, ParseError, ParseErrorKind, RestrictConfig,
+	complete_name, Builder, Context, Error, ExecError, FsAccess, Interpreter, ParseError,
+	ParseErrorKind, RestrictConfig,
 };
 use linefeed::{
 	Command, Completer, Completion, Function, Interface, Prompter, ReadResult, Signal, Suffix,
@@ -97,22 +98,32 @@ fn run() -> i32 {
 				return 1;
 			}
 		}
+	} else {
+		// Scripts run without restrictions may access the filesystem
+		// and run external programs
+		builder = builder
+			.filesystem(FsAccess::Unrestricted)
+			.allow_process(true);
 	}
 
 	let interp = builder.finish();
 
 	let interactive = opts.interactive || (opts.free.is_empty() && opts.expr.is_none());
 
-	if let Some(ref expr) = opts.expr {
-		if !run_expr(&interp, &expr) && !interactive {
-			return 1;
-		}
+	let res = if let Some(ref expr) = opts.expr {
+		run_expr(&interp, &expr)
 	} else if !opts.free.is_empty() {
 		interp.set_args(&opts.free);
 
-		if !run_file(&interp, Path::new(&opts.free[0])) && !interactive {
-			return 1;
-		}
+		run_file(&interp, Path::new(&opts.free[0]))
+	} else {
+		Ok(())
+	};
+
+	match res {
+		Err(Some(status)) => return status,
+		Err(None) if !interactive => return 1,
+		_ => (),
 	}
 
 	if interactive {
@@ -121,13 +132,16 @@ fn run() -> i32 {
 				let rc = p.join(".ketosrc.ket");
 				if rc.is_file() {
 					// Ignore error in interactive mode
-					run_file(&interp, &rc);
+					if let Err(Some(status)) = run_file(&interp, &rc) {
+						return status;
+					}
 				}
 			}
 		}
 
-		if let Err(e) = run_repl(&interp) {
-			eprintln!("terminal device error: {}", e);
+		match run_repl(&interp) {
+			Ok(status) => return status,
+			Err(e) => eprintln!("terminal device error: {}", e),
 		}
 	}
 
@@ -157,6 +171,17 @@ fn parse_restrict(params: &str) -> Result<RestrictConfig, String> {
 					"memory_limit" => res.memory_limit = parse_param(name, value)?,
 					"max_integer_size" => res.max_integer_size = parse_param(name, value)?,
 					"max_syntax_nesting" => res.max_syntax_nesting = parse_param(name, value)?,
+					"max_regex_size" => res.max_regex_size = parse_param(name, value)?,
+					"allow_sleep" => res.allow_sleep = parse_param(name, value)?,
+					"allow_os" => res.allow_os = parse_param(name, value)?,
+					"allow_process" => res.allow_process = parse_param(name, value)?,
+					"filesystem" => {
+						res.filesystem = match value {
+							"none" => FsAccess::Denied,
+							"all" => FsAccess::Unrestricted,
+							dir => FsAccess::Root(dir.into()),
+						}
+					}
 					_ => return Err(format!("unrecognized parameter: {}", name)),
 				}
 			}
@@ -179,30 +204,43 @@ fn display_error(interp: &Interpreter, e: &Error) {
 	interp.display_error(e);
 }
 
-fn run_expr(interp: &Interpreter, expr: &str) -> bool {
+/// Returns the exit status requested by an error, if any.
+fn exit_status(e: &Error) -> Option<i32> {
+	match *e {
+		Error::ExecError(ExecError::Exit(status)) => Some(status),
+		_ => None,
+	}
+}
+
+/// Displays an error, unless the error is a request to exit.
+///
+/// Returns the requested exit status, if any.
+fn handle_error(interp: &Interpreter, e: &Error) -> Option<i32> {
+	let status = exit_status(e);
+
+	if status.is_none() {
+		display_error(interp, e);
+	}
+
+	status
+}
+
+fn run_expr(interp: &Interpreter, expr: &str) -> Result<(), Option<i32>> {
 	match interp.run_single_expr(expr, None) {
 		Ok(value) => {
 			interp.display_value(&value);
-			true
-		}
-		Err(e) => {
-			display_error(&interp, &e);
-			false
+			Ok(())
 		}
+		Err(e) => Err(handle_error(interp, &e)),
 	}
 }
 
-fn run_file(interp: &Interpreter, file: &Path) -> bool {
-	match interp.run_file(file) {
-		Ok(()) => true,
-		Err(e) => {
-			display_error(&interp, &e);
-			false
-		}
-	}
+fn run_file(interp: &Interpreter, file: &Path) -> Result<(), Option<i32>> {
+	interp.run_file(file).map_err(|e| handle_error(interp, &e))
 }
 
-fn run_repl(interp: &Interpreter) -> io::Result<()> {
+/// Runs the interactive interpreter, returning an exit status.
+fn run_repl(interp: &Interpreter) -> io::Result<i32> {
 	let interface = Interface::new("ketos")?;
 
 	set_thread_context(interp.context().clone());
@@ -241,7 +279,11 @@ fn run_repl(interp: &Interpreter) -> io::Result<()> {
 						if !code.is_empty() {
 							match interp.execute_program(code) {
 								Ok(v) => interp.display_value(&v),
-								Err(e) => display_error(&interp, &e),
+								Err(e) => {
+									if let Some(status) = handle_error(interp, &e) {
+										return Ok(status);
+									}
+								}
 							}
 						}
 					}
@@ -261,7 +303,7 @@ fn run_repl(interp: &Interpreter) -> io::Result<()> {
 
 	println!();
 
-	Ok(())
+	Ok(0)
 }
 
 struct KetosCompleter;
@@ -335,6 +377,10 @@ impl<Term: Terminal> Function<Term> for KetosAccept {
 				kind: ParseErrorKind::MissingCloseParen,
 				..
 			}))
+			| Err(Error::ParseError(ParseError {
+				kind: ParseErrorKind::MissingCloseBrace,
+				..
+			}))
 			| Err(Error::ParseError(ParseError {
 				kind: ParseErrorKind::UnterminatedComment,
 				..
@@ -393,6 +439,11 @@ fn print_restrict_usage() {
       memory_limit            Maximum total held memory, in abstract units
       max_integer_size        Maximum integer size, in bits
       max_syntax_nesting      Maximum nested syntax elements
+      max_regex_size          Maximum compiled regex size, in bytes
+      allow_sleep             Whether `sleep` is allowed (true or false)
+      filesystem              Filesystem access: `none`, `all`, or a directory
+      allow_os                Whether the `os` module is allowed (true or false)
+      allow_process           Whether the `process` module is allowed (true or false)
 "#
 	);
 }
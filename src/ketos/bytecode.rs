@@ -27,7 +27,7 @@ use crate::value::Value;
 /// change to the bytecode format. The version represents a `ketos` version
 /// number, e.g. `0x01_02_03_00` corresponds to version `1.2.3`.
 /// (The least significant 8 bits don't mean anything yet.)
-pub const BYTECODE_VERSION: u32 = 0x00_0b_00_00;
+pub const BYTECODE_VERSION: u32 = 0x00_0c_00_00;
 
 /// Maximum value of a short-encoded operand.
 pub const MAX_SHORT_OPERAND: u32 = 0x7f;
@@ -176,10 +176,34 @@ pub enum Instruction {
 	CallSelf(u32),
 	/// Perform tail-recursive call with *n* arguments from the top of the stack
 	TailCallSelf(u32),
+	/// Perform tail call to function on the stack with *n* arguments
+	/// from the top of the stack
+	TailCall(u32),
+	/// Perform tail call to const function with arguments on the stack;
+	/// parameters are `(const, n_args)`.
+	TailCallConst(u32, u32),
+	/// Perform tail call to function on the stack with *n* stack arguments,
+	/// plus additional arguments from list value
+	TailApply(u32),
+	/// Perform tail call to const function with *n* stack arguments,
+	/// plus additional arguments from list value;
+	/// parameters are `(const, n_args)`.
+	TailApplyConst(u32, u32),
 	/// Remove *n* values from the top of the stack
 	Skip(u32),
 	/// Return value from function
 	Return,
+	/// Install an error handler; if a catchable error is raised before the
+	/// handler is removed, the stack is unwound and execution resumes at label
+	/// with the error value loaded into value.
+	PushHandler(u32),
+	/// Remove the most recently installed error handler
+	PopHandler,
+	/// Raise an error indicating that value matched no pattern
+	NoMatch,
+	/// Raise an error indicating that value does not match the binding
+	/// pattern in const *n*
+	PatternError(u32),
 }
 
 macro_rules! opcodes {
@@ -320,6 +344,14 @@ opcodes! {
 	SKIP_3 = 121,
 	SKIP_4 = 122,
 	RETURN = 123,
+	PUSH_HANDLER = 124,
+	POP_HANDLER = 125,
+	NO_MATCH = 126,
+	PATTERN_ERROR = 127,
+	TAIL_CALL = 128,
+	TAIL_CALL_CONST = 129,
+	TAIL_APPLY = 130,
+	TAIL_APPLY_CONST = 131,
 }
 
 impl Instruction {
@@ -455,12 +487,20 @@ impl Instruction {
 			TAIL_APPLY_SELF => TailApplySelf(operand!()),
 			CALL_SELF => CallSelf(operand!()),
 			TAIL_CALL_SELF => TailCallSelf(operand!()),
+			TAIL_CALL => TailCall(operand!()),
+			TAIL_CALL_CONST => TailCallConst(operand!(), operand!()),
+			TAIL_APPLY => TailApply(operand!()),
+			TAIL_APPLY_CONST => TailApplyConst(operand!(), operand!()),
 			SKIP => Skip(operand!()),
 			SKIP_1 => Skip(1),
 			SKIP_2 => Skip(2),
 			SKIP_3 => Skip(3),
 			SKIP_4 => Skip(4),
 			RETURN => Return,
+			PUSH_HANDLER => PushHandler(operand!()),
+			POP_HANDLER => PopHandler,
+			NO_MATCH => NoMatch,
+			PATTERN_ERROR => PatternError(operand!()),
 			_ => return Err(ExecError::UnrecognizedOpCode(op)),
 		};
 
@@ -615,12 +655,20 @@ impl Instruction {
 			TailApplySelf(n) => op!(TAIL_APPLY_SELF, n),
 			CallSelf(n) => op!(CALL_SELF, n),
 			TailCallSelf(n) => op!(TAIL_CALL_SELF, n),
+			TailCall(n) => op!(TAIL_CALL, n),
+			TailCallConst(n, n_args) => op!(TAIL_CALL_CONST, n, n_args),
+			TailApply(n) => op!(TAIL_APPLY, n),
+			TailApplyConst(n, n_args) => op!(TAIL_APPLY_CONST, n, n_args),
 			Skip(1) => op!(SKIP_1),
 			Skip(2) => op!(SKIP_2),
 			Skip(3) => op!(SKIP_3),
 			Skip(4) => op!(SKIP_4),
 			Skip(n) => op!(SKIP, n),
 			Return => op!(RETURN),
+			PushHandler(label) => jump_op!(PUSH_HANDLER, label),
+			PopHandler => op!(POP_HANDLER),
+			NoMatch => op!(NO_MATCH),
+			PatternError(n) => op!(PATTERN_ERROR, n),
 		}
 	}
 
@@ -650,7 +698,8 @@ impl Instruction {
 			| JumpIfEq(label)
 			| JumpIfNotEq(label)
 			| JumpIfEqConst(label, _)
-			| JumpIfNotEqConst(label, _) => Some(label),
+			| JumpIfNotEqConst(label, _)
+			| PushHandler(label) => Some(label),
 			_ => None,
 		}
 	}
@@ -676,6 +725,7 @@ pub enum JumpInstruction {
 	JumpIfNotEq,
 	JumpIfEqConst(u32),
 	JumpIfNotEqConst(u32),
+	PushHandler,
 }
 
 impl JumpInstruction {
@@ -694,6 +744,7 @@ impl JumpInstruction {
 			JumpIfNotEq => Instruction::JumpIfNotEq(label),
 			JumpIfEqConst(n) => Instruction::JumpIfEqConst(label, n),
 			JumpIfNotEqConst(n) => Instruction::JumpIfNotEqConst(label, n),
+			PushHandler => Instruction::PushHandler(label),
 		}
 	}
 
@@ -703,9 +754,8 @@ impl JumpInstruction {
 		let len = if short { 1 } else { 2 };
 
 		match self {
-			Jump | JumpIf | JumpIfNot | JumpIfNull | JumpIfNotNull | JumpIfEq | JumpIfNotEq => {
-				1 + len
-			}
+			Jump | JumpIf | JumpIfNot | JumpIfNull | JumpIfNotNull | JumpIfEq | JumpIfNotEq
+			| PushHandler => 1 + len,
 			JumpIfBound(n) | JumpIfEqConst(n) | JumpIfNotEqConst(n) => {
 				let op_len = if is_short_operand(n) { 1 } else { 2 };
 				1 + len + op_len
@@ -797,9 +847,12 @@ pub mod code_flags {
 	pub const PARAM_FLAGS_MASK: u32 = 0x6;
 	/// Whether the code object has an associated docstring
 	pub const HAS_DOC_STRING: u32 = 0x8;
+	/// Whether the code is a `syntax-rules` macro, returning its rules
+	/// rather than an expansion
+	pub const IS_SYNTAX_RULES: u32 = 0x10;
 
 	/// Mask of all valid flags
-	pub const ALL_FLAGS: u32 = 0xf;
+	pub const ALL_FLAGS: u32 = 0x1f;
 }
 
 /// Reads `Instruction` values from a stream of bytes.
@@ -1076,6 +1129,10 @@ fn merge_instructions(a: Instruction, b: Instruction) -> Option<Instruction> {
 		(NotEqConst(n), Not) => EqConst(n),
 		(ApplySelf(n), Return) => TailApplySelf(n),
 		(CallSelf(n), Return) => TailCallSelf(n),
+		(Call(n), Return) => TailCall(n),
+		(CallConst(n, n_args), Return) => TailCallConst(n, n_args),
+		(Apply(n), Return) => TailApply(n),
+		(ApplyConst(n, n_args), Return) => TailApplyConst(n, n_args),
 		(Skip(_), Return) => Return,
 		_ => return None,
 	};
@@ -0,0 +1,130 @@
+//! Provides an abstraction over the system clock.
+//!
+//! Functions in the `time` module read time through the `Clock` instance
+//! of the global scope. Hosts may install a `FakeClock` (or another `Clock`
+//! implementation) using `Builder::clock` for deterministic execution.
+//!
+//! # Example
+//!
+//! ```
+//! use std::rc::Rc;
+//! use std::time::{Duration, UNIX_EPOCH};
+//! use ketos::{Builder, FakeClock};
+//!
+//! let clock = Rc::new(FakeClock::new(UNIX_EPOCH + Duration::from_secs(86400)));
+//!
+//! let interp = Builder::new()
+//!     .clock(clock.clone())
+//!     .finish();
+//!
+//! let v = interp.run_code(r#"
+//!     (use time (format-time unix-time))
+//!     (format-time (unix-time))
+//!     "#, None).unwrap();
+//!
+//! assert_eq!(interp.format_value(&v), r#""1970-01-02T00:00:00Z""#);
+//! ```
+
+use std::cell::Cell;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Represents a source of monotonic and wall-clock time
+pub trait Clock {
+	/// Returns the time elapsed since an arbitrary, fixed point.
+	///
+	/// Successive calls must never return a decreasing value.
+	fn monotonic(&self) -> Duration;
+
+	/// Returns the current wall-clock time.
+	fn system_time(&self) -> SystemTime;
+
+	/// Blocks the current thread for the given duration.
+	fn sleep(&self, d: Duration);
+}
+
+/// Reads time from the operating system
+pub struct SystemClock {
+	start: Instant,
+}
+
+impl SystemClock {
+	/// Creates a new `SystemClock`.
+	pub fn new() -> SystemClock {
+		SystemClock {
+			start: Instant::now(),
+		}
+	}
+}
+
+impl Default for SystemClock {
+	fn default() -> SystemClock {
+		SystemClock::new()
+	}
+}
+
+impl Clock for SystemClock {
+	fn monotonic(&self) -> Duration {
+		self.start.elapsed()
+	}
+
+	fn system_time(&self) -> SystemTime {
+		SystemTime::now()
+	}
+
+	fn sleep(&self, d: Duration) {
+		thread::sleep(d);
+	}
+}
+
+/// Clock whose time changes only when advanced by the host or by `sleep`
+///
+/// Monotonic time begins at zero.
+pub struct FakeClock {
+	elapsed: Cell<Duration>,
+	// Wall-clock time at which monotonic time was zero
+	base_time: Cell<SystemTime>,
+}
+
+impl FakeClock {
+	/// Creates a new `FakeClock` whose wall-clock time begins at `start_time`.
+	pub fn new(start_time: SystemTime) -> FakeClock {
+		FakeClock {
+			elapsed: Cell::new(Duration::from_secs(0)),
+			base_time: Cell::new(start_time),
+		}
+	}
+
+	/// Advances both monotonic and wall-clock time by the given duration.
+	pub fn advance(&self, d: Duration) {
+		self.elapsed.set(self.elapsed.get() + d);
+	}
+
+	/// Sets the wall-clock time, without affecting monotonic time.
+	pub fn set_system_time(&self, t: SystemTime) {
+		let elapsed = self.elapsed.get();
+		self.base_time
+			.set(t.checked_sub(elapsed).expect("system time out of range"));
+	}
+}
+
+impl Default for FakeClock {
+	/// Creates a `FakeClock` whose wall-clock time begins at the Unix epoch.
+	fn default() -> FakeClock {
+		FakeClock::new(UNIX_EPOCH)
+	}
+}
+
+impl Clock for FakeClock {
+	fn monotonic(&self) -> Duration {
+		self.elapsed.get()
+	}
+
+	fn system_time(&self) -> SystemTime {
+		self.base_time.get() + self.elapsed.get()
+	}
+
+	fn sleep(&self, d: Duration) {
+		self.advance(d);
+	}
+}
@@ -15,13 +15,16 @@ use crate::const_fold::{
 use crate::error::Error;
 use crate::exec::{execute_lambda, Context, ExecError};
 use crate::function::Arity::*;
-use crate::function::{Arity, Lambda};
+use crate::function::{type_of, Arity, Lambda};
+use crate::integer::Integer;
+use crate::map::Map;
 use crate::name::{
 	get_system_fn, is_system_operator, standard_names, Name, NameDisplay, NameMap, NameSet,
 	NameStore, NUM_SYSTEM_OPERATORS, SYSTEM_OPERATORS_BEGIN,
 };
 use crate::scope::{GlobalScope, ImportSet, MasterScope, Scope};
 use crate::structs::{StructDef, StructValueDef};
+use crate::syntax;
 use crate::trace::{set_traceback, take_traceback, Trace, TraceItem};
 use crate::value::{FromValueRef, Value};
 
@@ -175,13 +178,43 @@ pub fn compile(ctx: &Context, value: &Value) -> Result<Code, Error> {
 	})
 }
 
+/// Expands a macro call expression once.
+///
+/// If the expression is not a macro call, it is returned unchanged.
+pub fn macroexpand_1(ctx: &Context, value: &Value) -> Result<Value, Error> {
+	let mut compiler = Compiler::new(ctx);
+
+	match compiler.expand_macro_call(value) {
+		Ok(r) => Ok(r.unwrap_or_else(|| value.clone())),
+		Err(e) => {
+			set_traceback(compiler.take_trace());
+			Err(e)
+		}
+	}
+}
+
+/// Repeatedly expands a macro call expression until the result
+/// is no longer a macro call.
+pub fn macroexpand(ctx: &Context, value: &Value) -> Result<Value, Error> {
+	let mut compiler = Compiler::new(ctx);
+	let mut value = value.clone();
+
+	loop {
+		match compiler.expand_macro_call(&value) {
+			Ok(Some(v)) => value = v,
+			Ok(None) => return Ok(value),
+			Err(e) => {
+				set_traceback(compiler.take_trace());
+				return Err(e);
+			}
+		}
+	}
+}
+
 fn compile_lambda(
 	compiler: &mut Compiler,
 	name: Option<Name>,
-	params: Vec<(Name, Option<Value>)>,
-	req_params: u32,
-	kw_params: Vec<(Name, Option<Value>)>,
-	rest: Option<Name>,
+	params: LambdaParams,
 	value: &Value,
 ) -> Result<(Code, Vec<Name>), Error> {
 	let r = {
@@ -194,7 +227,7 @@ fn compile_lambda(
 
 		let mut sub = Compiler::with_outer(&compiler.ctx, name, &outer);
 
-		sub.compile_lambda(name, params, req_params, kw_params, rest, value)
+		sub.compile_lambda(name, params, value)
 			.map_err(|e| (sub.take_trace(), e))
 	};
 
@@ -204,6 +237,36 @@ fn compile_lambda(
 	})
 }
 
+/// Parameters accepted by a lambda
+struct LambdaParams {
+	/// Positional parameters, paired with default value expressions
+	params: Vec<(Name, Option<Value>)>,
+	/// Number of required positional parameters
+	req_params: u32,
+	/// Keyword parameters, paired with default value expressions
+	kw_params: Vec<(Name, Option<Value>)>,
+	/// Name of parameter receiving remaining arguments
+	rest: Option<Name>,
+	/// Patterns destructuring required parameters, paired with parameter index
+	patterns: Vec<(u32, Value)>,
+}
+
+/// Tracks the state of a `loop` expression being compiled
+struct LoopInfo {
+	/// Name given to a named `let`
+	name: Option<Name>,
+	/// Block at the beginning of the loop body
+	head: u32,
+	/// Stack offset of the first loop value
+	base: u32,
+	/// Number of loop values
+	n_vars: u32,
+	/// Number of named stack values, including loop values
+	n_stack: usize,
+	/// Whether the loop is in tail position with respect to the enclosing loop
+	tail: bool,
+}
+
 /// Compiles a single expression or function body
 struct Compiler<'a> {
 	/// Compile context
@@ -227,6 +290,17 @@ struct Compiler<'a> {
 	self_name: Option<Name>,
 	/// Depth of macro expansion
 	macro_recursion: u32,
+	/// Depth of `try` expressions being compiled; errors in evaluating
+	/// constant expressions within a `try` are deferred until runtime.
+	try_depth: u32,
+	/// Enclosing `loop` expressions, innermost last
+	loops: Vec<LoopInfo>,
+	/// Whether the next expression compiled is in tail position
+	/// with respect to the innermost loop
+	tail: bool,
+	/// Whether the operator expression being compiled is in tail position;
+	/// read by operators which compile expressions in tail position.
+	op_tail: bool,
 	/// Traces item currently being compiled; used when errors are generated
 	trace: Vec<TraceItem>,
 	/// Expression added to trace
@@ -254,6 +328,10 @@ impl<'a> Compiler<'a> {
 			outer,
 			self_name: name,
 			macro_recursion: 0,
+			try_depth: 0,
+			loops: Vec::new(),
+			tail: false,
+			op_tail: false,
 			trace: Vec::new(),
 			trace_expr: None,
 		}
@@ -296,6 +374,13 @@ impl<'a> Compiler<'a> {
 		// Blocks which are a target of a conditional jump must be written out
 		// even if they contain only a Return instruction.
 		let mut must_live = vec![false; n_blocks];
+		// Blocks are consumed as they are written, so whether each returns
+		// must be determined beforehand; a loop may jump to an earlier block.
+		let returns = self
+			.blocks
+			.iter()
+			.map(|b| block_returns(b, &self.blocks))
+			.collect::<Vec<_>>();
 		let mut off = 0;
 		let mut i = 0;
 
@@ -310,7 +395,7 @@ impl<'a> Compiler<'a> {
 				_ => (),
 			}
 
-			if block_returns(&b, &self.blocks) {
+			if returns[i] {
 				// If the block is empty and no other blocks will conditionally
 				// jump to it, then the block may be pruned altogether.
 				// Any blocks which would *unconditionally* jump will
@@ -374,12 +459,17 @@ impl<'a> Compiler<'a> {
 	fn compile_lambda(
 		&mut self,
 		name: Option<Name>,
-		params: Vec<(Name, Option<Value>)>,
-		req_params: u32,
-		kw_params: Vec<(Name, Option<Value>)>,
-		rest: Option<Name>,
+		params: LambdaParams,
 		value: &Value,
 	) -> Result<(Code, Vec<Name>), Error> {
+		let LambdaParams {
+			params,
+			req_params,
+			kw_params,
+			rest,
+			patterns,
+		} = params;
+
 		let total_params = params.len() + kw_params.len() + if rest.is_some() { 1 } else { 0 };
 
 		let n_params = params.len();
@@ -435,6 +525,10 @@ impl<'a> Compiler<'a> {
 			}
 
 			self.stack[i].0 = name;
+
+			if let Some((_, pat)) = patterns.iter().find(|&&(n, _)| n == i as u32) {
+				compile_binding_pattern(self, pat, i as u32)?;
+			}
 		}
 
 		for (i, (name, default)) in kw_params.into_iter().enumerate() {
@@ -473,16 +567,32 @@ impl<'a> Compiler<'a> {
 		Ok((code, captures))
 	}
 
+	/// Compiles an expression in tail position, if `tail` is `true`.
+	fn compile_tail(&mut self, value: &Value, tail: bool) -> Result<(), Error> {
+		self.tail = tail;
+		self.compile_value(value)
+	}
+
 	fn compile_value(&mut self, value: &Value) -> Result<(), Error> {
 		let mut value = Borrowed(value);
+		let trace_len = self.trace.len();
+		let tail = replace(&mut self.tail, false);
 
-		match self.eval_constant(&value)? {
-			ConstResult::IsConstant | ConstResult::IsRuntime => (),
-			ConstResult::Partial(v) => value = Owned(v),
-			ConstResult::Constant(v) => {
+		match self.eval_constant(&value) {
+			Ok(ConstResult::IsConstant) | Ok(ConstResult::IsRuntime) => (),
+			Ok(ConstResult::Partial(v)) => value = Owned(v),
+			Ok(ConstResult::Constant(v)) => {
 				self.load_quoted_value(Owned(v))?;
 				return Ok(());
 			}
+			Err(Error::CompileError(e)) => return Err(From::from(e)),
+			Err(Error::RestrictError(e)) => return Err(From::from(e)),
+			// The error may instead be handled by `try` at runtime
+			Err(_) if self.try_depth != 0 => {
+				self.trace.truncate(trace_len);
+				self.trace_expr = None;
+			}
+			Err(e) => return Err(e),
 		}
 
 		match *value {
@@ -501,7 +611,9 @@ impl<'a> Compiler<'a> {
 
 				match *fn_v {
 					Value::Name(name) => {
-						if self.load_local_name(name)? {
+						if let Some(n) = self.find_loop(name) {
+							return self.compile_recur(n, &li[1..], &value, tail);
+						} else if self.load_local_name(name)? {
 							self.push_instruction(Instruction::Push)?;
 							pushed_fn = true;
 						} else if self.self_name != Some(name) {
@@ -511,13 +623,14 @@ impl<'a> Compiler<'a> {
 
 								self.macro_recursion += 1;
 								let v = self.expand_macro(name, &li[1..], &value)?;
-								self.compile_value(&v)?;
+								self.compile_tail(&v, tail)?;
 								self.macro_recursion -= 1;
 
 								self.trace.pop();
 
 								return Ok(());
 							} else if is_system_operator(name) {
+								self.op_tail = tail;
 								return self.compile_operator(name, &li[1..], &value);
 							} else if self.specialize_call(name, &li[1..])? {
 								return Ok(());
@@ -584,6 +697,20 @@ impl<'a> Compiler<'a> {
 				return Err(From::from(CompileError::UnbalancedComma));
 			}
 			Value::Quasiquote(ref v, n) => self.compile_quasiquote(v, n)?,
+			Value::Map(ref m) => {
+				for (k, v) in m.source_iter() {
+					self.compile_value(k)?;
+					self.push_instruction(Instruction::Push)?;
+					self.compile_value(v)?;
+					self.push_instruction(Instruction::Push)?;
+				}
+
+				let n_args = (m.len() * 2) as u32;
+				self.push_instruction(Instruction::CallSysArgs(
+					standard_names::HASH_MAP.get(),
+					n_args,
+				))?;
+			}
 			_ => self.load_const_value(&value)?,
 		}
 
@@ -609,6 +736,80 @@ impl<'a> Compiler<'a> {
 		Trace::new(replace(&mut self.trace, Vec::new()), self.trace_expr.take())
 	}
 
+	/// Returns the index of the innermost loop with the given name,
+	/// if the name is not shadowed by a loop value or other local value.
+	fn find_loop(&self, name: Name) -> Option<usize> {
+		let n = self.loops.iter().rposition(|l| l.name == Some(name))?;
+		let lp = &self.loops[n];
+
+		if self.stack[lp.n_stack - lp.n_vars as usize..]
+			.iter()
+			.any(|&(n, _)| n == name)
+		{
+			None
+		} else {
+			Some(n)
+		}
+	}
+
+	/// Compiles a jump to the beginning of the loop at index `n`,
+	/// rebinding loop values to the given expressions.
+	fn compile_recur(
+		&mut self,
+		n: usize,
+		args: &[Value],
+		expr: &Value,
+		tail: bool,
+	) -> Result<(), Error> {
+		let (name, head, base, n_vars) = {
+			let lp = &self.loops[n];
+			(lp.name, lp.head, lp.base, lp.n_vars)
+		};
+
+		if !tail || !self.loops[n + 1..].iter().all(|l| l.tail) {
+			self.set_trace_expr(expr);
+			return Err(From::from(CompileError::SyntaxError(
+				"loop must be continued from tail position",
+			)));
+		}
+
+		if args.len() as u32 != n_vars {
+			self.set_trace_expr(expr);
+			return Err(From::from(CompileError::ArityError {
+				name: name.unwrap_or(standard_names::RECUR),
+				expected: Arity::Exact(n_vars),
+				found: args.len() as u32,
+			}));
+		}
+
+		let stack_offset = self.stack_offset;
+
+		for arg in args {
+			self.compile_value(arg)?;
+			self.push_instruction(Instruction::Push)?;
+		}
+
+		for i in 0..n_vars {
+			self.push_instruction(Instruction::Load(stack_offset + i))?;
+			self.push_instruction(Instruction::Store(base + i))?;
+		}
+
+		let n_values = self.stack_offset - base - n_vars;
+		if n_values != 0 {
+			self.push_instruction(Instruction::Skip(n_values))?;
+		}
+
+		self.current_block().jump_to(JumpInstruction::Jump, head);
+
+		// Any code following is unreachable;
+		// stack bookkeeping continues as though a value were produced.
+		let b = self.new_block();
+		self.use_next(b);
+		self.stack_offset = stack_offset;
+
+		Ok(())
+	}
+
 	fn is_macro(&self, name: Name) -> bool {
 		self.scope().contains_macro(name)
 	}
@@ -624,10 +825,40 @@ impl<'a> Compiler<'a> {
 			.get_macro(name)
 			.expect("macro not found in expand_macro");
 
-		execute_lambda(&self.ctx, lambda, args.to_vec()).map_err(|e| {
+		let r = if lambda.code.flags & code_flags::IS_SYNTAX_RULES != 0 {
+			syntax::expand_macro(&self.ctx, lambda, args)
+		} else {
+			execute_lambda(&self.ctx, lambda, args.to_vec())
+		};
+
+		if r.is_err() {
 			self.extend_global_trace();
-			e
-		})
+		}
+
+		r
+	}
+
+	/// Expands the given expression, if it is a macro call.
+	fn expand_macro_call(&mut self, value: &Value) -> Result<Option<Value>, Error> {
+		let li = match *value {
+			Value::List(ref li) => li,
+			_ => return Ok(None),
+		};
+
+		let name = match li[0] {
+			Value::Name(name) if self.is_macro(name) => name,
+			_ => return Ok(None),
+		};
+
+		self.trace
+			.push(TraceItem::CallMacro(self.ctx.scope().name(), name));
+
+		self.macro_recursion += 1;
+		let v = self.expand_macro(name, &li[1..], value)?;
+
+		self.trace.pop();
+
+		Ok(Some(v))
 	}
 
 	fn self_name(&self) -> Option<Name> {
@@ -664,12 +895,54 @@ impl<'a> Compiler<'a> {
 				self.set_trace_expr(value);
 				Err(From::from(CompileError::UnbalancedComma))
 			}
+			Value::Map(ref m) => self.eval_constant_map(m),
 			Value::Quote(ref v, 1) => Ok(ConstResult::Constant((**v).clone())),
 			Value::Quote(ref v, n) => Ok(ConstResult::Constant((**v).clone().quote(n - 1))),
 			_ => Ok(ConstResult::IsConstant),
 		}
 	}
 
+	/// Evaluates the keys and values of a map literal.
+	/// If any key or value is not constant, the map is constructed at runtime.
+	fn eval_constant_map(&mut self, map: &Map) -> Result<ConstResult, Error> {
+		let mut entries = Vec::with_capacity(map.len());
+		let mut evaluated = false;
+
+		for (k, v) in map {
+			let k = match self.eval_constant(k)? {
+				ConstResult::IsRuntime | ConstResult::Partial(_) => {
+					return Ok(ConstResult::IsRuntime)
+				}
+				ConstResult::IsConstant => k.clone(),
+				ConstResult::Constant(k) => {
+					evaluated = true;
+					k
+				}
+			};
+
+			let v = match self.eval_constant(v)? {
+				ConstResult::IsRuntime | ConstResult::Partial(_) => {
+					return Ok(ConstResult::IsRuntime)
+				}
+				ConstResult::IsConstant => v.clone(),
+				ConstResult::Constant(v) => {
+					evaluated = true;
+					v
+				}
+			};
+
+			entries.push((k, v));
+		}
+
+		if evaluated {
+			Ok(ConstResult::Constant(Value::Map(
+				entries.into_iter().collect(),
+			)))
+		} else {
+			Ok(ConstResult::IsConstant)
+		}
+	}
+
 	fn eval_constant_function(&mut self, name: Name, args: &[Value]) -> Result<ConstResult, Error> {
 		self.trace
 			.push(TraceItem::CallCode(self.ctx.scope().name(), name));
@@ -772,6 +1045,7 @@ impl<'a> Compiler<'a> {
 	) -> Result<ConstResult, Error> {
 		match *value {
 			Value::List(ref li) => self.eval_constant_quasiquote_list(li, depth),
+			Value::Map(ref m) => self.eval_constant_quasiquote_map(m, depth),
 			Value::Comma(_, n) if n > depth => {
 				self.set_trace_expr(value);
 				Err(From::from(CompileError::UnbalancedComma))
@@ -800,6 +1074,41 @@ impl<'a> Compiler<'a> {
 		}
 	}
 
+	fn eval_constant_quasiquote_map(&mut self, m: &Map, depth: u32) -> Result<ConstResult, Error> {
+		let mut entries = Vec::with_capacity(m.len());
+		let mut new_constant = false;
+
+		for (k, v) in m {
+			let k = match self.eval_constant_quasi_value(k, depth)? {
+				ConstResult::IsConstant => k.clone(),
+				ConstResult::Constant(k) => {
+					new_constant = true;
+					k
+				}
+				res => return Ok(res),
+			};
+
+			let v = match self.eval_constant_quasi_value(v, depth)? {
+				ConstResult::IsConstant => v.clone(),
+				ConstResult::Constant(v) => {
+					new_constant = true;
+					v
+				}
+				res => return Ok(res),
+			};
+
+			entries.push((k, v));
+		}
+
+		if new_constant {
+			Ok(ConstResult::Constant(Value::Map(
+				entries.into_iter().collect(),
+			)))
+		} else {
+			Ok(ConstResult::IsConstant)
+		}
+	}
+
 	fn eval_constant_quasiquote_list(
 		&mut self,
 		li: &[Value],
@@ -937,6 +1246,20 @@ impl<'a> Compiler<'a> {
 				Err(From::from(CompileError::InvalidCommaAt))
 			}
 			Value::List(ref li) => self.compile_quasiquote_list(li, depth),
+			Value::Map(ref m) => {
+				for (k, v) in m.source_iter() {
+					self.compile_quasi_value(k, depth)?;
+					self.push_instruction(Instruction::Push)?;
+					self.compile_quasi_value(v, depth)?;
+					self.push_instruction(Instruction::Push)?;
+				}
+
+				self.push_instruction(Instruction::CallSysArgs(
+					standard_names::HASH_MAP.get(),
+					(m.len() * 2) as u32,
+				))?;
+				Ok(())
+			}
 			Value::Quote(ref v, n) => {
 				self.compile_quasi_value(v, depth)?;
 				self.push_instruction(Instruction::Quote(n))?;
@@ -1043,6 +1366,15 @@ impl<'a> Compiler<'a> {
 
 				Ok(true)
 			}
+			Value::Map(ref m) => {
+				for (k, v) in m {
+					if !self.check_quasi_const(k, depth)? || !self.check_quasi_const(v, depth)? {
+						return Ok(false);
+					}
+				}
+
+				Ok(true)
+			}
 			Value::Quasiquote(ref v, n) => self.check_quasi_const(v, depth + n),
 			Value::Quote(ref v, _) => self.check_quasi_const(v, depth),
 			Value::Comma(_, n) | Value::CommaAt(_, n) if n > depth => {
@@ -1448,7 +1780,8 @@ fn is_const_system_fn(name: Name) -> bool {
 		| APPEND | ELT | CONCAT | JOIN | LEN | SLICE | FIRST | SECOND | LAST | INIT | TAIL
 		| LIST | REVERSE | ABS | CEIL | FLOOR | ROUND | TRUNC | INT | FLOAT | INF | NAN | DENOM
 		| FRACT | NUMER | RAT | RECIP | CHARS | STRING | PATH | BYTES | ID | IS | IS_INSTANCE
-		| NULL | TYPE_OF | XOR | NOT => true,
+		| NULL | TYPE_OF | XOR | NOT | HASH_MAP | GET | ASSOC | DISSOC | KEYS | VALUES
+		| CONTAINS => true,
 		_ => false,
 	}
 }
@@ -1660,19 +1993,24 @@ enum ConstResult {
 }
 
 fn block_returns<'a>(mut b: &'a CodeBlock, blocks: &'a [CodeBlock]) -> bool {
-	loop {
+	// Jumps may be cyclical, in the case of loops.
+	// A chain longer than the number of blocks must contain a cycle
+	// and therefore will never return.
+	for _ in 0..=blocks.len() {
 		match (b.jump, b.next) {
+			(Some((JumpInstruction::Jump, n)), _) if blocks[n as usize].is_mostly_empty() => {
+				b = &blocks[n as usize];
+			}
+			(Some((JumpInstruction::Jump, _)), _) => return false,
 			(_, None) => return true,
-			// This assumes that jumps cannot be cyclical.
-			// Currently, the compiler does not emit cyclical jumps.
-			(Some((JumpInstruction::Jump, n)), _) | (_, Some(n))
-				if blocks[n as usize].is_mostly_empty() =>
-			{
+			(_, Some(n)) if blocks[n as usize].is_mostly_empty() => {
 				b = &blocks[n as usize];
 			}
 			_ => return false,
 		}
 	}
+
+	false
 }
 
 fn estimate_size(blocks: &[CodeBlock]) -> usize {
@@ -1710,7 +2048,7 @@ fn get_system_operator(name: Name) -> &'static Operator {
 static SYSTEM_OPERATORS: [Operator; NUM_SYSTEM_OPERATORS] = [
 	sys_op!(op_apply, Min(2)),
 	sys_op!(op_do, Min(1)),
-	sys_op!(op_let, Exact(2)),
+	sys_op!(op_let, Range(2, 3)),
 	sys_op!(op_define, Range(2, 3)),
 	sys_op!(op_macro, Range(2, 3)),
 	sys_op!(op_struct, Range(2, 3)),
@@ -1725,6 +2063,14 @@ static SYSTEM_OPERATORS: [Operator; NUM_SYSTEM_OPERATORS] = [
 	sys_op!(op_const, Range(2, 3)),
 	sys_op!(op_set_module_doc, Exact(1)),
 	sys_op!(op_call_self, Min(0)),
+	sys_op!(op_try, Min(2)),
+	sys_op!(op_match, Min(2)),
+	sys_op!(op_syntax_rules, Min(2)),
+	sys_op!(op_loop, Exact(2)),
+	sys_op!(op_recur, Min(0)),
+	sys_op!(op_while, Min(1)),
+	sys_op!(op_dotimes, Min(1)),
+	sys_op!(op_for, Min(1)),
 ];
 
 /// `apply` calls a function or lambda with a series of arguments.
@@ -1778,34 +2124,79 @@ fn op_apply(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
 /// `do` evaluates a series of expressions, yielding the value of the last
 /// expression.
 fn op_do(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
-	for arg in args {
+	let tail = compiler.op_tail;
+	let (last, init) = args.split_last().unwrap();
+
+	for arg in init {
 		compiler.compile_value(arg)?;
 	}
-	Ok(())
+	compiler.compile_tail(last, tail)
 }
 
 /// `let` defines a series of named value bindings.
 ///
+/// A binding may instead destructure a value using a list or struct pattern.
+///
+/// A named `let` defines a loop, as with `loop`. Calling the name in tail
+/// position within the body continues the loop with new values.
+///
 /// ```lisp
 /// (let ((a (foo))
 ///       (b (bar)))
 ///   (baz a b))
+///
+/// (let (((a b :rest c) (foo))
+///       ((Point :x x :y y) (bar)))
+///   (baz a b c x y))
+///
+/// (let sum ((n 10) (acc 0))
+///   (if (= n 0)
+///     acc
+///     (sum (- n 1) (+ acc n))))
 /// ```
 fn op_let(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
-	let mut n_vars = 0;
+	let tail = compiler.op_tail;
+
+	if let Value::Name(name) = args[0] {
+		if args.len() != 3 {
+			return Err(From::from(CompileError::ArityError {
+				name: standard_names::LET,
+				expected: Exact(3),
+				found: args.len() as u32,
+			}));
+		}
+
+		return compile_loop(compiler, Some(name), &args[1], &args[2], tail);
+	} else if args.len() != 2 {
+		return Err(From::from(CompileError::ArityError {
+			name: standard_names::LET,
+			expected: Exact(2),
+			found: args.len() as u32,
+		}));
+	}
+
+	let stack_offset = compiler.stack_offset;
+	let n_stack = compiler.stack.len();
 
 	match args[0] {
 		Value::Unit => (),
 		Value::List(ref li) => {
-			n_vars = li.len() as u32;
 			for v in li {
 				match *v {
 					Value::List(ref li) if li.len() == 2 => {
-						let name = get_name(compiler, &li[0])?;
-
 						compiler.compile_value(&li[1])?;
-						compiler.push_var(name);
-						compiler.push_instruction(Instruction::Push)?;
+
+						match li[0] {
+							Value::Name(name) => {
+								compiler.push_var(name);
+								compiler.push_instruction(Instruction::Push)?;
+							}
+							ref pat => {
+								compiler.push_instruction(Instruction::Push)?;
+								let pos = compiler.stack_offset - 1;
+								compile_binding_pattern(compiler, pat, pos)?;
+							}
+						}
 					}
 					_ => {
 						compiler.set_trace_expr(v);
@@ -1822,91 +2213,362 @@ fn op_let(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
 		}
 	}
 
-	compiler.compile_value(&args[1])?;
+	compiler.compile_tail(&args[1], tail)?;
 
 	// Create a new block containing the Skip.
 	// This helps to optimize out unnecessary instructions in the assembly phase.
 	let next_block = compiler.new_block();
 	compiler.use_next(next_block);
 
-	compiler.push_instruction(Instruction::Skip(n_vars))?;
+	let n_values = compiler.stack_offset - stack_offset;
+	let n_vars = (compiler.stack.len() - n_stack) as u32;
+
+	compiler.push_instruction(Instruction::Skip(n_values))?;
 	compiler.pop_vars(n_vars);
 
 	Ok(())
 }
 
-/// `define` declares a value binding or function binding in global scope.
+/// `loop` defines a series of named value bindings, as with `let`, and
+/// evaluates an expression. Within the expression, `recur` in tail position
+/// continues from the beginning of the loop with new values.
 ///
 /// ```lisp
-/// (define foo 123)
-///
-/// (define (bar a) (+ a foo))
+/// (loop ((n 10) (acc 0))
+///   (if (= n 0)
+///     acc
+///     (recur (- n 1) (+ acc n))))
 /// ```
-fn op_define(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
-	match args[0] {
-		Value::Name(name) => {
-			// Replace operator item with more specific item
-			compiler.trace.pop();
-			compiler
-				.trace
-				.push(TraceItem::Define(compiler.ctx.scope().name(), name));
+fn op_loop(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
+	let tail = compiler.op_tail;
+	compile_loop(compiler, None, &args[0], &args[1], tail)
+}
 
-			let (doc, body) = extract_doc_string(args)?;
-			test_define_name(compiler.scope(), name)?;
-			compiler.compile_value(&body)?;
+fn compile_loop(
+	compiler: &mut Compiler,
+	name: Option<Name>,
+	bindings: &Value,
+	body: &Value,
+	tail: bool,
+) -> Result<(), Error> {
+	let stack_offset = compiler.stack_offset;
+	let n_stack = compiler.stack.len();
 
-			if let Some(doc) = doc {
-				compiler.scope().add_doc_string(name, doc.to_owned());
+	match *bindings {
+		Value::Unit => (),
+		Value::List(ref li) => {
+			for v in li {
+				match *v {
+					Value::List(ref li) if li.len() == 2 => {
+						let name = get_name(compiler, &li[0])?;
+						compiler.compile_value(&li[1])?;
+						compiler.push_var(name);
+						compiler.push_instruction(Instruction::Push)?;
+					}
+					_ => {
+						compiler.set_trace_expr(v);
+						return Err(From::from(CompileError::SyntaxError(
+							"expected list of 2 elements",
+						)));
+					}
+				}
 			}
-
-			let c = compiler.add_const(Owned(Value::Name(name)));
-			compiler.push_instruction(Instruction::SetDef(c))?;
-			Ok(())
 		}
-		Value::List(ref li) => {
-			let name = get_name(compiler, &li[0])?;
+		ref v => {
+			compiler.set_trace_expr(v);
+			return Err(From::from(CompileError::SyntaxError("expected list")));
+		}
+	}
 
-			// Replace operator item with more specific item
-			compiler.trace.pop();
-			compiler
-				.trace
-				.push(TraceItem::Define(compiler.ctx.scope().name(), name));
+	let head = compiler.new_block();
+	compiler.use_next(head);
 
-			test_define_name(compiler.scope(), name)?;
-			let (doc, body) = extract_doc_string(args)?;
+	compiler.loops.push(LoopInfo {
+		name,
+		head,
+		base: stack_offset,
+		n_vars: compiler.stack_offset - stack_offset,
+		n_stack: compiler.stack.len(),
+		tail,
+	});
 
-			let c = compiler.add_const(Owned(Value::Name(name)));
+	let r = compiler.compile_tail(body, true);
+	compiler.loops.pop();
+	r?;
 
-			let (lambda, captures) = make_lambda(compiler, Some(name), &li[1..], body, doc)?;
+	// As in `let`, a separate block permits a tail call in the expression.
+	let next_block = compiler.new_block();
+	compiler.use_next(next_block);
 
-			if compiler.is_top_level() && captures.is_empty() {
-				// Add top-level, non-capturing lambdas to global scope immediately.
-				compiler.scope().add_value(name, Value::Lambda(lambda));
-				let c = compiler.add_const(Owned(Value::Name(name)));
-				compiler.push_instruction(Instruction::Const(c))?;
-			} else {
-				let code_c = compiler.add_const(Owned(Value::Lambda(lambda)));
-				compiler.load_lambda(code_c, &captures)?;
-				compiler.push_instruction(Instruction::SetDef(c))?;
-			}
+	let n_values = compiler.stack_offset - stack_offset;
+	let n_vars = (compiler.stack.len() - n_stack) as u32;
 
-			Ok(())
-		}
-		ref v => {
-			compiler.set_trace_expr(v);
-			Err(From::from(CompileError::SyntaxError(
-				"expected name or list",
-			)))
-		}
-	}
+	compiler.push_instruction(Instruction::Skip(n_values))?;
+	compiler.pop_vars(n_vars);
+
+	Ok(())
 }
 
-/// `macro` defines a compile-time macro function in global scope.
-fn op_macro(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
-	let (name, params) = match args[0] {
-		Value::List(ref li) => {
-			let name = get_name(compiler, &li[0])?;
-			(name, &li[1..])
+/// `recur` continues from the beginning of the innermost `loop`,
+/// binding new values to its names. It must appear in tail position.
+///
+/// ```lisp
+/// (loop ((n 0))
+///   (if (< n 10)
+///     (recur (+ n 1))
+///     n))
+/// ```
+fn op_recur(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
+	let tail = compiler.op_tail;
+
+	if compiler.loops.is_empty() {
+		return Err(From::from(CompileError::SyntaxError(
+			"`recur` outside loop",
+		)));
+	}
+
+	let n = compiler.loops.len() - 1;
+	let expr = Value::from(
+		Some(Value::Name(standard_names::RECUR))
+			.into_iter()
+			.chain(args.iter().cloned())
+			.collect::<Vec<_>>(),
+	);
+
+	compiler.compile_recur(n, args, &expr, tail)
+}
+
+/// `while` repeatedly evaluates a series of expressions for as long as
+/// a condition evaluates `true`. Yields `()`.
+///
+/// ```lisp
+/// (while (running)
+///   (step))
+/// ```
+fn op_while(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
+	let head = compiler.new_block();
+	let end = compiler.new_block();
+
+	compiler.use_next(head);
+	compiler.compile_value(&args[0])?;
+	compiler
+		.current_block()
+		.jump_to(JumpInstruction::JumpIfNot, end);
+
+	let body = compiler.new_block();
+	compiler.use_next(body);
+
+	for arg in &args[1..] {
+		compiler.compile_value(arg)?;
+	}
+
+	compiler
+		.current_block()
+		.jump_to(JumpInstruction::Jump, head);
+
+	compiler.use_next(end);
+	compiler.push_instruction(Instruction::Unit)?;
+	Ok(())
+}
+
+/// `dotimes` evaluates a series of expressions once for each integer from
+/// zero up to, but not including, a given count. Yields `()`.
+///
+/// ```lisp
+/// (dotimes (i 10)
+///   (println "~a" i))
+/// ```
+fn op_dotimes(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
+	let (name, count) = get_loop_spec(compiler, &args[0])?;
+
+	compiler.compile_value(count)?;
+	compiler.push_instruction(Instruction::Push)?;
+	let count_pos = compiler.stack_offset - 1;
+
+	compiler.load_quoted_value(Owned(Value::Integer(Integer::zero())))?;
+	compiler.push_var(name);
+	compiler.push_instruction(Instruction::Push)?;
+	let pos = compiler.stack_offset - 1;
+
+	let head = compiler.new_block();
+	let end = compiler.new_block();
+
+	compiler.use_next(head);
+	compiler.push_instruction(Instruction::Load(pos))?;
+	compiler.push_instruction(Instruction::Push)?;
+	compiler.push_instruction(Instruction::Load(count_pos))?;
+	compiler.push_instruction(Instruction::Push)?;
+	compiler.write_call_sys(
+		standard_names::LT,
+		get_system_fn(standard_names::LT).unwrap().arity,
+		2,
+	)?;
+	compiler
+		.current_block()
+		.jump_to(JumpInstruction::JumpIfNot, end);
+
+	let body = compiler.new_block();
+	compiler.use_next(body);
+
+	for arg in &args[1..] {
+		compiler.compile_value(arg)?;
+	}
+
+	compiler.push_instruction(Instruction::Load(pos))?;
+	compiler.push_instruction(Instruction::Inc)?;
+	compiler.push_instruction(Instruction::Store(pos))?;
+	compiler
+		.current_block()
+		.jump_to(JumpInstruction::Jump, head);
+
+	compiler.use_next(end);
+	compiler.push_instruction(Instruction::Skip(2))?;
+	compiler.pop_vars(1);
+	compiler.push_instruction(Instruction::Unit)?;
+	Ok(())
+}
+
+/// `for` evaluates a series of expressions once for each element of a list,
+/// binding the element to a name. Yields `()`.
+///
+/// ```lisp
+/// (for (x '(1 2 3))
+///   (println "~a" x))
+/// ```
+fn op_for(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
+	let (name, list) = get_loop_spec(compiler, &args[0])?;
+
+	compiler.compile_value(list)?;
+	compiler.push_instruction(Instruction::Push)?;
+	let list_pos = compiler.stack_offset - 1;
+
+	compiler.push_instruction(Instruction::Unit)?;
+	compiler.push_var(name);
+	compiler.push_instruction(Instruction::Push)?;
+	let pos = compiler.stack_offset - 1;
+
+	let head = compiler.new_block();
+	let end = compiler.new_block();
+
+	compiler.use_next(head);
+	compiler.push_instruction(Instruction::Load(list_pos))?;
+	compiler
+		.current_block()
+		.jump_to(JumpInstruction::JumpIfNull, end);
+
+	let body = compiler.new_block();
+	compiler.use_next(body);
+
+	compiler.push_instruction(Instruction::Load(list_pos))?;
+	compiler.push_instruction(Instruction::First)?;
+	compiler.push_instruction(Instruction::Store(pos))?;
+	compiler.push_instruction(Instruction::Load(list_pos))?;
+	compiler.push_instruction(Instruction::Tail)?;
+	compiler.push_instruction(Instruction::Store(list_pos))?;
+
+	for arg in &args[1..] {
+		compiler.compile_value(arg)?;
+	}
+
+	compiler
+		.current_block()
+		.jump_to(JumpInstruction::Jump, head);
+
+	compiler.use_next(end);
+	compiler.push_instruction(Instruction::Skip(2))?;
+	compiler.pop_vars(1);
+	compiler.push_instruction(Instruction::Unit)?;
+	Ok(())
+}
+
+/// Parses a `(name expr)` loop specification, as used by `dotimes` and `for`.
+fn get_loop_spec<'v>(compiler: &mut Compiler, spec: &'v Value) -> Result<(Name, &'v Value), Error> {
+	match *spec {
+		Value::List(ref li) if li.len() == 2 => {
+			let name = get_name(compiler, &li[0])?;
+			Ok((name, &li[1]))
+		}
+		ref v => {
+			compiler.set_trace_expr(v);
+			Err(From::from(CompileError::SyntaxError(
+				"expected list of 2 elements",
+			)))
+		}
+	}
+}
+
+/// `define` declares a value binding or function binding in global scope.
+///
+/// ```lisp
+/// (define foo 123)
+///
+/// (define (bar a) (+ a foo))
+/// ```
+fn op_define(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
+	match args[0] {
+		Value::Name(name) => {
+			// Replace operator item with more specific item
+			compiler.trace.pop();
+			compiler
+				.trace
+				.push(TraceItem::Define(compiler.ctx.scope().name(), name));
+
+			let (doc, body) = extract_doc_string(args)?;
+			test_define_name(compiler.scope(), name)?;
+			compiler.compile_value(&body)?;
+
+			if let Some(doc) = doc {
+				compiler.scope().add_doc_string(name, doc.to_owned());
+			}
+
+			let c = compiler.add_const(Owned(Value::Name(name)));
+			compiler.push_instruction(Instruction::SetDef(c))?;
+			Ok(())
+		}
+		Value::List(ref li) => {
+			let name = get_name(compiler, &li[0])?;
+
+			// Replace operator item with more specific item
+			compiler.trace.pop();
+			compiler
+				.trace
+				.push(TraceItem::Define(compiler.ctx.scope().name(), name));
+
+			test_define_name(compiler.scope(), name)?;
+			let (doc, body) = extract_doc_string(args)?;
+
+			let c = compiler.add_const(Owned(Value::Name(name)));
+
+			let (lambda, captures) = make_lambda(compiler, Some(name), &li[1..], body, doc)?;
+
+			if compiler.is_top_level() && captures.is_empty() {
+				// Add top-level, non-capturing lambdas to global scope immediately.
+				compiler.scope().add_value(name, Value::Lambda(lambda));
+				let c = compiler.add_const(Owned(Value::Name(name)));
+				compiler.push_instruction(Instruction::Const(c))?;
+			} else {
+				let code_c = compiler.add_const(Owned(Value::Lambda(lambda)));
+				compiler.load_lambda(code_c, &captures)?;
+				compiler.push_instruction(Instruction::SetDef(c))?;
+			}
+
+			Ok(())
+		}
+		ref v => {
+			compiler.set_trace_expr(v);
+			Err(From::from(CompileError::SyntaxError(
+				"expected name or list",
+			)))
+		}
+	}
+}
+
+/// `macro` defines a compile-time macro function in global scope.
+fn op_macro(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
+	let (name, params) = match args[0] {
+		Value::List(ref li) => {
+			let name = get_name(compiler, &li[0])?;
+			(name, &li[1..])
 		}
 		ref v => {
 			compiler.set_trace_expr(v);
@@ -1939,6 +2601,47 @@ fn op_macro(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
 	Ok(())
 }
 
+/// `syntax-rules` defines a pattern-based macro in global scope.
+/// Names bound within a template are renamed upon each expansion.
+///
+/// ```lisp
+/// (syntax-rules swap
+///   ((_ a b) (let ((tmp a)) (list b tmp))))
+/// ```
+fn op_syntax_rules(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
+	let name = get_name(compiler, &args[0])?;
+
+	// Replace operator item with more specific item
+	compiler.trace.pop();
+	compiler
+		.trace
+		.push(TraceItem::DefineMacro(compiler.ctx.scope().name(), name));
+
+	let (doc, rules) = match args[1] {
+		Value::String(ref s) if args.len() > 2 => (Some(&s[..]), &args[2..]),
+		_ => (None, &args[1..]),
+	};
+
+	if let Err(e) = syntax::check_rules(rules) {
+		compiler.set_trace_expr(&Value::from(rules.to_vec()));
+		return Err(From::from(e));
+	}
+
+	test_define_name(compiler.scope(), name)?;
+
+	// The macro body returns its rules; expansion is performed by the compiler.
+	let body = Value::from(rules.to_vec()).quote(1);
+
+	let (mut lambda, _) = make_lambda(compiler, Some(name), &[], &body, doc)?;
+	Rc::make_mut(&mut lambda.code).flags |= code_flags::IS_SYNTAX_RULES;
+
+	compiler.scope().add_macro(name, lambda);
+
+	let c = compiler.add_const(Owned(Value::Name(name)));
+	compiler.push_instruction(Instruction::Const(c))?;
+	Ok(())
+}
+
 /// `struct` creates a struct definition and binds to global scope.
 ///
 /// ```lisp
@@ -2008,6 +2711,7 @@ fn op_struct(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
 ///   (baz))
 /// ```
 fn op_if(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
+	let tail = compiler.op_tail;
 	let then_block = compiler.new_block();
 	let else_block = compiler.new_block();
 	let final_block = compiler.new_block();
@@ -2018,14 +2722,14 @@ fn op_if(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
 		.jump_to(JumpInstruction::JumpIfNot, else_block);
 
 	compiler.use_next(then_block);
-	compiler.compile_value(&args[1])?;
+	compiler.compile_tail(&args[1], tail)?;
 	compiler
 		.current_block()
 		.jump_to(JumpInstruction::Jump, final_block);
 
 	compiler.use_next(else_block);
 	match args.get(2) {
-		Some(value) => compiler.compile_value(value)?,
+		Some(value) => compiler.compile_tail(value, tail)?,
 		None => compiler.push_instruction(Instruction::Unit)?,
 	}
 
@@ -2037,6 +2741,7 @@ fn op_if(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
 /// of all expressions. If a `false` value is evaluated, no further expressions
 /// will be evaluated.
 fn op_and(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
+	let tail = compiler.op_tail;
 	let (last, init) = args.split_last().unwrap();
 	let last_block = compiler.new_block();
 
@@ -2056,7 +2761,7 @@ fn op_and(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
 		compiler.use_next(block);
 	}
 
-	compiler.compile_value(last)?;
+	compiler.compile_tail(last, tail)?;
 	compiler.use_next(last_block);
 	Ok(())
 }
@@ -2065,6 +2770,7 @@ fn op_and(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
 /// of all expressions. If a `true` value is evaluated, no further expressions
 /// will be evaluated.
 fn op_or(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
+	let tail = compiler.op_tail;
 	let (last, init) = args.split_last().unwrap();
 	let last_block = compiler.new_block();
 
@@ -2084,7 +2790,7 @@ fn op_or(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
 		compiler.use_next(block);
 	}
 
-	compiler.compile_value(last)?;
+	compiler.compile_tail(last, tail)?;
 	compiler.use_next(last_block);
 	Ok(())
 }
@@ -2106,6 +2812,7 @@ fn op_or(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
 ///   (else      'c))
 /// ```
 fn op_case(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
+	let tail = compiler.op_tail;
 	let final_block = compiler.new_block();
 	let mut code_blocks = Vec::with_capacity(args.len());
 	let mut else_case = false;
@@ -2173,7 +2880,7 @@ fn op_case(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
 
 		let prev_block = compiler.cur_block as u32;
 		compiler.use_block(code_begin);
-		compiler.compile_value(code)?;
+		compiler.compile_tail(code, tail)?;
 		compiler
 			.current_block()
 			.jump_to(JumpInstruction::Jump, final_block);
@@ -2216,6 +2923,7 @@ fn op_case(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
 ///   (else      'huge))
 /// ```
 fn op_cond(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
+	let tail = compiler.op_tail;
 	let final_block = compiler.new_block();
 	let mut code_blocks = Vec::with_capacity(args.len());
 	let mut else_case = false;
@@ -2257,7 +2965,7 @@ fn op_cond(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
 
 		let prev_block = compiler.cur_block as u32;
 		compiler.use_block(code_begin);
-		compiler.compile_value(code)?;
+		compiler.compile_tail(code, tail)?;
 		compiler
 			.current_block()
 			.jump_to(JumpInstruction::Jump, final_block);
@@ -2285,6 +2993,493 @@ fn op_cond(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
 	Ok(())
 }
 
+/// `match` compares a value against a series of patterns and evaluates the
+/// expression of the first clause whose pattern matches.
+///
+/// Names within a pattern are bound to the corresponding part of the value;
+/// `_` matches any value without binding it. A pattern may be followed by
+/// `:when` and a guard expression, which must also evaluate `true`
+/// for the clause to be chosen.
+///
+/// If no pattern matches, an error is raised.
+///
+/// ```lisp
+/// (match value
+///   (()                     'empty)
+///   ((list x)               x)
+///   ((list x y :rest _)     :when (< x y) 'ascending)
+///   ((Point :x 0 :y y)      y)
+///   ('foo                   'foo)
+///   (_                      'other))
+/// ```
+fn op_match(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
+	let tail = compiler.op_tail;
+	let final_block = compiler.new_block();
+	let mut exhaustive = false;
+
+	compiler.compile_value(&args[0])?;
+	compiler.push_instruction(Instruction::Push)?;
+
+	let pos = compiler.stack_offset - 1;
+
+	for arg in &args[1..] {
+		if exhaustive {
+			compiler.set_trace_expr(arg);
+			return Err(From::from(CompileError::SyntaxError("unreachable pattern")));
+		}
+
+		let (pat, guard, code) = match *arg {
+			Value::List(ref li) if li.len() == 2 => (&li[0], None, &li[1]),
+			Value::List(ref li)
+				if li.len() == 4 && matches!(li[1], Value::Keyword(standard_names::WHEN)) =>
+			{
+				(&li[0], Some(&li[2]), &li[3])
+			}
+			_ => {
+				compiler.set_trace_expr(arg);
+				return Err(From::from(CompileError::SyntaxError(
+					"expected `(pattern [:when guard] expr)`",
+				)));
+			}
+		};
+
+		let mut clause = MatchClause::new(compiler);
+
+		compile_pattern(compiler, &mut clause, pat, pos)?;
+
+		match guard {
+			Some(guard) => {
+				compiler.compile_value(guard)?;
+				clause.jump_fail(compiler, JumpInstruction::JumpIfNot);
+			}
+			None => exhaustive = matches!(*pat, Value::Name(_)),
+		}
+
+		compiler.compile_tail(code, tail)?;
+
+		// As in `let`, a separate block permits a tail call in the expression.
+		let b = compiler.new_block();
+		compiler.use_next(b);
+
+		let n = compiler.stack_offset - clause.base;
+		if n != 0 {
+			compiler.push_instruction(Instruction::Skip(n))?;
+		}
+		compiler.pop_vars(clause.bound.len() as u32);
+		compiler
+			.current_block()
+			.jump_to(JumpInstruction::Jump, final_block);
+
+		clause.finish(compiler)?;
+	}
+
+	if !exhaustive {
+		compiler.push_instruction(Instruction::Load(pos))?;
+		compiler.push_instruction(Instruction::NoMatch)?;
+	}
+
+	compiler.use_next(final_block);
+	compiler.push_instruction(Instruction::Skip(1))?;
+	Ok(())
+}
+
+/// Tracks the state of a clause of a `match` expression
+struct MatchClause {
+	/// Stack offset at the beginning of the clause
+	base: u32,
+	/// Block at the beginning of the next clause
+	next_block: u32,
+	/// Blocks which discard values pushed by the clause and jump to the next
+	/// clause, paired with the number of values to discard
+	fail_blocks: Vec<(u32, u32)>,
+	/// Names bound by the pattern
+	bound: Vec<Name>,
+}
+
+impl MatchClause {
+	fn new(compiler: &mut Compiler) -> MatchClause {
+		MatchClause {
+			base: compiler.stack_offset,
+			next_block: compiler.new_block(),
+			fail_blocks: Vec::new(),
+			bound: Vec::new(),
+		}
+	}
+
+	/// Writes blocks which discard pushed values before jumping to the next
+	/// clause, then begins the next clause.
+	fn finish(self, compiler: &mut Compiler) -> Result<(), Error> {
+		for (n, block) in self.fail_blocks {
+			compiler.use_next(block);
+			compiler.stack_offset = self.base + n;
+			compiler.push_instruction(Instruction::Skip(n))?;
+			compiler
+				.current_block()
+				.jump_to(JumpInstruction::Jump, self.next_block);
+		}
+
+		compiler.use_next(self.next_block);
+		Ok(())
+	}
+
+	/// Ends the current block with a jump to the next clause.
+	fn jump_fail(&mut self, compiler: &mut Compiler, instr: JumpInstruction) {
+		let n = compiler.stack_offset - self.base;
+
+		let block = if n == 0 {
+			self.next_block
+		} else {
+			match self.fail_blocks.iter().find(|&&(m, _)| m == n) {
+				Some(&(_, block)) => block,
+				None => {
+					let block = compiler.new_block();
+					self.fail_blocks.push((n, block));
+					block
+				}
+			}
+		};
+
+		compiler.current_block().jump_to(instr, block);
+
+		let b = compiler.new_block();
+		compiler.use_next(b);
+	}
+
+	/// Fails unless the value at stack offset `pos` is of the given type.
+	fn match_type(&mut self, compiler: &mut Compiler, pos: u32, ty: Name) -> Result<(), Error> {
+		compiler.push_instruction(Instruction::Load(pos))?;
+		compiler.push_instruction(Instruction::Push)?;
+		compiler.write_call_sys(standard_names::TYPE_OF, Exact(1), 1)?;
+
+		let c = compiler.add_const(Owned(Value::Name(ty)));
+		self.jump_fail(compiler, JumpInstruction::JumpIfNotEqConst(c));
+		Ok(())
+	}
+
+	/// Binds a name to the value at stack offset `pos`.
+	fn bind(
+		&mut self,
+		compiler: &mut Compiler,
+		name: Name,
+		pat: &Value,
+		pos: u32,
+	) -> Result<(), Error> {
+		if self.bound.contains(&name) {
+			compiler.set_trace_expr(pat);
+			return Err(From::from(CompileError::SyntaxError(
+				"duplicate name in pattern",
+			)));
+		}
+
+		self.bound.push(name);
+		compiler.stack.push((name, pos));
+		Ok(())
+	}
+}
+
+/// Destructures the value at stack offset `pos` using a binding pattern,
+/// as accepted by `let` and lambda parameters.
+///
+/// If the value does not match the pattern, an error is raised.
+fn compile_binding_pattern(compiler: &mut Compiler, pat: &Value, pos: u32) -> Result<(), Error> {
+	let match_pat = binding_to_match_pattern(compiler, pat)?;
+
+	let mut clause = MatchClause::new(compiler);
+	compile_pattern(compiler, &mut clause, &match_pat, pos)?;
+
+	let stack_offset = compiler.stack_offset;
+	let final_block = compiler.new_block();
+
+	compiler
+		.current_block()
+		.jump_to(JumpInstruction::Jump, final_block);
+
+	clause.finish(compiler)?;
+
+	let c = compiler.add_const(Borrowed(pat));
+	compiler.push_instruction(Instruction::Load(pos))?;
+	compiler.push_instruction(Instruction::PatternError(c))?;
+
+	compiler.use_next(final_block);
+	compiler.stack_offset = stack_offset;
+	Ok(())
+}
+
+/// Converts a binding pattern into the equivalent `match` pattern.
+///
+/// `(a b :rest c)` becomes `(list a b :rest c)`;
+/// `(Foo :a a)` is a struct pattern in either form.
+fn binding_to_match_pattern(compiler: &mut Compiler, pat: &Value) -> Result<Value, Error> {
+	match *pat {
+		Value::Name(_) => Ok(pat.clone()),
+		Value::List(ref li)
+			if li.len() >= 2
+				&& matches!(li[1], Value::Keyword(kw) if kw != standard_names::REST) =>
+		{
+			get_name(compiler, &li[0])?;
+
+			if li.len() % 2 == 0 {
+				compiler.set_trace_expr(pat);
+				return Err(From::from(CompileError::SyntaxError(
+					"expected field name and pattern pairs",
+				)));
+			}
+
+			let mut res = Vec::with_capacity(li.len());
+			res.push(li[0].clone());
+
+			for pair in li[1..].chunks(2) {
+				res.push(pair[0].clone());
+				res.push(binding_to_match_pattern(compiler, &pair[1])?);
+			}
+
+			Ok(res.into())
+		}
+		Value::List(ref li) => {
+			let mut res = Vec::with_capacity(li.len() + 1);
+			res.push(Value::Name(standard_names::LIST));
+
+			for v in li {
+				match *v {
+					Value::Keyword(standard_names::REST) => res.push(v.clone()),
+					ref v => res.push(binding_to_match_pattern(compiler, v)?),
+				}
+			}
+
+			Ok(res.into())
+		}
+		ref v => {
+			compiler.set_trace_expr(v);
+			Err(From::from(CompileError::SyntaxError(
+				"expected name, list pattern, or struct pattern",
+			)))
+		}
+	}
+}
+
+/// Compiles a test of the value at stack offset `pos` against a pattern.
+fn compile_pattern(
+	compiler: &mut Compiler,
+	clause: &mut MatchClause,
+	pat: &Value,
+	pos: u32,
+) -> Result<(), Error> {
+	match *pat {
+		Value::Name(standard_names::UNDERSCORE) => Ok(()),
+		Value::Name(name) => clause.bind(compiler, name, pat, pos),
+		Value::Unit => {
+			compiler.push_instruction(Instruction::Load(pos))?;
+			clause.jump_fail(compiler, JumpInstruction::JumpIfNotNull);
+			Ok(())
+		}
+		Value::Bool(_)
+		| Value::Float(_)
+		| Value::Integer(_)
+		| Value::Ratio(_)
+		| Value::Keyword(_)
+		| Value::Char(_)
+		| Value::String(_)
+		| Value::Bytes(_)
+		| Value::Path(_) => compile_literal_pattern(compiler, clause, pat, pos),
+		Value::Quote(ref v, 1) => match **v {
+			Value::List(ref li) => {
+				let pats = li.iter().map(|v| v.clone().quote(1)).collect::<Vec<_>>();
+				compile_list_pattern(compiler, clause, &pats, None, pos)
+			}
+			Value::Unit => compile_pattern(compiler, clause, v, pos),
+			ref v => compile_literal_pattern(compiler, clause, v, pos),
+		},
+		Value::Quote(ref v, n) => {
+			compile_literal_pattern(compiler, clause, &(**v).clone().quote(n - 1), pos)
+		}
+		Value::List(ref li) => match li[0] {
+			Value::Name(standard_names::LIST) => {
+				let (pats, rest) = match li[1..]
+					.iter()
+					.position(|v| matches!(*v, Value::Keyword(standard_names::REST)))
+				{
+					Some(n) if n + 3 == li.len() => (&li[1..n + 1], Some(&li[n + 2])),
+					Some(_) => {
+						compiler.set_trace_expr(pat);
+						return Err(From::from(CompileError::SyntaxError(
+							"expected one pattern after `:rest`",
+						)));
+					}
+					None => (&li[1..], None),
+				};
+
+				compile_list_pattern(compiler, clause, pats, rest, pos)
+			}
+			Value::Name(_) => compile_struct_pattern(compiler, clause, &li[0], &li[1..], pos),
+			_ => {
+				compiler.set_trace_expr(pat);
+				Err(From::from(CompileError::SyntaxError(
+					"expected `list` or struct name",
+				)))
+			}
+		},
+		_ => {
+			compiler.set_trace_expr(pat);
+			Err(From::from(CompileError::SyntaxError("invalid pattern")))
+		}
+	}
+}
+
+/// Compiles a test for a literal value.
+/// The value must be of the same type as the literal and compare equal to it.
+fn compile_literal_pattern(
+	compiler: &mut Compiler,
+	clause: &mut MatchClause,
+	v: &Value,
+	pos: u32,
+) -> Result<(), Error> {
+	let ty = type_of(compiler.scope(), v);
+	clause.match_type(compiler, pos, ty)?;
+
+	let c = compiler.add_const(Borrowed(v));
+	compiler.push_instruction(Instruction::Load(pos))?;
+	clause.jump_fail(compiler, JumpInstruction::JumpIfNotEqConst(c));
+	Ok(())
+}
+
+/// Compiles a test for a list, matching each element against a pattern.
+/// If `rest` is given, it is matched against any remaining elements.
+fn compile_list_pattern(
+	compiler: &mut Compiler,
+	clause: &mut MatchClause,
+	pats: &[Value],
+	rest: Option<&Value>,
+	pos: u32,
+) -> Result<(), Error> {
+	let n = pats.len() as u32;
+
+	if n == 0 {
+		match rest {
+			None => {
+				compiler.push_instruction(Instruction::Load(pos))?;
+				clause.jump_fail(compiler, JumpInstruction::JumpIfNotNull);
+				return Ok(());
+			}
+			Some(rest) => {
+				// Matches either `()` or a list
+				let list_block = compiler.new_block();
+				let rest_block = compiler.new_block();
+
+				compiler.push_instruction(Instruction::Load(pos))?;
+				compiler
+					.current_block()
+					.jump_to(JumpInstruction::JumpIfNull, rest_block);
+				compiler.use_next(list_block);
+				clause.match_type(compiler, pos, standard_names::LIST)?;
+				compiler.use_next(rest_block);
+
+				return compile_pattern(compiler, clause, rest, pos);
+			}
+		}
+	}
+
+	clause.match_type(compiler, pos, standard_names::LIST)?;
+
+	let c = compiler.add_const(Owned(n.into()));
+	compiler.push_instruction(Instruction::Load(pos))?;
+	compiler.push_instruction(Instruction::Push)?;
+	compiler.write_call_sys(standard_names::LEN, Exact(1), 1)?;
+
+	if rest.is_some() {
+		compiler.push_instruction(Instruction::Push)?;
+		compiler.push_instruction(Instruction::Const(c))?;
+		compiler.push_instruction(Instruction::Push)?;
+		compiler.write_call_sys(standard_names::GE, Min(2), 2)?;
+		clause.jump_fail(compiler, JumpInstruction::JumpIfNot);
+	} else {
+		clause.jump_fail(compiler, JumpInstruction::JumpIfNotEqConst(c));
+	}
+
+	for (i, pat) in pats.iter().enumerate() {
+		if let Value::Name(standard_names::UNDERSCORE) = *pat {
+			continue;
+		}
+
+		compiler.push_instruction(Instruction::Load(pos))?;
+		for _ in 0..i {
+			compiler.push_instruction(Instruction::Tail)?;
+		}
+		compiler.push_instruction(Instruction::First)?;
+		compiler.push_instruction(Instruction::Push)?;
+
+		let elem = compiler.stack_offset - 1;
+		compile_pattern(compiler, clause, pat, elem)?;
+	}
+
+	if let Some(rest) = rest {
+		if let Value::Name(standard_names::UNDERSCORE) = *rest {
+			return Ok(());
+		}
+
+		compiler.push_instruction(Instruction::Load(pos))?;
+		for _ in 0..n {
+			compiler.push_instruction(Instruction::Tail)?;
+		}
+		compiler.push_instruction(Instruction::Push)?;
+
+		let tail = compiler.stack_offset - 1;
+		compile_pattern(compiler, clause, rest, tail)?;
+	}
+
+	Ok(())
+}
+
+/// Compiles a test for an instance of a struct,
+/// matching the named fields against patterns.
+fn compile_struct_pattern(
+	compiler: &mut Compiler,
+	clause: &mut MatchClause,
+	def: &Value,
+	fields: &[Value],
+	pos: u32,
+) -> Result<(), Error> {
+	if fields.len() % 2 == 1 {
+		compiler.set_trace_expr(def);
+		return Err(From::from(CompileError::SyntaxError(
+			"expected field name and pattern pairs",
+		)));
+	}
+
+	compiler.compile_value(def)?;
+	compiler.push_instruction(Instruction::Push)?;
+	compiler.push_instruction(Instruction::Load(pos))?;
+	compiler.push_instruction(Instruction::Push)?;
+	compiler.write_call_sys(standard_names::IS_INSTANCE, Exact(2), 2)?;
+	clause.jump_fail(compiler, JumpInstruction::JumpIfNot);
+
+	for pair in fields.chunks(2) {
+		let field = match pair[0] {
+			Value::Keyword(_) => &pair[0],
+			ref v => {
+				compiler.set_trace_expr(v);
+				return Err(From::from(CompileError::SyntaxError("expected keyword")));
+			}
+		};
+
+		if let Value::Name(standard_names::UNDERSCORE) = pair[1] {
+			continue;
+		}
+
+		let c = compiler.add_const(Borrowed(field));
+		compiler.push_instruction(Instruction::Load(pos))?;
+		compiler.push_instruction(Instruction::Push)?;
+		compiler.push_instruction(Instruction::Const(c))?;
+		compiler.push_instruction(Instruction::Push)?;
+		compiler.write_call_sys(standard_names::DOT, Exact(2), 2)?;
+		compiler.push_instruction(Instruction::Push)?;
+
+		let value = compiler.stack_offset - 1;
+		compile_pattern(compiler, clause, &pair[1], value)?;
+	}
+
+	Ok(())
+}
+
 /// `lambda` defines an anonymous lambda function which may enclose named values
 /// from the enclosing scope.
 ///
@@ -2500,6 +3695,157 @@ fn op_call_self(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
 	Ok(())
 }
 
+/// `try` evaluates an expression, handling any error that it raises.
+///
+/// A `catch` clause binds the error value to a name and evaluates a series of
+/// expressions, yielding the value of the last expression. A `finally` clause
+/// evaluates a series of expressions after the expression and any `catch`
+/// clause, whether or not an error was raised.
+///
+/// ```lisp
+/// (try (/ 1 0)
+///   (catch e (println "error: {}" e) 0)
+///   (finally (println "done")))
+/// ```
+fn op_try(compiler: &mut Compiler, args: &[Value]) -> Result<(), Error> {
+	use crate::name::standard_names::{CATCH, FINALLY};
+
+	let (body, clauses) = args.split_first().unwrap();
+	let mut catch = None;
+	let mut finally = None;
+
+	for clause in clauses {
+		match *clause {
+			Value::List(ref li)
+				if catch.is_none() && finally.is_none() && matches!(li[0], Value::Name(CATCH)) =>
+			{
+				if li.len() < 3 {
+					compiler.set_trace_expr(clause);
+					return Err(From::from(CompileError::SyntaxError(
+						"expected `(catch name expr ...)`",
+					)));
+				}
+
+				catch = Some((get_name(compiler, &li[1])?, &li[2..]));
+			}
+			Value::List(ref li) if finally.is_none() && matches!(li[0], Value::Name(FINALLY)) => {
+				finally = Some(&li[1..]);
+			}
+			ref v => {
+				compiler.set_trace_expr(v);
+				return Err(From::from(CompileError::SyntaxError(
+					"expected `catch` or `finally` clause",
+				)));
+			}
+		}
+	}
+
+	match finally {
+		Some(finally) => {
+			let body_block = compiler.new_block();
+			let error_block = compiler.new_block();
+			let final_block = compiler.new_block();
+
+			compiler
+				.current_block()
+				.jump_to(JumpInstruction::PushHandler, error_block);
+
+			compiler.use_next(body_block);
+			compiler.try_depth += 1;
+			let r = compile_try_catch(compiler, body, catch);
+			compiler.try_depth -= 1;
+			r?;
+			compiler.push_instruction(Instruction::PopHandler)?;
+			compile_finally(compiler, finally, false)?;
+			compiler
+				.current_block()
+				.jump_to(JumpInstruction::Jump, final_block);
+
+			compiler.use_next(error_block);
+			compile_finally(compiler, finally, true)?;
+
+			compiler.use_next(final_block);
+			Ok(())
+		}
+		None => compile_try_catch(compiler, body, catch),
+	}
+}
+
+/// Compiles the body of a `try` expression and its `catch` clause, if present.
+fn compile_try_catch(
+	compiler: &mut Compiler,
+	body: &Value,
+	catch: Option<(Name, &[Value])>,
+) -> Result<(), Error> {
+	let (name, handler) = match catch {
+		Some(catch) => catch,
+		None => return compiler.compile_value(body),
+	};
+
+	let body_block = compiler.new_block();
+	let catch_block = compiler.new_block();
+	let final_block = compiler.new_block();
+
+	compiler
+		.current_block()
+		.jump_to(JumpInstruction::PushHandler, catch_block);
+
+	compiler.use_next(body_block);
+	compile_try_body(compiler, body)?;
+	compiler.push_instruction(Instruction::PopHandler)?;
+	compiler
+		.current_block()
+		.jump_to(JumpInstruction::Jump, final_block);
+
+	// Error value is loaded when execution resumes here
+	compiler.use_next(catch_block);
+	compiler.push_var(name);
+	compiler.push_instruction(Instruction::Push)?;
+
+	for v in handler {
+		compiler.compile_value(v)?;
+	}
+
+	// As in `let`, a separate block permits a tail call in the handler.
+	let skip_block = compiler.new_block();
+	compiler.use_next(skip_block);
+
+	compiler.push_instruction(Instruction::Skip(1))?;
+	compiler.pop_vars(1);
+
+	compiler.use_next(final_block);
+	Ok(())
+}
+
+/// Compiles the body of a `try` expression, deferring constant errors.
+fn compile_try_body(compiler: &mut Compiler, body: &Value) -> Result<(), Error> {
+	compiler.try_depth += 1;
+	let r = compiler.compile_value(body);
+	compiler.try_depth -= 1;
+	r
+}
+
+/// Compiles the body of a `finally` clause, preserving the value.
+/// If `raise` is `true`, the value is an error which is raised again.
+fn compile_finally(compiler: &mut Compiler, body: &[Value], raise: bool) -> Result<(), Error> {
+	let pos = compiler.stack_offset;
+	compiler.push_instruction(Instruction::Push)?;
+
+	for v in body {
+		compiler.compile_value(v)?;
+	}
+
+	compiler.push_instruction(Instruction::Load(pos))?;
+
+	if raise {
+		compiler.push_instruction(Instruction::Push)?;
+		compiler.write_call_sys(standard_names::RAISE, Exact(1), 1)?;
+	}
+
+	compiler.push_instruction(Instruction::Skip(1))?;
+	Ok(())
+}
+
 fn import_names(
 	mod_name: Name,
 	imps: &mut ImportSet,
@@ -2597,6 +3943,7 @@ fn make_lambda(
 	let mut params = Vec::new();
 	let mut req_params = 0;
 	let mut kw_params = Vec::new();
+	let mut patterns = Vec::new();
 	// Whether we've encountered `:key`
 	let mut key = false;
 	// Whether we've encountered `:optional`
@@ -2670,6 +4017,22 @@ fn make_lambda(
 				}
 				continue;
 			}
+			// A name followed by a constant is a misplaced default value,
+			// rather than a pattern matching a constant.
+			Value::List(ref li)
+				if !(key || optional) && li.len() == 2 && is_misplaced_default(li) =>
+			{
+				compiler.set_trace_expr(v);
+				return Err(From::from(CompileError::SyntaxError(
+					"default value before `:optional` or `:key`",
+				)));
+			}
+			// Required parameters may be destructured
+			Value::List(_) if !(key || optional) => {
+				patterns.push((params.len() as u32, v.clone()));
+				params.push((Name::dummy(), None));
+				continue;
+			}
 			Value::List(ref li) if li.len() == 2 => {
 				let name = get_name(compiler, &li[0])?;
 				(name, Some(li[1].clone()))
@@ -2690,13 +4053,6 @@ fn make_lambda(
 			return Err(From::from(CompileError::DuplicateParameter(name)));
 		}
 
-		if default.is_some() && !(key || optional) {
-			compiler.set_trace_expr(v);
-			return Err(From::from(CompileError::SyntaxError(
-				"default value before `:optional` or `:key`",
-			)));
-		}
-
 		if key {
 			kw_params.push((name, default));
 		} else {
@@ -2720,8 +4076,15 @@ fn make_lambda(
 		)));
 	}
 
-	let (mut code, captures) =
-		compile_lambda(compiler, name, params, req_params, kw_params, rest, body)?;
+	let params = LambdaParams {
+		params,
+		req_params,
+		kw_params,
+		rest,
+		patterns,
+	};
+
+	let (mut code, captures) = compile_lambda(compiler, name, params, body)?;
 
 	if let Some(doc) = doc {
 		code.flags |= code_flags::HAS_DOC_STRING;
@@ -2730,3 +4093,17 @@ fn make_lambda(
 
 	Ok((Lambda::new(Rc::new(code), compiler.scope()), captures))
 }
+
+/// Returns whether a two-element parameter list is a name with a default
+/// value which is not itself a name or pattern, e.g. `(a 1)`.
+///
+/// Such a list written before `:optional` or `:key` is reported as a
+/// misplaced default value rather than an invalid pattern. A list of two
+/// names, e.g. `(a b)`, is always treated as a pattern.
+fn is_misplaced_default(li: &[Value]) -> bool {
+	match (&li[0], &li[1]) {
+		(&Value::Name(_), &Value::Name(_)) | (&Value::Name(_), &Value::List(_)) => false,
+		(&Value::Name(_), _) => true,
+		_ => false,
+	}
+}
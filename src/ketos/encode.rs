@@ -17,11 +17,13 @@ use crate::exec::Context;
 use crate::function::Lambda;
 use crate::integer::{Integer, Ratio, Sign};
 use crate::io::{IoError, IoMode};
+use crate::map::Map;
 use crate::module::ModuleCode;
 use crate::name::{
 	Name, NameDisplay, NameInputConversion, NameMap, NameOutputConversion, NameSet, NameStore,
 };
 use crate::scope::ImportSet;
+use crate::set::Set;
 use crate::structs::{StructDef, StructValueDef};
 use crate::value::Value;
 
@@ -491,6 +493,29 @@ impl<'a, 'data> ValueDecoder<'a, 'data> {
 
 				Ok(v.into())
 			}
+			MAP => {
+				let n = self.read_len()?;
+				let mut m = Map::new();
+
+				for _ in 0..n {
+					let k = self.read_value(names)?;
+					let v = self.read_value(names)?;
+					m.insert(k, v);
+				}
+
+				Ok(m.into())
+			}
+			SET => {
+				let n = self.read_len()?;
+				let mut v = Vec::with_capacity(n);
+
+				for _ in 0..n {
+					v.push(self.read_value(names)?);
+				}
+
+				// Names may be ordered differently once decoded, so the set is re-sorted.
+				Ok(Set::from_values(v).into())
+			}
 			LAMBDA => {
 				let code = self.read_code(names)?;
 				Ok(Value::Lambda(Lambda::new(Rc::new(code), self.ctx.scope())))
@@ -821,6 +846,23 @@ impl ValueEncoder {
 					self.write_value(v, names)?;
 				}
 			}
+			Value::Map(ref m) => {
+				self.write_u8(MAP);
+				self.write_len(m.len())?;
+
+				for (k, v) in m {
+					self.write_value(k, names)?;
+					self.write_value(v, names)?;
+				}
+			}
+			Value::Set(ref s) => {
+				self.write_u8(SET);
+				self.write_len(s.len())?;
+
+				for v in s {
+					self.write_value(v, names)?;
+				}
+			}
 			Value::Lambda(ref l) => {
 				if l.values.is_some() {
 					return Err(EncodeError::UnencodableValue("lambda with enclosed values"));
@@ -828,6 +870,8 @@ impl ValueEncoder {
 				self.write_u8(LAMBDA);
 				self.write_code(&l.code, names)?;
 			}
+			// A cell's identity and mutable state cannot be preserved
+			Value::Ref(_) => return Err(EncodeError::UnencodableType("ref")),
 			Value::Foreign(_) => return Err(EncodeError::UnencodableType("foreign value")),
 			ref v => return Err(EncodeError::UnencodableType(v.type_name())),
 		}
@@ -1013,4 +1057,6 @@ types! {
 	QUOTE_ONE = 25,
 	LIST = 26,
 	LAMBDA = 27,
+	MAP = 28,
+	SET = 29,
 }
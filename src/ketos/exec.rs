@@ -23,12 +23,12 @@
 //! returns a value, which is available to the calling function through the
 //! value register.
 
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
 use std::error::Error as StdError;
 use std::fmt;
 use std::mem::replace;
 use std::rc::Rc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use std::vec::Drain;
 
 use crate::bytecode::{Code, CodeReader};
@@ -43,11 +43,15 @@ use crate::restrict::{RestrictConfig, RestrictError};
 use crate::scope::{MasterScope, Scope};
 use crate::string_fmt::FormatError;
 use crate::trace::{set_traceback, Trace, TraceItem};
-use crate::value::{FromValueRef, Value};
+use crate::value::{ForeignValue, FromValueRef, Value};
 
 /// Interval, in instructions run, between checking time limit
 const TIME_CHECK_INTERVAL: u32 = 100;
 
+/// Number of calls counted toward the call stack limit by each nested
+/// level of execution
+const RUN_LEVEL_CALLS: usize = 8;
+
 /// Represents an execution context
 #[derive(Clone)]
 pub struct Context {
@@ -84,8 +88,14 @@ impl Context {
 	}
 
 	/// Creates a new execution context with the given scope.
+	///
+	/// Execution start time and memory held are carried along, so that
+	/// restrictions apply to any code executed within the new context.
 	pub fn with_scope(&self, scope: Scope) -> Context {
-		Context::new(scope, self.restrict.clone())
+		Context {
+			scope,
+			..self.clone()
+		}
 	}
 
 	/// Returns a reference to the contained restriction configuration.
@@ -101,16 +111,70 @@ impl Context {
 		}
 	}
 
-	fn inc_run_level(&self) {
+	/// Enters a new level of execution, returning an error if this would
+	/// exceed the call stack limit.
+	///
+	/// Levels are nested when a system function, e.g. `map` or `eval`,
+	/// calls back into the virtual machine. Each uses far more native stack
+	/// than a call within the machine, so it counts as `RUN_LEVEL_CALLS` calls.
+	fn inc_run_level(&self) -> Result<(), RestrictError> {
 		let n = self.run_level.get();
+		if (n as usize).saturating_mul(RUN_LEVEL_CALLS) >= self.restrict.call_stack_size {
+			return Err(RestrictError::CallStackExceeded);
+		}
 		self.run_level.set(n + 1);
 		if n == 0 {
 			self.run_start.set(Some(Instant::now()));
 		}
+		Ok(())
+	}
+
+	/// Returns an error if the execution time limit has been exceeded.
+	///
+	/// System functions which call back into the VM in a loop should call
+	/// this on each iteration, as each call starts a new instruction count.
+	pub(crate) fn check_time(&self) -> Result<(), RestrictError> {
+		if let (Some(time_limit), Some(start)) =
+			(self.restrict.execution_time, self.run_start.get())
+		{
+			if start.elapsed() >= time_limit {
+				return Err(RestrictError::ExecutionTimeExceeded);
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Returns the time remaining before the execution time limit is
+	/// exceeded, or `None` if there is no limit.
+	pub(crate) fn time_remaining(&self) -> Option<Duration> {
+		match (self.restrict.execution_time, self.run_start.get()) {
+			(Some(time_limit), Some(start)) => Some(time_limit.saturating_sub(start.elapsed())),
+			_ => None,
+		}
+	}
+
+	/// Returns an error if holding an additional value of size `n`
+	/// would exceed the memory limit.
+	pub(crate) fn check_memory(&self, n: usize) -> Result<(), RestrictError> {
+		if self.memory_held.get().saturating_add(n) > self.restrict.memory_limit {
+			Err(RestrictError::MemoryLimitExceeded)
+		} else {
+			Ok(())
+		}
 	}
 
-	fn start_time(&self) -> Instant {
-		self.run_start.get().expect("context missing start time")
+	/// Adjusts memory held when a value of size `old` is replaced with
+	/// a value of size `new` outside the value stack; e.g. in a `Ref`.
+	pub(crate) fn replace_memory(&self, old: usize, new: usize) -> Result<(), RestrictError> {
+		let total = self.set_memory(|m| m.saturating_sub(old).saturating_add(new));
+
+		if total > self.restrict.memory_limit {
+			self.set_memory(|m| m.saturating_sub(new).saturating_add(old));
+			Err(RestrictError::MemoryLimitExceeded)
+		} else {
+			Ok(())
+		}
 	}
 
 	fn set_memory<F>(&self, f: F) -> usize
@@ -150,6 +214,10 @@ pub enum ExecError {
 	DuplicateKeyword(Name),
 	/// Duplicate struct definition
 	DuplicateStructDef(Name),
+	/// Code requested that the program exit with the given status code.
+	///
+	/// This error is not caught by `try`.
+	Exit(i32),
 	/// No such field name in struct
 	FieldError {
 		/// Name of struct type
@@ -185,6 +253,8 @@ pub enum ExecError {
 	InvalidConst(u32),
 	/// Invalid (zero) depth value to `Quote`, `Quasiquote`, or `Comma` instruction
 	InvalidDepth,
+	/// `PopHandler` instruction without a corresponding `PushHandler`
+	InvalidHandler,
 	/// Invalid jump label
 	InvalidJump(u32),
 	/// Slice indices out of order
@@ -193,6 +263,8 @@ pub enum ExecError {
 	InvalidStack(u32),
 	/// Invalid system function
 	InvalidSystemFn(u32),
+	/// Attempt to access a key that is not present in a map
+	KeyError(Value),
 	/// `CallSys` instruction for system function which requires argument count
 	MissingArgCount(Name),
 	/// Attempt to construct a `Struct` without the given field
@@ -204,16 +276,27 @@ pub enum ExecError {
 	},
 	/// Attempt to lookup a name that did not exist in scope.
 	NameError(Name),
+	/// Value matched none of the patterns of a `match` expression
+	NoMatch(Value),
 	/// Attempt to slice a string not along UTF-8 code point boundaries.
 	NotCharBoundary(usize),
 	/// Odd number of parameters when keyword-value pairs expected
 	OddKeywordParams,
+	/// Odd number of parameters when key-value pairs expected
+	OddMapParams,
 	/// Attempt to access an element in a list that is out of bounds.
 	OutOfBounds(usize),
 	/// Integer overflow during certain arithmetic operations.
 	Overflow,
 	/// Code called `panic`
 	Panic(Option<Value>),
+	/// Value could not be destructured by a binding pattern
+	PatternError {
+		/// Binding pattern
+		pattern: Box<Value>,
+		/// Value received
+		value: Value,
+	},
 	/// Struct definition not found
 	StructDefError(Name),
 	/// Operation performed on unexpected type
@@ -274,6 +357,70 @@ where
 	From::from(ExecError::Panic(None))
 }
 
+/// Represents an error caught by a `try` expression.
+///
+/// Errors raised with a value, by `panic` or `raise`, are caught as that value.
+/// Any other error is caught as a value of this type, which may be inspected
+/// or raised again by passing it to `raise`.
+pub struct CaughtError {
+	error: RefCell<Option<Error>>,
+	description: &'static str,
+	message: String,
+}
+
+impl CaughtError {
+	/// Creates a new `CaughtError` wrapping the given error.
+	///
+	/// `names` is used to format a message describing the error.
+	pub fn new(error: Error, names: &NameStore) -> CaughtError {
+		CaughtError {
+			description: error.description(),
+			message: display_names(names, &error).to_string(),
+			error: RefCell::new(Some(error)),
+		}
+	}
+
+	/// Returns a string describing the nature of the error.
+	pub fn description(&self) -> &'static str {
+		self.description
+	}
+
+	/// Returns a message describing the error.
+	pub fn message(&self) -> &str {
+		&self.message
+	}
+
+	/// Takes the original error so that it may be raised again.
+	///
+	/// Returns `None` if the error has already been taken.
+	pub fn take_error(&self) -> Option<Error> {
+		self.error.borrow_mut().take()
+	}
+}
+
+impl fmt::Debug for CaughtError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("CaughtError")
+			.field("description", &self.description)
+			.field("message", &self.message)
+			.finish()
+	}
+}
+
+impl ForeignValue for CaughtError {
+	fn fmt_debug(&self, _names: &NameStore, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "<error: {}: {}>", self.description, self.message)
+	}
+
+	fn fmt_display(&self, _names: &NameStore, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(&self.message)
+	}
+
+	fn type_name(&self) -> &'static str {
+		"error"
+	}
+}
+
 impl ExecError {
 	/// Convenience function to return a `TypeError` value when `expected`
 	/// type is expected, but some other type of value is found.
@@ -310,6 +457,7 @@ impl fmt::Display for ExecError {
 			CannotDefine(_) => f.write_str("cannot define name of standard value or operator"),
 			CompareNaN => f.write_str("attempt to compare NaN value"),
 			DivideByZero => f.write_str("attempt to divide by zero"),
+			Exit(code) => write!(f, "exit with status {}", code),
 			DuplicateField(_) => f.write_str("duplicate field"),
 			DuplicateKeyword(_) => f.write_str("duplicate keyword"),
 			DuplicateStructDef(_) => f.write_str("duplicate struct definition"),
@@ -319,19 +467,24 @@ impl fmt::Display for ExecError {
 			InvalidClosureValue(n) => write!(f, "invalid closure value: {}", n),
 			InvalidConst(n) => write!(f, "invalid const: {}", n),
 			InvalidDepth => f.write_str("invalid depth operand"),
+			InvalidHandler => f.write_str("no error handler to remove"),
 			InvalidJump(label) => write!(f, "invalid jump label: {}", label),
 			InvalidSlice(begin, end) => write!(f, "invalid slice {}..{}", begin, end),
 			InvalidStack(n) => write!(f, "invalid stack index: {}", n),
 			InvalidSystemFn(n) => write!(f, "invalid system function: {}", n),
+			KeyError(_) => f.write_str("key not found in map"),
 			MissingArgCount(_) => write!(f, "system function requires argument count"),
 			MissingField { .. } => f.write_str("missing field in struct"),
 			NameError(_) => f.write_str("name not found in global scope"),
+			NoMatch(_) => f.write_str("no pattern matched value"),
 			StructDefError(_) => f.write_str("struct definition not found"),
 			NotCharBoundary(n) => write!(f, "index not on char boundary: {}", n),
 			OddKeywordParams => f.write_str("expected keyword-value pairs"),
+			OddMapParams => f.write_str("expected key-value pairs"),
 			OutOfBounds(n) => write!(f, "index out of bounds: {}", n),
 			Overflow => f.write_str("integer overflow"),
 			Panic(_) => f.write_str("panic"),
+			PatternError { .. } => f.write_str("value does not match pattern"),
 			TypeError {
 				expected, found, ..
 			} => write!(f, "type error: expected {}; found {}", expected, found),
@@ -419,10 +572,22 @@ impl NameDisplay for ExecError {
 				names.get(field),
 				names.get(struct_name)
 			),
+			KeyError(ref key) | NoMatch(ref key) => {
+				write!(f, "{}: {}", self, debug_names(names, key))
+			}
 			Panic(ref value) => match *value {
 				Some(ref v) => write!(f, "panic: {}", display_names(names, v)),
 				None => f.write_str("explicit panic"),
 			},
+			PatternError {
+				ref pattern,
+				ref value,
+			} => write!(
+				f,
+				"value does not match pattern `{}`: {}",
+				display_names(names, pattern),
+				debug_names(names, value)
+			),
 			StructMismatch { lhs, rhs } => write!(
 				f,
 				"struct type mismatch: `{}` and `{}`",
@@ -518,10 +683,21 @@ struct StackFrame {
 	fn_on_stack: bool,
 }
 
+/// Error handler installed by a `PushHandler` instruction
+struct Handler {
+	/// Length of call stack when the handler was installed
+	call_depth: usize,
+	/// Length of value stack when the handler was installed
+	stack_len: usize,
+	/// Instruction pointer at which execution resumes
+	label: u32,
+}
+
 struct Machine {
 	context: Context,
 	stack: Vec<Value>,
 	call_stack: Vec<StackFrame>,
+	handlers: Vec<Handler>,
 	value: Value,
 	sys_fn_call: Option<Name>,
 }
@@ -532,6 +708,7 @@ impl Machine {
 			context: ctx.clone(),
 			stack: Vec::with_capacity(ctx.restrict().value_stack_size),
 			call_stack: Vec::with_capacity(ctx.restrict().call_stack_size),
+			handlers: Vec::new(),
 			value: Value::Unit,
 			sys_fn_call: None,
 		}
@@ -626,7 +803,7 @@ impl Machine {
 	}
 
 	fn start(&mut self, mut frame: StackFrame) -> Result<Value, Error> {
-		self.context.inc_run_level();
+		self.context.inc_run_level()?;
 
 		let res = self.run(&mut frame);
 
@@ -642,6 +819,15 @@ impl Machine {
 	}
 
 	fn run(&mut self, frame: &mut StackFrame) -> Result<(), Error> {
+		loop {
+			match self.run_code(frame) {
+				Ok(()) => return Ok(()),
+				Err(e) => self.handle_error(frame, e)?,
+			}
+		}
+	}
+
+	fn run_code(&mut self, frame: &mut StackFrame) -> Result<(), Error> {
 		use crate::bytecode::Instruction::*;
 
 		let mut n_instructions = 0;
@@ -728,26 +914,105 @@ impl Machine {
 				TailApplySelf(n) => self.tail_apply_self(frame, n)?,
 				CallSelf(n) => self.call_self(frame, n)?,
 				TailCallSelf(n) => self.tail_call(frame, n)?,
+				TailCall(n) => {
+					if !self.tail_call_function(frame, n)? {
+						break;
+					}
+				}
+				TailCallConst(n, n_args) => {
+					if !self.tail_call_const(frame, n, n_args)? {
+						break;
+					}
+				}
+				TailApply(n) => {
+					if !self.tail_apply(frame, n)? {
+						break;
+					}
+				}
+				TailApplyConst(n, n_args) => {
+					if !self.tail_apply_const(frame, n, n_args)? {
+						break;
+					}
+				}
 				Skip(n) => self.skip_stack(n as usize)?,
 				Return => {
-					match self.call_stack.pop() {
-						None => break,
-						Some(call) => {
-							self.clean_stack(frame.sptr as usize);
-							if frame.fn_on_stack {
-								// Pop one more value for the function
-								self.pop()?;
-							}
-							*frame = call;
-						}
+					if !self.return_value(frame)? {
+						break;
 					}
 				}
+				PushHandler(label) => self.push_handler(frame, label)?,
+				PopHandler => self.pop_handler()?,
+				NoMatch => return Err(From::from(ExecError::NoMatch(self.value.take()))),
+				PatternError(n) => {
+					let pattern = Box::new(get_const(&frame.code, n)?.clone());
+					let value = self.value.take();
+					return Err(From::from(ExecError::PatternError { pattern, value }));
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Resumes execution at the most recently installed error handler,
+	/// with the error value loaded into value.
+	///
+	/// If no handler is installed or the error is a breach of restrictions
+	/// or a request to exit, the error is returned.
+	fn handle_error(&mut self, frame: &mut StackFrame, e: Error) -> Result<(), Error> {
+		if let Error::RestrictError(_) | Error::ExecError(ExecError::Exit(_)) = e {
+			return Err(e);
+		}
+
+		let handler = match self.handlers.pop() {
+			Some(h) => h,
+			None => return Err(e),
+		};
+
+		if handler.call_depth < self.call_stack.len() {
+			if let Some(f) = self.call_stack.drain(handler.call_depth..).next() {
+				*frame = f;
+			}
+		}
+
+		if handler.stack_len <= self.stack.len() {
+			self.clean_stack(handler.stack_len);
+		}
+
+		self.sys_fn_call = None;
+		self.value = match e {
+			Error::ExecError(ExecError::Panic(v)) => v.unwrap_or(Value::Unit),
+			e => {
+				let names = self.context.scope().borrow_names();
+				Value::Foreign(Rc::new(CaughtError::new(e, &names)))
 			}
+		};
+		frame.iptr = handler.label;
+
+		Ok(())
+	}
+
+	fn push_handler(&mut self, frame: &StackFrame, label: u32) -> Result<(), ExecError> {
+		if label as usize >= frame.code.code.len() {
+			return Err(ExecError::InvalidJump(label));
 		}
 
+		self.handlers.push(Handler {
+			call_depth: self.call_stack.len(),
+			stack_len: self.stack.len(),
+			label,
+		});
+
 		Ok(())
 	}
 
+	fn pop_handler(&mut self) -> Result<(), ExecError> {
+		match self.handlers.pop() {
+			Some(ref h) if h.call_depth == self.call_stack.len() => Ok(()),
+			_ => Err(ExecError::InvalidHandler),
+		}
+	}
+
 	fn build_closure(&mut self, code: &Code, n_const: u32, n_values: u32) -> Result<(), ExecError> {
 		let (code, scope) = match *get_const(code, n_const)? {
 			Value::Lambda(ref l) => (l.code.clone(), l.scope.clone()),
@@ -777,15 +1042,7 @@ impl Machine {
 	}
 
 	fn check_time(&self) -> Result<(), RestrictError> {
-		if let Some(time_limit) = self.context.restrict().execution_time {
-			let start = self.context.start_time();
-
-			if start.elapsed() >= time_limit {
-				return Err(RestrictError::ExecutionTimeExceeded);
-			}
-		}
-
-		Ok(())
+		self.context.check_time()
 	}
 
 	fn get_sys_fn(&self, n: u32) -> Result<(Name, &'static SystemFn), ExecError> {
@@ -841,8 +1098,10 @@ impl Machine {
 
 			let ctx = self.context.with_scope(frame.scope.clone());
 
-			let v = (sys_fn.callback)(&ctx, &mut args)?;
-			self.value = v;
+			let r = (sys_fn.callback)(&ctx, &mut args);
+			// Carry back memory accounted by the function; e.g. `set-ref!`
+			self.context.memory_held.set(ctx.memory_held.get());
+			self.value = r?;
 
 			self.sys_fn_call = None;
 
@@ -894,6 +1153,124 @@ impl Machine {
 		}
 	}
 
+	/// Returns from the current function to its caller.
+	///
+	/// Returns `Ok(false)` if there is no caller and execution should end.
+	fn return_value(&mut self, frame: &mut StackFrame) -> Result<bool, Error> {
+		match self.call_stack.pop() {
+			None => Ok(false),
+			Some(call) => {
+				self.clean_stack(frame.sptr as usize);
+				if frame.fn_on_stack {
+					// Pop one more value for the function
+					self.pop()?;
+				}
+				*frame = call;
+				Ok(true)
+			}
+		}
+	}
+
+	/// Performs a tail call to a function on the stack with `n_args` arguments.
+	///
+	/// Returns `Ok(false)` if execution should end; see `tail_call_value`.
+	fn tail_call_function(&mut self, frame: &mut StackFrame, n_args: u32) -> Result<bool, Error> {
+		let v = self.get_stack_top(n_args)?.clone();
+		self.tail_call_value(frame, v, n_args, true)
+	}
+
+	fn tail_call_const(
+		&mut self,
+		frame: &mut StackFrame,
+		n: u32,
+		n_args: u32,
+	) -> Result<bool, Error> {
+		let name = get_const_name(&frame.code, n)?;
+		let v = self.get_value(frame, name)?;
+
+		self.value = Value::Unit;
+		self.tail_call_value(frame, v, n_args, false)
+	}
+
+	fn tail_apply(&mut self, frame: &mut StackFrame, n_args: u32) -> Result<bool, Error> {
+		let n = self.expand_value()?;
+
+		self.tail_call_function(frame, n_args + n as u32)
+	}
+
+	fn tail_apply_const(
+		&mut self,
+		frame: &mut StackFrame,
+		n: u32,
+		n_args: u32,
+	) -> Result<bool, Error> {
+		let n_push = self.expand_value()?;
+
+		self.tail_call_const(frame, n, n_args + n_push as u32)
+	}
+
+	/// Calls a value in tail position.
+	///
+	/// A lambda replaces the current stack frame. Any other callable value is
+	/// called as usual, after which the current function returns.
+	///
+	/// Returns `Ok(false)` if there is no caller and execution should end.
+	fn tail_call_value(
+		&mut self,
+		frame: &mut StackFrame,
+		value: Value,
+		n_args: u32,
+		fn_on_stack: bool,
+	) -> Result<bool, Error> {
+		match value {
+			Value::Lambda(fun) => {
+				self.tail_call_lambda(frame, fun, n_args, fn_on_stack)?;
+				Ok(true)
+			}
+			v => {
+				self.call_value(frame, v, n_args, fn_on_stack)?;
+				self.return_value(frame)
+			}
+		}
+	}
+
+	fn tail_call_lambda(
+		&mut self,
+		frame: &mut StackFrame,
+		lambda: Lambda,
+		n_args: u32,
+		fn_on_stack: bool,
+	) -> Result<(), Error> {
+		let scope = lambda
+			.scope
+			.upgrade()
+			.expect("Lambda scope has been destroyed");
+
+		let n_fn = if fn_on_stack { 1 } else { 0 };
+
+		if self.stack.len() < (frame.sptr + n_args + n_fn) as usize {
+			return Err(From::from(ExecError::InvalidStack(self.stack.len() as u32)));
+		}
+
+		let n_args = self.setup_call(&lambda.code, n_args)?;
+
+		// Remove the values of the current frame, retaining only arguments
+		let start = frame.sptr as usize;
+		let end = self.stack.len() - n_args as usize;
+		self.drain_stack(start, end);
+
+		*frame = StackFrame {
+			code: lambda.code,
+			scope,
+			values: lambda.values,
+			iptr: 0,
+			sptr: frame.sptr,
+			fn_on_stack: frame.fn_on_stack,
+		};
+
+		Ok(())
+	}
+
 	fn call_lambda(
 		&mut self,
 		frame: &mut StackFrame,
@@ -1060,10 +1437,7 @@ impl Machine {
 		let start = frame.sptr as usize;
 		let end = len - n_args as usize;
 
-		let n = self.stack[start..end].iter().map(|v| v.size()).sum();
-		self.context.set_memory(|m| m.saturating_sub(n));
-
-		let _ = self.stack.drain(start..end);
+		self.drain_stack(start, end);
 		frame.iptr = 0;
 
 		self.setup_call(&frame.code, n_args)?;
@@ -1074,10 +1448,16 @@ impl Machine {
 	/// Cleans the stack when returning from a function.
 	/// All values `stack[pos..]` are removed.
 	fn clean_stack(&mut self, pos: usize) {
-		let n = self.stack[pos..].iter().map(|v| v.size()).sum();
+		let len = self.stack.len();
+		self.drain_stack(pos, len);
+	}
+
+	/// Removes values `stack[start..end]`.
+	fn drain_stack(&mut self, start: usize, end: usize) {
+		let n = self.stack[start..end].iter().map(|v| v.size()).sum();
 		self.context.set_memory(|m| m.saturating_sub(n));
 
-		let _ = self.stack.drain(pos..);
+		let _ = self.stack.drain(start..end);
 	}
 
 	/// Removes the top `n` elements from the stack.
@@ -12,14 +12,16 @@ use num::{Float, Zero};
 use crate::bytecode::Code;
 use crate::bytes::Bytes;
 use crate::error::Error;
-use crate::exec::{Context, ExecError};
+use crate::exec::{call_function, CaughtError, Context, ExecError};
 use crate::integer::{Integer, Ratio};
+use crate::map::Map;
 use crate::name::{Name, NUM_SYSTEM_FNS};
+use crate::reference::Ref;
 use crate::restrict::RestrictError;
 use crate::scope::{Scope, WeakScope};
 use crate::string_fmt::format_string;
 use crate::structs::StructDef;
-use crate::value::{FromValueRef, Value};
+use crate::value::{FromValue, FromValueRef, Value};
 
 use self::Arity::*;
 
@@ -446,6 +448,111 @@ It accepts an optional parameter describing the reason for the panic."
 		Exact(1),
 		"Returns the inverse of the given boolean value."
 	),
+	sys_fn!(
+		fn_hash_map,
+		Min(0),
+		"    (hash-map key value ...)
+
+Returns a map containing the given key-value pairs."
+	),
+	sys_fn!(
+		fn_get,
+		Range(2, 3),
+		"    (get map key [default])
+
+Returns the value associated with a key in a map.
+If the key is not present, `default` is returned, if given;
+otherwise, an error is raised."
+	),
+	sys_fn!(
+		fn_assoc,
+		Min(3),
+		"    (assoc map key value ...)
+
+Returns a map with the given keys associated with new values."
+	),
+	sys_fn!(
+		fn_dissoc,
+		Min(1),
+		"    (dissoc map key ...)
+
+Returns a map with the given keys removed."
+	),
+	sys_fn!(
+		fn_keys,
+		Exact(1),
+		"Returns a list of the keys in a map, in order."
+	),
+	sys_fn!(
+		fn_values,
+		Exact(1),
+		"Returns a list of the values in a map, in key order."
+	),
+	sys_fn!(
+		fn_contains,
+		Exact(2),
+		"    (contains map key)
+    (contains set value)
+
+Returns whether a map contains the given key or a set contains the given value."
+	),
+	sys_fn!(
+		fn_raise,
+		Exact(1),
+		"Raises an error with the given value, which may be caught by `try`.
+
+If the value is an error caught by `try`, the original error is raised again."
+	),
+	sys_fn!(
+		fn_gensym,
+		Range(0, 1),
+		"    (gensym)
+    (gensym prefix)
+
+Returns a new name, distinct from all existing names.
+An optional prefix, a string or name, begins the new name."
+	),
+	sys_fn!(
+		fn_ref,
+		Exact(1),
+		"Returns a new mutable reference cell containing the given value."
+	),
+	sys_fn!(
+		fn_deref,
+		Exact(1),
+		"Returns the value contained in a reference cell."
+	),
+	sys_fn!(
+		fn_set_ref,
+		Exact(2),
+		"    (set-ref! ref value)
+
+Stores a value in a reference cell, returning the previous value."
+	),
+	sys_fn!(
+		fn_swap_ref,
+		Min(2),
+		"    (swap-ref! ref function arg ...)
+
+Stores in a reference cell the result of calling a function with the
+current value, followed by any additional arguments. Returns the new value."
+	),
+	sys_fn!(
+		fn_read_line,
+		Exact(0),
+		"Reads a line from `stdin`, without the trailing newline.
+Returns `()` at end of input."
+	),
+	sys_fn!(
+		fn_read_char,
+		Exact(0),
+		"Reads a character from `stdin`. Returns `()` at end of input."
+	),
+	sys_fn!(
+		fn_read_all,
+		Exact(0),
+		"Reads all remaining input from `stdin` into a string."
+	),
 ];
 
 /// Describes the number of arguments a function may accept.
@@ -903,7 +1010,7 @@ fn try_mul_assign(ctx: &Context, lhs: &mut Integer, rhs: &Integer) -> Result<(),
 	Ok(())
 }
 
-fn check_bits(ctx: &Context, bits: usize) -> Result<(), RestrictError> {
+pub(crate) fn check_bits(ctx: &Context, bits: usize) -> Result<(), RestrictError> {
 	if bits > ctx.restrict().max_integer_size {
 		Err(RestrictError::IntegerLimitExceeded)
 	} else {
@@ -1396,6 +1503,110 @@ fn fn_not(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
 	}
 }
 
+/// `hash-map` returns a map containing the given key-value pairs.
+///
+/// ```lisp
+/// (hash-map :a 1 :b 2)
+/// ```
+fn fn_hash_map(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let mut map = Map::new();
+
+	assoc_pairs(&mut map, args)?;
+
+	Ok(map.into())
+}
+
+/// `get` returns the value associated with a key in a map.
+///
+/// ```lisp
+/// (get m :a)
+/// (get m :a 0)
+/// ```
+fn fn_get(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let map = <&Map>::from_value_ref(&args[0])?;
+
+	match map.get(&args[1]) {
+		Some(v) => Ok(v.clone()),
+		None if args.len() == 3 => Ok(args[2].take()),
+		None => Err(From::from(ExecError::KeyError(args[1].take()))),
+	}
+}
+
+/// `assoc` returns a map with the given keys associated with new values.
+///
+/// ```lisp
+/// (assoc m :a 1 :b 2)
+/// ```
+fn fn_assoc(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let mut map = get_map(args[0].take())?;
+
+	assoc_pairs(&mut map, &mut args[1..])?;
+
+	Ok(map.into())
+}
+
+/// `dissoc` returns a map with the given keys removed.
+///
+/// ```lisp
+/// (dissoc m :a :b)
+/// ```
+fn fn_dissoc(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let mut map = get_map(args[0].take())?;
+
+	for key in &args[1..] {
+		map.remove(key);
+	}
+
+	Ok(map.into())
+}
+
+/// `keys` returns a list of the keys in a map.
+fn fn_keys(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let map = <&Map>::from_value_ref(&args[0])?;
+
+	Ok(map.keys().cloned().collect::<Vec<_>>().into())
+}
+
+/// `values` returns a list of the values in a map.
+fn fn_values(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let map = <&Map>::from_value_ref(&args[0])?;
+
+	Ok(map.values().cloned().collect::<Vec<_>>().into())
+}
+
+/// `contains` returns whether a map contains the given key
+/// or a set contains the given value.
+fn fn_contains(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let r = match args[0] {
+		Value::Map(ref m) => m.contains_key(&args[1]),
+		Value::Set(ref s) => s.contains(&args[1]),
+		ref v => return Err(From::from(ExecError::expected("map or set", v))),
+	};
+
+	Ok(r.into())
+}
+
+fn get_map(v: Value) -> Result<Map, ExecError> {
+	match v {
+		Value::Map(m) => Ok(m),
+		ref v => Err(ExecError::expected("map", v)),
+	}
+}
+
+fn assoc_pairs(map: &mut Map, args: &mut [Value]) -> Result<(), ExecError> {
+	if args.len() % 2 == 1 {
+		return Err(ExecError::OddMapParams);
+	}
+
+	for pair in args.chunks_mut(2) {
+		let key = pair[0].take();
+		let value = pair[1].take();
+		map.insert(key, value);
+	}
+
+	Ok(())
+}
+
 /// `id` returns the unmodified value of the argument received.
 fn fn_id(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
 	Ok(args[0].take())
@@ -1435,7 +1646,7 @@ fn fn_null(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
 	Ok(is_null.into())
 }
 
-fn type_of(scope: &Scope, v: &Value) -> Name {
+pub(crate) fn type_of(scope: &Scope, v: &Value) -> Name {
 	use crate::name::standard_names::*;
 
 	match *v {
@@ -1456,6 +1667,9 @@ fn type_of(scope: &Scope, v: &Value) -> Name {
 		Value::Bytes(_) => BYTES,
 		Value::Path(_) => PATH,
 		Value::List(_) => LIST,
+		Value::Map(_) => MAP,
+		Value::Set(_) => SET,
+		Value::Ref(_) => REF,
 		Value::Function(_) => FUNCTION,
 		Value::Lambda(_) => LAMBDA,
 		Value::Quasiquote(_, _)
@@ -1834,6 +2048,8 @@ fn fn_len(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
 		Value::List(ref li) => li.len(),
 		Value::String(ref s) => s.len(),
 		Value::Bytes(ref b) => b.len(),
+		Value::Map(ref m) => m.len(),
+		Value::Set(ref s) => s.len(),
 		ref v => return Err(From::from(ExecError::expected("sequence", v))),
 	};
 
@@ -2354,3 +2570,167 @@ fn fn_panic(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
 		args.get_mut(0).map(|v| v.take()),
 	)))
 }
+
+/// `raise` raises an error with the given value.
+/// An error value caught by `try` is raised as the original error.
+///
+/// ```lisp
+/// (try (raise 'oops)
+///   (catch e (println "caught {}" e)))
+/// ```
+fn fn_raise(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let v = args[0].take();
+
+	if let Value::Foreign(ref fv) = v {
+		if let Some(e) = fv
+			.downcast_ref::<CaughtError>()
+			.and_then(|e| e.take_error())
+		{
+			return Err(e);
+		}
+	}
+
+	Err(From::from(ExecError::Panic(Some(v))))
+}
+
+/// `gensym` returns a new name, distinct from all existing names.
+///
+/// ```lisp
+/// (gensym)        ; g#42
+/// (gensym "tmp")  ; tmp#43
+/// ```
+fn fn_gensym(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let mut names = ctx.scope().borrow_names_mut();
+
+	let prefix = match args.first() {
+		None => "g".to_owned(),
+		Some(Value::String(s)) => s.to_string(),
+		Some(&Value::Name(name)) => names.get(name).to_owned(),
+		Some(v) => return Err(From::from(ExecError::expected("string or name", v))),
+	};
+
+	Ok(Value::Name(names.add_unique(&prefix)))
+}
+
+/// `ref` returns a new mutable reference cell containing the given value.
+///
+/// ```lisp
+/// (define counter (ref 0))
+/// ```
+fn fn_ref(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	Ok(Value::Ref(Ref::new(args[0].take())))
+}
+
+/// `deref` returns the value contained in a reference cell.
+fn fn_deref(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let r = <&Ref>::from_value_ref(&args[0])?;
+	Ok(r.get())
+}
+
+/// `set-ref!` stores a value in a reference cell and returns the previous value.
+///
+/// ```lisp
+/// (set-ref! counter 1)
+/// ```
+fn fn_set_ref(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let v = args[1].take();
+	let r = <&Ref>::from_value_ref(&args[0])?;
+	store_ref(ctx, r, v)
+}
+
+/// `swap-ref!` stores the result of calling a function with the value
+/// of a reference cell, followed by any additional arguments.
+/// Returns the new value.
+///
+/// ```lisp
+/// (swap-ref! counter + 1)
+/// ```
+fn fn_swap_ref(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let r = Ref::from_value(args[0].take())?;
+	let f = args[1].take();
+
+	let mut fn_args = Vec::with_capacity(args.len() - 1);
+	fn_args.push(r.get());
+	fn_args.extend(args[2..].iter_mut().map(|v| v.take()));
+
+	let v = call_function(ctx, f, fn_args)?;
+	store_ref(ctx, &r, v.clone())?;
+	Ok(v)
+}
+
+/// Stores a value in a reference cell, accounting for the change in size of
+/// its contents against the memory limit. Returns the previous value.
+///
+/// The accounting is approximate: a ref is sized with its current contents,
+/// so a ref held in several stack slots releases the change once per slot
+/// when they are popped, and the size of a ref which contains itself is
+/// never released. Memory held is discarded when execution ends.
+fn store_ref(ctx: &Context, r: &Ref, v: Value) -> Result<Value, Error> {
+	let new_size = v.size();
+	let old = r.replace(v);
+
+	if let Err(e) = ctx.replace_memory(old.size(), new_size) {
+		r.replace(old);
+		return Err(From::from(e));
+	}
+
+	Ok(old)
+}
+
+/// `read-line` reads a line from `stdin`, without the trailing newline.
+/// Returns `()` at end of input.
+fn fn_read_line(ctx: &Context, _args: &mut [Value]) -> Result<Value, Error> {
+	let mut s = String::new();
+
+	if ctx.scope().io().stdin.read_line(&mut s)? == 0 {
+		return Ok(Value::Unit);
+	}
+
+	if s.ends_with('\n') {
+		s.pop();
+		if s.ends_with('\r') {
+			s.pop();
+		}
+	}
+
+	ctx.check_memory(s.len())?;
+	Ok(s.into())
+}
+
+/// `read-char` reads a character from `stdin`.
+/// Returns `()` at end of input.
+fn fn_read_char(ctx: &Context, _args: &mut [Value]) -> Result<Value, Error> {
+	Ok(ctx
+		.scope()
+		.io()
+		.stdin
+		.read_char()?
+		.map_or(Value::Unit, Value::Char))
+}
+
+/// `read-all` reads all remaining input from `stdin` into a string.
+fn fn_read_all(ctx: &Context, _args: &mut [Value]) -> Result<Value, Error> {
+	let mut s = String::new();
+
+	ctx.scope().io().stdin.read_to_string(&mut s)?;
+
+	ctx.check_memory(s.len())?;
+	Ok(s.into())
+}
+
+/// Calls a function on behalf of a system function which iterates over values.
+///
+/// The execution time limit is checked before each call, as each call
+/// begins a new count of executed instructions.
+pub(crate) fn call_callback(ctx: &Context, f: &Value, args: Vec<Value>) -> Result<Value, Error> {
+	ctx.check_time()?;
+	call_function(ctx, f.clone(), args)
+}
+
+/// Calls a function which is expected to return a `bool`.
+pub(crate) fn call_predicate(ctx: &Context, f: &Value, args: Vec<Value>) -> Result<bool, Error> {
+	match call_callback(ctx, f, args)? {
+		Value::Bool(b) => Ok(b),
+		ref v => Err(From::from(ExecError::expected("bool", v))),
+	}
+}
@@ -241,6 +241,68 @@ impl Integer {
 		Integer(num::pow(self.0, exp))
 	}
 
+	/// Returns `(self ^ exp) mod modulus`, with the sign of `modulus`.
+	///
+	/// # Panics
+	///
+	/// If `exp` is negative or `modulus` is zero.
+	#[inline]
+	pub fn modpow(&self, exp: &Integer, modulus: &Integer) -> Integer {
+		Integer(self.0.modpow(&exp.0, &modulus.0))
+	}
+
+	/// Returns the remainder of floored division, with the sign of `rhs`.
+	///
+	/// # Panics
+	///
+	/// If `rhs` is zero.
+	#[inline]
+	pub fn mod_floor(&self, rhs: &Integer) -> Integer {
+		Integer(self.0.mod_floor(&rhs.0))
+	}
+
+	/// Returns the greatest common divisor of two `Integer`s.
+	///
+	/// The result is always non-negative.
+	#[inline]
+	pub fn gcd(&self, rhs: &Integer) -> Integer {
+		Integer(self.0.gcd(&rhs.0))
+	}
+
+	/// Returns the least common multiple of two `Integer`s.
+	///
+	/// The result is always non-negative.
+	#[inline]
+	pub fn lcm(&self, rhs: &Integer) -> Integer {
+		Integer(self.0.lcm(&rhs.0))
+	}
+
+	/// Returns the truncated principal square root of an `Integer`.
+	///
+	/// # Panics
+	///
+	/// If the value is negative.
+	#[inline]
+	pub fn sqrt(&self) -> Integer {
+		Integer(self.0.sqrt())
+	}
+
+	/// Returns the truncated principal `n`th root of an `Integer`.
+	///
+	/// # Panics
+	///
+	/// If `n` is zero, or if `n` is even and the value is negative.
+	#[inline]
+	pub fn nth_root(&self, n: u32) -> Integer {
+		Integer(self.0.nth_root(n))
+	}
+
+	/// Returns the number of one bits in the absolute value of an `Integer`.
+	pub fn count_ones(&self) -> usize {
+		let (_, digits) = self.0.to_u32_digits();
+		digits.iter().map(|d| d.count_ones() as usize).sum()
+	}
+
 	/// Returns the absolute value of an `Integer`.
 	#[inline]
 	pub fn abs(&self) -> Integer {
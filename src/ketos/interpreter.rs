@@ -7,6 +7,7 @@ use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
 use crate::bytecode::Code;
+use crate::clock::Clock;
 use crate::compile::compile;
 use crate::error::Error;
 use crate::exec::{call_function, execute, Context, ExecError};
@@ -15,7 +16,7 @@ use crate::lexer::{CodeMap, Lexer};
 use crate::module::{BuiltinModuleLoader, FileModuleLoader, ModuleLoader, ModuleRegistry};
 use crate::name::{debug_names, display_names, NameStore};
 use crate::parser::{ParseError, Parser};
-use crate::restrict::RestrictConfig;
+use crate::restrict::{FsAccess, RestrictConfig};
 use crate::scope::{GlobalScope, Scope};
 use crate::structs::StructDefMap;
 use crate::trace::{get_traceback, take_traceback, Trace};
@@ -45,7 +46,11 @@ pub struct Builder {
 	context: Option<Context>,
 	scope: Option<Scope>,
 	restrict: Option<RestrictConfig>,
+	filesystem: Option<FsAccess>,
+	allow_process: Option<bool>,
 	io: Option<Rc<GlobalIo>>,
+	clock: Option<Rc<dyn Clock>>,
+	seed: Option<u64>,
 	struct_defs: Option<Rc<RefCell<StructDefMap>>>,
 	module_loader: Option<Box<dyn ModuleLoader>>,
 	search_paths: Option<Vec<PathBuf>>,
@@ -74,7 +79,11 @@ impl Builder {
 			context: None,
 			scope: None,
 			restrict: None,
+			filesystem: None,
+			allow_process: None,
 			io: None,
+			clock: None,
+			seed: None,
 			struct_defs: None,
 			module_loader: None,
 			search_paths: None,
@@ -95,7 +104,11 @@ impl Builder {
 		exclude!(self.name, "context", "name");
 		exclude!(self.scope, "context", "scope");
 		exclude!(self.restrict, "context", "restrict");
+		exclude!(self.filesystem, "context", "filesystem");
+		exclude!(self.allow_process, "context", "allow_process");
 		exclude!(self.io, "context", "io");
+		exclude!(self.clock, "context", "clock");
+		exclude!(self.seed, "context", "seed");
 		exclude!(self.module_loader, "context", "module_loader");
 		exclude!(self.search_paths, "context", "search_paths");
 
@@ -111,11 +124,36 @@ impl Builder {
 		self
 	}
 
+	/// Sets the filesystem access permitted to code in the new context.
+	///
+	/// This overrides the `filesystem` field of any `RestrictConfig` given to
+	/// `Builder::restrict`.
+	pub fn filesystem(mut self, access: FsAccess) -> Self {
+		exclude!(self.context, "filesystem", "context");
+
+		self.filesystem = Some(access);
+		self
+	}
+
+	/// Sets whether code in the new context may run external programs
+	/// using the `process` module.
+	///
+	/// This overrides the `allow_process` field of any `RestrictConfig` given to
+	/// `Builder::restrict`.
+	pub fn allow_process(mut self, allow: bool) -> Self {
+		exclude!(self.context, "allow_process", "context");
+
+		self.allow_process = Some(allow);
+		self
+	}
+
 	/// Sets the scope in the new context.
 	pub fn scope(mut self, scope: Scope) -> Self {
 		exclude!(self.name, "scope", "name");
 		exclude!(self.context, "scope", "context");
 		exclude!(self.io, "scope", "io");
+		exclude!(self.clock, "scope", "clock");
+		exclude!(self.seed, "scope", "seed");
 		exclude!(self.module_loader, "scope", "module_loader");
 		exclude!(self.search_paths, "scope", "search_paths");
 
@@ -141,6 +179,30 @@ impl Builder {
 		self
 	}
 
+	/// Sets the clock in the new scope.
+	///
+	/// The clock is used by functions in the `time` module.
+	/// By default, a `SystemClock` is used.
+	pub fn clock(mut self, clock: Rc<dyn Clock>) -> Self {
+		exclude!(self.context, "clock", "context");
+		exclude!(self.scope, "clock", "scope");
+
+		self.clock = Some(clock);
+		self
+	}
+
+	/// Seeds the random number generator in the new scope.
+	///
+	/// The generator is used by functions in the `random` module.
+	/// By default, it is seeded from the operating system.
+	pub fn seed(mut self, seed: u64) -> Self {
+		exclude!(self.context, "seed", "context");
+		exclude!(self.scope, "seed", "scope");
+
+		self.seed = Some(seed);
+		self
+	}
+
 	/// Sets the module loader in the new scope.
 	pub fn module_loader(mut self, loader: Box<dyn ModuleLoader>) -> Self {
 		exclude!(self.context, "module_loader", "context");
@@ -167,20 +229,26 @@ impl Builder {
 	}
 
 	fn build_context(mut self) -> Context {
-		match self {
-			Builder {
-				context: Some(ctx), ..
-			} => ctx,
-			Builder {
-				scope: Some(scope), ..
-			} => Context::new(
-				scope,
-				self.restrict.unwrap_or_else(RestrictConfig::permissive),
-			),
-			_ => Context::new(
-				self.build_scope(),
-				self.restrict.unwrap_or_else(RestrictConfig::permissive),
-			),
+		if let Some(ctx) = self.context.take() {
+			return ctx;
+		}
+
+		let mut restrict = self
+			.restrict
+			.take()
+			.unwrap_or_else(RestrictConfig::permissive);
+
+		if let Some(access) = self.filesystem.take() {
+			restrict.filesystem = access;
+		}
+
+		if let Some(allow) = self.allow_process.take() {
+			restrict.allow_process = allow;
+		}
+
+		match self.scope.take() {
+			Some(scope) => Context::new(scope, restrict),
+			None => Context::new(self.build_scope(), restrict),
 		}
 	}
 
@@ -202,7 +270,17 @@ impl Builder {
 			.take()
 			.unwrap_or_else(|| Rc::new(RefCell::new(StructDefMap::new())));
 
-		Rc::new(GlobalScope::new(name, names, codemap, modules, io, defs))
+		let mut scope = GlobalScope::new(name, names, codemap, modules, io, defs);
+
+		if let Some(clock) = self.clock.take() {
+			scope.set_clock(clock);
+		}
+
+		if let Some(seed) = self.seed.take() {
+			scope.seed_rng(seed);
+		}
+
+		Rc::new(scope)
 	}
 
 	fn build_loader(&mut self) -> Box<dyn ModuleLoader> {
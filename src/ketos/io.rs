@@ -1,15 +1,20 @@
 //! Creates an abstraction layer to I/O operations
 
+use std::cell::RefCell;
 use std::fmt::{self, Arguments};
 use std::fs;
-use std::io::{self, Stderr, Stdout, Write};
+use std::io::{self, BufRead, Read, Stderr, Stdin, Stdout, Write};
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::str;
 
 use crate::name::{NameDisplay, NameStore};
 
 /// Contains global shared I/O objects
 pub struct GlobalIo {
+	/// Shared standard input reader
+	pub stdin: Rc<dyn SharedRead>,
+
 	/// Shared standard output writer
 	pub stdout: Rc<dyn SharedWrite>,
 
@@ -20,20 +25,34 @@ pub struct GlobalIo {
 impl GlobalIo {
 	/// Creates a `GlobalIo` instance using the given `stdout` and `stderr`
 	/// writers.
+	///
+	/// The resulting instance provides no input; use `with_stdin`
+	/// to supply a reader.
 	pub fn new(stdout: Rc<dyn SharedWrite>, stderr: Rc<dyn SharedWrite>) -> GlobalIo {
-		GlobalIo { stdout, stderr }
+		GlobalIo {
+			stdin: Rc::new(Sink),
+			stdout,
+			stderr,
+		}
 	}
 
-	/// Creates a `GlobalIo` instance that ignores all output.
+	/// Creates a `GlobalIo` instance that provides no input
+	/// and ignores all output.
 	pub fn null() -> GlobalIo {
 		GlobalIo::new(Rc::new(Sink), Rc::new(Sink))
 	}
+
+	/// Replaces the `stdin` reader.
+	pub fn with_stdin(mut self, stdin: Rc<dyn SharedRead>) -> GlobalIo {
+		self.stdin = stdin;
+		self
+	}
 }
 
 impl Default for GlobalIo {
-	/// Creates a `GlobalIo` instance using `stdout`/`stderr` writers.
+	/// Creates a `GlobalIo` instance using `stdin`/`stdout`/`stderr` streams.
 	fn default() -> GlobalIo {
-		GlobalIo::new(Rc::new(io::stdout()), Rc::new(io::stderr()))
+		GlobalIo::new(Rc::new(io::stdout()), Rc::new(io::stderr())).with_stdin(Rc::new(io::stdin()))
 	}
 }
 
@@ -61,10 +80,16 @@ impl IoError {
 
 impl fmt::Display for IoError {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let kind = match self.mode {
+			IoMode::CreateDir | IoMode::ReadDir => "directory",
+			_ => "file",
+		};
+
 		write!(
 			f,
-			"failed to {} file `{}`: {}",
+			"failed to {} {} `{}`: {}",
 			self.mode,
+			kind,
 			self.path.display(),
 			self.err
 		)
@@ -82,10 +107,18 @@ impl NameDisplay for IoError {
 pub enum IoMode {
 	/// Creating a new file
 	Create,
+	/// Creating a new directory
+	CreateDir,
+	/// Executing a program
+	Execute,
 	/// Opening an existing file
 	Open,
 	/// Reading data from an open file
 	Read,
+	/// Reading the entries of a directory
+	ReadDir,
+	/// Removing a file or directory
+	Remove,
 	/// Accessing file metadata
 	Stat,
 	/// Writing data to an open file
@@ -96,8 +129,12 @@ impl fmt::Display for IoMode {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		f.write_str(match *self {
 			IoMode::Create => "create",
+			IoMode::CreateDir => "create",
+			IoMode::Execute => "execute",
 			IoMode::Open => "open",
 			IoMode::Read => "read",
+			IoMode::ReadDir => "read",
+			IoMode::Remove => "remove",
 			IoMode::Stat => "stat",
 			IoMode::Write => "write",
 		})
@@ -145,9 +182,118 @@ macro_rules! shared_write {
 shared_write! { Stdout => "<stdout>" }
 shared_write! { Stderr => "<stderr>" }
 
-/// A shared writer which sends all data into the void.
+/// A reader object that can operate using shared references.
+pub trait SharedRead {
+	/// Analogous to `std::io::BufRead::read_line`; appends a line of input,
+	/// including the trailing newline, if any, to `buf`.
+	///
+	/// Returns the number of bytes read; `0` indicates end of input.
+	fn read_line(&self, buf: &mut String) -> Result<usize, IoError>;
+
+	/// Analogous to `std::io::Read::read_to_string`; appends all remaining
+	/// input to `buf`.
+	fn read_to_string(&self, buf: &mut String) -> Result<usize, IoError>;
+
+	/// Reads a single UTF-8 encoded character.
+	///
+	/// Returns `None` at end of input.
+	fn read_char(&self) -> Result<Option<char>, IoError>;
+}
+
+macro_rules! shared_read {
+	( $ty:ty => $name:expr ) => {
+		impl SharedRead for $ty {
+			fn read_line(&self, buf: &mut String) -> Result<usize, IoError> {
+				let mut lock = self.lock();
+				BufRead::read_line(&mut lock, buf)
+					.map_err(|e| IoError::new(IoMode::Read, Path::new($name), e))
+			}
+
+			fn read_to_string(&self, buf: &mut String) -> Result<usize, IoError> {
+				let mut lock = self.lock();
+				Read::read_to_string(&mut lock, buf)
+					.map_err(|e| IoError::new(IoMode::Read, Path::new($name), e))
+			}
+
+			fn read_char(&self) -> Result<Option<char>, IoError> {
+				let mut lock = self.lock();
+				read_char(&mut lock).map_err(|e| IoError::new(IoMode::Read, Path::new($name), e))
+			}
+		}
+	};
+}
+
+shared_read! { Stdin => "<stdin>" }
+
+/// Reads from a buffer, such as an `io::Cursor`, held by the host.
+impl<R: BufRead> SharedRead for RefCell<R> {
+	fn read_line(&self, buf: &mut String) -> Result<usize, IoError> {
+		BufRead::read_line(&mut *self.borrow_mut(), buf)
+			.map_err(|e| IoError::new(IoMode::Read, Path::new("<stdin>"), e))
+	}
+
+	fn read_to_string(&self, buf: &mut String) -> Result<usize, IoError> {
+		Read::read_to_string(&mut *self.borrow_mut(), buf)
+			.map_err(|e| IoError::new(IoMode::Read, Path::new("<stdin>"), e))
+	}
+
+	fn read_char(&self) -> Result<Option<char>, IoError> {
+		read_char(&mut *self.borrow_mut())
+			.map_err(|e| IoError::new(IoMode::Read, Path::new("<stdin>"), e))
+	}
+}
+
+fn read_char<R: BufRead>(r: &mut R) -> io::Result<Option<char>> {
+	let first = match r.fill_buf()?.first() {
+		Some(&b) => b,
+		None => return Ok(None),
+	};
+
+	let width = match first {
+		0x00..=0x7f => 1,
+		0xc0..=0xdf => 2,
+		0xe0..=0xef => 3,
+		0xf0..=0xf7 => 4,
+		_ => 0,
+	};
+
+	let mut bytes = [0; 4];
+	let invalid = || {
+		io::Error::new(
+			io::ErrorKind::InvalidData,
+			"stream did not contain valid UTF-8",
+		)
+	};
+
+	if width == 0 {
+		r.consume(1);
+		return Err(invalid());
+	}
+
+	r.read_exact(&mut bytes[..width])?;
+
+	match str::from_utf8(&bytes[..width]) {
+		Ok(s) => Ok(s.chars().next()),
+		Err(_) => Err(invalid()),
+	}
+}
+
+/// A shared reader and writer which provides no input
+/// and sends all output into the void.
 pub struct Sink;
 
+impl SharedRead for Sink {
+	fn read_line(&self, _buf: &mut String) -> Result<usize, IoError> {
+		Ok(0)
+	}
+	fn read_to_string(&self, _buf: &mut String) -> Result<usize, IoError> {
+		Ok(0)
+	}
+	fn read_char(&self) -> Result<Option<char>, IoError> {
+		Ok(None)
+	}
+}
+
 impl SharedWrite for Sink {
 	fn write_all(&self, _buf: &[u8]) -> Result<(), IoError> {
 		Ok(())
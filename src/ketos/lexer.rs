@@ -13,6 +13,10 @@ pub enum Token<'lex> {
 	LeftParen,
 	/// Right parenthesis `)`
 	RightParen,
+	/// Left brace `{`
+	LeftBrace,
+	/// Right brace `}`
+	RightBrace,
 	/// A series of line comments beginning with `;;`,
 	/// used to document declared values.
 	DocComment(&'lex str),
@@ -54,6 +58,8 @@ impl<'lex> Token<'lex> {
 		match *self {
 			Token::LeftParen => "(",
 			Token::RightParen => ")",
+			Token::LeftBrace => "{",
+			Token::RightBrace => "}",
 			Token::DocComment(_) => "doc-comment",
 			Token::Float(_) => "float",
 			Token::Integer(_, _) => "integer",
@@ -266,6 +272,8 @@ impl<'lex> Lexer<'lex> {
 			let res = match ch {
 				'(' => Ok((Token::LeftParen, 1)),
 				')' => Ok((Token::RightParen, 1)),
+				'{' => Ok((Token::LeftBrace, 1)),
+				'}' => Ok((Token::RightBrace, 1)),
 				'\'' => Ok((Token::Quote, 1)),
 				'`' => Ok((Token::BackQuote, 1)),
 				',' => match chars.next() {
@@ -720,6 +728,16 @@ mod test {
 			]
 		);
 
+		assert_eq!(
+			tokens("{:a 1}"),
+			[
+				(sp(0, 1), Token::LeftBrace),
+				(sp(1, 3), Token::Keyword("a")),
+				(sp(4, 5), Token::Integer("1", 10)),
+				(sp(5, 6), Token::RightBrace)
+			]
+		);
+
 		assert_eq!(
 			tokens("0b0101 0o777 0xdeadBEEF"),
 			[
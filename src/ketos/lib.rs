@@ -39,23 +39,33 @@ pub use ketos_derive::*;
 
 pub use crate::bytecode::Code;
 pub use crate::bytes::Bytes;
+pub use crate::clock::{Clock, FakeClock, SystemClock};
 pub use crate::compile::CompileError;
 pub use crate::completion::complete_name;
 pub use crate::encode::{DecodeError, EncodeError};
 pub use crate::error::Error;
-pub use crate::exec::{panic, panic_none, Context, ExecError};
+pub use crate::exec::{panic, panic_none, CaughtError, Context, ExecError};
 pub use crate::function::Arity;
 pub use crate::integer::{Integer, Ratio};
 pub use crate::interpreter::{Builder, Interpreter};
-pub use crate::io::{File, GlobalIo, IoError, SharedWrite};
+pub use crate::io::{File, GlobalIo, IoError, SharedRead, SharedWrite};
+pub use crate::map::Map;
+pub use crate::mod_bytes::{BytesError, BytesErrorKind};
+pub use crate::mod_json::{JsonError, JsonErrorKind};
+pub use crate::mod_math::MathError;
+pub use crate::mod_random::RandomError;
+pub use crate::mod_time::TimeError;
 pub use crate::module::{
 	BuiltinModuleLoader, FileModuleLoader, Module, ModuleBuilder, ModuleLoader,
 };
 pub use crate::name::{Name, NameStore};
 pub use crate::parser::{ParseError, ParseErrorKind};
-pub use crate::restrict::{RestrictConfig, RestrictError};
+pub use crate::reference::Ref;
+pub use crate::restrict::{FsAccess, RestrictConfig, RestrictError};
 pub use crate::run::run_code;
 pub use crate::scope::{GlobalScope, Scope};
+pub use crate::seq::{LazySeq, SeqIter};
+pub use crate::set::Set;
 pub use crate::structs::{StructDef, StructValue};
 pub use crate::trace::{clear_traceback, get_traceback, set_traceback, take_traceback, Trace};
 pub use crate::value::{ForeignValue, FromValue, FromValueRef, Value};
@@ -69,6 +79,7 @@ pub mod any;
 pub mod args;
 pub mod bytecode;
 pub mod bytes;
+pub mod clock;
 pub mod compile;
 pub mod completion;
 mod const_fold;
@@ -80,17 +91,22 @@ pub mod integer;
 pub mod interpreter;
 pub mod io;
 pub mod lexer;
+pub mod map;
 pub mod module;
 pub mod name;
 pub mod parser;
 pub mod pretty;
 pub mod rc_vec;
+pub mod reference;
 pub mod restrict;
 pub mod run;
 pub mod scope;
+pub mod seq;
+pub mod set;
 mod string;
 pub mod string_fmt;
 pub mod structs;
+mod syntax;
 pub mod trace;
 pub mod value;
 #[cfg(feature = "serde")]
@@ -98,6 +114,18 @@ pub mod value_decode;
 #[cfg(feature = "serde")]
 pub mod value_encode;
 
+mod mod_bytes;
 mod mod_code;
+mod mod_fs;
+mod mod_iter;
+mod mod_json;
+mod mod_list_core;
 mod mod_math;
+mod mod_os;
+mod mod_path;
+mod mod_process;
 mod mod_random;
+mod mod_regex;
+mod mod_set;
+mod mod_string;
+mod mod_time;
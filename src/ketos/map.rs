@@ -0,0 +1,295 @@
+//! Implements a reference-counted, persistent map of values.
+
+use std::cmp::Ordering;
+use std::collections::btree_map::{self, BTreeMap};
+use std::fmt;
+use std::iter::FromIterator;
+use std::rc::Rc;
+
+use crate::value::Value;
+
+/// Shared map of key-value pairs
+///
+/// `Map` is cheaply cloned. Modifying a `Map` whose contents are shared with
+/// another `Map` will first make a copy of the shared contents.
+///
+/// Any value may be used as a key. Two keys are considered the same key
+/// if they are identical; e.g. `1` and `1.0` are distinct keys.
+///
+/// Entries are ordered by key. Names and keywords are ordered by their
+/// interned value rather than by their string representation.
+#[derive(Clone, Default)]
+pub struct Map {
+	entries: Rc<BTreeMap<MapKey, Value>>,
+	/// Keys in the order they were written, for a map parsed from a literal
+	/// which has not since been modified
+	source_order: Option<Rc<[Value]>>,
+}
+
+impl Map {
+	/// Creates an empty map.
+	pub fn new() -> Map {
+		Map {
+			entries: Rc::new(BTreeMap::new()),
+			source_order: None,
+		}
+	}
+
+	/// Creates a map from the entries of a map literal.
+	///
+	/// If a key occurs more than once, the duplicate key is returned.
+	pub(crate) fn from_literal(entries: Vec<(Value, Value)>) -> Result<Map, Value> {
+		let mut map = Map::new();
+		let mut order = Vec::with_capacity(entries.len());
+
+		for (k, v) in entries {
+			if map.insert(k.clone(), v).is_some() {
+				return Err(k);
+			}
+			order.push(k);
+		}
+
+		map.source_order = Some(order.into());
+		Ok(map)
+	}
+
+	/// Returns an iterator over key-value pairs, in the order they were
+	/// written in a map literal; otherwise, in key order.
+	///
+	/// Used to evaluate the entries of a map literal in source order.
+	pub(crate) fn source_iter(&self) -> Box<dyn Iterator<Item = (&Value, &Value)> + '_> {
+		match self.source_order {
+			Some(ref order) => Box::new(order.iter().map(move |k| {
+				let v = self.get(k).expect("map literal key");
+				(k, v)
+			})),
+			None => Box::new(self.iter()),
+		}
+	}
+
+	/// Returns whether the map is empty.
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+
+	/// Returns the number of entries in the map.
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	/// Returns whether the map contains the given key.
+	pub fn contains_key(&self, key: &Value) -> bool {
+		self.entries.contains_key(&MapKey(key.clone()))
+	}
+
+	/// Returns the value associated with the given key.
+	pub fn get(&self, key: &Value) -> Option<&Value> {
+		self.entries.get(&MapKey(key.clone()))
+	}
+
+	/// Associates a value with the given key.
+	/// If the key was already present, the old value is returned.
+	pub fn insert(&mut self, key: Value, value: Value) -> Option<Value> {
+		self.source_order = None;
+		Rc::make_mut(&mut self.entries).insert(MapKey(key), value)
+	}
+
+	/// Removes the given key from the map, returning its associated value.
+	pub fn remove(&mut self, key: &Value) -> Option<Value> {
+		if self.contains_key(key) {
+			self.source_order = None;
+			Rc::make_mut(&mut self.entries).remove(&MapKey(key.clone()))
+		} else {
+			None
+		}
+	}
+
+	/// Returns an iterator over key-value pairs, in key order.
+	pub fn iter(&self) -> Iter<'_> {
+		Iter(self.entries.iter())
+	}
+
+	/// Returns an iterator over keys, in order.
+	pub fn keys(&self) -> impl Iterator<Item = &Value> {
+		self.entries.keys().map(|k| &k.0)
+	}
+
+	/// Returns an iterator over values, in key order.
+	pub fn values(&self) -> impl Iterator<Item = &Value> {
+		self.entries.values()
+	}
+}
+
+impl fmt::Debug for Map {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_map().entries(self.iter()).finish()
+	}
+}
+
+impl Extend<(Value, Value)> for Map {
+	fn extend<I>(&mut self, iterable: I)
+	where
+		I: IntoIterator<Item = (Value, Value)>,
+	{
+		self.source_order = None;
+		Rc::make_mut(&mut self.entries).extend(iterable.into_iter().map(|(k, v)| (MapKey(k), v)));
+	}
+}
+
+impl FromIterator<(Value, Value)> for Map {
+	fn from_iter<I>(iterable: I) -> Map
+	where
+		I: IntoIterator<Item = (Value, Value)>,
+	{
+		Map {
+			entries: Rc::new(iterable.into_iter().map(|(k, v)| (MapKey(k), v)).collect()),
+			source_order: None,
+		}
+	}
+}
+
+impl<'a> IntoIterator for &'a Map {
+	type Item = (&'a Value, &'a Value);
+	type IntoIter = Iter<'a>;
+
+	fn into_iter(self) -> Iter<'a> {
+		self.iter()
+	}
+}
+
+/// Iterator over the key-value pairs of a `Map`
+#[derive(Clone)]
+pub struct Iter<'a>(btree_map::Iter<'a, MapKey, Value>);
+
+impl<'a> Iterator for Iter<'a> {
+	type Item = (&'a Value, &'a Value);
+
+	fn next(&mut self) -> Option<(&'a Value, &'a Value)> {
+		self.0.next().map(|(k, v)| (&k.0, v))
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.0.size_hint()
+	}
+}
+
+impl<'a> DoubleEndedIterator for Iter<'a> {
+	fn next_back(&mut self) -> Option<(&'a Value, &'a Value)> {
+		self.0.next_back().map(|(k, v)| (&k.0, v))
+	}
+}
+
+impl<'a> ExactSizeIterator for Iter<'a> {}
+
+/// Wraps a `Value` to provide the total ordering used for map keys.
+#[derive(Clone, Debug)]
+pub(crate) struct MapKey(pub Value);
+
+impl PartialEq for MapKey {
+	fn eq(&self, rhs: &MapKey) -> bool {
+		cmp_keys(&self.0, &rhs.0) == Ordering::Equal
+	}
+}
+
+impl Eq for MapKey {}
+
+impl PartialOrd for MapKey {
+	fn partial_cmp(&self, rhs: &MapKey) -> Option<Ordering> {
+		Some(self.cmp(rhs))
+	}
+}
+
+impl Ord for MapKey {
+	fn cmp(&self, rhs: &MapKey) -> Ordering {
+		cmp_keys(&self.0, &rhs.0)
+	}
+}
+
+/// Returns a total ordering of two values, for use as map keys.
+///
+/// Values of different types are ordered by type. Values which cannot be
+/// compared by content (functions, lambdas, foreign values, etc.) are
+/// ordered by address. Two values are ordered `Equal` if and only if
+/// they are identical.
+pub(crate) fn cmp_keys(a: &Value, b: &Value) -> Ordering {
+	match (a, b) {
+		(Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+		(Value::Float(a), Value::Float(b)) => a.total_cmp(b),
+		(Value::Integer(a), Value::Integer(b)) => a.cmp(b),
+		(Value::Ratio(a), Value::Ratio(b)) => a.cmp(b),
+		(Value::Char(a), Value::Char(b)) => a.cmp(b),
+		(Value::String(a), Value::String(b)) => a.cmp(b),
+		(Value::Bytes(a), Value::Bytes(b)) => a.cmp(b),
+		(Value::Path(a), Value::Path(b)) => a.cmp(b),
+		(Value::Name(a), Value::Name(b)) | (Value::Keyword(a), Value::Keyword(b)) => a.cmp(b),
+		(Value::List(a), Value::List(b)) => cmp_key_slice(a, b),
+		(Value::Map(a), Value::Map(b)) => {
+			for ((ak, av), (bk, bv)) in a.iter().zip(b.iter()) {
+				match cmp_keys(ak, bk).then_with(|| cmp_keys(av, bv)) {
+					Ordering::Equal => (),
+					ord => return ord,
+				}
+			}
+			a.len().cmp(&b.len())
+		}
+		(Value::Set(a), Value::Set(b)) => cmp_key_slice(a.as_slice(), b.as_slice()),
+		(Value::Struct(a), Value::Struct(b)) => Rc::as_ptr(a.def())
+			.cmp(&Rc::as_ptr(b.def()))
+			.then_with(|| cmp_key_slice(a.fields(), b.fields())),
+		(Value::Quasiquote(a, na), Value::Quasiquote(b, nb))
+		| (Value::Comma(a, na), Value::Comma(b, nb))
+		| (Value::CommaAt(a, na), Value::CommaAt(b, nb))
+		| (Value::Quote(a, na), Value::Quote(b, nb)) => na.cmp(nb).then_with(|| cmp_keys(a, b)),
+		(Value::StructDef(a), Value::StructDef(b)) => Rc::as_ptr(a).cmp(&Rc::as_ptr(b)),
+		(Value::Function(a), Value::Function(b)) => {
+			(a.sys_fn.callback as usize).cmp(&(b.sys_fn.callback as usize))
+		}
+		(Value::Lambda(a), Value::Lambda(b)) => Rc::as_ptr(&a.code).cmp(&Rc::as_ptr(&b.code)),
+		(Value::Ref(a), Value::Ref(b)) => a.as_ptr().cmp(&b.as_ptr()),
+		(Value::Foreign(a), Value::Foreign(b)) => {
+			(Rc::as_ptr(a) as *const ()).cmp(&(Rc::as_ptr(b) as *const ()))
+		}
+		(a, b) => key_rank(a).cmp(&key_rank(b)),
+	}
+}
+
+fn cmp_key_slice(a: &[Value], b: &[Value]) -> Ordering {
+	for (a, b) in a.iter().zip(b) {
+		match cmp_keys(a, b) {
+			Ordering::Equal => (),
+			ord => return ord,
+		}
+	}
+
+	a.len().cmp(&b.len())
+}
+
+fn key_rank(v: &Value) -> u32 {
+	match *v {
+		Value::Unit => 0,
+		Value::Unbound => 1,
+		Value::Bool(_) => 2,
+		Value::Integer(_) => 3,
+		Value::Ratio(_) => 4,
+		Value::Float(_) => 5,
+		Value::Char(_) => 6,
+		Value::String(_) => 7,
+		Value::Bytes(_) => 8,
+		Value::Path(_) => 9,
+		Value::Name(_) => 10,
+		Value::Keyword(_) => 11,
+		Value::List(_) => 12,
+		Value::Map(_) => 13,
+		Value::Set(_) => 14,
+		Value::Struct(_) => 15,
+		Value::Quasiquote(_, _) => 16,
+		Value::Comma(_, _) => 17,
+		Value::CommaAt(_, _) => 18,
+		Value::Quote(_, _) => 19,
+		Value::StructDef(_) => 20,
+		Value::Function(_) => 21,
+		Value::Lambda(_) => 22,
+		Value::Ref(_) => 23,
+		Value::Foreign(_) => 24,
+	}
+}
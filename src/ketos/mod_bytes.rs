@@ -0,0 +1,486 @@
+//! Implements builtin `bytes` module.
+//!
+//! Provides text encodings of byte strings and packing of fixed-width numbers.
+//!
+//! Numeric types are named by keywords such as `:u8`, `:i32-le`, or `:f64-be`.
+//! A `-be` or `-le` suffix selects big-endian or little-endian byte order;
+//! without a suffix, big-endian order is used.
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::str;
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+
+use crate::bytes::Bytes;
+use crate::error::Error;
+use crate::exec::{Context, ExecError};
+use crate::function::Arity::{Exact, Min, Range};
+use crate::mod_string::check_value;
+use crate::module::{Module, ModuleBuilder};
+use crate::scope::Scope;
+use crate::value::{FromValueRef, Value};
+
+/// Loads the `bytes` module into the given scope.
+pub fn load(scope: Scope) -> Module {
+	ModuleBuilder::new("bytes", scope)
+		.add_function(
+			"hex-encode",
+			fn_hex_encode,
+			Exact(1),
+			Some("Returns a string of lowercase hexadecimal digits representing bytes."),
+		)
+		.add_function(
+			"hex-decode",
+			fn_hex_decode,
+			Exact(1),
+			Some("Returns the bytes represented by a string of hexadecimal digits."),
+		)
+		.add_function(
+			"base64-encode",
+			fn_base64_encode,
+			Exact(1),
+			Some("Returns a string of padded, standard base64 representing bytes."),
+		)
+		.add_function(
+			"base64-decode",
+			fn_base64_decode,
+			Exact(1),
+			Some(
+				"Returns the bytes represented by a string of standard base64.
+Trailing padding is optional.",
+			),
+		)
+		.add_function(
+			"utf8-decode",
+			fn_utf8_decode,
+			Exact(1),
+			Some("Returns a string decoded from UTF-8 encoded bytes."),
+		)
+		.add_function(
+			"pack",
+			fn_pack,
+			Min(2),
+			Some(
+				"    (pack type value type value ...)
+
+Returns bytes containing each value encoded as the numeric type preceding it.
+Types are `:u8`, `:i8`, `:u16`, `:i16`, `:u32`, `:i32`, `:u64`, `:i64`,
+`:f32`, and `:f64`, followed by `-be` or `-le` to select byte order.
+Without a suffix, big-endian order is used.",
+			),
+		)
+		.add_function(
+			"unpack",
+			fn_unpack,
+			Range(2, 3),
+			Some(
+				"    (unpack type bytes)
+    (unpack type bytes offset)
+
+Returns a number decoded as the given numeric type from bytes,
+beginning at `offset`, or `0` if no offset is given.",
+			),
+		)
+		.add_function(
+			"find",
+			fn_find,
+			Range(2, 3),
+			Some(
+				"    (find bytes pattern)
+    (find bytes pattern start)
+
+Returns the index of the first occurrence of `pattern` in `bytes`,
+beginning the search at `start`, or `()` if it is not found.",
+			),
+		)
+		.finish()
+}
+
+/// Represents an error in decoding bytes or text.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct BytesError {
+	/// Byte offset within input at which the error occurred
+	pub offset: usize,
+	/// Kind of error generated
+	pub kind: BytesErrorKind,
+}
+
+impl BytesError {
+	/// Creates a new `BytesError`.
+	pub fn new(offset: usize, kind: BytesErrorKind) -> BytesError {
+		BytesError { offset, kind }
+	}
+}
+
+impl fmt::Display for BytesError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{} at byte {}", self.kind, self.offset)
+	}
+}
+
+impl StdError for BytesError {
+	fn description(&self) -> &str {
+		"bytes error"
+	}
+}
+
+/// Describes the kind of error encountered in decoding bytes or text.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum BytesErrorKind {
+	/// Invalid base64 character or length
+	InvalidBase64,
+	/// Invalid hexadecimal digit or odd number of digits
+	InvalidHex,
+	/// Invalid UTF-8 sequence
+	InvalidUtf8,
+}
+
+impl fmt::Display for BytesErrorKind {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(match *self {
+			BytesErrorKind::InvalidBase64 => "invalid base64",
+			BytesErrorKind::InvalidHex => "invalid hexadecimal",
+			BytesErrorKind::InvalidUtf8 => "invalid utf-8",
+		})
+	}
+}
+
+impl From<BytesError> for Error {
+	fn from(e: BytesError) -> Error {
+		Error::custom(e)
+	}
+}
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+const BASE64_CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// `hex-encode` returns a string of hexadecimal digits.
+///
+/// ```lisp
+/// (hex-encode #b"\x01\xab")  ; "01ab"
+/// ```
+fn fn_hex_encode(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let b = <&Bytes>::from_value_ref(&args[0])?;
+
+	ctx.check_memory(b.len().saturating_mul(2))?;
+
+	let mut s = String::with_capacity(b.len() * 2);
+
+	for &byte in b.iter() {
+		s.push(HEX_DIGITS[(byte >> 4) as usize] as char);
+		s.push(HEX_DIGITS[(byte & 0xf) as usize] as char);
+	}
+
+	Ok(s.into())
+}
+
+/// `hex-decode` returns the bytes represented by hexadecimal digits.
+///
+/// ```lisp
+/// (hex-decode "01AB")  ; #b"\x01\xab"
+/// ```
+fn fn_hex_decode(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let s = <&str>::from_value_ref(&args[0])?.as_bytes();
+
+	if !s.len().is_multiple_of(2) {
+		return Err(From::from(BytesError::new(
+			s.len(),
+			BytesErrorKind::InvalidHex,
+		)));
+	}
+
+	let digit = |i: usize| {
+		(s[i] as char)
+			.to_digit(16)
+			.map(|d| d as u8)
+			.ok_or(BytesError::new(i, BytesErrorKind::InvalidHex))
+	};
+
+	let mut b = Vec::with_capacity(s.len() / 2);
+
+	for i in (0..s.len()).step_by(2) {
+		b.push((digit(i)? << 4) | digit(i + 1)?);
+	}
+
+	check_value(ctx, Bytes::new(b).into())
+}
+
+/// `base64-encode` returns a string of base64.
+///
+/// ```lisp
+/// (base64-encode (bytes "hello"))  ; "aGVsbG8="
+/// ```
+fn fn_base64_encode(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let b = <&Bytes>::from_value_ref(&args[0])?;
+
+	ctx.check_memory(b.len().div_ceil(3).saturating_mul(4))?;
+
+	let mut s = String::with_capacity(b.len().div_ceil(3) * 4);
+
+	for chunk in b.chunks(3) {
+		let n = chunk
+			.iter()
+			.enumerate()
+			.fold(0u32, |n, (i, &byte)| n | (byte as u32) << (16 - 8 * i));
+
+		for i in 0..4 {
+			if i <= chunk.len() {
+				s.push(BASE64_CHARS[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+			} else {
+				s.push('=');
+			}
+		}
+	}
+
+	Ok(s.into())
+}
+
+/// `base64-decode` returns the bytes represented by base64.
+///
+/// ```lisp
+/// (base64-decode "aGVsbG8=")  ; #b"hello"
+/// ```
+fn fn_base64_decode(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let s = <&str>::from_value_ref(&args[0])?.as_bytes();
+	let data = s
+		.strip_suffix(b"==")
+		.or_else(|| s.strip_suffix(b"="))
+		.unwrap_or(s);
+	let padded = data.len() < s.len();
+
+	if (padded && !s.len().is_multiple_of(4)) || data.len() % 4 == 1 {
+		return Err(From::from(BytesError::new(
+			s.len(),
+			BytesErrorKind::InvalidBase64,
+		)));
+	}
+
+	let mut b = Vec::with_capacity(data.len() / 4 * 3 + 2);
+
+	for (n, chunk) in data.chunks(4).enumerate() {
+		let mut bits = 0u32;
+
+		for (i, &c) in chunk.iter().enumerate() {
+			let v = match BASE64_CHARS.iter().position(|&x| x == c) {
+				Some(v) => v as u32,
+				None => {
+					return Err(From::from(BytesError::new(
+						n * 4 + i,
+						BytesErrorKind::InvalidBase64,
+					)))
+				}
+			};
+
+			bits |= v << (18 - 6 * i);
+		}
+
+		b.extend_from_slice(&bits.to_be_bytes()[1..chunk.len()]);
+	}
+
+	check_value(ctx, Bytes::new(b).into())
+}
+
+/// `utf8-decode` returns a string decoded from UTF-8 encoded bytes.
+///
+/// ```lisp
+/// (utf8-decode #b"caf\xc3\xa9")  ; "café"
+/// ```
+fn fn_utf8_decode(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let b = <&Bytes>::from_value_ref(&args[0])?;
+
+	match str::from_utf8(b) {
+		Ok(s) => Ok(s.into()),
+		Err(e) => Err(From::from(BytesError::new(
+			e.valid_up_to(),
+			BytesErrorKind::InvalidUtf8,
+		))),
+	}
+}
+
+/// `pack` returns bytes containing encoded numbers.
+///
+/// ```lisp
+/// (pack :u16-be 1 :f32-le 1.5)  ; #b"\x00\x01\x00\x00\xc0?"
+/// ```
+fn fn_pack(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	if !args.len().is_multiple_of(2) {
+		return Err(From::from(ExecError::OddKeywordParams));
+	}
+
+	let mut buf = Vec::new();
+
+	for pair in args.chunks(2) {
+		let (ty, order) = get_type(ctx, &pair[0])?;
+
+		let start = buf.len();
+		buf.resize(start + ty.size(), 0);
+
+		match order {
+			Order::Big => pack::<BigEndian>(&mut buf[start..], ty, &pair[1])?,
+			Order::Little => pack::<LittleEndian>(&mut buf[start..], ty, &pair[1])?,
+		}
+	}
+
+	check_value(ctx, Bytes::new(buf).into())
+}
+
+/// `unpack` returns a number decoded from bytes.
+///
+/// ```lisp
+/// (unpack :u16-be #b"\x00\x01")       ; 1
+/// (unpack :i8 #b"\x00\xff" 1)         ; -1
+/// ```
+fn fn_unpack(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let (ty, order) = get_type(ctx, &args[0])?;
+	let b = <&Bytes>::from_value_ref(&args[1])?;
+	let offset = match args.get(2) {
+		Some(v) => usize::from_value_ref(v)?,
+		None => 0,
+	};
+
+	let end = offset.saturating_add(ty.size());
+
+	if end > b.len() {
+		return Err(From::from(ExecError::OutOfBounds(end)));
+	}
+
+	let b = &b[offset..end];
+
+	Ok(match order {
+		Order::Big => unpack::<BigEndian>(b, ty),
+		Order::Little => unpack::<LittleEndian>(b, ty),
+	})
+}
+
+/// `find` returns the index of a byte sequence.
+///
+/// ```lisp
+/// (find #b"abcabc" #b"bc" 2)  ; 4
+/// ```
+fn fn_find(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let b = <&Bytes>::from_value_ref(&args[0])?;
+	let pat = <&Bytes>::from_value_ref(&args[1])?;
+	let start = match args.get(2) {
+		Some(v) => usize::from_value_ref(v)?,
+		None => 0,
+	};
+
+	if start > b.len() {
+		return Err(From::from(ExecError::OutOfBounds(start)));
+	}
+
+	let pos = if pat.is_empty() {
+		Some(0)
+	} else {
+		b[start..].windows(pat.len()).position(|w| w == &pat[..])
+	};
+
+	Ok(pos.map_or(Value::Unit, |i| (start + i).into()))
+}
+
+#[derive(Copy, Clone)]
+enum NumType {
+	U8,
+	I8,
+	U16,
+	I16,
+	U32,
+	I32,
+	U64,
+	I64,
+	F32,
+	F64,
+}
+
+impl NumType {
+	fn size(self) -> usize {
+		match self {
+			NumType::U8 | NumType::I8 => 1,
+			NumType::U16 | NumType::I16 => 2,
+			NumType::U32 | NumType::I32 | NumType::F32 => 4,
+			NumType::U64 | NumType::I64 | NumType::F64 => 8,
+		}
+	}
+}
+
+#[derive(Copy, Clone)]
+enum Order {
+	Big,
+	Little,
+}
+
+/// Interprets a numeric type keyword.
+fn get_type(ctx: &Context, v: &Value) -> Result<(NumType, Order), Error> {
+	let name = match *v {
+		Value::Keyword(name) => name,
+		ref v => return Err(From::from(ExecError::expected("keyword", v))),
+	};
+
+	let names = ctx.scope().borrow_names();
+	let s = names.get(name);
+
+	let (ty, order) = match s.rsplit_once('-') {
+		Some((ty, "be")) => (ty, Order::Big),
+		Some((ty, "le")) => (ty, Order::Little),
+		_ => (s, Order::Big),
+	};
+
+	let ty = match ty {
+		"u8" => NumType::U8,
+		"i8" => NumType::I8,
+		"u16" => NumType::U16,
+		"i16" => NumType::I16,
+		"u32" => NumType::U32,
+		"i32" => NumType::I32,
+		"u64" => NumType::U64,
+		"i64" => NumType::I64,
+		"f32" => NumType::F32,
+		"f64" => NumType::F64,
+		_ => return Err(From::from(ExecError::UnrecognizedKeyword(name))),
+	};
+
+	Ok((ty, order))
+}
+
+fn pack<B: ByteOrder>(buf: &mut [u8], ty: NumType, v: &Value) -> Result<(), ExecError> {
+	match ty {
+		NumType::U8 => buf[0] = u8::from_value_ref(v)?,
+		NumType::I8 => buf[0] = i8::from_value_ref(v)? as u8,
+		NumType::U16 => B::write_u16(buf, u16::from_value_ref(v)?),
+		NumType::I16 => B::write_i16(buf, i16::from_value_ref(v)?),
+		NumType::U32 => B::write_u32(buf, u32::from_value_ref(v)?),
+		NumType::I32 => B::write_i32(buf, i32::from_value_ref(v)?),
+		NumType::U64 => B::write_u64(buf, u64::from_value_ref(v)?),
+		NumType::I64 => B::write_i64(buf, i64::from_value_ref(v)?),
+		NumType::F32 => B::write_f32(buf, get_float(v)? as f32),
+		NumType::F64 => B::write_f64(buf, get_float(v)?),
+	}
+
+	Ok(())
+}
+
+fn unpack<B: ByteOrder>(buf: &[u8], ty: NumType) -> Value {
+	match ty {
+		NumType::U8 => buf[0].into(),
+		NumType::I8 => (buf[0] as i8).into(),
+		NumType::U16 => B::read_u16(buf).into(),
+		NumType::I16 => B::read_i16(buf).into(),
+		NumType::U32 => B::read_u32(buf).into(),
+		NumType::I32 => B::read_i32(buf).into(),
+		NumType::U64 => B::read_u64(buf).into(),
+		NumType::I64 => B::read_i64(buf).into(),
+		NumType::F32 => B::read_f32(buf).into(),
+		NumType::F64 => B::read_f64(buf).into(),
+	}
+}
+
+/// Returns a float value, converting from an integer if necessary.
+fn get_float(v: &Value) -> Result<f64, ExecError> {
+	match *v {
+		Value::Float(f) => Ok(f),
+		Value::Integer(ref i) => i.to_f64().ok_or(ExecError::Overflow),
+		ref v => Err(ExecError::expected("float", v)),
+	}
+}
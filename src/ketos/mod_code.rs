@@ -3,9 +3,9 @@
 use std::rc::Rc;
 
 use crate::bytecode::{CodeReader, Instruction};
-use crate::compile::compile;
+use crate::compile::{compile, macroexpand, macroexpand_1};
 use crate::error::Error;
-use crate::exec::{Context, ExecError};
+use crate::exec::{execute, Context, ExecError};
 use crate::function::Arity::*;
 use crate::function::{plural, Lambda};
 use crate::module::{Module, ModuleBuilder};
@@ -37,6 +37,19 @@ pub fn load(scope: Scope) -> Module {
 Returns `()` if the item has no documentation.",
 			),
 		)
+		.add_function(
+			"eval",
+			fn_eval,
+			Range(1, 2),
+			Some(
+				"    (eval expr)
+    (eval expr module)
+
+Compiles and evaluates an expression, returning the result.
+If a module name is given, the expression is evaluated within
+the scope of that module.",
+			),
+		)
 		.add_function(
 			"get-const",
 			fn_get_const,
@@ -57,6 +70,30 @@ Returns the nth const value of a lambda.",
 Returns the nth captured value of a lambda.",
 			),
 		)
+		.add_function(
+			"macroexpand",
+			fn_macroexpand,
+			Range(1, 2),
+			Some(
+				"    (macroexpand expr)
+    (macroexpand expr module)
+
+Repeatedly expands a macro call expression until the result
+is no longer a macro call. Other expressions are returned unchanged.",
+			),
+		)
+		.add_function(
+			"macroexpand-1",
+			fn_macroexpand_1,
+			Range(1, 2),
+			Some(
+				"    (macroexpand-1 expr)
+    (macroexpand-1 expr module)
+
+Expands a macro call expression once.
+Other expressions are returned unchanged.",
+			),
+		)
 		.add_function(
 			"module-documentation",
 			fn_module_documentation,
@@ -78,6 +115,39 @@ fn fn_compile(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
 	Ok(Value::Lambda(Lambda::new(Rc::new(code), ctx.scope())))
 }
 
+/// Returns a context for the optional module name argument at `args[1]`.
+///
+/// If no module is given, the caller's context is returned.
+fn module_context(ctx: &Context, args: &[Value]) -> Result<Context, Error> {
+	match args.get(1) {
+		None => Ok(ctx.clone()),
+		Some(&Value::Name(name)) => {
+			let m = ctx.scope().modules().load_module(name, ctx)?;
+			Ok(ctx.with_scope(m.scope))
+		}
+		Some(v) => Err(From::from(ExecError::expected("name", v))),
+	}
+}
+
+/// `eval` compiles and executes an expression.
+fn fn_eval(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let ctx = module_context(ctx, args)?;
+	let code = compile(&ctx, &args[0])?;
+	execute(&ctx, Rc::new(code))
+}
+
+/// `macroexpand` fully expands a macro call expression.
+fn fn_macroexpand(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let ctx = module_context(ctx, args)?;
+	macroexpand(&ctx, &args[0])
+}
+
+/// `macroexpand-1` expands a macro call expression once.
+fn fn_macroexpand_1(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let ctx = module_context(ctx, args)?;
+	macroexpand_1(&ctx, &args[0])
+}
+
 /// `disassemble` prints information about a `Lambda` code object.
 fn fn_disassemble(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
 	let l = match args[0] {
@@ -0,0 +1,366 @@
+//! Implements builtin `fs` module.
+//!
+//! Access to the filesystem is governed by the `filesystem` field of the
+//! active `RestrictConfig`. When access is limited to a root directory,
+//! relative paths are resolved against that directory.
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::bytes::Bytes;
+use crate::error::Error;
+use crate::exec::{Context, ExecError};
+use crate::function::Arity::{Exact, Range};
+use crate::io::{IoError, IoMode};
+use crate::map::Map;
+use crate::mod_string::check_value;
+use crate::module::{Module, ModuleBuilder};
+use crate::restrict::{FsAccess, RestrictError};
+use crate::scope::Scope;
+use crate::value::{FromValueRef, Value};
+
+/// Loads the `fs` module into the given scope.
+pub fn load(scope: Scope) -> Module {
+	ModuleBuilder::new("fs", scope)
+		.add_function(
+			"read-string",
+			fn_read_string,
+			Exact(1),
+			Some("Returns the contents of a file as a string."),
+		)
+		.add_function(
+			"read-bytes",
+			fn_read_bytes,
+			Exact(1),
+			Some("Returns the contents of a file as bytes."),
+		)
+		.add_function(
+			"write",
+			fn_write,
+			Exact(2),
+			Some(
+				"    (write path data)
+
+Writes a string or bytes to a file, replacing any existing contents.",
+			),
+		)
+		.add_function(
+			"append",
+			fn_append,
+			Exact(2),
+			Some(
+				"    (append path data)
+
+Appends a string or bytes to a file, creating the file if it does not exist.",
+			),
+		)
+		.add_function(
+			"list-dir",
+			fn_list_dir,
+			Exact(1),
+			Some("Returns a sorted list of the paths of entries in a directory."),
+		)
+		.add_function(
+			"exists",
+			fn_exists,
+			Exact(1),
+			Some("Returns whether a file or directory exists at the given path."),
+		)
+		.add_function(
+			"metadata",
+			fn_metadata,
+			Exact(1),
+			Some(
+				"Returns a map describing a file, with the keys
+`:size`, `:is-file`, `:is-dir`, `:is-symlink`, `:read-only`, and `:modified`.
+`:modified` is a Unix timestamp, or `()` if it is unavailable.",
+			),
+		)
+		.add_function(
+			"mkdir",
+			fn_mkdir,
+			Range(1, 2),
+			Some(
+				"    (mkdir path)
+    (mkdir path parents)
+
+Creates a directory. If `parents` is true, any missing parent directories
+are also created and no error is returned if the directory exists.",
+			),
+		)
+		.add_function(
+			"remove",
+			fn_remove,
+			Range(1, 2),
+			Some(
+				"    (remove path)
+    (remove path recursive)
+
+Removes a file or an empty directory. If `recursive` is true,
+a directory is removed along with all of its contents.",
+			),
+		)
+		.finish()
+}
+
+/// `read-string` returns the contents of a file as a string.
+fn fn_read_string(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let path = resolve_path(ctx, &args[0])?;
+
+	check_file_size(ctx, &path)?;
+	let s = fs::read_to_string(&path).map_err(|e| IoError::new(IoMode::Read, &path, e))?;
+
+	check_value(ctx, s.into())
+}
+
+/// `read-bytes` returns the contents of a file as bytes.
+fn fn_read_bytes(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let path = resolve_path(ctx, &args[0])?;
+
+	check_file_size(ctx, &path)?;
+	let b = fs::read(&path).map_err(|e| IoError::new(IoMode::Read, &path, e))?;
+
+	check_value(ctx, Bytes::new(b).into())
+}
+
+/// `write` writes data to a file, replacing its contents.
+///
+/// ```lisp
+/// (write "out.txt" "hello\n")
+/// ```
+fn fn_write(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let path = resolve_path(ctx, &args[0])?;
+	let data = get_data(&args[1])?;
+
+	fs::write(&path, data).map_err(|e| IoError::new(IoMode::Write, &path, e))?;
+
+	Ok(Value::Unit)
+}
+
+/// `append` appends data to a file.
+fn fn_append(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let path = resolve_path(ctx, &args[0])?;
+	let data = get_data(&args[1])?;
+
+	let mut f = OpenOptions::new()
+		.append(true)
+		.create(true)
+		.open(&path)
+		.map_err(|e| IoError::new(IoMode::Open, &path, e))?;
+
+	f.write_all(data)
+		.map_err(|e| IoError::new(IoMode::Write, &path, e))?;
+
+	Ok(Value::Unit)
+}
+
+/// `list-dir` returns the paths of entries in a directory.
+///
+/// Returned paths are joined to the path given, rather than the resolved path,
+/// so that they may be passed to other functions in the same way.
+fn fn_list_dir(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let dir = <&Path>::from_value_ref(&args[0])?;
+	let path = resolve_path(ctx, &args[0])?;
+
+	let mut entries = Vec::new();
+
+	for ent in fs::read_dir(&path).map_err(|e| IoError::new(IoMode::ReadDir, &path, e))? {
+		let ent = ent.map_err(|e| IoError::new(IoMode::ReadDir, &path, e))?;
+		entries.push(dir.join(ent.file_name()));
+	}
+
+	entries.sort();
+
+	check_value(ctx, entries.into())
+}
+
+/// `exists` returns whether a path exists.
+fn fn_exists(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let path = resolve_path(ctx, &args[0])?;
+	Ok(path.exists().into())
+}
+
+/// `metadata` returns a map describing a file.
+///
+/// ```lisp
+/// (get (metadata "foo.txt") :size)  ; 1024
+/// ```
+fn fn_metadata(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let path = resolve_path(ctx, &args[0])?;
+
+	let meta = fs::metadata(&path).map_err(|e| IoError::new(IoMode::Stat, &path, e))?;
+	let link_meta =
+		fs::symlink_metadata(&path).map_err(|e| IoError::new(IoMode::Stat, &path, e))?;
+
+	let modified = meta
+		.modified()
+		.ok()
+		.and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+		.map_or(Value::Unit, |d| Value::Float(d.as_secs_f64()));
+
+	let scope = ctx.scope();
+	let mut map = Map::new();
+
+	let mut insert = |key: &str, value: Value| {
+		map.insert(Value::Keyword(scope.add_name(key)), value);
+	};
+
+	insert("size", meta.len().into());
+	insert("is-file", meta.is_file().into());
+	insert("is-dir", meta.is_dir().into());
+	insert("is-symlink", link_meta.file_type().is_symlink().into());
+	insert("read-only", meta.permissions().readonly().into());
+	insert("modified", modified);
+
+	Ok(map.into())
+}
+
+/// `mkdir` creates a directory.
+fn fn_mkdir(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let path = resolve_path(ctx, &args[0])?;
+	let parents = match args.get(1) {
+		Some(v) => bool::from_value_ref(v)?,
+		None => false,
+	};
+
+	let r = if parents {
+		fs::create_dir_all(&path)
+	} else {
+		fs::create_dir(&path)
+	};
+
+	r.map_err(|e| IoError::new(IoMode::CreateDir, &path, e))?;
+
+	Ok(Value::Unit)
+}
+
+/// `remove` removes a file or directory.
+fn fn_remove(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let path = resolve_path(ctx, &args[0])?;
+	let recursive = match args.get(1) {
+		Some(v) => bool::from_value_ref(v)?,
+		None => false,
+	};
+
+	// The root of a restricted filesystem may be accessed, but not removed
+	if let FsAccess::Root(ref root) = ctx.restrict().filesystem {
+		if root.canonicalize().is_ok_and(|root| root == path) {
+			return Err(From::from(RestrictError::FilesystemAccessDenied));
+		}
+	}
+
+	let meta = fs::symlink_metadata(&path).map_err(|e| IoError::new(IoMode::Stat, &path, e))?;
+
+	let r = if !meta.is_dir() {
+		fs::remove_file(&path)
+	} else if recursive {
+		fs::remove_dir_all(&path)
+	} else {
+		fs::remove_dir(&path)
+	};
+
+	r.map_err(|e| IoError::new(IoMode::Remove, &path, e))?;
+
+	Ok(Value::Unit)
+}
+
+fn get_data(v: &Value) -> Result<&[u8], ExecError> {
+	match *v {
+		Value::String(ref s) => Ok(s.as_bytes()),
+		Value::Bytes(ref b) => Ok(b),
+		ref v => Err(ExecError::expected("string or bytes", v)),
+	}
+}
+
+fn check_file_size(ctx: &Context, path: &Path) -> Result<(), Error> {
+	let meta = fs::metadata(path).map_err(|e| IoError::new(IoMode::Stat, path, e))?;
+	let len = usize::try_from(meta.len()).unwrap_or(usize::MAX);
+
+	ctx.check_memory(len)?;
+	Ok(())
+}
+
+/// Returns the path to be accessed, if access is permitted.
+pub(crate) fn resolve_path(ctx: &Context, v: &Value) -> Result<PathBuf, Error> {
+	let path = <&Path>::from_value_ref(v)?;
+
+	match ctx.restrict().filesystem {
+		FsAccess::Denied => Err(From::from(RestrictError::FilesystemAccessDenied)),
+		FsAccess::Unrestricted => Ok(path.to_owned()),
+		FsAccess::Root(ref root) => {
+			let root = root
+				.canonicalize()
+				.map_err(|e| IoError::new(IoMode::Stat, root, e))?;
+
+			match resolve_within(&root, &root.join(path)) {
+				Some(path) => Ok(path),
+				None => Err(From::from(RestrictError::FilesystemAccessDenied)),
+			}
+		}
+	}
+}
+
+/// Resolves symbolic links and `..` components of the directory containing
+/// `path`, returning `None` if the result does not lie within `root`.
+///
+/// The final component is kept as written, so that a symbolic link is
+/// accessed as itself; e.g. `remove` removes the link rather than its target.
+/// As other functions follow the link, its target must also lie within `root`.
+fn resolve_within(root: &Path, path: &Path) -> Option<PathBuf> {
+	if path == root {
+		return Some(root.to_owned());
+	}
+
+	let resolved = match (path.parent(), path.file_name()) {
+		(Some(parent), Some(name)) => {
+			let mut resolved = resolve_dir(parent)?;
+			resolved.push(name);
+
+			let is_link = fs::symlink_metadata(&resolved)
+				.map(|meta| meta.file_type().is_symlink())
+				.unwrap_or(false);
+
+			if is_link && !resolved.canonicalize().ok()?.starts_with(root) {
+				return None;
+			}
+
+			resolved
+		}
+		// `path` ends with `..`
+		_ => resolve_dir(path)?,
+	};
+
+	if resolved.starts_with(root) {
+		Some(resolved)
+	} else {
+		None
+	}
+}
+
+/// Resolves symbolic links and `..` components of a directory path.
+///
+/// Components of `path` which do not yet exist must not contain `..`,
+/// and a dangling symbolic link is not followed.
+fn resolve_dir(path: &Path) -> Option<PathBuf> {
+	let (mut resolved, rest) = path.ancestors().find_map(|p| match p.canonicalize() {
+		Ok(base) => Some((base, path.strip_prefix(p).ok()?)),
+		Err(_) => None,
+	})?;
+
+	for c in rest.components() {
+		match c {
+			Component::Normal(c) => resolved.push(c),
+			_ => return None,
+		}
+
+		// A component which exists, but could not be canonicalized,
+		// is a dangling symbolic link, which may point anywhere.
+		if fs::symlink_metadata(&resolved).is_ok() {
+			return None;
+		}
+	}
+
+	Some(resolved)
+}
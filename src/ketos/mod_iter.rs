@@ -0,0 +1,243 @@
+//! Implements builtin `iter` module.
+//!
+//! Functions accepting a sequence accept either a `list` or a `lazy-seq`.
+//! Sequences are stateful; a value taken from a `lazy-seq` by `next` or
+//! any derived sequence is not produced again.
+//!
+//! Sequences derived by `take`, `map`, and `filter` may be nested no deeper
+//! than the `call_stack_size` limit, as producing a value recurses once
+//! for each nested sequence.
+
+use std::rc::Rc;
+
+use crate::error::Error;
+use crate::exec::{panic, Context};
+use crate::function::Arity::{Exact, Range};
+use crate::integer::Integer;
+use crate::module::{Module, ModuleBuilder};
+use crate::restrict::RestrictError;
+use crate::scope::Scope;
+use crate::seq::{get_seq, LazySeq};
+use crate::value::{FromValueRef, Value};
+
+/// Loads the `iter` module into the given scope.
+pub fn load(scope: Scope) -> Module {
+	ModuleBuilder::new("iter", scope)
+		.add_function(
+			"iter",
+			fn_iter,
+			Exact(1),
+			Some(
+				"Returns a `lazy-seq` producing the elements of a list.
+Given a `lazy-seq`, returns the same sequence.",
+			),
+		)
+		.add_function(
+			"next",
+			fn_next,
+			Range(1, 2),
+			Some(
+				"    (next seq)
+    (next seq default)
+
+Returns the next value of a sequence.
+If the sequence is exhausted, returns `default`, or `()` if not given.",
+			),
+		)
+		.add_function(
+			"range",
+			fn_range,
+			Range(1, 3),
+			Some(
+				"    (range end)
+    (range start end)
+    (range start end step)
+
+Returns a `lazy-seq` of integers from `start`, stepping by `step`,
+up to but not including `end`. By default, `start` is `0` and
+`step` is `1`. If `end` is `()`, the sequence is infinite.",
+			),
+		)
+		.add_function(
+			"iterate",
+			fn_iterate,
+			Exact(2),
+			Some(
+				"    (iterate fn value)
+
+Returns an infinite `lazy-seq` producing `value`, followed by
+the result of calling `fn` on each previous value.",
+			),
+		)
+		.add_function(
+			"take",
+			fn_take,
+			Exact(2),
+			Some(
+				"    (take n seq)
+
+Returns a `lazy-seq` producing at most `n` values of a sequence.",
+			),
+		)
+		.add_function(
+			"map",
+			fn_map,
+			Exact(2),
+			Some(
+				"    (map fn seq)
+
+Returns a `lazy-seq` producing the result of calling `fn` on each value
+of a sequence.",
+			),
+		)
+		.add_function(
+			"filter",
+			fn_filter,
+			Exact(2),
+			Some(
+				"    (filter fn seq)
+
+Returns a `lazy-seq` producing the values of a sequence
+for which `fn` returns `true`.",
+			),
+		)
+		.add_function(
+			"collect",
+			fn_collect,
+			Exact(1),
+			Some("Returns a list of all remaining values of a sequence."),
+		)
+		.finish()
+}
+
+/// `iter` returns a lazy sequence for a list.
+///
+/// ```lisp
+/// (iter '(1 2 3))  ; <lazy-seq>
+/// ```
+fn fn_iter(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let seq = get_seq(&args[0])?;
+	Ok(Value::Foreign(seq))
+}
+
+/// `next` returns the next value of a sequence.
+///
+/// ```lisp
+/// (next (range 3))  ; 0
+/// ```
+fn fn_next(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let seq = get_seq(&args[0])?;
+
+	match seq.next(ctx)? {
+		Some(v) => Ok(v),
+		None => Ok(args.get_mut(1).map_or(Value::Unit, Value::take)),
+	}
+}
+
+/// `range` returns a lazy sequence of integers.
+///
+/// ```lisp
+/// (collect (range 1 10 3))  ; (1 4 7)
+/// ```
+fn fn_range(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let (start, end, step) = match *args {
+		[ref end] => (Integer::zero(), end, Integer::one()),
+		[ref start, ref end] => (get_integer(start)?, end, Integer::one()),
+		[ref start, ref end, ref step] => (get_integer(start)?, end, get_integer(step)?),
+		_ => unreachable!(),
+	};
+
+	let end = match *end {
+		Value::Unit => None,
+		ref v => Some(get_integer(v)?),
+	};
+
+	if step.is_zero() {
+		return Err(panic("`range` got 0 step"));
+	}
+
+	Ok(LazySeq::range(start, end, step).into())
+}
+
+/// `iterate` returns an infinite sequence of repeated function calls.
+///
+/// ```lisp
+/// (collect (take 4 (iterate (lambda (n) (* n 2)) 1)))  ; (1 2 4 8)
+/// ```
+fn fn_iterate(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let f = args[0].take();
+	let value = args[1].take();
+
+	Ok(LazySeq::iterate(f, value).into())
+}
+
+/// `take` returns a sequence of at most `n` values.
+///
+/// ```lisp
+/// (collect (take 2 (range ())))  ; (0 1)
+/// ```
+fn fn_take(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let n = usize::from_value_ref(&args[0])?;
+	let seq = get_nested_seq(ctx, &args[1])?;
+
+	Ok(LazySeq::take(n, seq).into())
+}
+
+/// `map` returns a sequence of the results of a function.
+///
+/// ```lisp
+/// (collect (map (lambda (n) (* n n)) (range 4)))  ; (0 1 4 9)
+/// ```
+fn fn_map(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let seq = get_nested_seq(ctx, &args[1])?;
+	let f = args[0].take();
+
+	Ok(LazySeq::map(f, seq).into())
+}
+
+/// `filter` returns a sequence of values satisfying a predicate.
+///
+/// ```lisp
+/// (collect (filter (lambda (n) (= (rem n 2) 0)) (range 6)))  ; (0 2 4)
+/// ```
+fn fn_filter(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let seq = get_nested_seq(ctx, &args[1])?;
+	let f = args[0].take();
+
+	Ok(LazySeq::filter(f, seq).into())
+}
+
+/// `collect` returns a list of the remaining values of a sequence.
+///
+/// The memory limit is checked as each value is added to the list,
+/// so that collecting an infinite sequence fails rather than running
+/// until the time limit is reached.
+fn fn_collect(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let seq = get_seq(&args[0])?;
+	let mut values = Vec::new();
+	let mut size = 0;
+
+	while let Some(v) = seq.next(ctx)? {
+		size += v.size();
+		ctx.check_memory(size)?;
+		values.push(v);
+	}
+
+	Ok(values.into())
+}
+
+/// Returns a sequence to be nested within another, if the nesting depth
+/// would not exceed the call stack limit.
+fn get_nested_seq(ctx: &Context, v: &Value) -> Result<Rc<LazySeq>, Error> {
+	let seq = get_seq(v)?;
+
+	if seq.depth() >= ctx.restrict().call_stack_size {
+		return Err(From::from(RestrictError::CallStackExceeded));
+	}
+
+	Ok(seq)
+}
+
+fn get_integer(v: &Value) -> Result<Integer, Error> {
+	<&Integer>::from_value_ref(v).cloned().map_err(From::from)
+}
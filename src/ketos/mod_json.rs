@@ -0,0 +1,760 @@
+//! Implements builtin `json` module.
+//!
+//! JSON values are converted to and from `Value` as follows:
+//!
+//! | JSON              | `Value`                                   |
+//! | ----------------- | ----------------------------------------- |
+//! | `null`            | `()`                                      |
+//! | `true`, `false`   | `Bool`                                    |
+//! | integer number    | `Integer`                                 |
+//! | other number      | `Float`                                   |
+//! | string            | `String`                                  |
+//! | array             | `List`; an empty array is `()`            |
+//! | object            | `Map` with string keys, or keyword plist  |
+//!
+//! Encoding and decoding honour the `max_syntax_nesting` limit, and decoding
+//! the `max_integer_size` limit, of the active `RestrictConfig`.
+//!
+//! Object keys decoded with `:objects :plist` are interned as names, which
+//! are never freed; each new name counts toward the `memory_limit` for the
+//! remainder of execution.
+
+use std::error::Error as StdError;
+use std::fmt::{self, Write};
+
+use crate::error::Error;
+use crate::exec::{Context, ExecError};
+use crate::function::Arity::Min;
+use crate::integer::Integer;
+use crate::map::Map;
+use crate::mod_string::check_value;
+use crate::module::{Module, ModuleBuilder};
+use crate::name::{get_standard_name_for, Name};
+use crate::parser::check_integer;
+use crate::restrict::RestrictError;
+use crate::scope::Scope;
+use crate::value::{FromValueRef, Value};
+
+/// Loads the `json` module into the given scope.
+pub fn load(scope: Scope) -> Module {
+	ModuleBuilder::new("json", scope)
+		.add_function(
+			"decode",
+			fn_decode,
+			Min(1),
+			Some(
+				"    (decode string :objects :map)
+    (decode string :objects :plist)
+
+Returns the value represented by a string of JSON text.
+
+Objects are decoded as maps with string keys, unless `:objects :plist`
+is given, in which case they are decoded as lists of alternating
+keywords and values.",
+			),
+		)
+		.add_function(
+			"encode",
+			fn_encode,
+			Min(1),
+			Some(
+				"    (encode value)
+    (encode value :pretty true :indent 2 :objects :plist)
+
+Returns a string of JSON text representing a value.
+
+If `:pretty` is true, the output is spread across multiple lines
+and indented by `:indent` spaces per level. Giving `:indent` implies
+`:pretty true`.
+
+If `:objects :plist` is given, lists of alternating keywords and values
+are encoded as objects.",
+			),
+		)
+		.finish()
+}
+
+/// Represents an error in decoding JSON text.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct JsonError {
+	/// Byte offset within input at which the error occurred
+	pub offset: usize,
+	/// Kind of error generated
+	pub kind: JsonErrorKind,
+}
+
+impl JsonError {
+	/// Creates a new `JsonError`.
+	pub fn new(offset: usize, kind: JsonErrorKind) -> JsonError {
+		JsonError { offset, kind }
+	}
+}
+
+impl fmt::Display for JsonError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{} at byte {}", self.kind, self.offset)
+	}
+}
+
+impl StdError for JsonError {
+	fn description(&self) -> &str {
+		"json error"
+	}
+}
+
+/// Describes the kind of error encountered in decoding JSON text.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum JsonErrorKind {
+	/// Unescaped control character within a string
+	ControlCharacter(char),
+	/// Invalid escape sequence within a string
+	InvalidEscape(char),
+	/// Invalid number literal
+	InvalidNumber,
+	/// Invalid or unpaired `\u` escape sequence
+	InvalidUnicodeEscape,
+	/// Object key is not a string
+	KeyMustBeString,
+	/// Unexpected character
+	UnexpectedChar(char),
+	/// Unexpected end of input
+	UnexpectedEof,
+}
+
+impl fmt::Display for JsonErrorKind {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			JsonErrorKind::ControlCharacter(ch) => {
+				write!(f, "control character in string: {:?}", ch)
+			}
+			JsonErrorKind::InvalidEscape(ch) => write!(f, "invalid escape sequence: {:?}", ch),
+			JsonErrorKind::InvalidNumber => f.write_str("invalid number"),
+			JsonErrorKind::InvalidUnicodeEscape => f.write_str("invalid unicode escape"),
+			JsonErrorKind::KeyMustBeString => f.write_str("object key must be a string"),
+			JsonErrorKind::UnexpectedChar(ch) => write!(f, "unexpected character: {:?}", ch),
+			JsonErrorKind::UnexpectedEof => f.write_str("unexpected end of input"),
+		}
+	}
+}
+
+/// `decode` returns the value represented by a string of JSON text.
+///
+/// ```lisp
+/// (decode "{\"a\": [1, 2.5, null]}")                 ; {"a" (1 2.5)}
+/// (decode "{\"a\": true}" :objects :plist)           ; (:a true)
+/// ```
+fn fn_decode(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let text = <&str>::from_value_ref(&args[0])?;
+	let mut plist = false;
+
+	for (name, value) in options(ctx, &args[1..], &["objects"])? {
+		if name == "objects" {
+			plist = objects_plist(ctx, value)?;
+		}
+	}
+
+	let mut dec = Decoder {
+		ctx,
+		text,
+		pos: 0,
+		depth: 0,
+		plist,
+	};
+
+	dec.skip_whitespace();
+	let v = dec.decode_value()?;
+	dec.skip_whitespace();
+
+	if let Some(ch) = dec.peek_char() {
+		return Err(dec.error(JsonErrorKind::UnexpectedChar(ch)));
+	}
+
+	check_value(ctx, v)
+}
+
+/// `encode` returns a string of JSON text representing a value.
+///
+/// ```lisp
+/// (encode (hash-map "a" '(1 2)))                     ; "{\"a\":[1,2]}"
+/// (encode '(:a 1) :objects :plist :indent 4)
+/// ```
+fn fn_encode(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let mut enc = Encoder {
+		ctx,
+		out: String::new(),
+		indent: None,
+		plist: false,
+	};
+
+	for (name, value) in options(ctx, &args[1..], &["indent", "objects", "pretty"])? {
+		match name.as_str() {
+			"indent" => enc.indent = Some(usize::from_value_ref(value)?),
+			"objects" => enc.plist = objects_plist(ctx, value)?,
+			"pretty" => {
+				if bool::from_value_ref(value)? {
+					enc.indent.get_or_insert(2);
+				} else {
+					enc.indent = None;
+				}
+			}
+			_ => unreachable!(),
+		}
+	}
+
+	enc.encode_value(&args[0], 0)?;
+
+	Ok(enc.out.into())
+}
+
+/// Returns keyword-value pairs from trailing function arguments.
+pub(crate) fn options<'a>(
+	ctx: &Context,
+	args: &'a [Value],
+	accepted: &[&str],
+) -> Result<Vec<(String, &'a Value)>, Error> {
+	if !args.len().is_multiple_of(2) {
+		return Err(From::from(ExecError::OddKeywordParams));
+	}
+
+	let names = ctx.scope().borrow_names();
+	let mut res = Vec::with_capacity(args.len() / 2);
+
+	for pair in args.chunks(2) {
+		let name = match pair[0] {
+			Value::Keyword(name) => name,
+			ref v => return Err(From::from(ExecError::expected("keyword", v))),
+		};
+
+		let s = names.get(name);
+
+		if !accepted.contains(&s) {
+			return Err(From::from(ExecError::UnrecognizedKeyword(name)));
+		}
+
+		res.push((s.to_owned(), &pair[1]));
+	}
+
+	Ok(res)
+}
+
+/// Interprets the value of an `:objects` option.
+fn objects_plist(ctx: &Context, v: &Value) -> Result<bool, Error> {
+	if let Value::Keyword(name) = *v {
+		match ctx.scope().borrow_names().get(name) {
+			"map" => return Ok(false),
+			"plist" => return Ok(true),
+			_ => (),
+		}
+	}
+
+	Err(From::from(ExecError::expected("`:map` or `:plist`", v)))
+}
+
+struct Decoder<'a> {
+	ctx: &'a Context,
+	text: &'a str,
+	pos: usize,
+	depth: usize,
+	plist: bool,
+}
+
+impl<'a> Decoder<'a> {
+	fn error(&self, kind: JsonErrorKind) -> Error {
+		Error::custom(JsonError::new(self.pos, kind))
+	}
+
+	fn peek_char(&self) -> Option<char> {
+		self.text[self.pos..].chars().next()
+	}
+
+	fn next_char(&mut self) -> Result<char, Error> {
+		match self.peek_char() {
+			Some(ch) => {
+				self.pos += ch.len_utf8();
+				Ok(ch)
+			}
+			None => Err(self.error(JsonErrorKind::UnexpectedEof)),
+		}
+	}
+
+	fn expect_char(&mut self, expected: char) -> Result<(), Error> {
+		match self.peek_char() {
+			Some(ch) if ch == expected => {
+				self.pos += 1;
+				Ok(())
+			}
+			Some(ch) => Err(self.error(JsonErrorKind::UnexpectedChar(ch))),
+			None => Err(self.error(JsonErrorKind::UnexpectedEof)),
+		}
+	}
+
+	fn skip_whitespace(&mut self) {
+		let rest = &self.text[self.pos..];
+		let trimmed = rest.trim_start_matches([' ', '\t', '\n', '\r']);
+		self.pos += rest.len() - trimmed.len();
+	}
+
+	fn decode_value(&mut self) -> Result<Value, Error> {
+		match self.peek_char() {
+			Some('{') => self.nested(Decoder::decode_object),
+			Some('[') => self.nested(Decoder::decode_array),
+			Some('"') => self.decode_string().map(Value::from),
+			Some('-') | Some('0'..='9') => self.decode_number(),
+			Some(_) => self.decode_literal(),
+			None => Err(self.error(JsonErrorKind::UnexpectedEof)),
+		}
+	}
+
+	fn nested<F>(&mut self, f: F) -> Result<Value, Error>
+	where
+		F: FnOnce(&mut Self) -> Result<Value, Error>,
+	{
+		if self.depth >= self.ctx.restrict().max_syntax_nesting {
+			return Err(From::from(RestrictError::MaxSyntaxNestingExceeded));
+		}
+
+		self.depth += 1;
+		let r = f(self);
+		self.depth -= 1;
+		r
+	}
+
+	fn decode_literal(&mut self) -> Result<Value, Error> {
+		let rest = &self.text[self.pos..];
+
+		for &(lit, ref v) in &[
+			("null", Value::Unit),
+			("true", Value::Bool(true)),
+			("false", Value::Bool(false)),
+		] {
+			if rest.starts_with(lit) {
+				self.pos += lit.len();
+				return Ok(v.clone());
+			}
+		}
+
+		let ch = self.peek_char().expect("caller checked for end of input");
+		Err(self.error(JsonErrorKind::UnexpectedChar(ch)))
+	}
+
+	fn decode_number(&mut self) -> Result<Value, Error> {
+		let start = self.pos;
+		let bytes = self.text.as_bytes();
+		let mut is_float = false;
+
+		let digits = |pos: &mut usize| {
+			let begin = *pos;
+			while bytes.get(*pos).is_some_and(u8::is_ascii_digit) {
+				*pos += 1;
+			}
+			*pos - begin
+		};
+
+		let mut pos = self.pos;
+
+		if bytes[pos] == b'-' {
+			pos += 1;
+		}
+
+		let int_start = pos;
+		let n = digits(&mut pos);
+
+		if n == 0 || (n > 1 && bytes[int_start] == b'0') {
+			self.pos = int_start;
+			return Err(self.error(JsonErrorKind::InvalidNumber));
+		}
+
+		if bytes.get(pos) == Some(&b'.') {
+			pos += 1;
+			is_float = true;
+
+			if digits(&mut pos) == 0 {
+				self.pos = pos;
+				return Err(self.error(JsonErrorKind::InvalidNumber));
+			}
+		}
+
+		if let Some(b'e') | Some(b'E') = bytes.get(pos) {
+			pos += 1;
+			is_float = true;
+
+			if let Some(b'+') | Some(b'-') = bytes.get(pos) {
+				pos += 1;
+			}
+
+			if digits(&mut pos) == 0 {
+				self.pos = pos;
+				return Err(self.error(JsonErrorKind::InvalidNumber));
+			}
+		}
+
+		self.pos = pos;
+		let s = &self.text[start..pos];
+
+		if is_float {
+			let f: f64 = s
+				.parse()
+				.map_err(|_| self.error(JsonErrorKind::InvalidNumber))?;
+			Ok(Value::Float(f))
+		} else {
+			check_integer(self.ctx, s, 10)?;
+			let i = Integer::from_str_radix(s, 10)
+				.map_err(|_| self.error(JsonErrorKind::InvalidNumber))?;
+			Ok(Value::Integer(i))
+		}
+	}
+
+	fn decode_string(&mut self) -> Result<String, Error> {
+		self.expect_char('"')?;
+
+		let mut res = String::new();
+
+		loop {
+			let rest = &self.text[self.pos..];
+			let end = rest
+				.find(|ch: char| ch == '"' || ch == '\\' || ch < ' ')
+				.unwrap_or(rest.len());
+
+			res.push_str(&rest[..end]);
+			self.pos += end;
+
+			match self.next_char()? {
+				'"' => break,
+				'\\' => {
+					let esc_pos = self.pos;
+
+					let ch = match self.next_char()? {
+						'"' => '"',
+						'\\' => '\\',
+						'/' => '/',
+						'b' => '\x08',
+						'f' => '\x0c',
+						'n' => '\n',
+						'r' => '\r',
+						't' => '\t',
+						'u' => self.decode_unicode_escape()?,
+						ch => {
+							self.pos = esc_pos;
+							return Err(self.error(JsonErrorKind::InvalidEscape(ch)));
+						}
+					};
+
+					res.push(ch);
+				}
+				ch => {
+					self.pos -= ch.len_utf8();
+					return Err(self.error(JsonErrorKind::ControlCharacter(ch)));
+				}
+			}
+		}
+
+		Ok(res)
+	}
+
+	/// Decodes the hex digits of a `\u` escape, including a following
+	/// low surrogate escape, if the first is a high surrogate.
+	fn decode_unicode_escape(&mut self) -> Result<char, Error> {
+		let start = self.pos - 2;
+		let hi = self.decode_hex4(start)?;
+
+		let code = if (0xd800..0xdc00).contains(&hi) {
+			if !self.text[self.pos..].starts_with("\\u") {
+				self.pos = start;
+				return Err(self.error(JsonErrorKind::InvalidUnicodeEscape));
+			}
+
+			self.pos += 2;
+			let lo = self.decode_hex4(start)?;
+
+			if !(0xdc00..0xe000).contains(&lo) {
+				self.pos = start;
+				return Err(self.error(JsonErrorKind::InvalidUnicodeEscape));
+			}
+
+			0x10000 + ((hi - 0xd800) << 10) + (lo - 0xdc00)
+		} else {
+			hi
+		};
+
+		char::from_u32(code).ok_or_else(|| {
+			self.pos = start;
+			self.error(JsonErrorKind::InvalidUnicodeEscape)
+		})
+	}
+
+	fn decode_hex4(&mut self, start: usize) -> Result<u32, Error> {
+		let hex = self.text.get(self.pos..self.pos + 4);
+
+		match hex.and_then(|h| {
+			if h.bytes().all(|b| b.is_ascii_hexdigit()) {
+				u32::from_str_radix(h, 16).ok()
+			} else {
+				None
+			}
+		}) {
+			Some(n) => {
+				self.pos += 4;
+				Ok(n)
+			}
+			None => {
+				self.pos = start;
+				Err(self.error(JsonErrorKind::InvalidUnicodeEscape))
+			}
+		}
+	}
+
+	fn decode_array(&mut self) -> Result<Value, Error> {
+		self.expect_char('[')?;
+		self.skip_whitespace();
+
+		let mut values = Vec::new();
+
+		if self.peek_char() == Some(']') {
+			self.pos += 1;
+			return Ok(Value::Unit);
+		}
+
+		loop {
+			values.push(self.decode_value()?);
+			self.skip_whitespace();
+
+			match self.next_char()? {
+				',' => self.skip_whitespace(),
+				']' => break,
+				ch => {
+					self.pos -= ch.len_utf8();
+					return Err(self.error(JsonErrorKind::UnexpectedChar(ch)));
+				}
+			}
+		}
+
+		Ok(values.into())
+	}
+
+	fn decode_object(&mut self) -> Result<Value, Error> {
+		self.expect_char('{')?;
+		self.skip_whitespace();
+
+		let mut map = Map::new();
+		let mut plist = Vec::new();
+
+		if self.peek_char() == Some('}') {
+			self.pos += 1;
+		} else {
+			loop {
+				if let Some(ch) = self.peek_char() {
+					if ch != '"' {
+						return Err(self.error(JsonErrorKind::KeyMustBeString));
+					}
+				}
+
+				let key = self.decode_string()?;
+				self.skip_whitespace();
+				self.expect_char(':')?;
+				self.skip_whitespace();
+				let value = self.decode_value()?;
+
+				if self.plist {
+					plist.push(Value::Keyword(self.intern_key(&key)?));
+					plist.push(value);
+				} else {
+					map.insert(key.into(), value);
+				}
+
+				self.skip_whitespace();
+
+				match self.next_char()? {
+					',' => self.skip_whitespace(),
+					'}' => break,
+					ch => {
+						self.pos -= ch.len_utf8();
+						return Err(self.error(JsonErrorKind::UnexpectedChar(ch)));
+					}
+				}
+			}
+		}
+
+		if self.plist {
+			Ok(plist.into())
+		} else {
+			Ok(map.into())
+		}
+	}
+
+	/// Returns the name of a plist key. A new name is held for the remainder
+	/// of execution, so its size is added to memory held before it is interned.
+	fn intern_key(&self, key: &str) -> Result<Name, Error> {
+		let scope = self.ctx.scope();
+		let mut names = scope.borrow_names_mut();
+
+		if let Some(name) = get_standard_name_for(key).or_else(|| names.get_name(key)) {
+			return Ok(name);
+		}
+
+		self.ctx.replace_memory(0, key.len())?;
+
+		Ok(names.add(key))
+	}
+}
+
+struct Encoder<'a> {
+	ctx: &'a Context,
+	out: String,
+	indent: Option<usize>,
+	plist: bool,
+}
+
+impl<'a> Encoder<'a> {
+	fn encode_value(&mut self, v: &Value, level: usize) -> Result<(), Error> {
+		match *v {
+			Value::Unit => self.out.push_str("null"),
+			Value::Bool(b) => self.out.push_str(if b { "true" } else { "false" }),
+			Value::Integer(ref i) => write!(self.out, "{}", i).unwrap(),
+			Value::Float(f) => {
+				if !f.is_finite() {
+					return Err(From::from(ExecError::expected("finite float", v)));
+				}
+				write!(self.out, "{:?}", f).unwrap();
+			}
+			Value::Char(ch) => self.encode_str(ch.encode_utf8(&mut [0; 4])),
+			Value::String(ref s) => self.encode_str(s),
+			Value::Keyword(name) | Value::Name(name) => self.encode_name(name),
+			Value::List(ref li) => {
+				if self.plist && is_plist(li) {
+					let pairs = li.chunks(2).map(|pair| (&pair[0], &pair[1]));
+					self.encode_object(pairs, level)?;
+				} else {
+					self.encode_array(li.iter(), level)?;
+				}
+			}
+			Value::Set(ref s) => self.encode_array(s.iter(), level)?,
+			Value::Map(ref m) => self.encode_object(m.iter(), level)?,
+			ref v => return Err(From::from(ExecError::expected("json value", v))),
+		}
+
+		Ok(())
+	}
+
+	fn encode_array<'v, I>(&mut self, iter: I, level: usize) -> Result<(), Error>
+	where
+		I: ExactSizeIterator<Item = &'v Value>,
+	{
+		self.check_nesting(level)?;
+
+		if iter.len() == 0 {
+			self.out.push_str("[]");
+			return Ok(());
+		}
+
+		self.out.push('[');
+
+		for (i, v) in iter.enumerate() {
+			if i != 0 {
+				self.out.push(',');
+			}
+			self.newline(level + 1);
+			self.encode_value(v, level + 1)?;
+			self.check_memory()?;
+		}
+
+		self.newline(level);
+		self.out.push(']');
+
+		Ok(())
+	}
+
+	fn encode_object<'v, I>(&mut self, iter: I, level: usize) -> Result<(), Error>
+	where
+		I: ExactSizeIterator<Item = (&'v Value, &'v Value)>,
+	{
+		self.check_nesting(level)?;
+
+		if iter.len() == 0 {
+			self.out.push_str("{}");
+			return Ok(());
+		}
+
+		self.out.push('{');
+
+		for (i, (k, v)) in iter.enumerate() {
+			if i != 0 {
+				self.out.push(',');
+			}
+			self.newline(level + 1);
+
+			match *k {
+				Value::Char(ch) => self.encode_str(ch.encode_utf8(&mut [0; 4])),
+				Value::String(ref s) => self.encode_str(s),
+				Value::Keyword(name) | Value::Name(name) => self.encode_name(name),
+				ref k => return Err(From::from(ExecError::expected("string or keyword", k))),
+			}
+
+			self.out.push(':');
+			if self.indent.is_some() {
+				self.out.push(' ');
+			}
+
+			self.encode_value(v, level + 1)?;
+			self.check_memory()?;
+		}
+
+		self.newline(level);
+		self.out.push('}');
+
+		Ok(())
+	}
+
+	fn encode_name(&mut self, name: Name) {
+		let ctx = self.ctx;
+		let names = ctx.scope().borrow_names();
+		self.encode_str(names.get(name));
+	}
+
+	fn encode_str(&mut self, s: &str) {
+		self.out.push('"');
+
+		for ch in s.chars() {
+			match ch {
+				'"' => self.out.push_str("\\\""),
+				'\\' => self.out.push_str("\\\\"),
+				'\n' => self.out.push_str("\\n"),
+				'\r' => self.out.push_str("\\r"),
+				'\t' => self.out.push_str("\\t"),
+				'\x08' => self.out.push_str("\\b"),
+				'\x0c' => self.out.push_str("\\f"),
+				ch if ch < ' ' || ch == '\x7f' => write!(self.out, "\\u{:04x}", ch as u32).unwrap(),
+				ch => self.out.push(ch),
+			}
+		}
+
+		self.out.push('"');
+	}
+
+	fn newline(&mut self, level: usize) {
+		if let Some(indent) = self.indent {
+			self.out.push('\n');
+			self.out.extend((0..indent * level).map(|_| ' '));
+		}
+	}
+
+	fn check_memory(&self) -> Result<(), RestrictError> {
+		self.ctx.check_memory(self.out.len())
+	}
+
+	fn check_nesting(&self, level: usize) -> Result<(), RestrictError> {
+		if level >= self.ctx.restrict().max_syntax_nesting {
+			Err(RestrictError::MaxSyntaxNestingExceeded)
+		} else {
+			Ok(())
+		}
+	}
+}
+
+/// Returns whether a list consists of alternating keywords and values.
+fn is_plist(li: &[Value]) -> bool {
+	li.len().is_multiple_of(2)
+		&& li
+			.iter()
+			.step_by(2)
+			.all(|v| matches!(*v, Value::Keyword(_)))
+}
@@ -0,0 +1,380 @@
+//! Implements builtin `list-core` module.
+//!
+//! These functions are implemented in Rust and call back into the virtual
+//! machine to run any function given as an argument. They are re-exported
+//! by the `list` module, through which they are most conveniently imported.
+
+use std::cmp::Ordering;
+
+use crate::error::Error;
+use crate::exec::{Context, ExecError};
+use crate::function::Arity::{Exact, Min};
+use crate::function::{call_callback, call_predicate};
+use crate::map::Map;
+use crate::module::{Module, ModuleBuilder};
+use crate::scope::Scope;
+use crate::value::Value;
+
+/// Loads the `list-core` module into the given scope.
+pub fn load(scope: Scope) -> Module {
+	ModuleBuilder::new("list-core", scope)
+		.add_function(
+			"sort",
+			fn_sort,
+			Exact(1),
+			Some(
+				"Returns a list of values sorted in ascending order.
+
+Values of different types may not be compared. Attempts to do so will
+result in an error.",
+			),
+		)
+		.add_function(
+			"sort-by",
+			fn_sort_by,
+			Exact(2),
+			Some(
+				"    (sort-by function list)
+
+Returns a list of values sorted in ascending order of the key returned
+by calling `function` with each value. The sort is stable.",
+			),
+		)
+		.add_function(
+			"sort-with",
+			fn_sort_with,
+			Exact(2),
+			Some(
+				"    (sort-with function list)
+
+Returns a list of values sorted using `function`, which is called with two
+values and returns whether the first should be ordered before the second.
+The sort is stable.",
+			),
+		)
+		.add_function(
+			"map",
+			fn_map,
+			Exact(2),
+			Some(
+				"    (map function list)
+
+Returns a list of the results of calling `function` with each value.",
+			),
+		)
+		.add_function(
+			"filter",
+			fn_filter,
+			Exact(2),
+			Some(
+				"    (filter function list)
+
+Returns a list of the values for which `function` returns `true`.",
+			),
+		)
+		.add_function(
+			"fold",
+			fn_fold,
+			Exact(3),
+			Some(
+				"    (fold function init list)
+
+Calls `function` with an accumulated value, beginning with `init`,
+and each value in turn. Returns the final accumulated value.",
+			),
+		)
+		.add_function(
+			"zip",
+			fn_zip,
+			Min(1),
+			Some(
+				"    (zip list ...)
+
+Returns a list of lists containing corresponding values from each list.
+The result is as long as the shortest list.",
+			),
+		)
+		.add_function(
+			"group-by",
+			fn_group_by,
+			Exact(2),
+			Some(
+				"    (group-by function list)
+
+Returns a map of each key returned by calling `function` with each value
+to a list of the values which produced that key, in their original order.",
+			),
+		)
+		.add_function(
+			"partition",
+			fn_partition,
+			Exact(2),
+			Some(
+				"    (partition function list)
+
+Returns a list of two lists: the values for which `function` returns `true`,
+followed by those for which it returns `false`.",
+			),
+		)
+		.add_function(
+			"dedup",
+			fn_dedup,
+			Exact(1),
+			Some(
+				"Returns a list with consecutive equal values replaced by a single value.
+To remove all duplicate values, sort the list first.",
+			),
+		)
+		.finish()
+}
+
+/// `sort` returns a list of values sorted in ascending order.
+///
+/// ```lisp
+/// (sort '(3 1 2))  ; (1 2 3)
+/// ```
+fn fn_sort(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let li = take_list(args[0].take())?;
+
+	let li = merge_sort(li, &mut |a, b| Ok(sort_compare(a, b)? == Ordering::Less))?;
+	Ok(li.into())
+}
+
+/// `sort-by` returns a list of values sorted by a key function.
+///
+/// ```lisp
+/// (sort-by len '("ccc" "a" "bb"))  ; ("a" "bb" "ccc")
+/// ```
+fn fn_sort_by(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let f = args[0].take();
+	let li = take_list(args[1].take())?;
+
+	let keyed = li
+		.into_iter()
+		.map(|v| Ok((call_callback(ctx, &f, vec![v.clone()])?, v)))
+		.collect::<Result<Vec<_>, Error>>()?;
+
+	let keyed = merge_sort(keyed, &mut |a, b| {
+		Ok(sort_compare(&a.0, &b.0)? == Ordering::Less)
+	})?;
+	Ok(keyed.into_iter().map(|(_, v)| v).collect::<Vec<_>>().into())
+}
+
+/// Compares two values for `sort` and `sort-by`.
+///
+/// Values of different types cannot be ordered; such an error is reported
+/// as `CannotCompare`, naming the type of the first value.
+fn sort_compare(a: &Value, b: &Value) -> Result<Ordering, ExecError> {
+	a.compare(b).map_err(|e| match e {
+		ExecError::TypeMismatch { lhs, .. } => ExecError::CannotCompare(lhs),
+		e => e,
+	})
+}
+
+/// `sort-with` returns a list of values sorted by a comparison function.
+///
+/// ```lisp
+/// (sort-with > '(1 3 2))  ; (3 2 1)
+/// ```
+fn fn_sort_with(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let f = args[0].take();
+	let li = take_list(args[1].take())?;
+
+	let li = merge_sort(li, &mut |a, b| {
+		call_predicate(ctx, &f, vec![a.clone(), b.clone()])
+	})?;
+	Ok(li.into())
+}
+
+/// `map` returns a list of the results of calling a function with each value.
+///
+/// ```lisp
+/// (map (lambda (n) (* n 2)) '(1 2 3))  ; (2 4 6)
+/// ```
+fn fn_map(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let f = args[0].take();
+	let li = take_list(args[1].take())?;
+
+	let li = li
+		.into_iter()
+		.map(|v| call_callback(ctx, &f, vec![v]))
+		.collect::<Result<Vec<_>, _>>()?;
+	Ok(li.into())
+}
+
+/// `filter` returns a list of the values satisfying a predicate.
+///
+/// ```lisp
+/// (filter (lambda (n) (> n 1)) '(1 2 3))  ; (2 3)
+/// ```
+fn fn_filter(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let f = args[0].take();
+	let li = take_list(args[1].take())?;
+
+	let mut res = Vec::new();
+
+	for v in li {
+		if call_predicate(ctx, &f, vec![v.clone()])? {
+			res.push(v);
+		}
+	}
+
+	Ok(res.into())
+}
+
+/// `fold` accumulates a value by calling a function with each value in turn.
+///
+/// ```lisp
+/// (fold + 0 '(1 2 3))  ; 6
+/// ```
+fn fn_fold(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let f = args[0].take();
+	let mut acc = args[1].take();
+	let li = take_list(args[2].take())?;
+
+	for v in li {
+		acc = call_callback(ctx, &f, vec![acc, v])?;
+	}
+
+	Ok(acc)
+}
+
+/// `zip` returns a list of lists of corresponding values from each list.
+///
+/// ```lisp
+/// (zip '(1 2 3) '(a b))  ; ((1 a) (2 b))
+/// ```
+fn fn_zip(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let lists = args
+		.iter_mut()
+		.map(|v| take_list(v.take()))
+		.collect::<Result<Vec<_>, _>>()?;
+
+	let n = lists.iter().map(|li| li.len()).min().unwrap_or(0);
+	let mut iters = lists
+		.into_iter()
+		.map(|li| li.into_iter())
+		.collect::<Vec<_>>();
+
+	let res = (0..n)
+		.map(|_| {
+			iters
+				.iter_mut()
+				.filter_map(|it| it.next())
+				.collect::<Vec<_>>()
+				.into()
+		})
+		.collect::<Vec<Value>>();
+
+	Ok(res.into())
+}
+
+/// `group-by` returns a map of keys to lists of the values producing each key.
+///
+/// ```lisp
+/// (group-by len '("a" "bb" "c"))  ; {1 ("a" "c") 2 ("bb")}
+/// ```
+fn fn_group_by(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let f = args[0].take();
+	let li = take_list(args[1].take())?;
+
+	let mut groups = Map::new();
+
+	for v in li {
+		let key = call_callback(ctx, &f, vec![v.clone()])?;
+
+		let mut group = match groups.remove(&key) {
+			Some(Value::List(li)) => li.into_vec(),
+			_ => Vec::new(),
+		};
+
+		group.push(v);
+		groups.insert(key, group.into());
+	}
+
+	Ok(groups.into())
+}
+
+/// `partition` divides a list into values satisfying a predicate and the rest.
+///
+/// ```lisp
+/// (partition (lambda (n) (> n 1)) '(1 2 3))  ; ((2 3) (1))
+/// ```
+fn fn_partition(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let f = args[0].take();
+	let li = take_list(args[1].take())?;
+
+	let mut yes = Vec::new();
+	let mut no = Vec::new();
+
+	for v in li {
+		if call_predicate(ctx, &f, vec![v.clone()])? {
+			yes.push(v);
+		} else {
+			no.push(v);
+		}
+	}
+
+	Ok(vec![Value::from(yes), Value::from(no)].into())
+}
+
+/// `dedup` replaces consecutive equal values with a single value.
+///
+/// ```lisp
+/// (dedup '(1 1 2 1))  ; (1 2 1)
+/// ```
+fn fn_dedup(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let li = take_list(args[0].take())?;
+
+	let mut res: Vec<Value> = Vec::with_capacity(li.len());
+
+	for v in li {
+		match res.last() {
+			Some(last) if last.is_equal(&v)? => (),
+			_ => res.push(v),
+		}
+	}
+
+	Ok(res.into())
+}
+
+/// Returns the values of a list, or an empty `Vec` for `()`.
+fn take_list(v: Value) -> Result<Vec<Value>, ExecError> {
+	match v {
+		Value::Unit => Ok(Vec::new()),
+		Value::List(li) => Ok(li.into_vec()),
+		ref v => Err(ExecError::expected("list", v)),
+	}
+}
+
+/// Sorts values with a stable merge sort, using a fallible less-than predicate.
+///
+/// `slice::sort_by` is not used because comparisons may fail and
+/// a user-supplied comparison need not be a total order.
+fn merge_sort<T>(
+	mut v: Vec<T>,
+	less: &mut dyn FnMut(&T, &T) -> Result<bool, Error>,
+) -> Result<Vec<T>, Error> {
+	if v.len() < 2 {
+		return Ok(v);
+	}
+
+	let right = v.split_off(v.len() / 2);
+	let left = merge_sort(v, less)?;
+	let right = merge_sort(right, less)?;
+
+	let mut res = Vec::with_capacity(left.len() + right.len());
+	let mut left = left.into_iter().peekable();
+	let mut right = right.into_iter().peekable();
+
+	while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+		// Taking from the left unless strictly greater keeps the sort stable.
+		let next = if less(b, a)? { &mut right } else { &mut left };
+		res.extend(next.next());
+	}
+
+	res.extend(left);
+	res.extend(right);
+
+	Ok(res)
+}
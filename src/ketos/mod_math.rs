@@ -1,13 +1,18 @@
 //! Implements builtin `math` module.
 
+use std::error::Error as StdError;
 use std::f64::consts;
+use std::fmt;
+use std::mem::replace;
 
 use crate::error::Error;
 use crate::exec::{Context, ExecError};
-use crate::function::Arity::Exact;
+use crate::function::check_bits;
+use crate::function::Arity::{Exact, Min};
+use crate::integer::Integer;
 use crate::module::{Module, ModuleBuilder};
 use crate::scope::Scope;
-use crate::value::Value;
+use crate::value::{FromValueRef, Value};
 
 /// Loads the `math` module into the given scope.
 pub fn load(scope: Scope) -> Module {
@@ -84,6 +89,24 @@ Computes the four quadrant arctangent of `y` and `x`.
 * `y < 0`: `arctan(y/x) - pi` -> `(-pi, -pi/2)`",
 			),
 		)
+		.add_function(
+			"binomial",
+			fn_binomial,
+			Exact(2),
+			Some(
+				"\
+    (binomial n k)
+
+Returns the number of ways to choose `k` items from `n` items,
+for non-negative integers `n` and `k`.",
+			),
+		)
+		.add_function(
+			"bit-length",
+			fn_bit_length,
+			Exact(1),
+			Some("Returns the number of bits required to represent the absolute value of an integer."),
+		)
 		.add_function(
 			"cos",
 			fn_cos,
@@ -102,6 +125,53 @@ Computes the four quadrant arctangent of `y` and `x`.
 			Exact(1),
 			Some("Converts a value in radians to degrees."),
 		)
+		.add_function(
+			"factorial",
+			fn_factorial,
+			Exact(1),
+			Some("Returns the factorial of a non-negative integer."),
+		)
+		.add_function(
+			"gcd",
+			fn_gcd,
+			Min(1),
+			Some("Returns the greatest common divisor of one or more integers."),
+		)
+		.add_function(
+			"iroot",
+			fn_iroot,
+			Exact(2),
+			Some(
+				"\
+    (iroot x n)
+
+Returns the integer `n`th root of an integer, rounded toward zero.
+`x` may be negative only if `n` is odd.",
+			),
+		)
+		.add_function(
+			"is-prime",
+			fn_is_prime,
+			Exact(1),
+			Some(
+				"\
+Returns whether an integer is prime.
+The result is exact for values below `3.3e24`; larger values
+are tested with a strong probable prime test.",
+			),
+		)
+		.add_function(
+			"isqrt",
+			fn_isqrt,
+			Exact(1),
+			Some("Returns the square root of a non-negative integer, rounded down."),
+		)
+		.add_function(
+			"lcm",
+			fn_lcm,
+			Min(1),
+			Some("Returns the least common multiple of one or more integers."),
+		)
 		.add_function(
 			"ln",
 			fn_ln,
@@ -137,6 +207,37 @@ Returns the base 2 logarithm of a number.",
 Returns the base 10 logarithm of a number.",
 			),
 		)
+		.add_function(
+			"mod-inverse",
+			fn_mod_inverse,
+			Exact(2),
+			Some(
+				"\
+    (mod-inverse x m)
+
+Returns the integer `y` such that `(x * y) mod m` is `1`.
+The result has the same sign as `m`.",
+			),
+		)
+		.add_function(
+			"mod-pow",
+			fn_mod_pow,
+			Exact(3),
+			Some(
+				"\
+    (mod-pow base exp m)
+
+Returns `base` raised to the power `exp`, modulo `m`.
+The result has the same sign as `m`. A negative `exp` raises
+the modular inverse of `base` to the power `-exp`.",
+			),
+		)
+		.add_function(
+			"popcount",
+			fn_popcount,
+			Exact(1),
+			Some("Returns the number of one bits in the absolute value of an integer."),
+		)
 		.add_function(
 			"radians",
 			fn_radians,
@@ -222,6 +323,33 @@ fn fn_atan2(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
 	Ok(fa.atan2(fb).into())
 }
 
+/// `binomial` returns the binomial coefficient of `n` and `k`.
+fn fn_binomial(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let n = get_non_negative(&args[0], "binomial")?;
+	let k = get_non_negative(&args[1], "binomial")?;
+
+	if k > n {
+		return Ok(Integer::zero().into());
+	}
+
+	let k = (n - k).min(k.clone());
+	let k = k.to_u32().ok_or(ExecError::Overflow)?;
+	let mut r = Integer::one();
+
+	for i in 0..k {
+		r = r * (n - Integer::from_u32(i)) / Integer::from_u32(i + 1);
+		check_bits(ctx, r.bits())?;
+	}
+
+	Ok(r.into())
+}
+
+/// `bit-length` returns the number of bits required to represent an integer.
+fn fn_bit_length(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let i = <&Integer>::from_value_ref(&args[0])?;
+	Ok(i.bits().into())
+}
+
 /// `cos` computes the cosine of a number, in radians.
 fn fn_cos(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
 	let f = get_float(&args[0])?;
@@ -240,6 +368,83 @@ fn fn_degrees(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
 	Ok(f.to_degrees().into())
 }
 
+/// `factorial` returns the factorial of a non-negative integer.
+fn fn_factorial(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let n = get_non_negative(&args[0], "factorial")?;
+	let n = n.to_u32().ok_or(ExecError::Overflow)?;
+	let mut r = Integer::one();
+
+	for i in 2..=n {
+		r *= Integer::from_u32(i);
+		check_bits(ctx, r.bits())?;
+	}
+
+	Ok(r.into())
+}
+
+/// `gcd` returns the greatest common divisor of integers.
+///
+/// ```lisp
+/// (gcd 12 18 27)  ; 3
+/// ```
+fn fn_gcd(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let mut r = <&Integer>::from_value_ref(&args[0])?.abs();
+
+	for arg in &args[1..] {
+		r = r.gcd(<&Integer>::from_value_ref(arg)?);
+	}
+
+	Ok(r.into())
+}
+
+/// `iroot` returns the integer `n`th root of an integer.
+///
+/// ```lisp
+/// (iroot 30 3)  ; 3
+/// ```
+fn fn_iroot(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let x = <&Integer>::from_value_ref(&args[0])?;
+	let n = u32::from_value_ref(&args[1])?;
+
+	if n == 0 || (n.is_multiple_of(2) && x.is_negative()) {
+		return Err(From::from(MathError::DomainError("iroot")));
+	}
+
+	Ok(x.nth_root(n).into())
+}
+
+/// `is-prime` returns whether an integer is prime.
+fn fn_is_prime(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let n = <&Integer>::from_value_ref(&args[0])?;
+	Ok(is_prime(n).into())
+}
+
+/// `isqrt` returns the integer square root of a non-negative integer.
+///
+/// ```lisp
+/// (isqrt 17)  ; 4
+/// ```
+fn fn_isqrt(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let x = get_non_negative(&args[0], "isqrt")?;
+	Ok(x.sqrt().into())
+}
+
+/// `lcm` returns the least common multiple of integers.
+///
+/// ```lisp
+/// (lcm 4 6)  ; 12
+/// ```
+fn fn_lcm(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let mut r = <&Integer>::from_value_ref(&args[0])?.abs();
+
+	for arg in &args[1..] {
+		r = r.lcm(<&Integer>::from_value_ref(arg)?);
+		check_bits(ctx, r.bits())?;
+	}
+
+	Ok(r.into())
+}
+
 /// `ln` returns the natural logarithm of a number.
 fn fn_ln(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
 	let f = get_float(&args[0])?;
@@ -265,6 +470,47 @@ fn fn_log10(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
 	Ok(f.log10().into())
 }
 
+/// `mod-inverse` returns the modular multiplicative inverse of an integer.
+///
+/// ```lisp
+/// (mod-inverse 3 7)  ; 5
+/// ```
+fn fn_mod_inverse(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let x = <&Integer>::from_value_ref(&args[0])?;
+	let m = <&Integer>::from_value_ref(&args[1])?;
+
+	Ok(mod_inverse(x, m)?.into())
+}
+
+/// `mod-pow` returns an integer raised to a power, modulo another integer.
+///
+/// ```lisp
+/// (mod-pow 4 13 497)  ; 445
+/// ```
+fn fn_mod_pow(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let base = <&Integer>::from_value_ref(&args[0])?;
+	let exp = <&Integer>::from_value_ref(&args[1])?;
+	let m = <&Integer>::from_value_ref(&args[2])?;
+
+	if m.is_zero() {
+		return Err(From::from(ExecError::DivideByZero));
+	}
+
+	let r = if exp.is_negative() {
+		mod_inverse(base, m)?.modpow(&-exp, m)
+	} else {
+		base.modpow(exp, m)
+	};
+
+	Ok(r.into())
+}
+
+/// `popcount` returns the number of one bits in an integer.
+fn fn_popcount(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let i = <&Integer>::from_value_ref(&args[0])?;
+	Ok(i.count_ones().into())
+}
+
 /// `radians` converts a value in degrees to the equivalent value in radians.
 fn fn_radians(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
 	let f = get_float(&args[0])?;
@@ -301,7 +547,7 @@ fn fn_tanh(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
 	Ok(f.tanh().into())
 }
 
-fn get_float(v: &Value) -> Result<f64, ExecError> {
+pub(crate) fn get_float(v: &Value) -> Result<f64, ExecError> {
 	match *v {
 		Value::Float(f) => Ok(f),
 		Value::Integer(ref i) => i.to_f64().ok_or(ExecError::Overflow),
@@ -309,3 +555,125 @@ fn get_float(v: &Value) -> Result<f64, ExecError> {
 		ref v => Err(ExecError::expected("number", v)),
 	}
 }
+
+fn get_non_negative<'a>(v: &'a Value, name: &'static str) -> Result<&'a Integer, Error> {
+	let i = <&Integer>::from_value_ref(v)?;
+
+	if i.is_negative() {
+		Err(From::from(MathError::DomainError(name)))
+	} else {
+		Ok(i)
+	}
+}
+
+/// Returns the modular multiplicative inverse of `x`, with the sign of `m`.
+fn mod_inverse(x: &Integer, m: &Integer) -> Result<Integer, Error> {
+	if m.is_zero() {
+		return Err(From::from(ExecError::DivideByZero));
+	}
+
+	// Extended Euclidean algorithm
+	let (mut r0, mut r1) = (m.abs(), x.mod_floor(&m.abs()));
+	let (mut t0, mut t1) = (Integer::zero(), Integer::one());
+
+	while !r1.is_zero() {
+		let q = &r0 / &r1;
+
+		let r = &r0 - &q * &r1;
+		r0 = replace(&mut r1, r);
+
+		let t = &t0 - &q * &t1;
+		t0 = replace(&mut t1, t);
+	}
+
+	if !r0.is_one() {
+		return Err(From::from(MathError::NoInverse));
+	}
+
+	Ok(t0.mod_floor(m))
+}
+
+/// Bases for the Miller-Rabin test; the first 13 of these are sufficient
+/// to give an exact result for all values below `3.3e24`.
+const PRIME_BASES: [u32; 20] = [
+	2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71,
+];
+
+fn is_prime(n: &Integer) -> bool {
+	if n <= &Integer::one() {
+		return false;
+	}
+
+	for &p in &PRIME_BASES {
+		let p = Integer::from_u32(p);
+
+		if n == &p {
+			return true;
+		}
+		if n.is_multiple_of(&p) {
+			return false;
+		}
+	}
+
+	let one = Integer::one();
+	let two = Integer::from_u32(2);
+	let n_minus_one = n - &one;
+
+	let mut d = n_minus_one.clone();
+	let mut s = 0;
+
+	while d.is_multiple_of(&two) {
+		d = d >> 1;
+		s += 1;
+	}
+
+	'bases: for &a in &PRIME_BASES {
+		let mut x = Integer::from_u32(a).modpow(&d, n);
+
+		if x == one || x == n_minus_one {
+			continue;
+		}
+
+		for _ in 1..s {
+			x = (&x * &x).mod_floor(n);
+
+			if x == n_minus_one {
+				continue 'bases;
+			}
+		}
+
+		return false;
+	}
+
+	true
+}
+
+/// Represents an error in an integer math function.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MathError {
+	/// Argument outside the domain of the named function
+	DomainError(&'static str),
+	/// Modular inverse does not exist
+	NoInverse,
+}
+
+impl fmt::Display for MathError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			MathError::DomainError(name) => write!(f, "argument outside domain of `{}`", name),
+			MathError::NoInverse => f.write_str("modular inverse does not exist"),
+		}
+	}
+}
+
+impl StdError for MathError {
+	fn description(&self) -> &str {
+		"math error"
+	}
+}
+
+impl From<MathError> for Error {
+	fn from(e: MathError) -> Error {
+		Error::custom(e)
+	}
+}
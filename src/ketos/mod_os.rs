@@ -0,0 +1,215 @@
+//! Implements builtin `os` module.
+//!
+//! Functions in this module access the environment and process of the host.
+//! They are not allowed when `RestrictConfig::allow_os` is false.
+
+use std::env;
+use std::io;
+
+use crate::error::Error;
+use crate::exec::{Context, ExecError};
+use crate::function::Arity::{Exact, Range};
+use crate::map::Map;
+use crate::module::{Module, ModuleBuilder};
+use crate::restrict::RestrictError;
+use crate::scope::Scope;
+use crate::value::{FromValueRef, Value};
+
+/// Loads the `os` module into the given scope.
+pub fn load(scope: Scope) -> Module {
+	ModuleBuilder::new("os", scope)
+		.add_function(
+			"getenv",
+			fn_getenv,
+			Exact(1),
+			Some(
+				"Returns the value of an environment variable,
+or `()` if it is not set.",
+			),
+		)
+		.add_function(
+			"setenv",
+			fn_setenv,
+			Exact(2),
+			Some(
+				"    (setenv name value)
+
+Sets the value of an environment variable.
+If `value` is `()`, the variable is removed.",
+			),
+		)
+		.add_function(
+			"env-vars",
+			fn_env_vars,
+			Exact(0),
+			Some("Returns a map of all environment variables to their values."),
+		)
+		.add_function(
+			"args",
+			fn_args,
+			Exact(0),
+			Some(
+				"Returns the list of arguments given to the running script,
+beginning with the script path, or `()` if none were given.",
+			),
+		)
+		.add_function(
+			"cwd",
+			fn_cwd,
+			Exact(0),
+			Some("Returns the current working directory."),
+		)
+		.add_function(
+			"exit",
+			fn_exit,
+			Range(0, 1),
+			Some(
+				"    (exit)
+    (exit status)
+
+Stops execution and exits with the given status code, or `0` if none is given.
+The exit is not caught by `try`.",
+			),
+		)
+		.add_function(
+			"hostname",
+			fn_hostname,
+			Exact(0),
+			Some("Returns the host name of the system."),
+		)
+		.add_function(
+			"pid",
+			fn_pid,
+			Exact(0),
+			Some("Returns the ID of the running process."),
+		)
+		.finish()
+}
+
+/// `getenv` returns the value of an environment variable.
+///
+/// ```lisp
+/// (getenv "HOME")  ; "/home/user"
+/// ```
+fn fn_getenv(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	check_allowed(ctx)?;
+
+	let name = <&str>::from_value_ref(&args[0])?;
+
+	Ok(env::var(name).map_or(Value::Unit, Value::from))
+}
+
+/// `setenv` sets or removes an environment variable.
+fn fn_setenv(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	check_allowed(ctx)?;
+
+	let name = <&str>::from_value_ref(&args[0])?;
+
+	if name.is_empty() || name.contains(['=', '\0']) {
+		return Err(invalid_input("invalid environment variable name"));
+	}
+
+	match args[1] {
+		Value::Unit => env::remove_var(name),
+		Value::String(ref value) => {
+			if value.contains('\0') {
+				return Err(invalid_input("invalid environment variable value"));
+			}
+			env::set_var(name, &value[..]);
+		}
+		ref v => return Err(From::from(ExecError::expected("string", v))),
+	}
+
+	Ok(Value::Unit)
+}
+
+/// `env-vars` returns a map of all environment variables.
+///
+/// Variables whose names or values are not valid Unicode are omitted.
+fn fn_env_vars(ctx: &Context, _args: &mut [Value]) -> Result<Value, Error> {
+	check_allowed(ctx)?;
+
+	let map = env::vars_os()
+		.filter_map(|(k, v)| Some((k.into_string().ok()?, v.into_string().ok()?)))
+		.map(|(k, v)| (k.into(), v.into()))
+		.collect::<Map>();
+
+	Ok(map.into())
+}
+
+/// `args` returns the arguments given to the running script.
+///
+/// These are the same values assigned to `argv` by `Interpreter::set_args`.
+fn fn_args(ctx: &Context, _args: &mut [Value]) -> Result<Value, Error> {
+	check_allowed(ctx)?;
+
+	Ok(ctx.scope().get_named_value("argv").unwrap_or(Value::Unit))
+}
+
+/// `cwd` returns the current working directory.
+fn fn_cwd(ctx: &Context, _args: &mut [Value]) -> Result<Value, Error> {
+	check_allowed(ctx)?;
+
+	env::current_dir().map(Value::from).map_err(Error::custom)
+}
+
+/// `exit` stops execution with an exit status.
+///
+/// ```lisp
+/// (exit 1)
+/// ```
+fn fn_exit(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	check_allowed(ctx)?;
+
+	let code = match args.first() {
+		Some(v) => i32::from_value_ref(v)?,
+		None => 0,
+	};
+
+	Err(From::from(ExecError::Exit(code)))
+}
+
+/// `hostname` returns the host name of the system.
+fn fn_hostname(ctx: &Context, _args: &mut [Value]) -> Result<Value, Error> {
+	check_allowed(ctx)?;
+
+	hostname().map(Value::from).map_err(Error::custom)
+}
+
+/// `pid` returns the ID of the running process.
+fn fn_pid(ctx: &Context, _args: &mut [Value]) -> Result<Value, Error> {
+	check_allowed(ctx)?;
+
+	Ok(std::process::id().into())
+}
+
+fn check_allowed(ctx: &Context) -> Result<(), RestrictError> {
+	if ctx.restrict().allow_os {
+		Ok(())
+	} else {
+		Err(RestrictError::OsNotAllowed)
+	}
+}
+
+fn invalid_input(msg: &'static str) -> Error {
+	Error::custom(io::Error::new(io::ErrorKind::InvalidInput, msg))
+}
+
+#[cfg(unix)]
+fn hostname() -> io::Result<String> {
+	let mut buf = [0u8; 256];
+
+	let r = unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) };
+
+	if r != 0 {
+		return Err(io::Error::last_os_error());
+	}
+
+	let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+	Ok(String::from_utf8_lossy(&buf[..len]).into_owned())
+}
+
+#[cfg(not(unix))]
+fn hostname() -> io::Result<String> {
+	env::var("COMPUTERNAME").map_err(|e| io::Error::new(io::ErrorKind::NotFound, e))
+}
@@ -0,0 +1,202 @@
+//! Implements builtin `path` module.
+//!
+//! Functions accept either `path` or `string` values. Functions producing
+//! a path return a `path` value, while those producing a single component
+//! of a path return a `string`.
+
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+use crate::error::Error;
+use crate::exec::Context;
+use crate::function::Arity::{Exact, Min};
+use crate::io::{IoError, IoMode};
+use crate::mod_fs::resolve_path;
+use crate::module::{Module, ModuleBuilder};
+use crate::scope::Scope;
+use crate::value::{FromValueRef, Value};
+
+/// Loads the `path` module into the given scope.
+pub fn load(scope: Scope) -> Module {
+	ModuleBuilder::new("path", scope)
+		.add_function(
+			"join",
+			fn_join,
+			Min(1),
+			Some(
+				"    (join path component ...)
+
+Returns a path with each component appended in turn.
+An absolute component replaces the path built so far.",
+			),
+		)
+		.add_function(
+			"parent",
+			fn_parent,
+			Exact(1),
+			Some("Returns the path without its final component, or `()` if there is none."),
+		)
+		.add_function(
+			"file-name",
+			fn_file_name,
+			Exact(1),
+			Some("Returns the final component of a path, or `()` if there is none."),
+		)
+		.add_function(
+			"stem",
+			fn_stem,
+			Exact(1),
+			Some(
+				"Returns the final component of a path without its extension,
+or `()` if there is none.",
+			),
+		)
+		.add_function(
+			"extension",
+			fn_extension,
+			Exact(1),
+			Some("Returns the extension of a path, or `()` if there is none."),
+		)
+		.add_function(
+			"with-extension",
+			fn_with_extension,
+			Exact(2),
+			Some(
+				"    (with-extension path extension)
+
+Returns a path with its extension replaced.
+An empty extension removes any existing extension.",
+			),
+		)
+		.add_function(
+			"components",
+			fn_components,
+			Exact(1),
+			Some("Returns a list of the components of a path, as strings."),
+		)
+		.add_function(
+			"is-absolute",
+			fn_is_absolute,
+			Exact(1),
+			Some("Returns whether a path is absolute."),
+		)
+		.add_function(
+			"canonicalize",
+			fn_canonicalize,
+			Exact(1),
+			Some(
+				"Returns the absolute form of a path, with all symbolic links resolved.
+The path must exist. This function requires filesystem access.",
+			),
+		)
+		.finish()
+}
+
+/// `join` appends components to a path.
+///
+/// ```lisp
+/// (join "foo" "bar" "baz.txt")  ; #p"foo/bar/baz.txt"
+/// ```
+fn fn_join(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let mut path = PathBuf::new();
+
+	for arg in args.iter() {
+		path.push(<&Path>::from_value_ref(arg)?);
+	}
+
+	Ok(path.into())
+}
+
+/// `parent` returns a path without its final component.
+///
+/// ```lisp
+/// (parent "foo/bar.txt")  ; #p"foo"
+/// ```
+fn fn_parent(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let path = <&Path>::from_value_ref(&args[0])?;
+	Ok(path.parent().map_or(Value::Unit, Value::from))
+}
+
+/// `file-name` returns the final component of a path.
+///
+/// ```lisp
+/// (file-name "foo/bar.txt")  ; "bar.txt"
+/// ```
+fn fn_file_name(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let path = <&Path>::from_value_ref(&args[0])?;
+	Ok(os_str_value(path.file_name()))
+}
+
+/// `stem` returns the final component of a path without its extension.
+///
+/// ```lisp
+/// (stem "foo/bar.txt")  ; "bar"
+/// ```
+fn fn_stem(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let path = <&Path>::from_value_ref(&args[0])?;
+	Ok(os_str_value(path.file_stem()))
+}
+
+/// `extension` returns the extension of a path.
+///
+/// ```lisp
+/// (extension "foo/bar.txt")  ; "txt"
+/// ```
+fn fn_extension(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let path = <&Path>::from_value_ref(&args[0])?;
+	Ok(os_str_value(path.extension()))
+}
+
+/// `with-extension` returns a path with its extension replaced.
+///
+/// ```lisp
+/// (with-extension "foo/bar.txt" "md")  ; #p"foo/bar.md"
+/// ```
+fn fn_with_extension(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let path = <&Path>::from_value_ref(&args[0])?;
+	let ext = <&OsStr>::from_value_ref(&args[1])?;
+
+	Ok(path.with_extension(ext).into())
+}
+
+/// `components` returns a list of the components of a path.
+///
+/// ```lisp
+/// (components "/foo/bar.txt")  ; ("/" "foo" "bar.txt")
+/// ```
+fn fn_components(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let path = <&Path>::from_value_ref(&args[0])?;
+
+	Ok(path
+		.components()
+		.map(|c| os_str_value(Some(c.as_os_str())))
+		.collect::<Vec<_>>()
+		.into())
+}
+
+/// `is-absolute` returns whether a path is absolute.
+fn fn_is_absolute(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let path = <&Path>::from_value_ref(&args[0])?;
+	Ok(path.is_absolute().into())
+}
+
+/// `canonicalize` returns the absolute form of a path.
+///
+/// Access to the path is subject to the `filesystem` field
+/// of the active `RestrictConfig`.
+fn fn_canonicalize(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let path = resolve_path(ctx, &args[0])?;
+
+	let path = path
+		.canonicalize()
+		.map_err(|e| IoError::new(IoMode::Stat, &path, e))?;
+
+	Ok(path.into())
+}
+
+/// Returns a string value, or `()` if given `None`.
+///
+/// Invalid Unicode is replaced with `U+FFFD REPLACEMENT CHARACTER`.
+fn os_str_value(s: Option<&OsStr>) -> Value {
+	s.map_or(Value::Unit, |s| s.to_string_lossy().into_owned().into())
+}
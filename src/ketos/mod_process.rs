@@ -0,0 +1,290 @@
+//! Implements builtin `process` module.
+//!
+//! Running external programs is not allowed unless the host opts in,
+//! by setting `RestrictConfig::allow_process` or using `Builder::allow_process`.
+//!
+//! On Unix, each program is started in a new process group, so that
+//! a timeout also kills any processes it has started.
+
+use std::io::{self, Read, Write};
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::bytes::Bytes;
+use crate::error::Error;
+use crate::exec::{Context, ExecError};
+use crate::function::Arity::Min;
+use crate::io::{IoError, IoMode};
+use crate::map::Map;
+use crate::mod_json::options;
+use crate::mod_string::check_value;
+use crate::mod_time::get_seconds;
+use crate::module::{Module, ModuleBuilder};
+use crate::restrict::RestrictError;
+use crate::scope::Scope;
+use crate::value::{FromValueRef, Value};
+
+/// Interval at which a process with a timeout is polled for completion
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Time allowed for output to be closed after processes are killed
+const KILL_GRACE: Duration = Duration::from_millis(100);
+
+/// Loads the `process` module into the given scope.
+pub fn load(scope: Scope) -> Module {
+	ModuleBuilder::new("process", scope)
+		.add_function(
+			"run",
+			fn_run,
+			Min(1),
+			Some(
+				"    (run program)
+    (run program args :key value ...)
+
+Runs a program with a list of string arguments and waits for it to exit.
+Returns a map with keys `:status`, the exit code, or `()` if the process
+was terminated by a signal or timeout; `:success`; `:stdout`; `:stderr`;
+and `:timed-out`.
+
+Accepts the following keyword options:
+
+* `:stdin`, a string or bytes written to the standard input of the process
+* `:env`, a map of environment variable names to values;
+  a value of `()` removes the variable
+* `:cwd`, the working directory of the process
+* `:timeout`, a number of seconds or `duration` after which
+  the process, and any process it started, is killed
+* `:output`, `:string` (the default) or `:bytes`, the type of
+  `:stdout` and `:stderr` values",
+			),
+		)
+		.finish()
+}
+
+/// `run` runs a program and captures its output.
+///
+/// ```lisp
+/// (get (run "echo" '("hello")) :stdout)  ; "hello\n"
+/// ```
+fn fn_run(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	if !ctx.restrict().allow_process {
+		return Err(From::from(RestrictError::ProcessNotAllowed));
+	}
+
+	let program = <&Path>::from_value_ref(&args[0])?;
+	let mut cmd = Command::new(program);
+
+	let rest = match args.get(1) {
+		Some(&Value::Keyword(_)) | None => &args[1..],
+		Some(&Value::Unit) => &args[2..],
+		Some(Value::List(li)) => {
+			for arg in li.iter() {
+				cmd.arg(<&str>::from_value_ref(arg)?);
+			}
+			&args[2..]
+		}
+		Some(v) => return Err(From::from(ExecError::expected("list", v))),
+	};
+
+	let mut input = None;
+	let mut timeout = None;
+	let mut bytes = false;
+
+	for (name, value) in options(ctx, rest, &["stdin", "env", "cwd", "timeout", "output"])? {
+		match &name[..] {
+			"stdin" => {
+				input = Some(match *value {
+					Value::String(ref s) => s.as_bytes().to_vec(),
+					Value::Bytes(ref b) => b.to_vec(),
+					ref v => return Err(From::from(ExecError::expected("string or bytes", v))),
+				});
+			}
+			"env" => {
+				let map = <&Map>::from_value_ref(value)?;
+
+				for (k, v) in map.iter() {
+					let k = <&str>::from_value_ref(k)?;
+
+					match *v {
+						Value::Unit => {
+							cmd.env_remove(k);
+						}
+						ref v => {
+							cmd.env(k, <&str>::from_value_ref(v)?);
+						}
+					}
+				}
+			}
+			"cwd" => {
+				cmd.current_dir(<&Path>::from_value_ref(value)?);
+			}
+			"timeout" => timeout = Some(get_seconds(value)?),
+			"output" => bytes = output_bytes(ctx, value)?,
+			_ => unreachable!(),
+		}
+	}
+
+	cmd.stdin(if input.is_some() {
+		Stdio::piped()
+	} else {
+		Stdio::null()
+	})
+	.stdout(Stdio::piped())
+	.stderr(Stdio::piped());
+
+	#[cfg(unix)]
+	cmd.process_group(0);
+
+	let mut child = cmd
+		.spawn()
+		.map_err(|e| IoError::new(IoMode::Execute, program, e))?;
+
+	if let (Some(data), Some(mut stdin)) = (input, child.stdin.take()) {
+		// Errors are ignored; the process may exit without reading its input.
+		thread::spawn(move || {
+			let _ = stdin.write_all(&data);
+		});
+	}
+
+	let stdout = read_pipe(child.stdout.take());
+	let stderr = read_pipe(child.stderr.take());
+
+	let deadline = timeout.and_then(|d| Instant::now().checked_add(d));
+
+	let (status, mut timed_out) =
+		wait(&mut child, deadline).map_err(|e| IoError::new(IoMode::Execute, program, e))?;
+
+	// Processes started by the program may hold its output open after it exits
+	let stdout = recv_output(&mut child, &stdout, deadline, &mut timed_out);
+	let stderr = recv_output(&mut child, &stderr, deadline, &mut timed_out);
+
+	let output = |buf: Vec<u8>| -> Value {
+		if bytes {
+			Bytes::new(buf).into()
+		} else {
+			String::from_utf8_lossy(&buf).into_owned().into()
+		}
+	};
+
+	let scope = ctx.scope();
+	let mut map = Map::new();
+
+	let mut insert = |key: &str, value: Value| {
+		map.insert(Value::Keyword(scope.add_name(key)), value);
+	};
+
+	insert(
+		"status",
+		status
+			.and_then(|s| s.code())
+			.map_or(Value::Unit, Value::from),
+	);
+	insert("success", status.is_some_and(|s| s.success()).into());
+	insert("stdout", output(stdout));
+	insert("stderr", output(stderr));
+	insert("timed-out", timed_out.into());
+
+	check_value(ctx, map.into())
+}
+
+/// Interprets the value of an `:output` option.
+fn output_bytes(ctx: &Context, v: &Value) -> Result<bool, Error> {
+	if let Value::Keyword(name) = *v {
+		match ctx.scope().borrow_names().get(name) {
+			"string" => return Ok(false),
+			"bytes" => return Ok(true),
+			_ => (),
+		}
+	}
+
+	Err(From::from(ExecError::expected("`:string` or `:bytes`", v)))
+}
+
+/// Reads the whole of a pipe on a separate thread, so that a process
+/// is not blocked by writing to a full pipe.
+fn read_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> Receiver<Vec<u8>> {
+	let (tx, rx) = mpsc::channel();
+
+	thread::spawn(move || {
+		let mut buf = Vec::new();
+
+		if let Some(mut pipe) = pipe {
+			let _ = pipe.read_to_end(&mut buf);
+		}
+
+		let _ = tx.send(buf);
+	});
+
+	rx
+}
+
+/// Receives the output read from a pipe.
+///
+/// If the deadline passes first, the process group is killed and the output
+/// is discarded unless the pipe is closed shortly after.
+fn recv_output(
+	child: &mut Child,
+	rx: &Receiver<Vec<u8>>,
+	deadline: Option<Instant>,
+	timed_out: &mut bool,
+) -> Vec<u8> {
+	let deadline = match deadline {
+		Some(d) => d,
+		None => return rx.recv().unwrap_or_default(),
+	};
+
+	match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+		Ok(buf) => buf,
+		Err(RecvTimeoutError::Disconnected) => Vec::new(),
+		Err(RecvTimeoutError::Timeout) => {
+			*timed_out = true;
+			let _ = kill(child);
+			rx.recv_timeout(KILL_GRACE).unwrap_or_default()
+		}
+	}
+}
+
+/// Waits for a process to exit, killing it if the deadline passes.
+///
+/// Returns the exit status, or `None` if the process was killed,
+/// and whether the deadline passed.
+fn wait(child: &mut Child, deadline: Option<Instant>) -> io::Result<(Option<ExitStatus>, bool)> {
+	let deadline = match deadline {
+		Some(d) => d,
+		None => return child.wait().map(|s| (Some(s), false)),
+	};
+
+	loop {
+		if let Some(status) = child.try_wait()? {
+			return Ok((Some(status), false));
+		}
+
+		if Instant::now() >= deadline {
+			kill(child)?;
+			child.wait()?;
+			return Ok((None, true));
+		}
+
+		thread::sleep(POLL_INTERVAL);
+	}
+}
+
+/// Kills a process and, on Unix, all other processes in its process group.
+fn kill(child: &mut Child) -> io::Result<()> {
+	#[cfg(unix)]
+	{
+		// The process was started as the leader of a new process group
+		let pgid = -(child.id() as libc::pid_t);
+
+		if unsafe { libc::kill(pgid, libc::SIGKILL) } == 0 {
+			return Ok(());
+		}
+	}
+
+	child.kill()
+}
@@ -1,13 +1,25 @@
 //! Implements builtin `random` module.
+//!
+//! All functions draw from the random number generator of the calling
+//! scope, which may be seeded with `seed`, `GlobalScope::seed_rng`,
+//! or `Builder::seed` to produce a reproducible sequence of values.
 
-use rand::{seq::SliceRandom, thread_rng, Rng};
+use std::error::Error as StdError;
+use std::f64::consts::PI;
+use std::fmt;
 
+use rand::distributions::WeightedIndex;
+use rand::seq::{index, SliceRandom};
+use rand::Rng;
+
+use crate::bytes::Bytes;
 use crate::error::Error;
 use crate::exec::{Context, ExecError};
-use crate::function::Arity::Exact;
+use crate::function::Arity::{Exact, Range};
+use crate::mod_math::get_float;
 use crate::module::{Module, ModuleBuilder};
 use crate::scope::Scope;
-use crate::value::Value;
+use crate::value::{FromValueRef, Value};
 
 /// Loads the `random` module into the given scope.
 pub fn load(scope: Scope) -> Module {
@@ -18,30 +30,321 @@ pub fn load(scope: Scope) -> Module {
 			Exact(0),
 			Some("Returns a random float value in the range `[0.0, 1.0)`."),
 		)
+		.add_function(
+			"random-int",
+			fn_random_int,
+			Range(1, 2),
+			Some(
+				"    (random-int high)
+    (random-int low high)
+
+Returns a random integer in the range `[low, high)`.
+If `low` is not given, it is `0`.",
+			),
+		)
+		.add_function(
+			"normal",
+			fn_normal,
+			Range(0, 2),
+			Some(
+				"    (normal)
+    (normal mean)
+    (normal mean std-dev)
+
+Returns a random float value from a normal distribution.
+By default, `mean` is `0.0` and `std-dev` is `1.0`.",
+			),
+		)
+		.add_function(
+			"exponential",
+			fn_exponential,
+			Range(0, 1),
+			Some(
+				"    (exponential)
+    (exponential rate)
+
+Returns a random float value from an exponential distribution.
+By default, `rate` is `1.0`.",
+			),
+		)
+		.add_function(
+			"random-bytes",
+			fn_random_bytes,
+			Exact(1),
+			Some("Returns a `bytes` value of the given length, filled with random bytes."),
+		)
+		.add_function(
+			"choice",
+			fn_choice,
+			Exact(1),
+			Some("Returns a random element of a non-empty list."),
+		)
+		.add_function(
+			"sample",
+			fn_sample,
+			Exact(2),
+			Some(
+				"    (sample list n)
+
+Returns a list of `n` distinct elements of a list, chosen at random.",
+			),
+		)
+		.add_function(
+			"weighted-choice",
+			fn_weighted_choice,
+			Exact(2),
+			Some(
+				"    (weighted-choice list weights)
+
+Returns a random element of a list, where each element is chosen
+with a probability proportional to the corresponding number in `weights`.",
+			),
+		)
 		.add_function(
 			"shuffle",
 			fn_shuffle,
 			Exact(1),
 			Some("Given a list, returns a new list with the elements shuffled."),
 		)
+		.add_function(
+			"seed",
+			fn_seed,
+			Exact(1),
+			Some(
+				"Seeds the random number generator with a non-negative integer.
+Following calls to functions in this module produce the same sequence
+of values for the same seed.",
+			),
+		)
 		.finish()
 }
 
+/// Represents an error in generating a random value.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum RandomError {
+	/// Empty range given to `random-int`
+	EmptyRange,
+	/// Empty list given to a function which chooses an element
+	EmptyList,
+	/// Sample size is greater than the number of elements
+	SampleTooLarge {
+		/// Number of elements requested
+		requested: usize,
+		/// Number of elements available
+		available: usize,
+	},
+	/// Weights are negative, all zero, not finite, or do not match
+	/// the number of elements
+	InvalidWeights,
+	/// Distribution parameter is negative, zero, or not finite
+	InvalidParameter(&'static str),
+}
+
+impl fmt::Display for RandomError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			RandomError::EmptyRange => f.write_str("empty range"),
+			RandomError::EmptyList => f.write_str("cannot choose from empty list"),
+			RandomError::SampleTooLarge {
+				requested,
+				available,
+			} => write!(
+				f,
+				"cannot sample {} elements from list of length {}",
+				requested, available
+			),
+			RandomError::InvalidWeights => f.write_str("invalid weights"),
+			RandomError::InvalidParameter(name) => write!(f, "invalid {}", name),
+		}
+	}
+}
+
+impl StdError for RandomError {
+	fn description(&self) -> &str {
+		"random error"
+	}
+}
+
+impl From<RandomError> for Error {
+	fn from(e: RandomError) -> Error {
+		Error::custom(e)
+	}
+}
+
 /// `random` returns a random float value in the range `[0.0, 1.0)`.
-fn fn_random(_ctx: &Context, _args: &mut [Value]) -> Result<Value, Error> {
-	let value: f64 = thread_rng().gen();
+fn fn_random(ctx: &Context, _args: &mut [Value]) -> Result<Value, Error> {
+	let value: f64 = ctx.scope().rng().gen();
+	Ok(value.into())
+}
+
+/// `random-int` returns a random integer in a range.
+///
+/// ```lisp
+/// (random-int 1 7)  ; 4
+/// ```
+fn fn_random_int(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let (low, high) = match *args {
+		[ref high] => (0, i64::from_value_ref(high)?),
+		[ref low, ref high] => (i64::from_value_ref(low)?, i64::from_value_ref(high)?),
+		_ => unreachable!(),
+	};
+
+	if low >= high {
+		return Err(From::from(RandomError::EmptyRange));
+	}
+
+	let value = ctx.scope().rng().gen_range(low, high);
 	Ok(value.into())
 }
 
+/// `normal` returns a random float value from a normal distribution.
+fn fn_normal(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let mean = args.first().map_or(Ok(0.0), get_float)?;
+	let std_dev = args.get(1).map_or(Ok(1.0), get_float)?;
+
+	if !mean.is_finite() || !std_dev.is_finite() || std_dev < 0.0 {
+		return Err(From::from(RandomError::InvalidParameter(
+			"normal distribution parameter",
+		)));
+	}
+
+	let mut rng = ctx.scope().rng();
+
+	// Box-Muller transform; `u` is in the range `(0.0, 1.0]`.
+	let u = 1.0 - rng.gen::<f64>();
+	let v: f64 = rng.gen();
+	let z = (-2.0 * u.ln()).sqrt() * (2.0 * PI * v).cos();
+
+	Ok((mean + std_dev * z).into())
+}
+
+/// `exponential` returns a random float value from an exponential distribution.
+fn fn_exponential(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let rate = args.first().map_or(Ok(1.0), get_float)?;
+
+	if !rate.is_finite() || rate <= 0.0 {
+		return Err(From::from(RandomError::InvalidParameter(
+			"exponential distribution rate",
+		)));
+	}
+
+	// Inverse transform; `u` is in the range `(0.0, 1.0]`.
+	let u = 1.0 - ctx.scope().rng().gen::<f64>();
+
+	Ok((-u.ln() / rate).into())
+}
+
+/// `random-bytes` returns a `bytes` value filled with random bytes.
+fn fn_random_bytes(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let n = usize::from_value_ref(&args[0])?;
+
+	ctx.check_memory(n)?;
+
+	let mut buf = vec![0; n];
+	ctx.scope().rng().fill(&mut buf[..]);
+
+	Ok(Bytes::new(buf).into())
+}
+
+/// `choice` returns a random element of a list.
+///
+/// ```lisp
+/// (choice '(rock paper scissors))  ; paper
+/// ```
+fn fn_choice(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let li = get_list(&args[0])?;
+
+	li.choose(&mut *ctx.scope().rng())
+		.cloned()
+		.ok_or_else(|| From::from(RandomError::EmptyList))
+}
+
+/// `sample` returns distinct elements of a list, chosen at random.
+///
+/// ```lisp
+/// (sample '(1 2 3 4 5) 2)  ; (4 1)
+/// ```
+fn fn_sample(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let li = get_list(&args[0])?;
+	let n = usize::from_value_ref(&args[1])?;
+
+	if n > li.len() {
+		return Err(From::from(RandomError::SampleTooLarge {
+			requested: n,
+			available: li.len(),
+		}));
+	}
+
+	let indices = index::sample(&mut *ctx.scope().rng(), li.len(), n);
+
+	Ok(indices
+		.into_iter()
+		.map(|i| li[i].clone())
+		.collect::<Vec<_>>()
+		.into())
+}
+
+/// `weighted-choice` returns a random element of a list, according to weights.
+///
+/// ```lisp
+/// (weighted-choice '(common rare) '(9 1))  ; common
+/// ```
+fn fn_weighted_choice(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let li = get_list(&args[0])?;
+
+	if li.is_empty() {
+		return Err(From::from(RandomError::EmptyList));
+	}
+
+	let weights = get_list(&args[1])?
+		.iter()
+		.map(get_float)
+		.collect::<Result<Vec<_>, _>>()?;
+
+	// `WeightedIndex` panics if the weights or their sum are not finite
+	if weights.len() != li.len()
+		|| !weights.iter().all(|w| w.is_finite())
+		|| !weights.iter().sum::<f64>().is_finite()
+	{
+		return Err(From::from(RandomError::InvalidWeights));
+	}
+
+	let dist = WeightedIndex::new(&weights).map_err(|_| RandomError::InvalidWeights)?;
+	let i = ctx.scope().rng().sample(&dist);
+
+	Ok(li[i].clone())
+}
+
 /// `shuffle` shuffles the values of a list.
-fn fn_shuffle(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+fn fn_shuffle(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
 	let mut v = args[0].take();
 
 	match v {
 		Value::Unit => (),
-		Value::List(ref mut li) => li.shuffle(&mut thread_rng()),
+		Value::List(ref mut li) => li.shuffle(&mut *ctx.scope().rng()),
 		ref v => return Err(From::from(ExecError::expected("list", v))),
 	}
 
 	Ok(v)
 }
+
+/// `seed` seeds the random number generator.
+///
+/// ```lisp
+/// (seed 42)
+/// ```
+fn fn_seed(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let seed = u64::from_value_ref(&args[0])?;
+
+	ctx.scope().seed_rng(seed);
+	Ok(Value::Unit)
+}
+
+/// Returns the elements of a list, or an empty slice for `()`.
+fn get_list(v: &Value) -> Result<&[Value], ExecError> {
+	match *v {
+		Value::Unit => Ok(&[]),
+		Value::List(ref li) => Ok(li),
+		ref v => Err(ExecError::expected("list", v)),
+	}
+}
@@ -0,0 +1,323 @@
+//! Implements builtin `regex` module.
+//!
+//! Compiled regular expressions are represented as foreign values.
+//! Functions accepting a regex will also accept a pattern string,
+//! which is compiled for the duration of the call.
+//!
+//! Compilation honours the `max_regex_size` and `max_syntax_nesting` limits
+//! of the active `RestrictConfig`.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use regex::{Captures, RegexBuilder};
+
+use crate::error::Error;
+use crate::exec::{call_function, Context, ExecError};
+use crate::function::Arity::{Exact, Range};
+use crate::map::Map;
+use crate::mod_string::{check_value, get_string, substring};
+use crate::module::{Module, ModuleBuilder};
+use crate::name::NameStore;
+use crate::rc_vec::RcString;
+use crate::restrict::RestrictError;
+use crate::scope::Scope;
+use crate::value::{ForeignValue, FromValueRef, Value};
+
+/// Loads the `regex` module into the given scope.
+pub fn load(scope: Scope) -> Module {
+	ModuleBuilder::new("regex", scope)
+		.add_function(
+			"regex",
+			fn_regex,
+			Exact(1),
+			Some("Returns a compiled regular expression."),
+		)
+		.add_function(
+			"match?",
+			fn_is_match,
+			Exact(2),
+			Some("Returns whether a regex matches anywhere in a string."),
+		)
+		.add_function(
+			"find",
+			fn_find,
+			Exact(2),
+			Some("Returns the first substring matching a regex, or `()` if none match."),
+		)
+		.add_function(
+			"find-all",
+			fn_find_all,
+			Exact(2),
+			Some("Returns a list of all non-overlapping substrings matching a regex."),
+		)
+		.add_function(
+			"captures",
+			fn_captures,
+			Exact(2),
+			Some(
+				"Returns the capture groups of the first match of a regex,
+or `()` if there is no match.
+
+If the regex contains named groups, a map of keywords to strings is returned.
+Otherwise, a list of all groups is returned, beginning with the whole match.
+Groups which did not participate in the match are `()`.",
+			),
+		)
+		.add_function(
+			"replace",
+			fn_replace,
+			Range(3, 4),
+			Some(
+				"    (replace regex string replacement)
+    (replace regex string replacement count)
+
+Returns a string with matches of a regex replaced.
+If a count is given, at most that many matches are replaced.
+
+The replacement may be a string, in which `$1` or `${name}` refer to
+capture groups, or a function, which is called with the captures of
+each match and must return a string.",
+			),
+		)
+		.add_function(
+			"split",
+			fn_split,
+			Range(2, 3),
+			Some(
+				"    (split regex string)
+    (split regex string limit)
+
+Returns a list of substrings separated by matches of a regex.
+If a limit is given, at most that many substrings are returned.",
+			),
+		)
+		.finish()
+}
+
+/// Compiled regular expression
+#[derive(Clone, Debug)]
+struct Regex(regex::Regex);
+
+impl ForeignValue for Regex {
+	fn is_equal_to(&self, rhs: &dyn ForeignValue) -> Result<bool, ExecError> {
+		match rhs.downcast_ref::<Regex>() {
+			Some(rhs) => Ok(self.0.as_str() == rhs.0.as_str()),
+			None => Err(ExecError::TypeMismatch {
+				lhs: self.type_name(),
+				rhs: rhs.type_name(),
+			}),
+		}
+	}
+
+	fn fmt_debug(&self, _names: &NameStore, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "<regex {:?}>", self.0.as_str())
+	}
+
+	fn fmt_display(&self, _names: &NameStore, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(self.0.as_str())
+	}
+
+	fn type_name(&self) -> &'static str {
+		"regex"
+	}
+
+	fn size(&self) -> usize {
+		1 + self.0.as_str().len()
+	}
+}
+
+/// `regex` returns a compiled regular expression.
+///
+/// ```lisp
+/// (define re (regex "[0-9]+"))
+/// ```
+fn fn_regex(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let pattern = <&str>::from_value_ref(&args[0])?;
+	Ok(Value::new_foreign(compile(ctx, pattern)?))
+}
+
+/// `match?` returns whether a regex matches a string.
+///
+/// ```lisp
+/// (match? "^a+$" "aaa")  ; true
+/// ```
+fn fn_is_match(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let re = get_regex(ctx, &args[0])?;
+	let s = <&str>::from_value_ref(&args[1])?;
+
+	Ok(re.0.is_match(s).into())
+}
+
+/// `find` returns the first substring matching a regex.
+///
+/// ```lisp
+/// (find "[0-9]+" "abc123def456")  ; "123"
+/// ```
+fn fn_find(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let re = get_regex(ctx, &args[0])?;
+	let s = get_string(&args[1])?;
+
+	Ok(re
+		.0
+		.find(s)
+		.map_or(Value::Unit, |m| substring(s, m.as_str()).into()))
+}
+
+/// `find-all` returns all substrings matching a regex.
+///
+/// ```lisp
+/// (find-all "[0-9]+" "abc123def456")  ; ("123" "456")
+/// ```
+fn fn_find_all(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let re = get_regex(ctx, &args[0])?;
+	let s = get_string(&args[1])?;
+
+	let v =
+		re.0.find_iter(s)
+			.map(|m| Value::String(substring(s, m.as_str())))
+			.collect::<Vec<_>>();
+
+	check_value(ctx, v.into())
+}
+
+/// `captures` returns the capture groups of the first match of a regex.
+///
+/// ```lisp
+/// (captures "(a)(b)?" "ac")             ; ("a" "a" ())
+/// (captures "(?P<x>[0-9]+)" "abc123")   ; {:x "123"}
+/// ```
+fn fn_captures(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let re = get_regex(ctx, &args[0])?;
+	let s = get_string(&args[1])?;
+
+	match re.0.captures(s) {
+		Some(caps) => check_value(ctx, captures_value(ctx, &re.0, s, &caps)),
+		None => Ok(Value::Unit),
+	}
+}
+
+/// `replace` returns a string with matches of a regex replaced.
+///
+/// ```lisp
+/// (replace "[0-9]" "a1b2" "#")                    ; "a#b#"
+/// (replace "([a-z])([0-9])" "a1b2" "$2$1")        ; "1a2b"
+/// (replace "[a-z]" "a1b2" (lambda (c) "_") 1)     ; "_1b2"
+/// ```
+fn fn_replace(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let re = get_regex(ctx, &args[0])?;
+	let s = get_string(&args[1])?;
+	let count = match args.get(3) {
+		Some(n) => usize::from_value_ref(n)?,
+		None => usize::MAX,
+	};
+
+	let mut res = String::new();
+	let mut last = 0;
+	let mut n = 0;
+
+	for caps in re.0.captures_iter(s).take(count) {
+		let m = caps.get(0).expect("group 0 is always present");
+
+		res.push_str(&s[last..m.start()]);
+
+		match args[2] {
+			Value::String(ref rep) => caps.expand(rep, &mut res),
+			ref f => {
+				let captures = captures_value(ctx, &re.0, s, &caps);
+				let v = call_function(ctx, f.clone(), vec![captures])?;
+				res.push_str(<&str>::from_value_ref(&v)?);
+			}
+		}
+
+		last = m.end();
+		n += 1;
+
+		ctx.check_memory(res.len())?;
+	}
+
+	if n == 0 {
+		return Ok(Value::String(s.clone()));
+	}
+
+	res.push_str(&s[last..]);
+
+	check_value(ctx, res.into())
+}
+
+/// `split` returns a list of substrings separated by matches of a regex.
+///
+/// ```lisp
+/// (split "[, ]+" "a, b,c")      ; ("a" "b" "c")
+/// (split "[, ]+" "a, b,c" 2)    ; ("a" "b,c")
+/// ```
+fn fn_split(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let re = get_regex(ctx, &args[0])?;
+	let s = get_string(&args[1])?;
+
+	let v: Vec<_> = match args.get(2) {
+		Some(n) => {
+			let n = usize::from_value_ref(n)?;
+			re.0.splitn(s, n)
+				.map(|sub| Value::String(substring(s, sub)))
+				.collect()
+		}
+		None => {
+			re.0.split(s)
+				.map(|sub| Value::String(substring(s, sub)))
+				.collect()
+		}
+	};
+
+	check_value(ctx, v.into())
+}
+
+/// Compiles a regex within the limits of the context's `RestrictConfig`.
+fn compile(ctx: &Context, pattern: &str) -> Result<Regex, Error> {
+	let restrict = ctx.restrict();
+	let nest_limit = restrict.max_syntax_nesting.min(u32::MAX as usize) as u32;
+
+	RegexBuilder::new(pattern)
+		.size_limit(restrict.max_regex_size)
+		.dfa_size_limit(restrict.max_regex_size)
+		.nest_limit(nest_limit)
+		.build()
+		.map(Regex)
+		.map_err(|e| match e {
+			regex::Error::CompiledTooBig(_) => From::from(RestrictError::RegexSizeExceeded),
+			e => Error::custom(e),
+		})
+}
+
+/// Returns a regex value or compiles a pattern string.
+fn get_regex<'a>(ctx: &Context, v: &'a Value) -> Result<Cow<'a, Regex>, Error> {
+	match *v {
+		Value::Foreign(ref fv) => match fv.downcast_ref::<Regex>() {
+			Some(re) => Ok(Cow::Borrowed(re)),
+			None => Err(From::from(ExecError::expected("regex", v))),
+		},
+		Value::String(ref s) => compile(ctx, s).map(Cow::Owned),
+		ref v => Err(From::from(ExecError::expected("regex", v))),
+	}
+}
+
+fn captures_value(ctx: &Context, re: &regex::Regex, s: &RcString, caps: &Captures) -> Value {
+	let group = |i| {
+		caps.get(i)
+			.map_or(Value::Unit, |m| substring(s, m.as_str()).into())
+	};
+
+	if re.capture_names().any(|name| name.is_some()) {
+		let scope = ctx.scope();
+
+		re.capture_names()
+			.enumerate()
+			.filter_map(|(i, name)| {
+				name.map(|name| (Value::Keyword(scope.add_name(name)), group(i)))
+			})
+			.collect::<Map>()
+			.into()
+	} else {
+		(0..caps.len()).map(group).collect::<Vec<_>>().into()
+	}
+}
@@ -0,0 +1,165 @@
+//! Implements builtin `set` module.
+
+use crate::error::Error;
+use crate::exec::{Context, ExecError};
+use crate::function::Arity::{Exact, Min};
+use crate::module::{Module, ModuleBuilder};
+use crate::scope::Scope;
+use crate::set::Set;
+use crate::value::{FromValueRef, Value};
+
+/// Loads the `set` module into the given scope.
+pub fn load(scope: Scope) -> Module {
+	ModuleBuilder::new("set", scope)
+		.add_function(
+			"set",
+			fn_set,
+			Min(0),
+			Some("Returns a set containing the given values."),
+		)
+		.add_function(
+			"from-list",
+			fn_from_list,
+			Exact(1),
+			Some("Returns a set containing the elements of a list."),
+		)
+		.add_function(
+			"to-list",
+			fn_to_list,
+			Exact(1),
+			Some("Returns a list of the elements of a set, in order."),
+		)
+		.add_function(
+			"insert",
+			fn_insert,
+			Min(1),
+			Some("Returns a set with the given values added."),
+		)
+		.add_function(
+			"remove",
+			fn_remove,
+			Min(1),
+			Some("Returns a set with the given values removed."),
+		)
+		.add_function(
+			"union",
+			fn_union,
+			Min(1),
+			Some("Returns a set of values contained in any of the given sets."),
+		)
+		.add_function(
+			"intersection",
+			fn_intersection,
+			Min(1),
+			Some("Returns a set of values contained in all of the given sets."),
+		)
+		.add_function(
+			"difference",
+			fn_difference,
+			Min(1),
+			Some(
+				"Returns a set of values contained in the first set \
+				 but not in any of the remaining sets.",
+			),
+		)
+		.add_function(
+			"subset?",
+			fn_is_subset,
+			Exact(2),
+			Some("Returns whether every value in the first set is contained in the second."),
+		)
+		.finish()
+}
+
+/// `set` returns a set containing the given values.
+fn fn_set(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let set = Set::from_values(args.iter_mut().map(Value::take));
+	Ok(set.into())
+}
+
+/// `from-list` returns a set containing the elements of a list.
+fn fn_from_list(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let set = match args[0].take() {
+		Value::Unit => Set::new(),
+		Value::List(li) => Set::from_values(li.into_vec()),
+		ref v => return Err(From::from(ExecError::expected("list", v))),
+	};
+
+	Ok(set.into())
+}
+
+/// `to-list` returns a list of the elements of a set.
+fn fn_to_list(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let set = <&Set>::from_value_ref(&args[0])?;
+	Ok(set.as_slice().into())
+}
+
+/// `insert` returns a set with the given values added.
+fn fn_insert(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let (first, rest) = args.split_first_mut().unwrap();
+	let mut set = get_set(first.take())?;
+
+	for v in rest {
+		set.insert(v.take());
+	}
+
+	Ok(set.into())
+}
+
+/// `remove` returns a set with the given values removed.
+fn fn_remove(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let (first, rest) = args.split_first_mut().unwrap();
+	let mut set = get_set(first.take())?;
+
+	for v in rest {
+		set.remove(v);
+	}
+
+	Ok(set.into())
+}
+
+/// `union` returns a set of values contained in any of the given sets.
+fn fn_union(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	fold_sets(args, Set::union)
+}
+
+/// `intersection` returns a set of values contained in all of the given sets.
+fn fn_intersection(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	fold_sets(args, Set::intersection)
+}
+
+/// `difference` returns a set of values contained in the first set
+/// but not in any of the remaining sets.
+fn fn_difference(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	fold_sets(args, Set::difference)
+}
+
+/// `subset?` returns whether every value in the first set is contained
+/// in the second set.
+fn fn_is_subset(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let a = <&Set>::from_value_ref(&args[0])?;
+	let b = <&Set>::from_value_ref(&args[1])?;
+
+	Ok(a.is_subset(b).into())
+}
+
+fn fold_sets<F>(args: &mut [Value], f: F) -> Result<Value, Error>
+where
+	F: Fn(&Set, &Set) -> Set,
+{
+	let (first, rest) = args.split_first_mut().unwrap();
+	let mut set = get_set(first.take())?;
+
+	for v in rest {
+		set = f(&set, <&Set>::from_value_ref(v)?);
+	}
+
+	Ok(set.into())
+}
+
+fn get_set(v: Value) -> Result<Set, ExecError> {
+	match v {
+		Value::Set(s) => Ok(s),
+		ref v => Err(ExecError::expected("set", v)),
+	}
+}
@@ -0,0 +1,402 @@
+//! Implements builtin `string` module.
+//!
+//! Functions which return portions of a string, such as `split` and `trim`,
+//! share the buffer of the original string rather than copying it.
+
+use crate::error::Error;
+use crate::exec::{Context, ExecError};
+use crate::function::Arity::{Exact, Range};
+use crate::module::{Module, ModuleBuilder};
+use crate::rc_vec::RcString;
+use crate::scope::Scope;
+use crate::value::{FromValueRef, Value};
+
+/// Loads the `string` module into the given scope.
+pub fn load(scope: Scope) -> Module {
+	ModuleBuilder::new("string", scope)
+		.add_function(
+			"split",
+			fn_split,
+			Range(2, 3),
+			Some(
+				"    (split string separator)
+    (split string separator limit)
+
+Returns a list of substrings separated by the given separator.
+If a limit is given, at most that many substrings are returned.",
+			),
+		)
+		.add_function(
+			"split-whitespace",
+			fn_split_whitespace,
+			Exact(1),
+			Some("Returns a list of substrings separated by whitespace."),
+		)
+		.add_function(
+			"lines",
+			fn_lines,
+			Exact(1),
+			Some("Returns a list of the lines of a string, without line terminators."),
+		)
+		.add_function(
+			"trim",
+			fn_trim,
+			Range(1, 2),
+			Some(
+				"    (trim string)
+    (trim string chars)
+
+Returns a string with leading and trailing whitespace removed.
+If a string of chars is given, those chars are removed instead.",
+			),
+		)
+		.add_function(
+			"trim-start",
+			fn_trim_start,
+			Range(1, 2),
+			Some("Returns a string with leading whitespace, or the given chars, removed."),
+		)
+		.add_function(
+			"trim-end",
+			fn_trim_end,
+			Range(1, 2),
+			Some("Returns a string with trailing whitespace, or the given chars, removed."),
+		)
+		.add_function(
+			"starts-with",
+			fn_starts_with,
+			Exact(2),
+			Some("Returns whether a string begins with the given prefix."),
+		)
+		.add_function(
+			"ends-with",
+			fn_ends_with,
+			Exact(2),
+			Some("Returns whether a string ends with the given suffix."),
+		)
+		.add_function(
+			"find",
+			fn_find,
+			Exact(2),
+			Some(
+				"Returns the byte index of the first occurrence of a substring,
+or `()` if it does not occur.",
+			),
+		)
+		.add_function(
+			"rfind",
+			fn_rfind,
+			Exact(2),
+			Some(
+				"Returns the byte index of the last occurrence of a substring,
+or `()` if it does not occur.",
+			),
+		)
+		.add_function(
+			"replace",
+			fn_replace,
+			Range(3, 4),
+			Some(
+				"    (replace string from to)
+    (replace string from to count)
+
+Returns a string with occurrences of `from` replaced with `to`.
+If a count is given, at most that many occurrences are replaced.",
+			),
+		)
+		.add_function(
+			"upper",
+			fn_upper,
+			Exact(1),
+			Some("Returns a string converted to uppercase."),
+		)
+		.add_function(
+			"lower",
+			fn_lower,
+			Exact(1),
+			Some("Returns a string converted to lowercase."),
+		)
+		.add_function(
+			"pad-start",
+			fn_pad_start,
+			Range(2, 3),
+			Some(
+				"    (pad-start string width)
+    (pad-start string width char)
+
+Returns a string padded at the start to the given width, in chars.
+The string is padded with spaces, unless another char is given.",
+			),
+		)
+		.add_function(
+			"pad-end",
+			fn_pad_end,
+			Range(2, 3),
+			Some(
+				"    (pad-end string width)
+    (pad-end string width char)
+
+Returns a string padded at the end to the given width, in chars.
+The string is padded with spaces, unless another char is given.",
+			),
+		)
+		.add_function(
+			"repeat",
+			fn_repeat,
+			Exact(2),
+			Some("Returns a string repeated the given number of times."),
+		)
+		.finish()
+}
+
+/// `split` returns a list of substrings separated by a separator.
+///
+/// ```lisp
+/// (split "a,b,c" ",")    ; ("a" "b" "c")
+/// (split "a,b,c" "," 2)  ; ("a" "b,c")
+/// ```
+fn fn_split(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let s = get_string(&args[0])?;
+	let sep = <&str>::from_value_ref(&args[1])?;
+
+	let v = match args.get(2) {
+		Some(n) => {
+			let n = usize::from_value_ref(n)?;
+			substrings(s, s.splitn(n, sep))
+		}
+		None => substrings(s, s.split(sep)),
+	};
+
+	check_value(ctx, v)
+}
+
+/// `split-whitespace` returns a list of substrings separated by whitespace.
+fn fn_split_whitespace(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let s = get_string(&args[0])?;
+	check_value(ctx, substrings(s, s.split_whitespace()))
+}
+
+/// `lines` returns a list of the lines of a string.
+fn fn_lines(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let s = get_string(&args[0])?;
+	check_value(ctx, substrings(s, s.lines()))
+}
+
+/// `trim` returns a string with leading and trailing whitespace removed.
+///
+/// ```lisp
+/// (trim "  foo  ")     ; "foo"
+/// (trim "--foo--" "-") ; "foo"
+/// ```
+fn fn_trim(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let s = get_string(&args[0])?;
+
+	let t = match args.get(1) {
+		Some(chars) => {
+			let chars = <&str>::from_value_ref(chars)?;
+			s.trim_matches(|c| chars.contains(c))
+		}
+		None => s.trim(),
+	};
+
+	Ok(substring(s, t).into())
+}
+
+/// `trim-start` returns a string with leading whitespace removed.
+fn fn_trim_start(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let s = get_string(&args[0])?;
+
+	let t = match args.get(1) {
+		Some(chars) => {
+			let chars = <&str>::from_value_ref(chars)?;
+			s.trim_start_matches(|c| chars.contains(c))
+		}
+		None => s.trim_start(),
+	};
+
+	Ok(substring(s, t).into())
+}
+
+/// `trim-end` returns a string with trailing whitespace removed.
+fn fn_trim_end(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let s = get_string(&args[0])?;
+
+	let t = match args.get(1) {
+		Some(chars) => {
+			let chars = <&str>::from_value_ref(chars)?;
+			s.trim_end_matches(|c| chars.contains(c))
+		}
+		None => s.trim_end(),
+	};
+
+	Ok(substring(s, t).into())
+}
+
+/// `starts-with` returns whether a string begins with a prefix.
+fn fn_starts_with(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let s = <&str>::from_value_ref(&args[0])?;
+	let prefix = <&str>::from_value_ref(&args[1])?;
+
+	Ok(s.starts_with(prefix).into())
+}
+
+/// `ends-with` returns whether a string ends with a suffix.
+fn fn_ends_with(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let s = <&str>::from_value_ref(&args[0])?;
+	let suffix = <&str>::from_value_ref(&args[1])?;
+
+	Ok(s.ends_with(suffix).into())
+}
+
+/// `find` returns the byte index of the first occurrence of a substring.
+///
+/// ```lisp
+/// (find "foobar" "o")  ; 1
+/// ```
+fn fn_find(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let s = <&str>::from_value_ref(&args[0])?;
+	let pat = <&str>::from_value_ref(&args[1])?;
+
+	Ok(s.find(pat).map_or(Value::Unit, Value::from))
+}
+
+/// `rfind` returns the byte index of the last occurrence of a substring.
+///
+/// ```lisp
+/// (rfind "foobar" "o")  ; 2
+/// ```
+fn fn_rfind(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let s = <&str>::from_value_ref(&args[0])?;
+	let pat = <&str>::from_value_ref(&args[1])?;
+
+	Ok(s.rfind(pat).map_or(Value::Unit, Value::from))
+}
+
+/// `replace` returns a string with occurrences of a substring replaced.
+///
+/// ```lisp
+/// (replace "foo" "o" "0")    ; "f00"
+/// (replace "foo" "o" "0" 1)  ; "f0o"
+/// ```
+fn fn_replace(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let s = get_string(&args[0])?;
+	let from = <&str>::from_value_ref(&args[1])?;
+	let to = <&str>::from_value_ref(&args[2])?;
+	let count = match args.get(3) {
+		Some(n) => usize::from_value_ref(n)?,
+		None => usize::MAX,
+	};
+
+	let n = s.matches(from).take(count).count();
+
+	if n == 0 {
+		return Ok(Value::String(s.clone()));
+	}
+
+	let len = (s.len() - n * from.len()).saturating_add(n.saturating_mul(to.len()));
+	ctx.check_memory(len)?;
+
+	Ok(s.replacen(from, to, count).into())
+}
+
+/// `upper` returns a string converted to uppercase.
+fn fn_upper(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let s = <&str>::from_value_ref(&args[0])?;
+	check_value(ctx, s.to_uppercase().into())
+}
+
+/// `lower` returns a string converted to lowercase.
+fn fn_lower(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let s = <&str>::from_value_ref(&args[0])?;
+	check_value(ctx, s.to_lowercase().into())
+}
+
+/// `pad-start` returns a string padded at the start to a given width.
+///
+/// ```lisp
+/// (pad-start "7" 3 #'0')  ; "007"
+/// ```
+fn fn_pad_start(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	pad(ctx, args, true)
+}
+
+/// `pad-end` returns a string padded at the end to a given width.
+///
+/// ```lisp
+/// (pad-end "ab" 4)  ; "ab  "
+/// ```
+fn fn_pad_end(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	pad(ctx, args, false)
+}
+
+fn pad(ctx: &Context, args: &mut [Value], start: bool) -> Result<Value, Error> {
+	let s = get_string(&args[0])?;
+	let width = usize::from_value_ref(&args[1])?;
+	let ch = match args.get(2) {
+		Some(ch) => char::from_value_ref(ch)?,
+		None => ' ',
+	};
+
+	let n = width.saturating_sub(s.chars().count());
+
+	if n == 0 {
+		return Ok(Value::String(s.clone()));
+	}
+
+	ctx.check_memory(s.len().saturating_add(n.saturating_mul(ch.len_utf8())))?;
+
+	let mut res = String::with_capacity(s.len() + n * ch.len_utf8());
+
+	if !start {
+		res.push_str(s);
+	}
+
+	res.extend((0..n).map(|_| ch));
+
+	if start {
+		res.push_str(s);
+	}
+
+	Ok(res.into())
+}
+
+/// `repeat` returns a string repeated a number of times.
+///
+/// ```lisp
+/// (repeat "ab" 3)  ; "ababab"
+/// ```
+fn fn_repeat(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let s = <&str>::from_value_ref(&args[0])?;
+	let n = usize::from_value_ref(&args[1])?;
+
+	ctx.check_memory(s.len().saturating_mul(n))?;
+
+	Ok(s.repeat(n).into())
+}
+
+pub(crate) fn get_string(v: &Value) -> Result<&RcString, ExecError> {
+	match *v {
+		Value::String(ref s) => Ok(s),
+		ref v => Err(ExecError::expected("string", v)),
+	}
+}
+
+/// Returns a slice of `s` sharing its buffer; `sub` must be a slice of `s`.
+pub(crate) fn substring(s: &RcString, sub: &str) -> RcString {
+	let start = sub.as_ptr() as usize - s.as_ptr() as usize;
+	s.slice(start..start + sub.len())
+}
+
+fn substrings<'a, I>(s: &RcString, iter: I) -> Value
+where
+	I: Iterator<Item = &'a str>,
+{
+	iter.map(|sub| Value::String(substring(s, sub)))
+		.collect::<Vec<_>>()
+		.into()
+}
+
+pub(crate) fn check_value(ctx: &Context, v: Value) -> Result<Value, Error> {
+	ctx.check_memory(v.size())?;
+	Ok(v)
+}
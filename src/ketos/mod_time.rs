@@ -0,0 +1,1084 @@
+//! Implements builtin `time` module.
+//!
+//! Monotonic time is represented by foreign `instant` values and
+//! spans of time by foreign `duration` values. Wall-clock timestamps are
+//! numbers of seconds since the Unix epoch, in UTC.
+//!
+//! All time is read from the `Clock` of the global scope.
+
+use std::cmp::Ordering;
+use std::error::Error as StdError;
+use std::fmt::{self, Write};
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::error::Error;
+use crate::exec::{Context, ExecError};
+use crate::function::Arity::{Exact, Range};
+use crate::integer::Integer;
+use crate::module::{Module, ModuleBuilder};
+use crate::name::NameStore;
+use crate::restrict::RestrictError;
+use crate::scope::Scope;
+use crate::value::{ForeignValue, FromValueRef, Value};
+
+/// Loads the `time` module into the given scope.
+pub fn load(scope: Scope) -> Module {
+	ModuleBuilder::new("time", scope)
+		.add_function(
+			"now",
+			fn_now,
+			Exact(0),
+			Some("Returns an `instant` representing the current monotonic time."),
+		)
+		.add_function(
+			"elapsed",
+			fn_elapsed,
+			Exact(1),
+			Some("Returns the `duration` elapsed since the given `instant`."),
+		)
+		.add_function(
+			"unix-time",
+			fn_unix_time,
+			Exact(0),
+			Some("Returns the current wall-clock time, in seconds since the Unix epoch."),
+		)
+		.add_function(
+			"duration",
+			fn_duration,
+			Range(1, 2),
+			Some(
+				"    (duration n)
+    (duration n unit)
+
+Returns a `duration` of `n` units. `unit` is one of `:ns`, `:us`, `:ms`,
+`:s`, `:min`, `:h`, or `:d`. The default unit is `:s`.",
+			),
+		)
+		.add_function(
+			"as-secs",
+			fn_as_secs,
+			Exact(1),
+			Some("Returns the length of a `duration` in seconds, as a float."),
+		)
+		.add_function(
+			"as-millis",
+			fn_as_millis,
+			Exact(1),
+			Some("Returns the length of a `duration` in whole milliseconds."),
+		)
+		.add_function(
+			"as-nanos",
+			fn_as_nanos,
+			Exact(1),
+			Some("Returns the length of a `duration` in nanoseconds."),
+		)
+		.add_function(
+			"add",
+			fn_add,
+			Exact(2),
+			Some("Adds a `duration` to an `instant` or another `duration`."),
+		)
+		.add_function(
+			"sub",
+			fn_sub,
+			Exact(2),
+			Some(
+				"    (sub instant instant)
+    (sub instant duration)
+    (sub duration duration)
+
+Returns the difference between two `instant` values as a `duration`,
+or subtracts a `duration` from an `instant` or another `duration`.
+An error is returned if the result would be negative.",
+			),
+		)
+		.add_function(
+			"mul",
+			fn_mul,
+			Exact(2),
+			Some("Multiplies a `duration` by a non-negative number."),
+		)
+		.add_function(
+			"div",
+			fn_div,
+			Exact(2),
+			Some("Divides a `duration` by a positive number."),
+		)
+		.add_function(
+			"sleep",
+			fn_sleep,
+			Exact(1),
+			Some(
+				"Suspends execution for a `duration` or a number of seconds.
+
+`sleep` is not allowed when `RestrictConfig::allow_sleep` is false,
+and fails without sleeping if it would exceed the execution time limit.",
+			),
+		)
+		.add_function(
+			"format-time",
+			fn_format_time,
+			Range(1, 2),
+			Some(
+				"    (format-time timestamp)
+    (format-time timestamp format)
+
+Formats a Unix timestamp, in UTC, using a `strftime`-style format string.
+Without a format, the timestamp is formatted according to RFC 3339.",
+			),
+		)
+		.add_function(
+			"parse-time",
+			fn_parse_time,
+			Range(1, 2),
+			Some(
+				"    (parse-time string)
+    (parse-time string format)
+
+Parses a string into a Unix timestamp using a `strftime`-style format
+string. Without a format, the string is parsed according to RFC 3339.
+
+The result is an integer, or a float if the time has a fractional part.",
+			),
+		)
+		.finish()
+}
+
+/// Represents an error in formatting or parsing a time value.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TimeError {
+	/// Unrecognized specifier in format string
+	InvalidFormat(char),
+	/// Time string did not match format at the given byte offset
+	ParseError(usize),
+	/// Date or time value out of range
+	OutOfRange,
+}
+
+impl fmt::Display for TimeError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			TimeError::InvalidFormat(ch) => write!(f, "invalid format specifier: %{}", ch),
+			TimeError::ParseError(offset) => {
+				write!(f, "time string does not match format at byte {}", offset)
+			}
+			TimeError::OutOfRange => f.write_str("time value out of range"),
+		}
+	}
+}
+
+impl StdError for TimeError {
+	fn description(&self) -> &str {
+		"time error"
+	}
+}
+
+impl From<TimeError> for Error {
+	fn from(e: TimeError) -> Error {
+		Error::custom(e)
+	}
+}
+
+macro_rules! time_value {
+	( $ty:ident , $name:expr ) => {
+		impl ForeignValue for $ty {
+			fn compare_to(&self, rhs: &dyn ForeignValue) -> Result<Ordering, ExecError> {
+				match rhs.downcast_ref::<$ty>() {
+					Some(rhs) => Ok(self.0.cmp(&rhs.0)),
+					None => Err(ExecError::TypeMismatch {
+						lhs: self.type_name(),
+						rhs: rhs.type_name(),
+					}),
+				}
+			}
+
+			fn is_equal_to(&self, rhs: &dyn ForeignValue) -> Result<bool, ExecError> {
+				match rhs.downcast_ref::<$ty>() {
+					Some(rhs) => Ok(self.0 == rhs.0),
+					None => Err(ExecError::TypeMismatch {
+						lhs: self.type_name(),
+						rhs: rhs.type_name(),
+					}),
+				}
+			}
+
+			fn fmt_debug(&self, _names: &NameStore, f: &mut fmt::Formatter) -> fmt::Result {
+				write!(f, concat!("<", $name, " {:?}>"), self.0)
+			}
+
+			fn type_name(&self) -> &'static str {
+				$name
+			}
+		}
+	};
+}
+
+/// Point in monotonic time, relative to the start of the clock
+#[derive(Copy, Clone, Debug)]
+struct Instant(Duration);
+
+/// Span of time
+#[derive(Copy, Clone, Debug)]
+struct TimeSpan(Duration);
+
+time_value! { Instant, "instant" }
+time_value! { TimeSpan, "duration" }
+
+/// Default format used by `format-time`
+const RFC3339_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.fZ";
+
+/// Default format used by `parse-time`; `%z` also accepts `Z`.
+const RFC3339_PARSE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f%z";
+
+/// Earliest timestamp accepted; `0000-01-01T00:00:00Z`
+const MIN_TIMESTAMP: i64 = -62_167_219_200;
+
+/// Latest timestamp accepted; `9999-12-31T23:59:59Z`
+const MAX_TIMESTAMP: i64 = 253_402_300_799;
+
+const NANOS_PER_SEC: u32 = 1_000_000_000;
+
+/// `now` returns the current monotonic time.
+fn fn_now(ctx: &Context, _args: &mut [Value]) -> Result<Value, Error> {
+	Ok(Value::new_foreign(Instant(ctx.scope().clock().monotonic())))
+}
+
+/// `elapsed` returns the duration elapsed since an instant.
+///
+/// ```lisp
+/// (define start (now))
+/// (elapsed start)  ; <duration 1.2ms>
+/// ```
+fn fn_elapsed(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let start = get_instant(&args[0])?;
+	let now = ctx.scope().clock().monotonic();
+
+	Ok(duration_value(now.saturating_sub(start)))
+}
+
+/// `unix-time` returns the current wall-clock time as a Unix timestamp.
+fn fn_unix_time(ctx: &Context, _args: &mut [Value]) -> Result<Value, Error> {
+	let t = ctx.scope().clock().system_time();
+
+	let secs = match t.duration_since(UNIX_EPOCH) {
+		Ok(d) => d.as_secs_f64(),
+		Err(e) => -e.duration().as_secs_f64(),
+	};
+
+	Ok(Value::Float(secs))
+}
+
+/// `duration` returns a duration of a number of units.
+///
+/// ```lisp
+/// (duration 1.5)      ; <duration 1.5s>
+/// (duration 250 :ms)  ; <duration 250ms>
+/// ```
+fn fn_duration(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let nanos_per_unit: u64 = match args.get(1) {
+		Some(&Value::Keyword(name)) => match ctx.scope().borrow_names().get(name) {
+			"ns" => 1,
+			"us" => 1_000,
+			"ms" => 1_000_000,
+			"s" => 1_000_000_000,
+			"min" => 60 * 1_000_000_000,
+			"h" => 60 * 60 * 1_000_000_000,
+			"d" => 24 * 60 * 60 * 1_000_000_000,
+			_ => return Err(From::from(ExecError::UnrecognizedKeyword(name))),
+		},
+		Some(v) => return Err(From::from(ExecError::expected("keyword", v))),
+		None => 1_000_000_000,
+	};
+
+	let d = match args[0] {
+		Value::Integer(ref i) => {
+			let n = i.to_u64().ok_or(ExecError::Overflow)?;
+			let nanos = u128::from(n) * u128::from(nanos_per_unit);
+			let secs = u64::try_from(nanos / u128::from(NANOS_PER_SEC))
+				.map_err(|_| ExecError::Overflow)?;
+
+			Duration::new(secs, (nanos % u128::from(NANOS_PER_SEC)) as u32)
+		}
+		Value::Float(f) if f >= 0.0 => Duration::try_from_secs_f64(f * nanos_per_unit as f64 / 1e9)
+			.map_err(|_| ExecError::Overflow)?,
+		ref v => return Err(From::from(ExecError::expected("non-negative number", v))),
+	};
+
+	Ok(duration_value(d))
+}
+
+/// `as-secs` returns the length of a duration in seconds.
+fn fn_as_secs(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	Ok(Value::Float(get_duration(&args[0])?.as_secs_f64()))
+}
+
+/// `as-millis` returns the length of a duration in whole milliseconds.
+fn fn_as_millis(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let d = get_duration(&args[0])?;
+	let millis = Integer::from_u64(d.as_secs()) * Integer::from_u32(1_000)
+		+ Integer::from_u32(d.subsec_millis());
+
+	Ok(Value::Integer(millis))
+}
+
+/// `as-nanos` returns the length of a duration in nanoseconds.
+fn fn_as_nanos(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let d = get_duration(&args[0])?;
+	let nanos = Integer::from_u64(d.as_secs()) * Integer::from_u32(NANOS_PER_SEC)
+		+ Integer::from_u32(d.subsec_nanos());
+
+	Ok(Value::Integer(nanos))
+}
+
+/// `add` adds a duration to an instant or another duration.
+///
+/// ```lisp
+/// (add (duration 1) (duration 500 :ms))  ; <duration 1.5s>
+/// ```
+fn fn_add(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	if let Some(d) = get_foreign::<TimeSpan>(&args[1]) {
+		if let Some(t) = get_foreign::<Instant>(&args[0]) {
+			let t = t.0.checked_add(d.0).ok_or(ExecError::Overflow)?;
+			return Ok(Value::new_foreign(Instant(t)));
+		}
+	}
+
+	let a = get_duration(&args[0])?;
+	let b = get_duration(&args[1])?;
+
+	Ok(duration_value(a.checked_add(b).ok_or(ExecError::Overflow)?))
+}
+
+/// `sub` returns the difference between two instants or durations,
+/// or subtracts a duration from an instant.
+///
+/// ```lisp
+/// (sub (duration 1) (duration 500 :ms))  ; <duration 500ms>
+/// ```
+fn fn_sub(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	if let Some(a) = get_foreign::<Instant>(&args[0]) {
+		return match args[1] {
+			Value::Foreign(ref v) if v.is::<Instant>() => {
+				let b = get_instant(&args[1])?;
+				let d = a.0.checked_sub(b).ok_or(ExecError::Overflow)?;
+				Ok(duration_value(d))
+			}
+			ref v => {
+				let d = get_duration(v)?;
+				let t = a.0.checked_sub(d).ok_or(ExecError::Overflow)?;
+				Ok(Value::new_foreign(Instant(t)))
+			}
+		};
+	}
+
+	let a = get_duration(&args[0])?;
+	let b = get_duration(&args[1])?;
+
+	Ok(duration_value(a.checked_sub(b).ok_or(ExecError::Overflow)?))
+}
+
+/// `mul` multiplies a duration by a number.
+fn fn_mul(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let d = get_duration(&args[0])?;
+
+	let r = match args[1] {
+		Value::Integer(ref i) => i.to_u32().and_then(|n| d.checked_mul(n)),
+		Value::Float(f) if f >= 0.0 => Duration::try_from_secs_f64(d.as_secs_f64() * f).ok(),
+		ref v => return Err(From::from(ExecError::expected("non-negative number", v))),
+	};
+
+	Ok(duration_value(r.ok_or(ExecError::Overflow)?))
+}
+
+/// `div` divides a duration by a number.
+fn fn_div(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let d = get_duration(&args[0])?;
+
+	let r = match args[1] {
+		Value::Integer(ref i) if i.is_zero() => return Err(From::from(ExecError::DivideByZero)),
+		Value::Integer(ref i) if i.is_positive() => div_nanos(d, i),
+		Value::Float(0.0) => return Err(From::from(ExecError::DivideByZero)),
+		Value::Float(f) if f > 0.0 => {
+			Duration::try_from_secs_f64(d.as_secs_f64() / f).map_err(|_| ExecError::Overflow)?
+		}
+		ref v => return Err(From::from(ExecError::expected("positive number", v))),
+	};
+
+	Ok(duration_value(r))
+}
+
+/// Divides a duration by a positive integer, in nanoseconds.
+fn div_nanos(d: Duration, n: &Integer) -> Duration {
+	let nanos = d.as_nanos();
+
+	let q = match n.to_u64() {
+		Some(n) => nanos / u128::from(n),
+		None => {
+			// A divisor beyond `u64` may still leave a result of up to one second
+			let nanos =
+				(Integer::from_u64((nanos >> 64) as u64) << 64) + Integer::from_u64(nanos as u64);
+			u128::from((nanos / n).to_u64().unwrap_or(0))
+		}
+	};
+
+	let secs = (q / u128::from(NANOS_PER_SEC)) as u64;
+	Duration::new(secs, (q % u128::from(NANOS_PER_SEC)) as u32)
+}
+
+/// `sleep` suspends execution for a duration or number of seconds.
+fn fn_sleep(ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	if !ctx.restrict().allow_sleep {
+		return Err(From::from(RestrictError::SleepNotAllowed));
+	}
+
+	let d = get_seconds(&args[0])?;
+
+	// A sleep which would pass the execution time limit is not begun
+	if ctx.time_remaining().is_some_and(|rem| d >= rem) {
+		return Err(From::from(RestrictError::ExecutionTimeExceeded));
+	}
+
+	ctx.scope().clock().sleep(d);
+	Ok(Value::Unit)
+}
+
+/// `format-time` formats a Unix timestamp.
+///
+/// ```lisp
+/// (format-time 0)                      ; "1970-01-01T00:00:00Z"
+/// (format-time 1.5 "%H:%M:%S%.3f")     ; "00:00:01.500"
+/// ```
+fn fn_format_time(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let (secs, nanos) = get_timestamp(&args[0])?;
+	let fmt = match args.get(1) {
+		Some(v) => <&str>::from_value_ref(v)?,
+		None => RFC3339_FORMAT,
+	};
+
+	let dt = DateTime::from_timestamp(secs, nanos);
+	let mut res = String::new();
+
+	dt.format(fmt, &mut res)?;
+
+	Ok(res.into())
+}
+
+/// `parse-time` parses a string into a Unix timestamp.
+///
+/// ```lisp
+/// (parse-time "1970-01-02T00:00:00Z")       ; 86400
+/// (parse-time "02/01/70" "%d/%m/%y")        ; 86400
+/// ```
+fn fn_parse_time(_ctx: &Context, args: &mut [Value]) -> Result<Value, Error> {
+	let s = <&str>::from_value_ref(&args[0])?;
+	let fmt = match args.get(1) {
+		Some(v) => <&str>::from_value_ref(v)?,
+		None => RFC3339_PARSE_FORMAT,
+	};
+
+	let mut p = TimeParser::new(s);
+
+	p.parse(fmt)?;
+
+	if p.pos != s.len() {
+		return Err(From::from(TimeError::ParseError(p.pos)));
+	}
+
+	let (secs, nanos) = p.timestamp()?;
+
+	if nanos == 0 {
+		Ok(Value::Integer(Integer::from_i64(secs)))
+	} else {
+		Ok(Value::Float(secs as f64 + f64::from(nanos) / 1e9))
+	}
+}
+
+fn duration_value(d: Duration) -> Value {
+	Value::new_foreign(TimeSpan(d))
+}
+
+fn get_foreign<T: ForeignValue>(v: &Value) -> Option<&T> {
+	match *v {
+		Value::Foreign(ref fv) => fv.downcast_ref::<T>(),
+		_ => None,
+	}
+}
+
+fn get_instant(v: &Value) -> Result<Duration, ExecError> {
+	get_foreign::<Instant>(v)
+		.map(|t| t.0)
+		.ok_or_else(|| ExecError::expected("instant", v))
+}
+
+fn get_duration(v: &Value) -> Result<Duration, ExecError> {
+	get_foreign::<TimeSpan>(v)
+		.map(|d| d.0)
+		.ok_or_else(|| ExecError::expected("duration", v))
+}
+
+/// Returns a `duration` value or a non-negative number of seconds.
+pub(crate) fn get_seconds(v: &Value) -> Result<Duration, ExecError> {
+	match *v {
+		Value::Integer(ref i) => Ok(Duration::from_secs(i.to_u64().ok_or(ExecError::Overflow)?)),
+		Value::Float(f) if f >= 0.0 => {
+			Duration::try_from_secs_f64(f).map_err(|_| ExecError::Overflow)
+		}
+		ref v => get_duration(v),
+	}
+}
+
+/// Returns whole seconds and nanoseconds of a numeric timestamp.
+fn get_timestamp(v: &Value) -> Result<(i64, u32), Error> {
+	let (secs, nanos) = match *v {
+		Value::Integer(ref i) => (i.to_i64().ok_or(TimeError::OutOfRange)?, 0),
+		Value::Float(f) if f.is_finite() => {
+			let secs = f.floor();
+			let nanos = ((f - secs) * 1e9).round() as u32;
+
+			if secs < i64::MIN as f64 || secs > i64::MAX as f64 {
+				return Err(From::from(TimeError::OutOfRange));
+			}
+
+			if nanos >= NANOS_PER_SEC {
+				(secs as i64 + 1, 0)
+			} else {
+				(secs as i64, nanos)
+			}
+		}
+		ref v => return Err(From::from(ExecError::expected("number", v))),
+	};
+
+	if !(MIN_TIMESTAMP..=MAX_TIMESTAMP).contains(&secs) {
+		return Err(From::from(TimeError::OutOfRange));
+	}
+
+	Ok((secs, nanos))
+}
+
+const MONTH_NAMES: [&str; 12] = [
+	"January",
+	"February",
+	"March",
+	"April",
+	"May",
+	"June",
+	"July",
+	"August",
+	"September",
+	"October",
+	"November",
+	"December",
+];
+
+const WEEKDAY_NAMES: [&str; 7] = [
+	"Sunday",
+	"Monday",
+	"Tuesday",
+	"Wednesday",
+	"Thursday",
+	"Friday",
+	"Saturday",
+];
+
+/// Returns the expansion of a composite format specifier.
+fn composite_format(ch: char) -> Option<&'static str> {
+	match ch {
+		'D' => Some("%m/%d/%y"),
+		'F' => Some("%Y-%m-%d"),
+		'R' => Some("%H:%M"),
+		'T' => Some("%H:%M:%S"),
+		_ => None,
+	}
+}
+
+/// Format specifier, following a `%` character
+struct Spec {
+	ch: char,
+	/// `%.f`
+	dot: bool,
+	/// `%:z`
+	colon: bool,
+	/// `%3f`, `%6f`, or `%9f`
+	width: Option<usize>,
+}
+
+/// Reads a format specifier following a `%` character.
+fn read_spec(chars: &mut std::str::Chars) -> Result<Spec, TimeError> {
+	let mut spec = Spec {
+		ch: '%',
+		dot: false,
+		colon: false,
+		width: None,
+	};
+
+	loop {
+		match chars.next() {
+			Some('.') if !spec.dot => spec.dot = true,
+			Some(':') if !spec.colon => spec.colon = true,
+			Some(ch @ ('3' | '6' | '9')) if spec.width.is_none() => {
+				spec.width = Some(ch as usize - '0' as usize)
+			}
+			Some(ch) => {
+				spec.ch = ch;
+				break;
+			}
+			None => return Err(TimeError::InvalidFormat('%')),
+		}
+	}
+
+	let valid = match spec.ch {
+		'f' => !spec.colon,
+		'z' => !spec.dot && spec.width.is_none(),
+		_ => !spec.dot && !spec.colon && spec.width.is_none(),
+	};
+
+	if valid {
+		Ok(spec)
+	} else {
+		Err(TimeError::InvalidFormat(spec.ch))
+	}
+}
+
+fn is_leap_year(y: i64) -> bool {
+	y % 4 == 0 && (y % 100 != 0 || y % 400 == 0)
+}
+
+fn days_in_month(y: i64, m: u32) -> u32 {
+	match m {
+		2 if is_leap_year(y) => 29,
+		2 => 28,
+		4 | 6 | 9 | 11 => 30,
+		_ => 31,
+	}
+}
+
+/// Returns the number of days since the Unix epoch of a civil date.
+fn days_from_civil(y: i64, m: u32, d: u32) -> i64 {
+	let y = if m <= 2 { y - 1 } else { y };
+	let era = y.div_euclid(400);
+	let yoe = y.rem_euclid(400);
+	let mp = i64::from((m + 9) % 12);
+	let doy = (153 * mp + 2) / 5 + i64::from(d) - 1;
+	let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+	era * 146_097 + doe - 719_468
+}
+
+/// Returns the civil date of a number of days since the Unix epoch.
+fn civil_from_days(z: i64) -> (i64, u32, u32) {
+	let z = z + 719_468;
+	let era = z.div_euclid(146_097);
+	let doe = z.rem_euclid(146_097);
+	let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+	let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+	let mp = (5 * doy + 2) / 153;
+	let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+	let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+	let y = yoe + era * 400;
+
+	(if m <= 2 { y + 1 } else { y }, m, d)
+}
+
+/// Broken-down UTC date and time
+struct DateTime {
+	timestamp: i64,
+	year: i64,
+	month: u32,
+	day: u32,
+	hour: u32,
+	minute: u32,
+	second: u32,
+	nanos: u32,
+	/// Days since Sunday, `0..=6`
+	weekday: u32,
+	/// Day of the year, `1..=366`
+	yday: u32,
+}
+
+impl DateTime {
+	fn from_timestamp(secs: i64, nanos: u32) -> DateTime {
+		let days = secs.div_euclid(86_400);
+		let rem = secs.rem_euclid(86_400) as u32;
+		let (year, month, day) = civil_from_days(days);
+
+		DateTime {
+			timestamp: secs,
+			year,
+			month,
+			day,
+			hour: rem / 3600,
+			minute: rem / 60 % 60,
+			second: rem % 60,
+			nanos,
+			weekday: (days + 4).rem_euclid(7) as u32,
+			yday: (days - days_from_civil(year, 1, 1) + 1) as u32,
+		}
+	}
+
+	fn format(&self, fmt: &str, out: &mut String) -> Result<(), TimeError> {
+		let mut chars = fmt.chars();
+
+		while let Some(ch) = chars.next() {
+			if ch != '%' {
+				out.push(ch);
+				continue;
+			}
+
+			let spec = read_spec(&mut chars)?;
+
+			if let Some(sub) = composite_format(spec.ch) {
+				self.format(sub, out)?;
+				continue;
+			}
+
+			let month_name = MONTH_NAMES[self.month as usize - 1];
+			let weekday_name = WEEKDAY_NAMES[self.weekday as usize];
+
+			let _ = match spec.ch {
+				'Y' => write!(out, "{:04}", self.year),
+				'y' => write!(out, "{:02}", self.year % 100),
+				'm' => write!(out, "{:02}", self.month),
+				'd' => write!(out, "{:02}", self.day),
+				'e' => write!(out, "{:2}", self.day),
+				'j' => write!(out, "{:03}", self.yday),
+				'H' => write!(out, "{:02}", self.hour),
+				'I' => write!(out, "{:02}", (self.hour + 11) % 12 + 1),
+				'p' => out.write_str(if self.hour < 12 { "AM" } else { "PM" }),
+				'M' => write!(out, "{:02}", self.minute),
+				'S' => write!(out, "{:02}", self.second),
+				'f' => {
+					let width = match spec.width {
+						Some(w) => w,
+						None if spec.dot => match self.nanos {
+							0 => 0,
+							n if n % 1_000_000 == 0 => 3,
+							n if n % 1_000 == 0 => 6,
+							_ => 9,
+						},
+						None => 9,
+					};
+
+					if width != 0 {
+						if spec.dot {
+							out.push('.');
+						}
+						let n = self.nanos / 10u32.pow(9 - width as u32);
+						write!(out, "{:0width$}", n, width = width)
+					} else {
+						Ok(())
+					}
+				}
+				'a' => out.write_str(&weekday_name[..3]),
+				'A' => out.write_str(weekday_name),
+				'b' | 'h' => out.write_str(&month_name[..3]),
+				'B' => out.write_str(month_name),
+				'u' => write!(out, "{}", (self.weekday + 6) % 7 + 1),
+				'w' => write!(out, "{}", self.weekday),
+				's' => write!(out, "{}", self.timestamp),
+				'z' => out.write_str(if spec.colon { "+00:00" } else { "+0000" }),
+				'Z' => out.write_str("UTC"),
+				'n' => out.write_str("\n"),
+				't' => out.write_str("\t"),
+				'%' => out.write_str("%"),
+				ch => return Err(TimeError::InvalidFormat(ch)),
+			};
+		}
+
+		Ok(())
+	}
+}
+
+/// Accumulates fields of a time string parsed according to a format
+struct TimeParser<'a> {
+	input: &'a str,
+	pos: usize,
+	year: i64,
+	month: u32,
+	day: u32,
+	yday: Option<u32>,
+	hour: u32,
+	pm: Option<bool>,
+	minute: u32,
+	second: u32,
+	nanos: u32,
+	offset: i64,
+	timestamp: Option<i64>,
+}
+
+impl<'a> TimeParser<'a> {
+	fn new(input: &'a str) -> TimeParser<'a> {
+		TimeParser {
+			input,
+			pos: 0,
+			year: 1970,
+			month: 1,
+			day: 1,
+			yday: None,
+			hour: 0,
+			pm: None,
+			minute: 0,
+			second: 0,
+			nanos: 0,
+			offset: 0,
+			timestamp: None,
+		}
+	}
+
+	fn error(&self) -> TimeError {
+		TimeError::ParseError(self.pos)
+	}
+
+	fn rest(&self) -> &'a str {
+		&self.input[self.pos..]
+	}
+
+	fn peek(&self) -> Option<char> {
+		self.rest().chars().next()
+	}
+
+	fn eat(&mut self, ch: char) -> bool {
+		if self.peek() == Some(ch) {
+			self.pos += ch.len_utf8();
+			true
+		} else {
+			false
+		}
+	}
+
+	/// Parses between `min` and `max` decimal digits.
+	fn digits(&mut self, min: usize, max: usize) -> Result<(u64, usize), TimeError> {
+		let n = self
+			.rest()
+			.bytes()
+			.take(max)
+			.take_while(u8::is_ascii_digit)
+			.count();
+
+		if n < min {
+			return Err(self.error());
+		}
+
+		let s = &self.rest()[..n];
+		self.pos += n;
+
+		// At most 19 digits are read, so this cannot overflow.
+		Ok((s.parse().unwrap(), n))
+	}
+
+	fn number(&mut self, max: usize) -> Result<u32, TimeError> {
+		self.digits(1, max).map(|(n, _)| n as u32)
+	}
+
+	/// Parses one of a list of names, in full or abbreviated to three chars,
+	/// returning its index.
+	fn name(&mut self, names: &[&str]) -> Result<usize, TimeError> {
+		let rest = self.rest();
+
+		for &len in &[None, Some(3)] {
+			for (i, name) in names.iter().enumerate() {
+				let name = len.map_or(*name, |n| &name[..n]);
+
+				if rest
+					.get(..name.len())
+					.is_some_and(|s| s.eq_ignore_ascii_case(name))
+				{
+					self.pos += name.len();
+					return Ok(i);
+				}
+			}
+		}
+
+		Err(self.error())
+	}
+
+	fn parse(&mut self, fmt: &str) -> Result<(), TimeError> {
+		let mut chars = fmt.chars();
+
+		while let Some(ch) = chars.next() {
+			if ch != '%' {
+				match self.peek() {
+					Some(c)
+						if c == ch || (c.is_ascii_alphabetic() && c.eq_ignore_ascii_case(&ch)) =>
+					{
+						self.pos += c.len_utf8();
+					}
+					_ => return Err(self.error()),
+				}
+				continue;
+			}
+
+			let spec = read_spec(&mut chars)?;
+
+			if let Some(sub) = composite_format(spec.ch) {
+				self.parse(sub)?;
+				continue;
+			}
+
+			match spec.ch {
+				'Y' => {
+					let neg = self.eat('-');
+					let n = i64::from(self.number(4)?);
+					self.year = if neg { -n } else { n };
+				}
+				'y' => {
+					let n = i64::from(self.number(2)?);
+					self.year = if n < 69 { 2000 + n } else { 1900 + n };
+				}
+				'm' => self.month = self.number(2)?,
+				'd' => self.day = self.number(2)?,
+				'e' => {
+					self.eat(' ');
+					self.day = self.number(2)?;
+				}
+				'j' => self.yday = Some(self.number(3)?),
+				'H' => self.hour = self.number(2)?,
+				'I' => {
+					self.hour = self.number(2)?;
+					self.pm.get_or_insert(false);
+				}
+				'p' => {
+					let rest = self.rest();
+					let pm = match rest.get(..2) {
+						Some(s) if s.eq_ignore_ascii_case("am") => false,
+						Some(s) if s.eq_ignore_ascii_case("pm") => true,
+						_ => return Err(self.error()),
+					};
+					self.pos += 2;
+					self.pm = Some(pm);
+				}
+				'M' => self.minute = self.number(2)?,
+				'S' => self.second = self.number(2)?,
+				'f' => {
+					if spec.dot && !self.eat('.') {
+						if spec.width.is_none() {
+							continue;
+						}
+						return Err(self.error());
+					}
+
+					let (n, len) = match spec.width {
+						Some(w) => self.digits(w, w)?,
+						None => self.digits(1, 9)?,
+					};
+
+					self.nanos = (n * 10u64.pow(9 - len as u32)) as u32;
+				}
+				'a' | 'A' => {
+					self.name(&WEEKDAY_NAMES)?;
+				}
+				'b' | 'B' | 'h' => self.month = self.name(&MONTH_NAMES)? as u32 + 1,
+				's' => {
+					let neg = self.eat('-');
+					let (n, _) = self.digits(1, 18)?;
+					let n = n as i64;
+					self.timestamp = Some(if neg { -n } else { n });
+				}
+				'z' => {
+					if self.eat('Z') || self.eat('z') {
+						self.offset = 0;
+						continue;
+					}
+
+					let neg = match self.peek() {
+						Some('+') => false,
+						Some('-') => true,
+						_ => return Err(self.error()),
+					};
+					self.pos += 1;
+
+					let (h, _) = self.digits(2, 2)?;
+					self.eat(':');
+					let (m, _) = self.digits(2, 2)?;
+
+					if h > 23 || m > 59 {
+						return Err(TimeError::OutOfRange);
+					}
+
+					let offset = (h * 3600 + m * 60) as i64;
+					self.offset = if neg { -offset } else { offset };
+				}
+				'Z' => {
+					let rest = self.rest();
+
+					if rest.starts_with('Z') || rest.starts_with('z') {
+						self.pos += 1;
+					} else if rest.get(..3).is_some_and(|s| {
+						s.eq_ignore_ascii_case("UTC") || s.eq_ignore_ascii_case("GMT")
+					}) {
+						self.pos += 3;
+					} else {
+						return Err(self.error());
+					}
+				}
+				'n' | 't' => {
+					let rest = self.rest();
+					self.pos += rest.len() - rest.trim_start().len();
+				}
+				'%' => {
+					if !self.eat('%') {
+						return Err(self.error());
+					}
+				}
+				ch => return Err(TimeError::InvalidFormat(ch)),
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Returns the parsed Unix timestamp, as whole seconds and nanoseconds.
+	fn timestamp(&self) -> Result<(i64, u32), TimeError> {
+		if let Some(t) = self.timestamp {
+			if !(MIN_TIMESTAMP..=MAX_TIMESTAMP).contains(&t) {
+				return Err(TimeError::OutOfRange);
+			}
+			return Ok((t, self.nanos));
+		}
+
+		let hour = match self.pm {
+			Some(_) if self.hour == 0 || self.hour > 12 => return Err(TimeError::OutOfRange),
+			Some(pm) => self.hour % 12 + if pm { 12 } else { 0 },
+			None => self.hour,
+		};
+
+		let days = match self.yday {
+			Some(yday) => {
+				let len = if is_leap_year(self.year) { 366 } else { 365 };
+
+				if yday == 0 || yday > len {
+					return Err(TimeError::OutOfRange);
+				}
+
+				days_from_civil(self.year, 1, 1) + i64::from(yday) - 1
+			}
+			None => {
+				if self.month == 0
+					|| self.month > 12
+					|| self.day == 0
+					|| self.day > days_in_month(self.year, self.month)
+				{
+					return Err(TimeError::OutOfRange);
+				}
+
+				days_from_civil(self.year, self.month, self.day)
+			}
+		};
+
+		// A leap second, `60`, is accepted and rolls over into the next minute.
+		if hour > 23 || self.minute > 59 || self.second > 60 {
+			return Err(TimeError::OutOfRange);
+		}
+
+		let secs = days * 86_400
+			+ i64::from(hour) * 3600
+			+ i64::from(self.minute) * 60
+			+ i64::from(self.second)
+			- self.offset;
+
+		if !(MIN_TIMESTAMP..=MAX_TIMESTAMP).contains(&secs) {
+			return Err(TimeError::OutOfRange);
+		}
+
+		Ok((secs, self.nanos))
+	}
+}
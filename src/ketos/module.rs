@@ -6,7 +6,7 @@ use std::io::{stderr, Read, Write};
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
-use crate::bytecode::Code;
+use crate::bytecode::{code_flags, Code};
 use crate::compile::{compile, CompileError};
 use crate::encode::{read_bytecode_file, write_bytecode_file, DecodeError};
 use crate::error::Error;
@@ -17,11 +17,24 @@ use crate::lexer::Lexer;
 use crate::name::{Name, NameMap, NameSetSlice};
 use crate::parser::Parser;
 use crate::scope::{GlobalScope, ImportSet, Scope};
+use crate::syntax;
 use crate::value::Value;
 
+use crate::mod_bytes;
 use crate::mod_code;
+use crate::mod_fs;
+use crate::mod_iter;
+use crate::mod_json;
+use crate::mod_list_core;
 use crate::mod_math;
+use crate::mod_os;
+use crate::mod_path;
+use crate::mod_process;
 use crate::mod_random;
+use crate::mod_regex;
+use crate::mod_set;
+use crate::mod_string;
+use crate::mod_time;
 
 /// Contains the values in a loaded module's namespace.
 #[derive(Clone)]
@@ -202,6 +215,11 @@ impl ModuleCode {
 
 		for (name, code) in self.macros {
 			let mac = Lambda::new(code, ctx.scope());
+
+			if mac.code.flags & code_flags::IS_SYNTAX_RULES != 0 {
+				syntax::check_macro(ctx, mac.clone())?;
+			}
+
 			ctx.scope().add_macro(name, mac);
 		}
 
@@ -339,29 +357,63 @@ where
 }
 
 /// Loads builtin modules.
+///
+/// Builtin modules include those implemented in Rust and those implemented
+/// in Ketos, whose source is included in the library. Neither requires
+/// access to the filesystem.
 pub struct BuiltinModuleLoader;
 
 impl ModuleLoader for BuiltinModuleLoader {
 	fn load_module(&self, name: Name, ctx: Context) -> Result<Module, Error> {
-		load_builtin_module(name, ctx.scope())
+		load_builtin_module(name, &ctx)
 	}
 }
 
 fn get_loader(name: &str) -> Option<fn(Scope) -> Module> {
 	match name {
+		"bytes" => Some(mod_bytes::load),
 		"code" => Some(mod_code::load),
+		"fs" => Some(mod_fs::load),
+		"iter" => Some(mod_iter::load),
+		"json" => Some(mod_json::load),
+		"list-core" => Some(mod_list_core::load),
 		"math" => Some(mod_math::load),
+		"os" => Some(mod_os::load),
+		"path" => Some(mod_path::load),
+		"process" => Some(mod_process::load),
 		"random" => Some(mod_random::load),
+		"regex" => Some(mod_regex::load),
+		"set" => Some(mod_set::load),
+		"string" => Some(mod_string::load),
+		"time" => Some(mod_time::load),
+		_ => None,
+	}
+}
+
+/// Returns the source of a builtin module implemented in Ketos.
+///
+/// These are the modules found in the `lib` directory of the source
+/// distribution, included in the library so that they are available
+/// without filesystem access.
+fn get_source(name: &str) -> Option<&'static str> {
+	match name {
+		"list" => Some(include_str!("../../lib/list.ket")),
+		"test" => Some(include_str!("../../lib/test.ket")),
 		_ => None,
 	}
 }
 
-fn load_builtin_module(name: Name, scope: &Scope) -> Result<Module, Error> {
-	let loader = scope.with_name(name, |name| get_loader(name));
+fn load_builtin_module(name: Name, ctx: &Context) -> Result<Module, Error> {
+	let scope = ctx.scope();
+	let name_str = scope.with_name(name, |name| name.to_owned());
 
-	match loader {
-		Some(l) => Ok(l(scope.clone())),
-		None => Err(From::from(CompileError::ModuleError(name))),
+	if let Some(l) = get_loader(&name_str) {
+		Ok(l(scope.clone()))
+	} else if let Some(src) = get_source(&name_str) {
+		let src_name = format!("<builtin>/{}.{}", name_str, FILE_EXTENSION);
+		load_module_from_source(ctx.clone(), name, src, src_name, None)
+	} else {
+		Err(From::from(CompileError::ModuleError(name)))
 	}
 }
 
@@ -558,13 +610,29 @@ fn load_module_from_file(
 	file.read_to_string(&mut buf)
 		.map_err(|e| IoError::new(IoMode::Read, src_path, e))?;
 
+	load_module_from_source(
+		ctx,
+		name,
+		&buf,
+		src_path.to_string_lossy().into_owned(),
+		code_path,
+	)
+}
+
+fn load_module_from_source(
+	ctx: Context,
+	name: Name,
+	src: &str,
+	src_name: String,
+	code_path: Option<&Path>,
+) -> Result<Module, Error> {
 	let exprs = {
 		let offset = ctx
 			.scope()
 			.borrow_codemap_mut()
-			.add_source(&buf, Some(src_path.to_string_lossy().into_owned()));
+			.add_source(src, Some(src_name));
 
-		Parser::new(&ctx, Lexer::new(&buf, offset)).parse_exprs()?
+		Parser::new(&ctx, Lexer::new(src, offset)).parse_exprs()?
 	};
 
 	let code = exprs
@@ -174,73 +174,104 @@ standard_names! {
 	"panic" => PANIC = 68,
 	"xor" => XOR = 69,
 	"not" => NOT = 70,
+	"hash-map" => HASH_MAP = 71,
+	"get" => GET = 72,
+	"assoc" => ASSOC = 73,
+	"dissoc" => DISSOC = 74,
+	"keys" => KEYS = 75,
+	"values" => VALUES = 76,
+	"contains" => CONTAINS = 77,
+	"raise" => RAISE = 78,
+	"gensym" => GENSYM = 79,
+	"ref" => REF = 80,
+	"deref" => DEREF = 81,
+	"set-ref!" => SET_REF = 82,
+	"swap-ref!" => SWAP_REF = 83,
+	"read-line" => READ_LINE = 84,
+	"read-char" => READ_CHAR = 85,
+	"read-all" => READ_ALL = 86,
 	// End of names referring to system functions.
 	// The constant `NUM_SYSTEM_FNS` below should be one greater than
 	// the value immediately above this comment.
 
 	// Boolean names; the parser will replace these with boolean values.
 	// These names must follow immediately after system function names.
-	"false" => FALSE = 71,
-	"true" => TRUE = 72,
+	"false" => FALSE = 87,
+	"true" => TRUE = 88,
 	// End of names referring to standard values.
 	// The constant `NUM_STANDARD_VALUES` below should be one greater than
 	// the value immediately above this comment.
 
 	// Special operators follow; these are not represented as values in global
 	// scope. They are only handled by the compiler.
-	"apply" => APPLY = 73,
-	"do" => DO = 74,
-	"let" => LET = 75,
-	"define" => DEFINE = 76,
-	"macro" => MACRO = 77,
-	"struct" => STRUCT = 78,
-	"if" => IF = 79,
-	"and" => AND = 80,
-	"or" => OR = 81,
-	"case" => CASE = 82,
-	"cond" => COND = 83,
-	"lambda" => LAMBDA = 84,
-	"export" => EXPORT = 85,
-	"use" => USE = 86,
-	"const" => CONST = 87,
-	"set-module-doc" => SET_MODULE_DOC = 88,
-	"call-self" => CALL_SELF = 89,
+	"apply" => APPLY = 89,
+	"do" => DO = 90,
+	"let" => LET = 91,
+	"define" => DEFINE = 92,
+	"macro" => MACRO = 93,
+	"struct" => STRUCT = 94,
+	"if" => IF = 95,
+	"and" => AND = 96,
+	"or" => OR = 97,
+	"case" => CASE = 98,
+	"cond" => COND = 99,
+	"lambda" => LAMBDA = 100,
+	"export" => EXPORT = 101,
+	"use" => USE = 102,
+	"const" => CONST = 103,
+	"set-module-doc" => SET_MODULE_DOC = 104,
+	"call-self" => CALL_SELF = 105,
+	"try" => TRY = 106,
+	"match" => MATCH = 107,
+	"syntax-rules" => SYNTAX_RULES = 108,
+	"loop" => LOOP = 109,
+	"recur" => RECUR = 110,
+	"while" => WHILE = 111,
+	"dotimes" => DOTIMES = 112,
+	"for" => FOR = 113,
 
 	// Just plain names follow; these are used by system functions or operators
 	// to delineate syntactical constructs or just as name values.
-	"all" => ALL = 90,
-	"else" => ELSE = 91,
-	"optional" => OPTIONAL = 92,
-	"key" => KEY = 93,
-	"rest" => REST = 94,
-	"unbound" => UNBOUND = 95,
-	"unit" => UNIT = 96,
-	"bool" => BOOL = 97,
-	"char" => CHAR = 98,
-	"integer" => INTEGER = 99,
-	"ratio" => RATIO = 100,
-	"struct-def" => STRUCT_DEF = 101,
-	"keyword" => KEYWORD = 102,
-	"object" => OBJECT = 103,
-	"name" => NAME = 104,
-	"number" => NUMBER = 105,
-	"function" => FUNCTION = 106,
-	"self" => SELF = 107,
+	"all" => ALL = 114,
+	"else" => ELSE = 115,
+	"optional" => OPTIONAL = 116,
+	"key" => KEY = 117,
+	"rest" => REST = 118,
+	"unbound" => UNBOUND = 119,
+	"unit" => UNIT = 120,
+	"bool" => BOOL = 121,
+	"char" => CHAR = 122,
+	"integer" => INTEGER = 123,
+	"ratio" => RATIO = 124,
+	"struct-def" => STRUCT_DEF = 125,
+	"keyword" => KEYWORD = 126,
+	"object" => OBJECT = 127,
+	"name" => NAME = 128,
+	"number" => NUMBER = 129,
+	"function" => FUNCTION = 130,
+	"self" => SELF = 131,
+	"map" => MAP = 132,
+	"set" => SET = 133,
+	"catch" => CATCH = 134,
+	"finally" => FINALLY = 135,
+	"when" => WHEN = 136,
+	"_" => UNDERSCORE = 137,
+	"..." => ELLIPSIS = 138,
 }
 
 /// Number of standard names
-pub const NUM_STANDARD_NAMES: u32 = 108;
+pub const NUM_STANDARD_NAMES: u32 = 139;
 
 /// Number of names, starting at `0`, which refer to system functions.
-pub const NUM_SYSTEM_FNS: usize = 71;
+pub const NUM_SYSTEM_FNS: usize = 87;
 
 /// Number of names, starting at `0`, which refer to standard values.
-pub const NUM_STANDARD_VALUES: u32 = 73;
+pub const NUM_STANDARD_VALUES: u32 = 89;
 
 /// First standard name which refers to a system operator.
 pub const SYSTEM_OPERATORS_BEGIN: u32 = NUM_STANDARD_VALUES;
 /// One-past-the-end of standard names which refer to system operators.
-pub const SYSTEM_OPERATORS_END: u32 = 90;
+pub const SYSTEM_OPERATORS_END: u32 = 114;
 
 /// Number of system operators, beginning at `SYSTEM_OPERATORS_BEGIN`.
 pub const NUM_SYSTEM_OPERATORS: usize = (SYSTEM_OPERATORS_END - SYSTEM_OPERATORS_BEGIN) as usize;
@@ -402,12 +433,18 @@ impl<'a> NameOutputConversion<'a> {
 pub struct NameStore {
 	/// Name string representation mapped to name values.
 	names: Vec<Box<str>>,
+	/// Suffix of the next name created by `add_unique`;
+	/// greater than the numeric suffix of any name added.
+	next_unique: u64,
 }
 
 impl NameStore {
 	/// Constructs an empty `NameStore`.
 	pub fn new() -> NameStore {
-		NameStore { names: Vec::new() }
+		NameStore {
+			names: Vec::new(),
+			next_unique: 0,
+		}
 	}
 
 	/// Adds a name to the `NameStore` if it is not present.
@@ -418,12 +455,35 @@ impl NameStore {
 		} else if let Some(pos) = self.iter().position(|n| n == name) {
 			Name(pos as u32 + NUM_STANDARD_NAMES)
 		} else {
-			let n = self.names.len();
-			self.names.push(name.to_owned().into_boxed_str());
-			Name(n as u32 + NUM_STANDARD_NAMES)
+			// Names such as `g#12` may be loaded from compiled code;
+			// ensure `add_unique` never produces the same name.
+			if let Some(n) = name
+				.rsplit_once('#')
+				.and_then(|(_, n)| n.parse::<u64>().ok())
+			{
+				self.next_unique = self.next_unique.max(n.saturating_add(1));
+			}
+
+			self.push(name.to_owned())
 		}
 	}
 
+	/// Adds a new name to the `NameStore`, distinct from all existing names.
+	/// The new name is formed from the given prefix, followed by `#` and a number.
+	///
+	/// Each call uses a new number, so no search of existing names is required.
+	pub fn add_unique(&mut self, prefix: &str) -> Name {
+		let name = format!("{}#{}", prefix, self.next_unique);
+		self.next_unique += 1;
+		self.push(name)
+	}
+
+	fn push(&mut self, name: String) -> Name {
+		let n = self.names.len();
+		self.names.push(name.into_boxed_str());
+		Name(n as u32 + NUM_STANDARD_NAMES)
+	}
+
 	/// Returns the `Name` value of a given string, if it exists.
 	pub fn get_name(&self, name: &str) -> Option<Name> {
 		if let Some(pos) = self.iter().position(|n| n == name) {
@@ -10,6 +10,7 @@ use crate::error::Error;
 use crate::exec::Context;
 use crate::integer::{Integer, Ratio};
 use crate::lexer::{Lexer, Span, Token};
+use crate::map::Map;
 use crate::name::{get_standard_name_for, standard_names, Name, NameDisplay, NameStore};
 use crate::restrict::RestrictError;
 use crate::string;
@@ -60,6 +61,8 @@ pub enum ParseErrorKind {
 	CannotDocumentItem,
 	/// Doc comment at end-of-file
 	DocCommentEof,
+	/// Key occurs more than once in map literal
+	DuplicateMapKey,
 	/// Error in parsing literal
 	InvalidLiteral,
 	/// Error in parsing token
@@ -74,8 +77,12 @@ pub enum ParseErrorKind {
 	InvalidNumericEscape(char),
 	/// Error parsing literal string into value
 	LiteralParseError,
+	/// Missing closing brace
+	MissingCloseBrace,
 	/// Missing closing parenthesis
 	MissingCloseParen,
+	/// Odd number of elements in map literal
+	OddMapLiteral,
 	/// More commas than backquotes
 	UnbalancedComma,
 	/// Unexpected end-of-file
@@ -89,6 +96,8 @@ pub enum ParseErrorKind {
 	},
 	/// Unrecognized character escape
 	UnknownCharEscape(char),
+	/// Unmatched `}`
+	UnmatchedBrace,
 	/// Unmatched `)`
 	UnmatchedParen,
 	/// Unterminated character constant
@@ -106,6 +115,7 @@ impl fmt::Display for ParseErrorKind {
 				f.write_str("doc comment precedes item that cannot be documented")
 			}
 			ParseErrorKind::DocCommentEof => f.write_str("doc comment at end-of-file"),
+			ParseErrorKind::DuplicateMapKey => f.write_str("duplicate key in map literal"),
 			ParseErrorKind::InvalidLiteral => f.write_str("invalid numeric literal"),
 			ParseErrorKind::InvalidToken => f.write_str("invalid token"),
 			ParseErrorKind::InvalidByte(ch) => write!(f, "byte literal must be ASCII: {:?}", ch),
@@ -117,13 +127,18 @@ impl fmt::Display for ParseErrorKind {
 				write!(f, "invalid character in escape sequence: {:?}", ch)
 			}
 			ParseErrorKind::LiteralParseError => f.write_str("literal parse error"),
+			ParseErrorKind::MissingCloseBrace => f.write_str("missing close brace"),
 			ParseErrorKind::MissingCloseParen => f.write_str("missing close paren"),
+			ParseErrorKind::OddMapLiteral => {
+				f.write_str("map literal must contain an even number of elements")
+			}
 			ParseErrorKind::UnbalancedComma => f.write_str("unbalanced ` and ,"),
 			ParseErrorKind::UnexpectedEof => f.write_str("unexpected end-of-file"),
 			ParseErrorKind::UnexpectedToken { expected, found } => {
 				write!(f, "expected {}; found {}", expected, found)
 			}
 			ParseErrorKind::UnknownCharEscape(ch) => write!(f, "unknown char escape: {:?}", ch),
+			ParseErrorKind::UnmatchedBrace => f.write_str("unmatched `}`"),
 			ParseErrorKind::UnmatchedParen => f.write_str("unmatched `)`"),
 			ParseErrorKind::UnterminatedChar => f.write_str("unterminated char constant"),
 			ParseErrorKind::UnterminatedComment => f.write_str("unterminated block comment"),
@@ -142,6 +157,8 @@ enum Group<'lex> {
 	Quotes(u32),
 	/// Values in a parenthetical expression
 	Parens(Vec<Value>, Option<(Span, &'lex str)>),
+	/// Alternating keys and values in a map literal
+	Braces(Vec<Value>),
 }
 
 impl<'a, 'lex> Parser<'a, 'lex> {
@@ -211,6 +228,13 @@ impl<'a, 'lex> Parser<'a, 'lex> {
 						Group::Parens(values, doc) => {
 							insert_doc_comment(values, doc).map_err(From::from)
 						}
+						Group::Braces(_) => Err(From::from(ParseError::new(
+							sp,
+							ParseErrorKind::UnexpectedToken {
+								expected: "}",
+								found: ")",
+							},
+						))),
 						_ => Err(From::from(ParseError::new(
 							sp,
 							ParseErrorKind::UnexpectedToken {
@@ -220,6 +244,51 @@ impl<'a, 'lex> Parser<'a, 'lex> {
 						))),
 					}
 				}
+				Token::LeftBrace => {
+					if let Some((doc_sp, _)) = doc {
+						return Err(From::from(ParseError::new(
+							doc_sp,
+							ParseErrorKind::CannotDocumentItem,
+						)));
+					}
+
+					stack.push(Group::Braces(Vec::new()));
+					continue;
+				}
+				Token::RightBrace => {
+					let group = stack
+						.pop()
+						.ok_or_else(|| ParseError::new(sp, ParseErrorKind::UnmatchedBrace))?;
+
+					match group {
+						Group::Braces(values) => {
+							if values.len() % 2 == 0 {
+								build_map(values).map(Value::Map).map_err(|_| {
+									From::from(ParseError::new(sp, ParseErrorKind::DuplicateMapKey))
+								})
+							} else {
+								Err(From::from(ParseError::new(
+									sp,
+									ParseErrorKind::OddMapLiteral,
+								)))
+							}
+						}
+						Group::Parens(_, _) => Err(From::from(ParseError::new(
+							sp,
+							ParseErrorKind::UnexpectedToken {
+								expected: ")",
+								found: "}",
+							},
+						))),
+						_ => Err(From::from(ParseError::new(
+							sp,
+							ParseErrorKind::UnexpectedToken {
+								expected: "expression",
+								found: "}",
+							},
+						))),
+					}
+				}
 				Token::Float(f) => parse_float(f)
 					.map(Value::Float)
 					.map_err(|kind| From::from(ParseError::new(sp, kind))),
@@ -283,12 +352,17 @@ impl<'a, 'lex> Parser<'a, 'lex> {
 						)));
 					}
 
-					let any_paren = stack.iter().any(|group| match *group {
-						Group::Parens(_, _) => true,
-						_ => false,
-					});
+					let open_group = stack
+						.iter()
+						.rev()
+						.find(|group| matches!(**group, Group::Parens(_, _) | Group::Braces(_)));
 
-					if any_paren {
+					if let Some(&Group::Braces(_)) = open_group {
+						Err(From::from(ParseError::new(
+							sp,
+							ParseErrorKind::MissingCloseBrace,
+						)))
+					} else if open_group.is_some() {
 						Err(From::from(ParseError::new(
 							sp,
 							ParseErrorKind::MissingCloseParen,
@@ -314,7 +388,8 @@ impl<'a, 'lex> Parser<'a, 'lex> {
 			loop {
 				match stack.last_mut() {
 					None => return Ok(v),
-					Some(&mut Group::Parens(ref mut values, _)) => {
+					Some(&mut Group::Parens(ref mut values, _))
+					| Some(&mut Group::Braces(ref mut values)) => {
 						values.push(v);
 						break;
 					}
@@ -478,6 +553,17 @@ fn trim_first<F: FnOnce(char) -> bool>(s: &str, f: F) -> &str {
 	}
 }
 
+fn build_map(values: Vec<Value>) -> Result<Map, Value> {
+	let mut iter = values.into_iter();
+	let mut entries = Vec::new();
+
+	while let (Some(k), Some(v)) = (iter.next(), iter.next()) {
+		entries.push((k, v));
+	}
+
+	Map::from_literal(entries)
+}
+
 fn insert_doc_comment(
 	mut items: Vec<Value>,
 	doc: Option<(Span, &str)>,
@@ -559,7 +645,7 @@ fn parse_ratio(ctx: &Context, s: &str, sp: Span) -> Result<Ratio, Error> {
 		.map_err(|_| From::from(ParseError::new(sp, ParseErrorKind::LiteralParseError)))
 }
 
-fn check_integer(ctx: &Context, mut s: &str, base: u32) -> Result<(), RestrictError> {
+pub(crate) fn check_integer(ctx: &Context, mut s: &str, base: u32) -> Result<(), RestrictError> {
 	let limit = ctx.restrict().max_integer_size;
 
 	if limit == usize::max_value() {
@@ -656,6 +742,54 @@ mod test {
 				kind: ParseErrorKind::UnbalancedComma
 			}
 		);
+		assert_eq!(
+			parse("{:a 1 :b}").unwrap_err(),
+			ParseError {
+				span: Span { lo: 8, hi: 9 },
+				kind: ParseErrorKind::OddMapLiteral
+			}
+		);
+		assert_eq!(
+			parse("{:a 1 :b 2 :a 3}").unwrap_err(),
+			ParseError {
+				span: Span { lo: 15, hi: 16 },
+				kind: ParseErrorKind::DuplicateMapKey
+			}
+		);
+		assert_eq!(
+			parse("({:a 1)").unwrap_err(),
+			ParseError {
+				span: Span { lo: 6, hi: 7 },
+				kind: ParseErrorKind::UnexpectedToken {
+					expected: "}",
+					found: ")"
+				}
+			}
+		);
+		assert_eq!(
+			parse("{:a (foo}").unwrap_err(),
+			ParseError {
+				span: Span { lo: 8, hi: 9 },
+				kind: ParseErrorKind::UnexpectedToken {
+					expected: ")",
+					found: "}"
+				}
+			}
+		);
+		assert_eq!(
+			parse("{:a 1").unwrap_err(),
+			ParseError {
+				span: Span { lo: 5, hi: 5 },
+				kind: ParseErrorKind::MissingCloseBrace
+			}
+		);
+		assert_eq!(
+			parse("}").unwrap_err(),
+			ParseError {
+				span: Span { lo: 0, hi: 1 },
+				kind: ParseErrorKind::UnmatchedBrace
+			}
+		);
 	}
 
 	#[test]
@@ -47,17 +47,76 @@ pub fn pretty_print<W: Write>(w: &mut W, names: &NameStore, v: &Value, indent: u
 
 			w.write_char(')')
 		}
+		Value::Map(ref m) => {
+			let short = m.len() <= 2
+				&& m.iter()
+					.all(|(k, v)| is_short_value(k) && is_short_value(v));
+
+			w.write_char('{')?;
+
+			for (i, (k, v)) in m.iter().enumerate() {
+				if i != 0 {
+					if short {
+						w.write_char(' ')?;
+					} else {
+						w.write_char('\n')?;
+						write_indent(w, indent + 1)?;
+					}
+				}
+
+				pretty_print(w, names, k, indent + 1)?;
+				w.write_char(' ')?;
+				pretty_print(w, names, v, indent + 1)?;
+			}
+
+			w.write_char('}')
+		}
+		Value::Set(ref s) => {
+			let short = is_short_args(s.as_slice());
+
+			w.write_str("#{")?;
+
+			for (i, v) in s.iter().enumerate() {
+				if i != 0 {
+					if short {
+						w.write_char(' ')?;
+					} else {
+						w.write_char('\n')?;
+						write_indent(w, indent + 2)?;
+					}
+				}
+
+				pretty_print(w, names, v, indent + 2)?;
+			}
+
+			w.write_char('}')
+		}
+		Value::Ref(ref r) => {
+			let res = r.visit(|v| {
+				w.write_str("<ref ")?;
+				pretty_print(w, names, v, indent + 5)?;
+				w.write_char('>')
+			});
+
+			res.unwrap_or_else(|| w.write_str("<ref ...>"))
+		}
 		_ => write!(w, "{}", debug_names(names, v)),
 	}
 }
 
 fn is_short_args(args: &[Value]) -> bool {
-	args.len() <= 5
-		&& args.iter().all(|v| match *v {
-			Value::List(_) => false,
-			Value::String(ref s) if s.len() > 15 => false,
-			_ => true,
-		})
+	args.len() <= 5 && args.iter().all(is_short_value)
+}
+
+fn is_short_value(v: &Value) -> bool {
+	match *v {
+		Value::List(_) => false,
+		Value::Map(ref m) => m.is_empty(),
+		Value::Set(ref s) => s.is_empty(),
+		Value::Ref(ref r) => r.visit(is_short_value).unwrap_or(true),
+		Value::String(ref s) if s.len() > 15 => false,
+		_ => true,
+	}
 }
 
 fn write_indent<W: Write>(w: &mut W, n: u32) -> fmt::Result {
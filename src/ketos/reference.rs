@@ -0,0 +1,63 @@
+//! Implements mutable reference cells.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::value::Value;
+
+/// Shared, mutable cell containing a single value
+///
+/// `Ref` is cheaply cloned. All clones of a `Ref` refer to the same cell;
+/// a value stored through one is visible through all others.
+///
+/// Two `Ref` values are equal only if they refer to the same cell.
+#[derive(Clone)]
+pub struct Ref(Rc<RefCell<Value>>);
+
+impl Ref {
+	/// Creates a new cell containing the given value.
+	pub fn new(value: Value) -> Ref {
+		Ref(Rc::new(RefCell::new(value)))
+	}
+
+	/// Returns a copy of the contained value.
+	pub fn get(&self) -> Value {
+		self.0.borrow().clone()
+	}
+
+	/// Stores a value in the cell, returning the previous value.
+	pub fn replace(&self, value: Value) -> Value {
+		self.0.replace(value)
+	}
+
+	/// Returns whether two `Ref` values refer to the same cell.
+	pub fn ptr_eq(&self, other: &Ref) -> bool {
+		Rc::ptr_eq(&self.0, &other.0)
+	}
+
+	/// Returns a pointer uniquely identifying the cell.
+	pub fn as_ptr(&self) -> *const () {
+		Rc::as_ptr(&self.0) as *const ()
+	}
+
+	/// Calls a function with a reference to the contained value.
+	///
+	/// A cell may contain a value which refers back to the cell itself.
+	/// If the cell is already being visited by an enclosing call to `visit`,
+	/// `None` is returned rather than visiting the value again.
+	pub fn visit<F, R>(&self, f: F) -> Option<R>
+	where
+		F: FnOnce(&Value) -> R,
+	{
+		let v = self.0.try_borrow_mut().ok()?;
+		Some(f(&v))
+	}
+}
+
+impl fmt::Debug for Ref {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.visit(|v| write!(f, "Ref({:?})", v))
+			.unwrap_or_else(|| f.write_str("Ref(..)"))
+	}
+}
@@ -30,6 +30,7 @@
 //! ```
 
 use std::fmt;
+use std::path::PathBuf;
 use std::time::Duration;
 
 use crate::name::{NameDisplay, NameStore};
@@ -47,7 +48,10 @@ pub struct RestrictConfig {
 	/// limit manually.
 	pub execution_time: Option<Duration>,
 	/// Limits the call stack depth during execution to a number of nested
-	/// functions calls
+	/// functions calls.
+	///
+	/// A function called by a system function, e.g. `map` or `eval`,
+	/// counts as several calls, as it runs in a nested virtual machine.
 	pub call_stack_size: usize,
 	/// Limits the number of values that can be stored on the stack during
 	/// execution
@@ -62,6 +66,41 @@ pub struct RestrictConfig {
 	pub max_integer_size: usize,
 	/// Maximum nested depth of syntactical elements
 	pub max_syntax_nesting: usize,
+	/// Maximum size, in bytes, of a compiled regular expression
+	pub max_regex_size: usize,
+	/// Whether code may suspend execution with the `time` module's `sleep`
+	pub allow_sleep: bool,
+	/// Access permitted to functions in the `fs` module.
+	///
+	/// This is `FsAccess::Denied` in both `permissive` and `strict`
+	/// configurations; hosts must opt in, either by setting this field
+	/// or by using `Builder::filesystem`.
+	pub filesystem: FsAccess,
+	/// Whether code may use the `os` module to access the environment
+	/// and process of the host
+	pub allow_os: bool,
+	/// Whether code may use the `process` module to run external programs.
+	///
+	/// This is false in both `permissive` and `strict` configurations;
+	/// hosts must opt in, either by setting this field or by using
+	/// `Builder::allow_process`.
+	pub allow_process: bool,
+}
+
+/// Describes the filesystem access permitted to executing code
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FsAccess {
+	/// No access is permitted
+	Denied,
+	/// Access is permitted only within the given directory.
+	///
+	/// Relative paths are resolved against this directory.
+	/// Paths which, after resolving symbolic links, lie outside the directory
+	/// are refused. A symbolic link named as the final component of a path
+	/// is accessed as itself, but its target must also lie within the directory.
+	Root(PathBuf),
+	/// Access is permitted to any path
+	Unrestricted,
 }
 
 /// Represents an error caused by breach of runtime execution restrictions
@@ -81,6 +120,16 @@ pub enum RestrictError {
 	IntegerLimitExceeded,
 	/// Nested syntax exceeded limit
 	MaxSyntaxNestingExceeded,
+	/// Compiled regular expression exceeded limit
+	RegexSizeExceeded,
+	/// Attempt to sleep when sleeping is not allowed
+	SleepNotAllowed,
+	/// Attempt to access a path not permitted by filesystem restrictions
+	FilesystemAccessDenied,
+	/// Attempt to use the `os` module when it is not allowed
+	OsNotAllowed,
+	/// Attempt to run a process when it is not allowed
+	ProcessNotAllowed,
 }
 
 impl RestrictError {
@@ -96,6 +145,11 @@ impl RestrictError {
 			MemoryLimitExceeded => "max memory limit exceeded",
 			IntegerLimitExceeded => "integer size limit exceeded",
 			MaxSyntaxNestingExceeded => "max syntax nesting exceeded",
+			RegexSizeExceeded => "regex size limit exceeded",
+			SleepNotAllowed => "sleep not allowed",
+			FilesystemAccessDenied => "filesystem access denied",
+			OsNotAllowed => "os access not allowed",
+			ProcessNotAllowed => "running processes not allowed",
 		}
 	}
 }
@@ -127,7 +181,9 @@ pub const STRICT_VALUE_STACK_SIZE: usize = PERMISSIVE_VALUE_STACK_SIZE / 16;
 impl RestrictConfig {
 	/// Returns a `RestrictConfig` that is most permissive.
 	///
-	/// No restrictions are placed on executing code.
+	/// No restrictions are placed on executing code, except that accessing
+	/// the filesystem and running external programs are not allowed unless
+	/// `filesystem` and `allow_process` are set.
 	pub fn permissive() -> RestrictConfig {
 		RestrictConfig {
 			execution_time: None,
@@ -137,6 +193,11 @@ impl RestrictConfig {
 			memory_limit: usize::max_value(),
 			max_integer_size: usize::max_value(),
 			max_syntax_nesting: usize::max_value(),
+			max_regex_size: usize::MAX,
+			allow_sleep: true,
+			filesystem: FsAccess::Denied,
+			allow_os: true,
+			allow_process: false,
 		}
 	}
 
@@ -153,6 +214,11 @@ impl RestrictConfig {
 			memory_limit: STRICT_VALUE_STACK_SIZE,
 			max_integer_size: 100,
 			max_syntax_nesting: 32,
+			max_regex_size: 1 << 16,
+			allow_sleep: false,
+			filesystem: FsAccess::Denied,
+			allow_os: false,
+			allow_process: false,
 		}
 	}
 }
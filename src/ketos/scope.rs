@@ -4,6 +4,10 @@ use std::any::TypeId;
 use std::cell::{Ref, RefCell, RefMut};
 use std::rc::{Rc, Weak};
 
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::clock::{Clock, SystemClock};
 use crate::function::{Function, Lambda};
 use crate::io::GlobalIo;
 use crate::lexer::CodeMap;
@@ -23,6 +27,9 @@ pub struct GlobalScope {
 	codemap: Rc<RefCell<CodeMap>>,
 	modules: Rc<ModuleRegistry>,
 	io: Rc<GlobalIo>,
+	clock: Rc<dyn Clock>,
+	/// Seeded from the operating system upon first use
+	rng: Rc<RefCell<Option<StdRng>>>,
 	struct_defs: Rc<RefCell<StructDefMap>>,
 }
 
@@ -89,6 +96,8 @@ impl GlobalScope {
 			codemap,
 			modules: registry,
 			io,
+			clock: Rc::new(SystemClock::new()),
+			rng: Rc::new(RefCell::new(None)),
 			struct_defs,
 		}
 	}
@@ -109,14 +118,18 @@ impl GlobalScope {
 
 	/// Creates a new global scope using the shared data from the given scope.
 	pub fn new_using(name: Name, scope: &Scope) -> Scope {
-		Rc::new(GlobalScope::new(
+		let mut new_scope = GlobalScope::new(
 			name,
 			scope.name_store.clone(),
 			scope.codemap.clone(),
 			scope.modules.clone(),
 			scope.io.clone(),
 			scope.struct_defs.clone(),
-		))
+		);
+
+		new_scope.set_clock(scope.clock.clone());
+		new_scope.rng = scope.rng.clone();
+		Rc::new(new_scope)
 	}
 
 	/// Creates a semi-"deep" clone of the `GlobalScope` object.
@@ -132,6 +145,8 @@ impl GlobalScope {
 			codemap: self.codemap.clone(),
 			modules: self.modules.clone(),
 			io: self.io.clone(),
+			clock: self.clock.clone(),
+			rng: self.rng.clone(),
 			struct_defs: self.struct_defs.clone(),
 		})
 	}
@@ -263,6 +278,40 @@ impl GlobalScope {
 		&self.io
 	}
 
+	/// Returns a borrowed reference to the contained `Clock`.
+	pub fn clock(&self) -> &Rc<dyn Clock> {
+		&self.clock
+	}
+
+	/// Sets the `Clock` used by this scope.
+	///
+	/// A new `GlobalScope` uses a `SystemClock`.
+	pub fn set_clock(&mut self, clock: Rc<dyn Clock>) {
+		self.clock = clock;
+	}
+
+	/// Returns a mutable reference to the random number generator
+	/// used by the `random` module.
+	///
+	/// If the generator has not been seeded, it is seeded from the
+	/// operating system.
+	pub(crate) fn rng(&self) -> RefMut<'_, StdRng> {
+		RefMut::map(self.rng.borrow_mut(), |rng| {
+			rng.get_or_insert_with(StdRng::from_entropy)
+		})
+	}
+
+	/// Reseeds the random number generator used by this scope.
+	///
+	/// Otherwise, the generator is seeded from the operating system
+	/// when it is first used.
+	/// After seeding, the `random` module produces the same sequence of
+	/// values for the same seed. The sequence for a given seed may change
+	/// between versions of Ketos.
+	pub fn seed_rng(&self, seed: u64) {
+		*self.rng.borrow_mut() = Some(StdRng::seed_from_u64(seed));
+	}
+
 	/// Returns a borrowed reference to the contained `ModuleRegistry`.
 	pub fn modules(&self) -> &Rc<ModuleRegistry> {
 		&self.modules
@@ -0,0 +1,307 @@
+//! Implements lazy sequences, whose values are produced on demand.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::error::Error;
+use crate::exec::{Context, ExecError};
+use crate::function::{call_callback, call_predicate};
+use crate::integer::Integer;
+use crate::name::NameStore;
+use crate::value::{ForeignValue, FromValueRef, Value};
+
+/// Sequence of values, each produced only when requested
+///
+/// A `LazySeq` is stateful: each value is produced once and consumed by
+/// whichever caller requests it. A sequence shared between several values
+/// (e.g. the source of a `map` and the `map` itself) yields each value to
+/// only one of them.
+///
+/// Rust iterators may be exposed to scripts as lazy sequences:
+///
+/// ```
+/// use ketos::{FromValue, Interpreter, LazySeq};
+///
+/// let interp = Interpreter::new();
+///
+/// let squares = LazySeq::new((1..).map(|i: i64| i * i));
+/// interp.scope().add_named_value("squares", squares.into());
+///
+/// let v = interp.run_code(r#"
+///     (use iter :self)
+///     (iter/collect (iter/take 4 squares))
+///     "#, None).unwrap();
+///
+/// assert_eq!(Vec::<i64>::from_value(v).unwrap(), [1, 4, 9, 16]);
+/// ```
+pub struct LazySeq {
+	state: RefCell<State>,
+	/// Number of sequences nested within this one
+	depth: usize,
+}
+
+enum State {
+	/// Values produced by a Rust iterator
+	Iter(Box<dyn Iterator<Item = Value>>),
+	/// Integers from `start`, stepping by `step`, until `end` is reached
+	Range {
+		start: Integer,
+		end: Option<Integer>,
+		step: Integer,
+	},
+	/// `value`, followed by repeated application of `f`
+	Iterate {
+		f: Value,
+		value: Value,
+		started: bool,
+	},
+	/// Values of `seq`, passed through `f`
+	Map { f: Value, seq: Rc<LazySeq> },
+	/// Values of `seq` for which `f` returns `true`
+	Filter { f: Value, seq: Rc<LazySeq> },
+	/// At most `n` more values of `seq`
+	Take { n: usize, seq: Rc<LazySeq> },
+}
+
+/// Step of a sequence which calls back into the interpreter
+///
+/// The state is not borrowed while calling a function, which may itself
+/// request values from the same sequence.
+enum Pending {
+	Iterate(Value, Value),
+	Map(Value, Rc<LazySeq>),
+	Filter(Value, Rc<LazySeq>),
+	Take(Rc<LazySeq>),
+}
+
+impl LazySeq {
+	/// Creates a lazy sequence producing the values of a Rust iterator.
+	pub fn new<I>(iter: I) -> LazySeq
+	where
+		I: IntoIterator,
+		I::IntoIter: 'static,
+		I::Item: Into<Value> + 'static,
+	{
+		LazySeq::from_state(State::Iter(Box::new(iter.into_iter().map(Into::into))), 0)
+	}
+
+	/// Creates a lazy sequence producing the integers from `start`,
+	/// stepping by `step`, up to but not including `end`.
+	///
+	/// If `end` is `None`, the sequence is infinite.
+	///
+	/// # Panics
+	///
+	/// If `step` is zero.
+	pub fn range(start: Integer, end: Option<Integer>, step: Integer) -> LazySeq {
+		assert!(!step.is_zero(), "LazySeq::range step must be non-zero");
+		LazySeq::from_state(State::Range { start, end, step }, 0)
+	}
+
+	/// Creates an infinite sequence producing `value`, followed by the
+	/// result of calling `f` on each previous value.
+	pub fn iterate(f: Value, value: Value) -> LazySeq {
+		LazySeq::from_state(
+			State::Iterate {
+				f,
+				value,
+				started: false,
+			},
+			0,
+		)
+	}
+
+	/// Creates a sequence producing the result of calling `f` on each value of `seq`.
+	pub fn map(f: Value, seq: Rc<LazySeq>) -> LazySeq {
+		let depth = seq.depth + 1;
+		LazySeq::from_state(State::Map { f, seq }, depth)
+	}
+
+	/// Creates a sequence producing the values of `seq` for which `f` returns `true`.
+	pub fn filter(f: Value, seq: Rc<LazySeq>) -> LazySeq {
+		let depth = seq.depth + 1;
+		LazySeq::from_state(State::Filter { f, seq }, depth)
+	}
+
+	/// Creates a sequence producing at most `n` values of `seq`.
+	pub fn take(n: usize, seq: Rc<LazySeq>) -> LazySeq {
+		let depth = seq.depth + 1;
+		LazySeq::from_state(State::Take { n, seq }, depth)
+	}
+
+	fn from_state(state: State, depth: usize) -> LazySeq {
+		LazySeq {
+			state: RefCell::new(state),
+			depth,
+		}
+	}
+
+	/// Returns the number of sequences nested within this one;
+	/// e.g. the `map` of a `range` has depth `1`.
+	///
+	/// Producing a value, or dropping the sequence, recurses once
+	/// for each nested sequence.
+	pub fn depth(&self) -> usize {
+		self.depth
+	}
+
+	/// Produces the next value of the sequence, or `None` if it is exhausted.
+	///
+	/// The execution time limit of the context is checked before producing
+	/// each value, so that consuming an infinite sequence cannot run forever.
+	pub fn next(&self, ctx: &Context) -> Result<Option<Value>, Error> {
+		ctx.check_time()?;
+
+		let pending = match *self.state.borrow_mut() {
+			State::Iter(ref mut iter) => return Ok(iter.next()),
+			State::Range {
+				ref mut start,
+				ref end,
+				ref step,
+			} => {
+				if let Some(ref end) = *end {
+					let done = if step.is_negative() {
+						*start <= *end
+					} else {
+						*start >= *end
+					};
+
+					if done {
+						return Ok(None);
+					}
+				}
+
+				let next = &*start + step;
+				return Ok(Some(std::mem::replace(start, next).into()));
+			}
+			State::Iterate {
+				ref value,
+				ref mut started,
+				..
+			} if !*started => {
+				*started = true;
+				return Ok(Some(value.clone()));
+			}
+			State::Iterate {
+				ref f, ref value, ..
+			} => Pending::Iterate(f.clone(), value.clone()),
+			State::Map { ref f, ref seq } => Pending::Map(f.clone(), seq.clone()),
+			State::Filter { ref f, ref seq } => Pending::Filter(f.clone(), seq.clone()),
+			State::Take { ref mut n, ref seq } => {
+				if *n == 0 {
+					return Ok(None);
+				}
+				*n -= 1;
+				Pending::Take(seq.clone())
+			}
+		};
+
+		match pending {
+			Pending::Iterate(f, value) => {
+				let next = call_callback(ctx, &f, vec![value])?;
+
+				if let State::Iterate { ref mut value, .. } = *self.state.borrow_mut() {
+					*value = next.clone();
+				}
+
+				Ok(Some(next))
+			}
+			Pending::Map(f, seq) => match seq.next(ctx)? {
+				Some(v) => call_callback(ctx, &f, vec![v]).map(Some),
+				None => Ok(None),
+			},
+			Pending::Filter(f, seq) => {
+				while let Some(v) = seq.next(ctx)? {
+					if call_predicate(ctx, &f, vec![v.clone()])? {
+						return Ok(Some(v));
+					}
+				}
+
+				Ok(None)
+			}
+			Pending::Take(seq) => seq.next(ctx),
+		}
+	}
+
+	/// Returns an iterator over the remaining values of the sequence.
+	///
+	/// Iteration ends after the first error is returned.
+	pub fn iter<'a>(&'a self, ctx: &'a Context) -> SeqIter<'a> {
+		SeqIter {
+			seq: self,
+			ctx,
+			done: false,
+		}
+	}
+}
+
+impl fmt::Debug for LazySeq {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str("LazySeq")
+	}
+}
+
+impl ForeignValue for LazySeq {
+	fn fmt_debug(&self, _names: &NameStore, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str("<lazy-seq>")
+	}
+
+	fn type_name(&self) -> &'static str {
+		"lazy-seq"
+	}
+}
+
+impl From<LazySeq> for Value {
+	fn from(seq: LazySeq) -> Value {
+		Value::new_foreign(seq)
+	}
+}
+
+impl<'a> FromValueRef<'a> for &'a LazySeq {
+	fn from_value_ref(v: &'a Value) -> Result<&'a LazySeq, ExecError> {
+		if let Value::Foreign(ref fv) = *v {
+			if let Some(seq) = fv.downcast_ref::<LazySeq>() {
+				return Ok(seq);
+			}
+		}
+
+		Err(ExecError::expected("lazy-seq", v))
+	}
+}
+
+/// Iterator over the values of a `LazySeq`
+///
+/// Created by the method `LazySeq::iter`.
+pub struct SeqIter<'a> {
+	seq: &'a LazySeq,
+	ctx: &'a Context,
+	done: bool,
+}
+
+impl<'a> Iterator for SeqIter<'a> {
+	type Item = Result<Value, Error>;
+
+	fn next(&mut self) -> Option<Result<Value, Error>> {
+		if self.done {
+			return None;
+		}
+
+		let r = self.seq.next(self.ctx).transpose();
+		self.done = !matches!(r, Some(Ok(_)));
+		r
+	}
+}
+
+/// Returns a lazy sequence for a `list` or `lazy-seq` value.
+///
+/// A `lazy-seq` value is returned as is; it is not copied.
+pub fn get_seq(v: &Value) -> Result<Rc<LazySeq>, ExecError> {
+	match *v {
+		Value::Unit => Ok(Rc::new(LazySeq::new(None::<Value>))),
+		Value::List(ref li) => Ok(Rc::new(LazySeq::new(li.to_vec()))),
+		Value::Foreign(ref fv) => <dyn ForeignValue>::downcast_rc::<LazySeq>(fv.clone())
+			.map_err(|_| ExecError::expected("list or lazy-seq", v)),
+		ref v => Err(ExecError::expected("list or lazy-seq", v)),
+	}
+}
@@ -0,0 +1,176 @@
+//! Implements a reference-counted, persistent set of values.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::rc::Rc;
+use std::slice;
+
+use crate::map::cmp_keys;
+use crate::value::Value;
+
+/// Shared, sorted set of unique values
+///
+/// `Set` is cheaply cloned. Modifying a `Set` whose contents are shared with
+/// another `Set` will first make a copy of the shared contents.
+///
+/// Elements follow the same rules as the keys of a `Map`: any value may be
+/// an element, and two elements are considered the same element only if
+/// they are identical; e.g. `1` and `1.0` are distinct elements.
+/// Elements are kept in the same order as map keys.
+#[derive(Clone, Default)]
+pub struct Set(Rc<Vec<Value>>);
+
+impl Set {
+	/// Creates an empty set.
+	pub fn new() -> Set {
+		Set(Rc::new(Vec::new()))
+	}
+
+	/// Creates a set from a series of values.
+	/// Duplicate values are discarded.
+	pub fn from_values<I>(values: I) -> Set
+	where
+		I: IntoIterator<Item = Value>,
+	{
+		let mut values = values.into_iter().collect::<Vec<_>>();
+
+		// The sort is stable, so the first of any duplicate values is kept.
+		values.sort_by(cmp_keys);
+		values.dedup_by(|a, b| cmp_keys(a, b) == Ordering::Equal);
+
+		Set(Rc::new(values))
+	}
+
+	/// Returns whether the set is empty.
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+
+	/// Returns the number of elements in the set.
+	pub fn len(&self) -> usize {
+		self.0.len()
+	}
+
+	/// Returns the set elements as a sorted slice.
+	pub fn as_slice(&self) -> &[Value] {
+		&self.0
+	}
+
+	/// Returns whether the set contains the given value.
+	pub fn contains(&self, value: &Value) -> bool {
+		self.search(value).is_ok()
+	}
+
+	/// Adds a value to the set.
+	/// Returns whether the value was newly inserted.
+	pub fn insert(&mut self, value: Value) -> bool {
+		match self.search(&value) {
+			Ok(_) => false,
+			Err(pos) => {
+				Rc::make_mut(&mut self.0).insert(pos, value);
+				true
+			}
+		}
+	}
+
+	/// Removes a value from the set.
+	/// Returns whether the value was present.
+	pub fn remove(&mut self, value: &Value) -> bool {
+		match self.search(value) {
+			Ok(pos) => {
+				Rc::make_mut(&mut self.0).remove(pos);
+				true
+			}
+			Err(_) => false,
+		}
+	}
+
+	/// Returns a set of all values contained in either set.
+	pub fn union(&self, other: &Set) -> Set {
+		self.merge(other, true, true, true)
+	}
+
+	/// Returns a set of all values contained in both sets.
+	pub fn intersection(&self, other: &Set) -> Set {
+		self.merge(other, false, true, false)
+	}
+
+	/// Returns a set of all values contained in this set but not in `other`.
+	pub fn difference(&self, other: &Set) -> Set {
+		self.merge(other, true, false, false)
+	}
+
+	/// Returns whether every value in this set is contained in `other`.
+	pub fn is_subset(&self, other: &Set) -> bool {
+		self.len() <= other.len() && self.iter().all(|v| other.contains(v))
+	}
+
+	/// Returns an iterator over the values of the set, in order.
+	pub fn iter(&self) -> slice::Iter<'_, Value> {
+		self.0.iter()
+	}
+
+	/// Performs a binary search for the given value.
+	///
+	/// Returns `Ok(index)` if the value is present; otherwise, `Err(index)`,
+	/// where `index` is the position at which the value would be inserted.
+	fn search(&self, value: &Value) -> Result<usize, usize> {
+		self.0.binary_search_by(|v| cmp_keys(v, value))
+	}
+
+	/// Merges two sorted sets, keeping values that appear only in `self`,
+	/// in both sets, or only in `other`, as indicated by the flags.
+	fn merge(&self, other: &Set, only_self: bool, both: bool, only_other: bool) -> Set {
+		let mut res = Vec::new();
+		let mut a = self.iter().peekable();
+		let mut b = other.iter().peekable();
+
+		loop {
+			let ord = match (a.peek(), b.peek()) {
+				(Some(x), Some(y)) => cmp_keys(x, y),
+				(Some(_), None) => Ordering::Less,
+				(None, Some(_)) => Ordering::Greater,
+				(None, None) => break,
+			};
+
+			match ord {
+				Ordering::Less => {
+					let v = a.next().unwrap();
+					if only_self {
+						res.push(v.clone());
+					}
+				}
+				Ordering::Greater => {
+					let v = b.next().unwrap();
+					if only_other {
+						res.push(v.clone());
+					}
+				}
+				Ordering::Equal => {
+					let v = a.next().unwrap();
+					b.next();
+					if both {
+						res.push(v.clone());
+					}
+				}
+			}
+		}
+
+		Set(Rc::new(res))
+	}
+}
+
+impl fmt::Debug for Set {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_set().entries(self.iter()).finish()
+	}
+}
+
+impl<'a> IntoIterator for &'a Set {
+	type Item = &'a Value;
+	type IntoIter = slice::Iter<'a, Value>;
+
+	fn into_iter(self) -> slice::Iter<'a, Value> {
+		self.iter()
+	}
+}
@@ -0,0 +1,573 @@
+//! Implements pattern-based macros defined with the `syntax-rules` operator.
+//!
+//! A `syntax-rules` macro consists of a series of rules, each a pattern and
+//! a template. The macro expands to the template of the first rule whose
+//! pattern matches the macro arguments, with pattern variables replaced
+//! by the matched values.
+//!
+//! Names bound by `let`, `loop`, `dotimes`, `for`, `lambda`, `define`,
+//! `match`, and `try` forms within a template are renamed to fresh names
+//! upon each expansion, so that they cannot capture names in the expressions
+//! given as macro arguments.
+
+use crate::compile::CompileError;
+use crate::error::Error;
+use crate::exec::{execute_lambda, Context, ExecError};
+use crate::function::Lambda;
+use crate::name::{standard_names, Name, NameMap};
+use crate::value::Value;
+
+/// Value bound to a pattern variable
+#[derive(Clone)]
+enum Binding {
+	/// Value matched by a pattern variable
+	One(Value),
+	/// Series of bindings matched by a pattern followed by an ellipsis
+	Many(Vec<Binding>),
+}
+
+/// Validates a series of `(pattern template)` rules.
+pub fn check_rules(rules: &[Value]) -> Result<(), CompileError> {
+	for rule in rules {
+		let (pat, tpl) = get_rule(rule)?;
+
+		let mut vars = NameMap::new();
+
+		match *pat {
+			Value::List(ref li) => check_pattern_list(&li[1..], 0, &mut vars)?,
+			_ => return Err(CompileError::SyntaxError("expected `(pattern template)`")),
+		}
+
+		check_template(tpl, 0, &vars)?;
+	}
+
+	Ok(())
+}
+
+/// Validates the rules of a `syntax-rules` macro loaded from compiled code.
+///
+/// Rules compiled from source are validated once, by the `syntax-rules`
+/// operator, and are not checked again upon expansion.
+pub fn check_macro(ctx: &Context, lambda: Lambda) -> Result<(), Error> {
+	let rules = execute_lambda(ctx, lambda, Vec::new())?;
+	check_rules(get_rules(&rules)?)?;
+	Ok(())
+}
+
+/// Expands a call to a `syntax-rules` macro.
+///
+/// `args` are the arguments given to the macro.
+pub fn expand_macro(ctx: &Context, lambda: Lambda, args: &[Value]) -> Result<Value, Error> {
+	let rules = execute_lambda(ctx, lambda, Vec::new())?;
+	expand(ctx, get_rules(&rules)?, args)
+}
+
+fn get_rules(rules: &Value) -> Result<&[Value], ExecError> {
+	match *rules {
+		Value::List(ref li) => Ok(&li[..]),
+		Value::Unit => Ok(&[][..]),
+		ref v => Err(ExecError::expected("list", v)),
+	}
+}
+
+/// Expands a macro call using a series of validated `(pattern template)` rules.
+fn expand(ctx: &Context, rules: &[Value], args: &[Value]) -> Result<Value, Error> {
+	for rule in rules {
+		let (pat, tpl) = get_rule(rule)?;
+
+		let pat = match *pat {
+			Value::List(ref li) => &li[1..],
+			_ => unreachable!(),
+		};
+
+		let mut vars = NameMap::new();
+
+		if match_list(pat, args, &mut vars) {
+			let mut bound = Vec::new();
+			collect_bindings(tpl, &vars, &mut bound);
+
+			let renames = {
+				let mut names = ctx.scope().borrow_names_mut();
+				bound
+					.into_iter()
+					.map(|name| {
+						// Strip any suffix from a previous renaming
+						let prefix = names.get(name).split('#').next().unwrap_or("").to_owned();
+						(name, names.add_unique(&prefix))
+					})
+					.collect::<NameMap<_>>()
+			};
+
+			return expand_template(tpl, &vars, &renames);
+		}
+	}
+
+	Err(From::from(ExecError::NoMatch(args.to_vec().into())))
+}
+
+fn get_rule(rule: &Value) -> Result<(&Value, &Value), CompileError> {
+	match *rule {
+		Value::List(ref li) if li.len() == 2 => Ok((&li[0], &li[1])),
+		_ => Err(CompileError::SyntaxError("expected `(pattern template)`")),
+	}
+}
+
+fn is_ellipsis(v: &Value) -> bool {
+	matches!(*v, Value::Name(standard_names::ELLIPSIS))
+}
+
+/// Returns the position of the single ellipsis in a list pattern, if any.
+fn find_ellipsis(pat: &[Value]) -> Result<Option<usize>, CompileError> {
+	let mut pos = None;
+
+	for (i, v) in pat.iter().enumerate() {
+		if is_ellipsis(v) {
+			if i == 0 {
+				return Err(CompileError::SyntaxError("ellipsis must follow a pattern"));
+			}
+			if pos.is_some() {
+				return Err(CompileError::SyntaxError("duplicate ellipsis in pattern"));
+			}
+			pos = Some(i);
+		}
+	}
+
+	Ok(pos)
+}
+
+fn check_pattern(pat: &Value, depth: u32, vars: &mut NameMap<u32>) -> Result<(), CompileError> {
+	match *pat {
+		Value::Name(standard_names::UNDERSCORE) => Ok(()),
+		Value::Name(standard_names::ELLIPSIS) => {
+			Err(CompileError::SyntaxError("ellipsis must follow a pattern"))
+		}
+		Value::Name(name) => {
+			if vars.insert(name, depth).is_some() {
+				Err(CompileError::SyntaxError("duplicate pattern variable"))
+			} else {
+				Ok(())
+			}
+		}
+		Value::List(ref li) => check_pattern_list(li, depth, vars),
+		_ => Ok(()),
+	}
+}
+
+fn check_pattern_list(
+	pat: &[Value],
+	depth: u32,
+	vars: &mut NameMap<u32>,
+) -> Result<(), CompileError> {
+	let ellipsis = find_ellipsis(pat)?;
+
+	for (i, v) in pat.iter().enumerate() {
+		if Some(i) == ellipsis {
+			continue;
+		}
+
+		let depth = if Some(i + 1) == ellipsis {
+			depth + 1
+		} else {
+			depth
+		};
+
+		check_pattern(v, depth, vars)?;
+	}
+
+	Ok(())
+}
+
+fn check_template(tpl: &Value, depth: u32, vars: &NameMap<u32>) -> Result<(), CompileError> {
+	match *tpl {
+		Value::Name(standard_names::ELLIPSIS) => {
+			Err(CompileError::SyntaxError("ellipsis must follow a template"))
+		}
+		Value::Name(name) => match vars.get(name) {
+			Some(&d) if d > depth => Err(CompileError::SyntaxError(
+				"pattern variable used without ellipsis",
+			)),
+			_ => Ok(()),
+		},
+		Value::List(ref li) => {
+			let mut iter = li.iter().peekable();
+
+			while let Some(v) = iter.next() {
+				if is_ellipsis(v) {
+					return Err(CompileError::SyntaxError("ellipsis must follow a template"));
+				}
+
+				if iter.peek().is_some_and(|v| is_ellipsis(v)) {
+					iter.next();
+
+					if !has_ellipsis_var(v, depth, vars) {
+						return Err(CompileError::SyntaxError(
+							"ellipsis template contains no ellipsis pattern variable",
+						));
+					}
+
+					check_template(v, depth + 1, vars)?;
+				} else {
+					check_template(v, depth, vars)?;
+				}
+			}
+
+			Ok(())
+		}
+		Value::Quote(ref v, _)
+		| Value::Quasiquote(ref v, _)
+		| Value::Comma(ref v, _)
+		| Value::CommaAt(ref v, _) => check_template(v, depth, vars),
+		_ => Ok(()),
+	}
+}
+
+/// Returns whether the template contains a pattern variable bound
+/// at a depth greater than `depth`.
+fn has_ellipsis_var(tpl: &Value, depth: u32, vars: &NameMap<u32>) -> bool {
+	match *tpl {
+		Value::Name(name) => vars.get(name).is_some_and(|&d| d > depth),
+		Value::List(ref li) => li.iter().any(|v| has_ellipsis_var(v, depth, vars)),
+		Value::Quote(ref v, _)
+		| Value::Quasiquote(ref v, _)
+		| Value::Comma(ref v, _)
+		| Value::CommaAt(ref v, _) => has_ellipsis_var(v, depth, vars),
+		_ => false,
+	}
+}
+
+fn match_pattern(pat: &Value, value: &Value, vars: &mut NameMap<Binding>) -> bool {
+	match *pat {
+		Value::Name(standard_names::UNDERSCORE) => true,
+		Value::Name(name) => {
+			vars.insert(name, Binding::One(value.clone()));
+			true
+		}
+		Value::List(ref li) => match *value {
+			Value::List(ref values) => match_list(li, values, vars),
+			Value::Unit => match_list(li, &[], vars),
+			_ => false,
+		},
+		ref pat => pat.is_identical(value),
+	}
+}
+
+fn match_list(pat: &[Value], values: &[Value], vars: &mut NameMap<Binding>) -> bool {
+	let ellipsis = find_ellipsis(pat).expect("invalid pattern in match_list");
+
+	let pos = match ellipsis {
+		None => {
+			return pat.len() == values.len()
+				&& pat
+					.iter()
+					.zip(values)
+					.all(|(p, v)| match_pattern(p, v, vars));
+		}
+		Some(pos) => pos,
+	};
+
+	let before = &pat[..pos - 1];
+	let repeat = &pat[pos - 1];
+	let after = &pat[pos + 1..];
+
+	if values.len() < before.len() + after.len() {
+		return false;
+	}
+
+	let n_repeat = values.len() - before.len() - after.len();
+
+	if !before
+		.iter()
+		.zip(values)
+		.all(|(p, v)| match_pattern(p, v, vars))
+	{
+		return false;
+	}
+
+	let mut matches = Vec::with_capacity(n_repeat);
+
+	for v in &values[before.len()..before.len() + n_repeat] {
+		let mut m = NameMap::new();
+
+		if !match_pattern(repeat, v, &mut m) {
+			return false;
+		}
+
+		matches.push(m);
+	}
+
+	let mut names = Vec::new();
+	pattern_vars(repeat, &mut names);
+
+	for name in names {
+		let items = matches
+			.iter()
+			.map(|m| m.get(name).cloned().expect("missing pattern variable"))
+			.collect();
+		vars.insert(name, Binding::Many(items));
+	}
+
+	after
+		.iter()
+		.zip(&values[before.len() + n_repeat..])
+		.all(|(p, v)| match_pattern(p, v, vars))
+}
+
+fn pattern_vars(pat: &Value, names: &mut Vec<Name>) {
+	match *pat {
+		Value::Name(standard_names::UNDERSCORE) | Value::Name(standard_names::ELLIPSIS) => (),
+		Value::Name(name) => names.push(name),
+		Value::List(ref li) => {
+			for v in li.iter() {
+				pattern_vars(v, names);
+			}
+		}
+		_ => (),
+	}
+}
+
+fn expand_template(
+	tpl: &Value,
+	vars: &NameMap<Binding>,
+	renames: &NameMap<Name>,
+) -> Result<Value, Error> {
+	match *tpl {
+		Value::Name(name) => match vars.get(name) {
+			Some(Binding::One(v)) => Ok(v.clone()),
+			Some(&Binding::Many(_)) => Err(From::from(CompileError::SyntaxError(
+				"pattern variable used without ellipsis",
+			))),
+			None => Ok(Value::Name(renames.get(name).cloned().unwrap_or(name))),
+		},
+		Value::List(ref li) => {
+			let mut res = Vec::with_capacity(li.len());
+			let mut iter = li.iter().peekable();
+
+			while let Some(v) = iter.next() {
+				if iter.peek().is_some_and(|v| is_ellipsis(v)) {
+					iter.next();
+					expand_ellipsis(v, vars, renames, &mut res)?;
+				} else {
+					res.push(expand_template(v, vars, renames)?);
+				}
+			}
+
+			Ok(res.into())
+		}
+		Value::Quote(ref v, n) => Ok(Value::Quote(
+			Box::new(expand_template(v, vars, renames)?),
+			n,
+		)),
+		Value::Quasiquote(ref v, n) => Ok(Value::Quasiquote(
+			Box::new(expand_template(v, vars, renames)?),
+			n,
+		)),
+		Value::Comma(ref v, n) => Ok(Value::Comma(
+			Box::new(expand_template(v, vars, renames)?),
+			n,
+		)),
+		Value::CommaAt(ref v, n) => Ok(Value::CommaAt(
+			Box::new(expand_template(v, vars, renames)?),
+			n,
+		)),
+		ref v => Ok(v.clone()),
+	}
+}
+
+/// Expands a template followed by an ellipsis once for each value bound
+/// to the pattern variables it contains.
+fn expand_ellipsis(
+	tpl: &Value,
+	vars: &NameMap<Binding>,
+	renames: &NameMap<Name>,
+	res: &mut Vec<Value>,
+) -> Result<(), Error> {
+	let mut names = Vec::new();
+	pattern_vars(tpl, &mut names);
+
+	let mut n_repeat = None;
+	let mut repeat = Vec::new();
+
+	for name in names {
+		if let Some(Binding::Many(items)) = vars.get(name) {
+			match n_repeat {
+				Some(n) if n != items.len() => {
+					return Err(From::from(CompileError::SyntaxError(
+						"mismatched ellipsis pattern variable lengths",
+					)));
+				}
+				_ => n_repeat = Some(items.len()),
+			}
+
+			repeat.push((name, items));
+		}
+	}
+
+	let n_repeat = match n_repeat {
+		Some(n) => n,
+		None => {
+			return Err(From::from(CompileError::SyntaxError(
+				"ellipsis template contains no ellipsis pattern variable",
+			)))
+		}
+	};
+
+	for i in 0..n_repeat {
+		let mut vars = vars.clone();
+
+		for (name, items) in &repeat {
+			vars.insert(*name, items[i].clone());
+		}
+
+		res.push(expand_template(tpl, &vars, renames)?);
+	}
+
+	Ok(())
+}
+
+/// Collects names bound by binding forms within a template,
+/// excluding pattern variables.
+fn collect_bindings(tpl: &Value, vars: &NameMap<Binding>, bound: &mut Vec<Name>) {
+	let li = match *tpl {
+		Value::List(ref li) => li,
+		Value::Quasiquote(ref v, _) | Value::Comma(ref v, _) | Value::CommaAt(ref v, _) => {
+			return collect_bindings(v, vars, bound);
+		}
+		_ => return,
+	};
+
+	let mut names = Vec::new();
+
+	match li[0] {
+		Value::Name(standard_names::LET) | Value::Name(standard_names::LOOP) if li.len() >= 2 => {
+			let mut bindings = &li[1];
+
+			if let Value::Name(name) = li[1] {
+				names.push(name);
+				bindings = li.get(2).unwrap_or(&Value::Unit);
+			}
+
+			if let Value::List(ref li) = *bindings {
+				for b in li.iter() {
+					if let Value::List(ref b) = *b {
+						binding_pattern_names(&b[0], &mut names);
+					}
+				}
+			}
+		}
+		Value::Name(standard_names::DOTIMES) | Value::Name(standard_names::FOR)
+			if li.len() >= 2 =>
+		{
+			if let Value::List(ref spec) = li[1] {
+				if let Value::Name(name) = spec[0] {
+					names.push(name);
+				}
+			}
+		}
+		Value::Name(standard_names::LAMBDA) if li.len() >= 2 => {
+			param_names(&li[1], &mut names);
+		}
+		Value::Name(standard_names::DEFINE) if li.len() >= 2 => {
+			if let Value::List(ref sig) = li[1] {
+				for v in &sig[1..] {
+					param_name(v, &mut names);
+				}
+			}
+		}
+		Value::Name(standard_names::MATCH) => {
+			for clause in li.iter().skip(2) {
+				if let Value::List(ref clause) = *clause {
+					match_pattern_names(&clause[0], &mut names);
+				}
+			}
+		}
+		Value::Name(standard_names::TRY) => {
+			for clause in li.iter().skip(2) {
+				if let Value::List(ref clause) = *clause {
+					if let (Value::Name(standard_names::CATCH), Some(&Value::Name(name))) =
+						(&clause[0], clause.get(1))
+					{
+						names.push(name);
+					}
+				}
+			}
+		}
+		_ => (),
+	}
+
+	for name in names {
+		if name != standard_names::UNDERSCORE
+			&& name != standard_names::ELLIPSIS
+			&& !vars.contains_key(name)
+			&& !bound.contains(&name)
+		{
+			bound.push(name);
+		}
+	}
+
+	for v in li.iter() {
+		collect_bindings(v, vars, bound);
+	}
+}
+
+fn param_names(params: &Value, names: &mut Vec<Name>) {
+	if let Value::List(ref li) = *params {
+		for v in li.iter() {
+			param_name(v, names);
+		}
+	}
+}
+
+fn param_name(param: &Value, names: &mut Vec<Name>) {
+	match *param {
+		Value::Name(name) => names.push(name),
+		// Either `(name default)` or a destructuring pattern;
+		// in both cases, the names contained are bound.
+		Value::List(_) => binding_pattern_names(param, names),
+		_ => (),
+	}
+}
+
+/// Collects names from a `let` or `lambda` binding target.
+fn binding_pattern_names(pat: &Value, names: &mut Vec<Name>) {
+	match *pat {
+		Value::Name(name) => names.push(name),
+		Value::List(ref li) => match li.get(1) {
+			// `(StructName :field pattern ...)`
+			Some(&Value::Keyword(kw)) if kw != standard_names::REST => {
+				for v in li.iter().skip(2).step_by(2) {
+					binding_pattern_names(v, names);
+				}
+			}
+			_ => {
+				for v in li.iter() {
+					binding_pattern_names(v, names);
+				}
+			}
+		},
+		_ => (),
+	}
+}
+
+/// Collects names from a `match` pattern.
+fn match_pattern_names(pat: &Value, names: &mut Vec<Name>) {
+	match *pat {
+		Value::Name(name) => names.push(name),
+		Value::List(ref li) => {
+			let fields = if let Value::Name(standard_names::LIST) = li[0] {
+				// `(list pattern ... :rest pattern)`
+				li.iter()
+					.skip(1)
+					.filter(|v| !matches!(**v, Value::Keyword(_)))
+					.collect::<Vec<_>>()
+			} else {
+				// `(StructName :field pattern ...)`
+				li.iter().skip(2).step_by(2).collect()
+			};
+
+			for v in fields {
+				match_pattern_names(v, names);
+			}
+		}
+		_ => (),
+	}
+}
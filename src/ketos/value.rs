@@ -2,9 +2,11 @@
 
 use std::any::Any;
 use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::f64::{INFINITY, NEG_INFINITY};
 use std::ffi::{OsStr, OsString};
 use std::fmt;
+use std::hash::Hash;
 use std::mem::replace;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
@@ -14,8 +16,11 @@ use crate::error::Error;
 use crate::exec::{Context, ExecError};
 use crate::function::{Function, Lambda};
 use crate::integer::{Integer, Ratio};
+use crate::map::Map;
 use crate::name::{Name, NameDebug, NameDisplay, NameStore};
 use crate::rc_vec::{RcString, RcVec};
+use crate::reference::Ref;
+use crate::set::Set;
 use crate::structs::{Struct, StructDef, StructValueDef};
 
 /// Represents a value.
@@ -61,6 +66,12 @@ pub enum Value {
 	/// Series of one or more values.
 	/// **MUST NEVER be of length zero.** Use `Unit` to represent empty lists.
 	List(RcVec<Value>),
+	/// Map of keys to values
+	Map(Map),
+	/// Set of unique values
+	Set(Set),
+	/// Mutable reference cell
+	Ref(Ref),
 	/// Function implemented in Rust
 	Function(Function),
 	/// Compiled bytecode function
@@ -102,6 +113,10 @@ impl Value {
 			(&Value::Unit, &Value::List(_)) => Ordering::Less,
 			(&Value::List(_), &Value::Unit) => Ordering::Greater,
 			(&Value::List(ref a), &Value::List(ref b)) => cmp_value_slice(a, b)?,
+			(&Value::Map(ref a), &Value::Map(ref b)) => cmp_map(a, b)?,
+			(&Value::Set(ref a), &Value::Set(ref b)) => {
+				cmp_value_slice(a.as_slice(), b.as_slice())?
+			}
 			(&Value::Struct(ref a), &Value::Struct(ref b)) => {
 				if a.def() == b.def() {
 					cmp_value_slice(a.fields(), b.fields())?
@@ -145,6 +160,7 @@ impl Value {
 			(&Value::Lambda(_), &Value::Lambda(_)) => {
 				return Err(ExecError::CannotCompare("lambda"))
 			}
+			(&Value::Ref(_), &Value::Ref(_)) => return Err(ExecError::CannotCompare("ref")),
 			(&Value::Quote(_, _), &Value::Quote(_, _)) => {
 				return Err(ExecError::CannotCompare("quote"))
 			}
@@ -201,6 +217,8 @@ impl Value {
 			(&Value::Unit, &Value::List(_)) => false,
 			(&Value::List(_), &Value::Unit) => false,
 			(&Value::List(ref a), &Value::List(ref b)) => eq_value_slice(a, b)?,
+			(&Value::Map(ref a), &Value::Map(ref b)) => eq_map(a, b)?,
+			(&Value::Set(ref a), &Value::Set(ref b)) => eq_value_slice(a.as_slice(), b.as_slice())?,
 			(&Value::Struct(ref a), &Value::Struct(ref b)) => {
 				if a.def() == b.def() {
 					eq_value_slice(a.fields(), b.fields())?
@@ -214,6 +232,7 @@ impl Value {
 			(&Value::StructDef(ref a), &Value::StructDef(ref b)) => a == b,
 			(&Value::Function(ref a), &Value::Function(ref b)) => a == b,
 			(&Value::Lambda(ref a), &Value::Lambda(ref b)) => a == b,
+			(Value::Ref(a), Value::Ref(b)) => a.ptr_eq(b),
 
 			(&Value::Foreign(ref a), ref b) => a.is_equal_to_value(b)?,
 			(ref a, &Value::Foreign(ref b)) => b.is_equal_to_value(a)?,
@@ -259,8 +278,13 @@ impl Value {
 			(&Value::Comma(ref a, na), &Value::Comma(ref b, nb)) => na == nb && a.is_identical(b),
 			(&Value::Quote(ref a, na), &Value::Quote(ref b, nb)) => na == nb && a.is_identical(b),
 			(&Value::List(ref a), &Value::List(ref b)) => list_is_identical(a, b),
+			(&Value::Map(ref a), &Value::Map(ref b)) => map_is_identical(a, b),
+			(&Value::Set(ref a), &Value::Set(ref b)) => {
+				list_is_identical(a.as_slice(), b.as_slice())
+			}
 			(&Value::Function(ref a), &Value::Function(ref b)) => a == b,
 			(&Value::Lambda(ref a), &Value::Lambda(ref b)) => a == b,
+			(Value::Ref(a), Value::Ref(b)) => a.ptr_eq(b),
 
 			(&Value::Foreign(ref a), &Value::Foreign(ref b)) => a.is_identical_to(&**b),
 
@@ -308,6 +332,10 @@ impl Value {
 			| Value::Quasiquote(ref v, _)
 			| Value::Quote(ref v, _) => 1 + v.size(),
 			Value::List(ref li) => 1 + li.iter().map(|v| v.size()).sum::<usize>(),
+			Value::Map(ref m) => 1 + m.iter().map(|(k, v)| k.size() + v.size()).sum::<usize>(),
+			Value::Set(ref s) => 1 + s.iter().map(|v| v.size()).sum::<usize>(),
+			// A cell which contains itself is counted only once.
+			Value::Ref(ref r) => 1 + r.visit(|v| v.size()).unwrap_or(0),
 			Value::Lambda(ref l) => {
 				1 + l
 					.values
@@ -377,6 +405,9 @@ impl Value {
 			| Value::CommaAt(_, _)
 			| Value::Quote(_, _) => "object",
 			Value::List(_) => "list",
+			Value::Map(_) => "map",
+			Value::Set(_) => "set",
+			Value::Ref(_) => "ref",
 			Value::Struct(_) => "struct",
 			Value::StructDef(_) => "struct-def",
 			Value::Function(_) => "function",
@@ -678,6 +709,51 @@ impl NameDebug for Value {
 
 				write!(f, ")")
 			}
+			Value::Map(ref m) => {
+				write!(f, "{{")?;
+
+				let mut iter = m.iter();
+
+				if let Some((k, v)) = iter.next() {
+					NameDebug::fmt(k, names, f)?;
+					write!(f, " ")?;
+					NameDebug::fmt(v, names, f)?;
+				}
+
+				for (k, v) in iter {
+					write!(f, " ")?;
+					NameDebug::fmt(k, names, f)?;
+					write!(f, " ")?;
+					NameDebug::fmt(v, names, f)?;
+				}
+
+				write!(f, "}}")
+			}
+			Value::Set(ref s) => {
+				write!(f, "#{{")?;
+
+				let mut iter = s.iter();
+
+				if let Some(v) = iter.next() {
+					NameDebug::fmt(v, names, f)?;
+				}
+
+				for v in iter {
+					write!(f, " ")?;
+					NameDebug::fmt(v, names, f)?;
+				}
+
+				write!(f, "}}")
+			}
+			Value::Ref(ref r) => {
+				let r = r.visit(|v| {
+					write!(f, "<ref ")?;
+					NameDebug::fmt(v, names, f)?;
+					write!(f, ">")
+				});
+
+				r.unwrap_or_else(|| write!(f, "<ref ...>"))
+			}
 			// TODO: This output doesn't match the way structs are built.
 			// Write out "(new 'name ...)"? Implement a shortcut syntax?
 			Value::Struct(ref s) => {
@@ -846,6 +922,35 @@ fn eq_value_slice(a: &[Value], b: &[Value]) -> Result<bool, ExecError> {
 	Ok(true)
 }
 
+fn cmp_map(a: &Map, b: &Map) -> Result<Ordering, ExecError> {
+	for ((ak, av), (bk, bv)) in a.iter().zip(b.iter()) {
+		match ak.compare(bk)? {
+			Ordering::Equal => (),
+			ord => return Ok(ord),
+		}
+		match av.compare(bv)? {
+			Ordering::Equal => (),
+			ord => return Ok(ord),
+		}
+	}
+
+	Ok(a.len().cmp(&b.len()))
+}
+
+fn eq_map(a: &Map, b: &Map) -> Result<bool, ExecError> {
+	if a.len() != b.len() {
+		return Ok(false);
+	}
+
+	for ((ak, av), (bk, bv)) in a.iter().zip(b.iter()) {
+		if !ak.is_identical(bk) || !av.is_equal(bv)? {
+			return Ok(false);
+		}
+	}
+
+	Ok(true)
+}
+
 fn float_is_identical(a: f64, b: f64) -> bool {
 	a.to_bits() == b.to_bits()
 }
@@ -854,6 +959,13 @@ fn list_is_identical(a: &[Value], b: &[Value]) -> bool {
 	a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| a.is_identical(b))
 }
 
+fn map_is_identical(a: &Map, b: &Map) -> bool {
+	a.len() == b.len()
+		&& a.iter()
+			.zip(b.iter())
+			.all(|((ak, av), (bk, bv))| ak.is_identical(bk) && av.is_identical(bv))
+}
+
 /// Borrows a Rust value from a `Value`
 pub trait FromValueRef<'a>: Sized {
 	/// Returns the borrowed value
@@ -1002,6 +1114,83 @@ impl<'a, T: FromValueRef<'a>> FromValueRef<'a> for Vec<T> {
 	}
 }
 
+impl<'a> FromValueRef<'a> for &'a Map {
+	fn from_value_ref(v: &'a Value) -> Result<&'a Map, ExecError> {
+		match *v {
+			Value::Map(ref m) => Ok(m),
+			ref v => Err(ExecError::expected("map", v)),
+		}
+	}
+}
+
+impl<'a, K, V> FromValueRef<'a> for HashMap<K, V>
+where
+	K: FromValueRef<'a> + Eq + Hash,
+	V: FromValueRef<'a>,
+{
+	fn from_value_ref(v: &'a Value) -> Result<HashMap<K, V>, ExecError> {
+		match *v {
+			Value::Map(ref m) => m
+				.iter()
+				.map(|(k, v)| Ok((K::from_value_ref(k)?, V::from_value_ref(v)?)))
+				.collect(),
+			ref v => Err(ExecError::expected("map", v)),
+		}
+	}
+}
+
+impl<'a, K, V> FromValueRef<'a> for BTreeMap<K, V>
+where
+	K: FromValueRef<'a> + Ord,
+	V: FromValueRef<'a>,
+{
+	fn from_value_ref(v: &'a Value) -> Result<BTreeMap<K, V>, ExecError> {
+		match *v {
+			Value::Map(ref m) => m
+				.iter()
+				.map(|(k, v)| Ok((K::from_value_ref(k)?, V::from_value_ref(v)?)))
+				.collect(),
+			ref v => Err(ExecError::expected("map", v)),
+		}
+	}
+}
+
+impl<'a> FromValueRef<'a> for &'a Ref {
+	fn from_value_ref(v: &'a Value) -> Result<&'a Ref, ExecError> {
+		match *v {
+			Value::Ref(ref r) => Ok(r),
+			ref v => Err(ExecError::expected("ref", v)),
+		}
+	}
+}
+
+impl<'a> FromValueRef<'a> for &'a Set {
+	fn from_value_ref(v: &'a Value) -> Result<&'a Set, ExecError> {
+		match *v {
+			Value::Set(ref s) => Ok(s),
+			ref v => Err(ExecError::expected("set", v)),
+		}
+	}
+}
+
+impl<'a, T: FromValueRef<'a> + Eq + Hash> FromValueRef<'a> for HashSet<T> {
+	fn from_value_ref(v: &'a Value) -> Result<HashSet<T>, ExecError> {
+		match *v {
+			Value::Set(ref s) => s.iter().map(|v| T::from_value_ref(v)).collect(),
+			ref v => Err(ExecError::expected("set", v)),
+		}
+	}
+}
+
+impl<'a, T: FromValueRef<'a> + Ord> FromValueRef<'a> for BTreeSet<T> {
+	fn from_value_ref(v: &'a Value) -> Result<BTreeSet<T>, ExecError> {
+		match *v {
+			Value::Set(ref s) => s.iter().map(|v| T::from_value_ref(v)).collect(),
+			ref v => Err(ExecError::expected("set", v)),
+		}
+	}
+}
+
 impl<'a> FromValueRef<'a> for &'a Lambda {
 	fn from_value_ref(v: &'a Value) -> Result<&'a Lambda, ExecError> {
 		match *v {
@@ -1059,6 +1248,9 @@ simple_from_value! { String; "string"; Value::String(s) => s.into_string() }
 simple_from_value! { Bytes; "bytes"; Value::Bytes(s) => s }
 simple_from_value! { Integer; "integer"; Value::Integer(i) => i }
 simple_from_value! { Ratio; "ratio"; Value::Ratio(r) => r }
+simple_from_value! { Map; "map"; Value::Map(m) => m }
+simple_from_value! { Set; "set"; Value::Set(s) => s }
+simple_from_value! { Ref; "ref"; Value::Ref(r) => r }
 
 integer_from_value! { i8 to_i8 }
 integer_from_value! { i16 to_i16 }
@@ -1113,6 +1305,56 @@ impl<T: FromValue> FromValue for Vec<T> {
 	}
 }
 
+impl<K, V> FromValue for HashMap<K, V>
+where
+	K: FromValue + Eq + Hash,
+	V: FromValue,
+{
+	fn from_value(v: Value) -> Result<HashMap<K, V>, ExecError> {
+		match v {
+			Value::Map(m) => m
+				.iter()
+				.map(|(k, v)| Ok((K::from_value(k.clone())?, V::from_value(v.clone())?)))
+				.collect(),
+			ref v => Err(ExecError::expected("map", v)),
+		}
+	}
+}
+
+impl<K, V> FromValue for BTreeMap<K, V>
+where
+	K: FromValue + Ord,
+	V: FromValue,
+{
+	fn from_value(v: Value) -> Result<BTreeMap<K, V>, ExecError> {
+		match v {
+			Value::Map(m) => m
+				.iter()
+				.map(|(k, v)| Ok((K::from_value(k.clone())?, V::from_value(v.clone())?)))
+				.collect(),
+			ref v => Err(ExecError::expected("map", v)),
+		}
+	}
+}
+
+impl<T: FromValue + Eq + Hash> FromValue for HashSet<T> {
+	fn from_value(v: Value) -> Result<HashSet<T>, ExecError> {
+		match v {
+			Value::Set(s) => s.iter().map(|v| T::from_value(v.clone())).collect(),
+			ref v => Err(ExecError::expected("set", v)),
+		}
+	}
+}
+
+impl<T: FromValue + Ord> FromValue for BTreeSet<T> {
+	fn from_value(v: Value) -> Result<BTreeSet<T>, ExecError> {
+		match v {
+			Value::Set(s) => s.iter().map(|v| T::from_value(v.clone())).collect(),
+			ref v => Err(ExecError::expected("set", v)),
+		}
+	}
+}
+
 impl<T: FromValue> FromValue for Option<T> {
 	fn from_value(v: Value) -> Result<Option<T>, ExecError> {
 		match v {
@@ -1139,6 +1381,9 @@ value_from! { Integer; i => Value::Integer(i) }
 value_from! { Ratio; r => Value::Ratio(r) }
 value_from! { String; s => Value::String(RcString::new(s)) }
 value_from! { Bytes; s => Value::Bytes(s) }
+value_from! { Map; m => Value::Map(m) }
+value_from! { Set; s => Value::Set(s) }
+value_from! { Ref; r => Value::Ref(r) }
 value_from! { PathBuf; p => Value::Path(p) }
 value_from! { OsString; s => Value::Path(PathBuf::from(s)) }
 value_from! { f32; f => Value::Float(f64::from(f)) }
@@ -1214,6 +1459,30 @@ impl From<RcVec<Value>> for Value {
 	}
 }
 
+impl<K: Into<Value>, V: Into<Value>> From<HashMap<K, V>> for Value {
+	fn from(m: HashMap<K, V>) -> Value {
+		Value::Map(m.into_iter().map(|(k, v)| (k.into(), v.into())).collect())
+	}
+}
+
+impl<K: Into<Value>, V: Into<Value>> From<BTreeMap<K, V>> for Value {
+	fn from(m: BTreeMap<K, V>) -> Value {
+		Value::Map(m.into_iter().map(|(k, v)| (k.into(), v.into())).collect())
+	}
+}
+
+impl<T: Into<Value>> From<HashSet<T>> for Value {
+	fn from(s: HashSet<T>) -> Value {
+		Value::Set(Set::from_values(s.into_iter().map(Into::into)))
+	}
+}
+
+impl<T: Into<Value>> From<BTreeSet<T>> for Value {
+	fn from(s: BTreeSet<T>) -> Value {
+		Value::Set(Set::from_values(s.into_iter().map(Into::into)))
+	}
+}
+
 macro_rules! from_integer {
 	( $ty:ident $meth:ident ) => {
 		impl From<$ty> for Value {
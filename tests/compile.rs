@@ -263,6 +263,62 @@ fn test_tail_recursion() {
 	);
 }
 
+#[test]
+fn test_tail_call() {
+	assert_eq!(
+		lambda("(define (test a) (foo a))").unwrap(),
+		[LOAD_PUSH_0, TAIL_CALL_CONST, 0, 1,]
+	);
+
+	assert_eq!(
+		lambda("(define (test a) (a 1))").unwrap(),
+		[LOAD_PUSH_0, CONST_PUSH_0, TAIL_CALL, 1,]
+	);
+
+	assert_eq!(
+		lambda("(define (test a) (apply foo a))").unwrap(),
+		[LOAD_0, TAIL_APPLY_CONST, 0, 0,]
+	);
+
+	assert_eq!(
+		lambda("(define (test a) (if a (foo) (bar)))").unwrap(),
+		[
+			LOAD_0,
+			JUMP_IF_NOT,
+			6,
+			TAIL_CALL_CONST,
+			0,
+			0,
+			TAIL_CALL_CONST,
+			1,
+			0,
+		]
+	);
+}
+
+#[test]
+fn test_loop() {
+	assert_eq!(
+		lambda("(define (test a) (loop ((n a)) (if n (recur (foo)) n)))").unwrap(),
+		[
+			LOAD_PUSH_0,
+			LOAD_1,
+			JUMP_IF_NOT,
+			12,
+			CALL_CONST_0,
+			0,
+			PUSH,
+			LOAD_2,
+			STORE_1,
+			SKIP_1,
+			JUMP,
+			1,
+			LOAD_1,
+			RETURN,
+		]
+	);
+}
+
 #[test]
 fn test_tail_recursion_apply() {
 	assert_eq!(
@@ -3,10 +3,11 @@ extern crate assert_matches;
 
 extern crate ketos;
 
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::ffi::{OsStr, OsString};
 use std::path::{Path, PathBuf};
 
-use ketos::{Bytes, ExecError, FromValue, FromValueRef, Value};
+use ketos::{Bytes, ExecError, FromValue, FromValueRef, Map, Set, Value};
 
 fn from<T: FromValue>(v: Value) -> Result<T, ExecError> {
 	T::from_value(v)
@@ -60,6 +61,21 @@ fn test_from_value() {
 		from::<Bytes>(into(Bytes::from("foo"))).unwrap(),
 		Bytes::from("foo")
 	);
+
+	let mut m = HashMap::new();
+	m.insert("foo".to_owned(), 1);
+	m.insert("bar".to_owned(), 2);
+	assert_eq!(from::<HashMap<String, i32>>(into(m.clone())).unwrap(), m);
+
+	let mut m = BTreeMap::new();
+	m.insert(1, "foo".to_owned());
+	assert_eq!(from::<BTreeMap<i32, String>>(into(m.clone())).unwrap(), m);
+
+	let s: HashSet<String> = ["foo".to_owned(), "bar".to_owned()].into_iter().collect();
+	assert_eq!(from::<HashSet<String>>(into(s.clone())).unwrap(), s);
+
+	let s: BTreeSet<i32> = [3, 1, 2].into_iter().collect();
+	assert_eq!(from::<BTreeSet<i32>>(into(s.clone())).unwrap(), s);
 }
 
 #[test]
@@ -98,6 +114,17 @@ fn test_from_value_ref() {
 		from_ref::<&[u8]>(&into(Bytes::from("foo"))).unwrap(),
 		b"foo"
 	);
+
+	let mut m = BTreeMap::new();
+	m.insert("foo", 1);
+	let v = into(m.clone());
+	assert_eq!(from_ref::<BTreeMap<&str, i32>>(&v).unwrap(), m);
+	assert_eq!(from_ref::<&Map>(&v).unwrap().len(), 1);
+
+	let s: BTreeSet<&str> = ["foo", "bar"].into_iter().collect();
+	let v = into(s.clone());
+	assert_eq!(from_ref::<BTreeSet<&str>>(&v).unwrap(), s);
+	assert_eq!(from_ref::<&Set>(&v).unwrap().len(), 2);
 }
 
 #[test]
@@ -115,4 +142,6 @@ fn test_into_value() {
 
 	assert_matches!(into(Vec::<i32>::new()), Value::Unit);
 	assert_matches!(into(Vec::<Value>::new()), Value::Unit);
+	assert_matches!(into(HashMap::<i32, i32>::new()), Value::Map(ref m) if m.is_empty());
+	assert_matches!(into(HashSet::<i32>::new()), Value::Set(ref s) if s.is_empty());
 }
@@ -3,7 +3,20 @@ extern crate assert_matches;
 
 extern crate ketos;
 
-use ketos::{CompileError, Error, ExecError, FromValue, Interpreter, Value};
+use std::cell::RefCell;
+use std::env;
+use std::fs;
+use std::io::Cursor;
+use std::process;
+use std::rc::Rc;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use ketos::io::IoMode;
+use ketos::{
+	Builder, BytesError, BytesErrorKind, CompileError, Error, ExecError, FakeClock, FromValue,
+	FsAccess, GlobalIo, Interpreter, IoError, JsonError, JsonErrorKind, MathError, ParseError,
+	ParseErrorKind, RandomError, TimeError, Value,
+};
 
 fn eval(s: &str) -> Result<String, Error> {
 	let interp = Interpreter::new();
@@ -1268,6 +1281,52 @@ fn test_let() {
 	assert_eq!(eval("(let ((id 0)) id)").unwrap(), "0");
 }
 
+#[test]
+fn test_let_destructure() {
+	assert_eq!(
+		run("
+        (struct Point ((x integer) (y integer)))
+        (let (((a b :rest c) '(1 2 3 4))
+              (d (+ a b)))
+          (list a b c d))
+        (let (((Point :x x :y y) (new Point :x 1 :y 2))) (+ x y))
+        (let ((((a _) (b :rest _)) '((1 2) (3 4)))) (+ a b))
+        (let (((a :rest b) '(1))) b)
+        ")
+		.unwrap(),
+		["Point", "(1 2 (3 4) 3)", "3", "4", "()"]
+	);
+
+	assert_matches!(
+		eval("(let (((a b) '(1 2 3))) a)").unwrap_err(),
+		Error::ExecError(ExecError::PatternError { .. })
+	);
+	assert_matches!(
+		eval("(let (((a b :rest c) '(1))) a)").unwrap_err(),
+		Error::ExecError(ExecError::PatternError { .. })
+	);
+	assert_matches!(
+		eval("(let (((a b) 1)) a)").unwrap_err(),
+		Error::ExecError(ExecError::PatternError { .. })
+	);
+	assert_matches!(
+		eval("(let ((1 2)) 1)").unwrap_err(),
+		Error::CompileError(CompileError::SyntaxError(_))
+	);
+	assert_matches!(
+		eval("(let (((a \"b\") 2)) 1)").unwrap_err(),
+		Error::CompileError(CompileError::SyntaxError(_))
+	);
+	assert_matches!(
+		eval("(let (((Foo :a) 2)) 1)").unwrap_err(),
+		Error::CompileError(CompileError::SyntaxError(_))
+	);
+	assert_matches!(
+		eval("(let (((a a) '(1 2))) 1)").unwrap_err(),
+		Error::CompileError(CompileError::SyntaxError(_))
+	);
+}
+
 #[test]
 fn test_chars() {
 	assert_eq!(eval(r#"(chars "")"#).unwrap(), "()");
@@ -1492,262 +1551,1483 @@ fn test_bytes_fns() {
 }
 
 #[test]
-fn test_list() {
-	assert_eq!(eval("(list 1 2 (+ 1 2))").unwrap(), "(1 2 3)");
-}
-
-#[test]
-fn test_reverse() {
-	assert_eq!(eval("(reverse ())").unwrap(), "()");
-	assert_eq!(eval("(reverse '(1 2 3))").unwrap(), "(3 2 1)");
-}
-
-#[test]
-fn test_abs() {
-	assert_eq!(eval("(abs -1)").unwrap(), "1");
-	assert_eq!(eval("(abs -1/2)").unwrap(), "1/2");
-	assert_eq!(eval("(abs -1.5)").unwrap(), "1.5");
-	assert_eq!(eval("(abs (- (inf)))").unwrap(), "inf");
-}
-
-#[test]
-fn test_ceil() {
-	assert_eq!(eval("(ceil 1)").unwrap(), "1");
-	assert_eq!(eval("(ceil 1/2)").unwrap(), "1/1");
-	assert_eq!(eval("(ceil 1.3)").unwrap(), "2.0");
-}
-
-#[test]
-fn test_floor() {
-	assert_eq!(eval("(floor 1)").unwrap(), "1");
-	assert_eq!(eval("(floor 1/2)").unwrap(), "0/1");
-	assert_eq!(eval("(floor -1.3)").unwrap(), "-2.0");
-}
-
-#[test]
-fn test_trunc() {
-	assert_eq!(eval("(trunc 1)").unwrap(), "1");
-	assert_eq!(eval("(trunc -1.3)").unwrap(), "-1.0");
-	assert_eq!(eval("(trunc 5/2)").unwrap(), "2/1");
-}
+fn test_map() {
+	assert_eq!(eval("{}").unwrap(), "{}");
+	assert_eq!(eval("{2 :b 1 :a}").unwrap(), "{1 :a 2 :b}");
+	assert_eq!(eval(r#"{"b" 2 "a" 1}"#).unwrap(), r#"{"a" 1 "b" 2}"#);
+	assert_eq!(eval("{:a (+ 1 2)}").unwrap(), "{:a 3}");
+	assert_eq!(eval("(let ((x 1)) {:x x})").unwrap(), "{:x 1}");
+	assert_eq!(eval("'{:a b}").unwrap(), "{:a b}");
+	assert_eq!(eval("(let ((x 1)) `{:x ,x :y y})").unwrap(), "{:x 1 :y y}");
+	assert_eq!(eval("(hash-map :a 1 :b 2)").unwrap(), "{:a 1 :b 2}");
+	assert_eq!(eval("(hash-map 1 :a 1.0 :b)").unwrap(), "{1 :a 1.0 :b}");
+	assert_eq!(eval("(len {:a 1 :b 2})").unwrap(), "2");
 
-#[test]
-fn test_int() {
-	assert_eq!(eval("(int 123)").unwrap(), "123");
-	assert_eq!(eval("(int 123.0)").unwrap(), "123");
-	assert_eq!(eval("(int 123/1)").unwrap(), "123");
+	assert_eq!(eval("(= {:a 1} {:a 1})").unwrap(), "true");
+	assert_eq!(eval("(= {:a 1} {:a 1.0})").unwrap(), "true");
+	assert_eq!(eval("(= {:a 1} {:a 2})").unwrap(), "false");
+	assert_eq!(eval("(< {:a 1} {:a 2})").unwrap(), "true");
 
 	assert_matches!(
-		eval("(int (inf))").unwrap_err(),
-		Error::ExecError(ExecError::Overflow)
-	);
-	assert_matches!(
-		eval("(int (nan))").unwrap_err(),
-		Error::ExecError(ExecError::Overflow)
+		eval("(hash-map :a)").unwrap_err(),
+		Error::ExecError(ExecError::OddMapParams)
 	);
 }
 
 #[test]
-fn test_float() {
-	assert_eq!(eval("(float 123)").unwrap(), "123.0");
-	assert_eq!(eval("(float 123.0)").unwrap(), "123.0");
-	assert_eq!(eval("(float 123/1)").unwrap(), "123.0");
-
-	assert_matches!(
-		eval("(float (^ 10 309))").unwrap_err(),
-		Error::ExecError(ExecError::Overflow)
+fn test_map_literal_order() {
+	// Entries are evaluated in the order written, not in key order.
+	assert_eq!(
+		run("
+        (define log (ref ()))
+        (define (note x) (do (swap-ref! log (lambda (li) (append li x))) x))
+        (= {(note :z) (note 1) (note :a) (note 2)} '{:a 2 :z 1})
+        (deref log)
+        ")
+		.unwrap(),
+		["log", "note", "true", "(:z 1 :a 2)"]
 	);
-}
-
-#[test]
-fn test_inf() {
-	assert_eq!(eval("(inf)").unwrap(), "inf");
-	assert_eq!(eval("(- (inf))").unwrap(), "-inf");
-	assert_eq!(eval("(inf (inf) (inf))").unwrap(), "true");
-	assert_eq!(eval("(inf (- (inf)))").unwrap(), "true");
-	assert_eq!(eval("(inf (inf) 1.0)").unwrap(), "false");
-}
-
-#[test]
-fn test_nan() {
-	assert_eq!(eval("(nan)").unwrap(), "NaN");
-	assert_eq!(eval("(nan (nan) (nan))").unwrap(), "true");
-	assert_eq!(eval("(nan (nan) 1.0)").unwrap(), "false");
-}
-
-#[test]
-fn test_fract() {
-	assert_eq!(eval("(fract 1.5)").unwrap(), "0.5");
-	assert_eq!(eval("(fract 5/2)").unwrap(), "1/2");
-}
 
-#[test]
-fn test_recip() {
-	assert_eq!(eval("(recip 3)").unwrap(), "1/3");
-	assert_eq!(eval("(recip 10.0)").unwrap(), "0.1");
-	assert_eq!(eval("(recip 2/3)").unwrap(), "3/2");
+	// Keys which are equal only at runtime take the last value written.
+	assert_eq!(eval("(let ((x :a) (y :a)) {x 1 y 2})").unwrap(), "{:a 2}");
+	assert_eq!(eval("(let ((x :a)) `{,x 1 :a 2})").unwrap(), "{:a 2}");
 
 	assert_matches!(
-		eval("(recip 0)").unwrap_err(),
-		Error::ExecError(ExecError::DivideByZero)
-	);
-	assert_matches!(
-		eval("(recip 0/1)").unwrap_err(),
-		Error::ExecError(ExecError::DivideByZero)
+		eval("{:a 1 :a 2}").unwrap_err(),
+		Error::ParseError(ParseError {
+			kind: ParseErrorKind::DuplicateMapKey,
+			..
+		})
 	);
 }
 
 #[test]
-fn test_ratio() {
-	assert_eq!(eval("(denom 1/2)").unwrap(), "2");
-	assert_eq!(eval("(numer 1/2)").unwrap(), "1");
+fn test_map_fns() {
+	assert_eq!(eval("(get {:a 1} :a)").unwrap(), "1");
+	assert_eq!(eval("(get {:a 1} :b 2)").unwrap(), "2");
+	assert_eq!(eval("(assoc {:a 1} :b 2 :a 3)").unwrap(), "{:a 3 :b 2}");
+	assert_eq!(eval("(dissoc {:a 1 :b 2} :a :c)").unwrap(), "{:b 2}");
+	assert_eq!(eval("(keys {2 :b 1 :a})").unwrap(), "(1 2)");
+	assert_eq!(eval("(values {2 :b 1 :a})").unwrap(), "(:a :b)");
+	assert_eq!(eval("(keys {})").unwrap(), "()");
+	assert_eq!(eval("(contains {:a 1} :a)").unwrap(), "true");
+	assert_eq!(eval("(contains {:a 1} :b)").unwrap(), "false");
 
-	assert_eq!(eval("(denom 123)").unwrap(), "1");
-	assert_eq!(eval("(numer 123)").unwrap(), "123");
+	assert_eq!(
+		run("
+        (define m {:a 1})
+        (define n (assoc m :a 2))
+        (get m :a)
+        (get n :a)
+        ")
+		.unwrap(),
+		["m", "n", "1", "2"]
+	);
 
-	assert_eq!(eval("(rat 1.5)").unwrap(), "3/2");
-	assert_eq!(eval("(rat 1/3)").unwrap(), "1/3");
-	assert_eq!(eval("(rat 3)").unwrap(), "3/1");
-	assert_eq!(eval("(rat 1 2)").unwrap(), "1/2");
 	assert_matches!(
-		eval("(rat (inf))").unwrap_err(),
-		Error::ExecError(ExecError::Overflow)
+		eval("(get {:a 1} :b)").unwrap_err(),
+		Error::ExecError(ExecError::KeyError(Value::Keyword(_)))
 	);
 	assert_matches!(
-		eval("(rat (nan))").unwrap_err(),
-		Error::ExecError(ExecError::Overflow)
+		eval("(assoc {} :a 1 :b)").unwrap_err(),
+		Error::ExecError(ExecError::OddMapParams)
+	);
+	assert_matches!(
+		eval("(get '(1 2) 0)").unwrap_err(),
+		Error::ExecError(ExecError::TypeError {
+			expected: "map",
+			..
+		})
 	);
 }
 
 #[test]
-fn test_id() {
-	assert_eq!(eval("(id 1)").unwrap(), "1");
-	assert_eq!(eval("(id #'a')").unwrap(), "#'a'");
-	assert_eq!(eval("(id \"a\")").unwrap(), "\"a\"");
-	assert_eq!(eval("(id '(1 2 3))").unwrap(), "(1 2 3)");
-}
-
-#[test]
-fn test_is() {
-	assert_eq!(eval("(is 'integer 1)").unwrap(), "true");
-	assert_eq!(eval("(is 'ratio 1/1)").unwrap(), "true");
-	assert_eq!(eval("(is 'float 1.0)").unwrap(), "true");
-	assert_eq!(eval("(is 'list '(1))").unwrap(), "true");
-	assert_eq!(eval("(is 'list ())").unwrap(), "true");
-	assert_eq!(eval("(is 'unit ())").unwrap(), "true");
-	assert_eq!(eval("(is 'name 'a)").unwrap(), "true");
-	assert_eq!(eval("(is 'keyword :a)").unwrap(), "true");
-	assert_eq!(eval("(is 'string \"a\")").unwrap(), "true");
-	assert_eq!(eval("(is 'path #p\"a\")").unwrap(), "true");
-	assert_eq!(eval("(is 'bytes #b\"a\")").unwrap(), "true");
-	assert_eq!(eval("(is 'function is)").unwrap(), "true");
-	assert_eq!(eval("(is 'lambda (lambda () ()))").unwrap(), "true");
-
-	assert_eq!(eval("(is 'number 1)").unwrap(), "true");
-	assert_eq!(eval("(is 'number 1/1)").unwrap(), "true");
-	assert_eq!(eval("(is 'number 1.0)").unwrap(), "true");
-
-	assert_eq!(eval("(is 'integer 1.0)").unwrap(), "false");
-	assert_eq!(eval("(is 'list 'foo)").unwrap(), "false");
-	assert_eq!(eval("(is 'foo ())").unwrap(), "false");
-}
-
-#[test]
-fn test_null() {
-	assert_eq!(eval("(null ())").unwrap(), "true");
-	assert_eq!(eval("(null 'a)").unwrap(), "false");
-	assert_eq!(eval("(null 1)").unwrap(), "false");
-}
-
-#[test]
-fn test_type_of() {
-	assert_eq!(eval("(type-of ())").unwrap(), "unit");
-	assert_eq!(eval("(type-of true)").unwrap(), "bool");
-	assert_eq!(eval("(type-of 1)").unwrap(), "integer");
-	assert_eq!(eval("(type-of 1/1)").unwrap(), "ratio");
-	assert_eq!(eval("(type-of 1.0)").unwrap(), "float");
-	assert_eq!(eval("(type-of 'a)").unwrap(), "name");
-	assert_eq!(eval("(type-of :a)").unwrap(), "keyword");
-	assert_eq!(eval("(type-of #'a')").unwrap(), "char");
-	assert_eq!(eval("(type-of \"a\")").unwrap(), "string");
-	assert_eq!(eval("(type-of #p\"a\")").unwrap(), "path");
-	assert_eq!(eval("(type-of #b\"a\")").unwrap(), "bytes");
-	assert_eq!(eval("(type-of ''a)").unwrap(), "object");
-	assert_eq!(eval("(type-of '(1))").unwrap(), "list");
-	assert_eq!(eval("(type-of id)").unwrap(), "function");
-	assert_eq!(eval("(type-of (lambda () ()))").unwrap(), "lambda");
-}
-
-#[test]
-fn test_define() {
-	assert_eq!(run("(define foo 123) foo").unwrap(), ["foo", "123"]);
-
-	// Standard names cannot be overriden in global scope
-	assert_matches!(run("(define (=) ())").unwrap_err(), Error::CompileError(_));
-	assert_matches!(run("(define (if) ())").unwrap_err(), Error::CompileError(_));
-
-	assert_eq!(
-		run("(define (foo n) (* n n)) (foo 3)").unwrap(),
-		["foo", "9"]
-	);
+fn test_set() {
 	assert_eq!(
 		run("
-        (define (foo a b :optional c d) (list a b c d))
-        (foo 1 2)
-        (foo 1 2 3)
-        (foo 1 2 3 4)
+        (use set :all)
+        (set 3 1 2 1)
+        (set)
+        (set 1 1.0)
+        (from-list '(\"b\" \"a\" \"b\"))
+        (to-list (set 2 1))
+        (insert (set 1) 3 2)
+        (remove (set 1 2 3) 2 4)
+        (len (set 1 2 2))
+        (contains (set 1 2) 2)
+        (contains (set 1 2) 3)
+        (type-of (set))
         ")
 		.unwrap(),
-		["foo", "(1 2 () ())", "(1 2 3 ())", "(1 2 3 4)"]
+		[
+			"()",
+			"#{1 2 3}",
+			"#{}",
+			"#{1 1.0}",
+			r#"#{"a" "b"}"#,
+			"(1 2)",
+			"#{1 2 3}",
+			"#{1 3}",
+			"2",
+			"true",
+			"false",
+			"set"
+		]
 	);
 
 	assert_eq!(
 		run("
-        (define (foo a b :key c d) (list a b c d))
-        (foo 1 2)
-        (foo 1 2 :c 3)
-        (foo 1 2 :d 3)
-        (foo 1 2 :c 3 :d 4)
+        (use set :all)
+        (union (set 1 2) (set 2 3) (set 5))
+        (intersection (set 1 2 3) (set 2 3 4))
+        (difference (set 1 2 3) (set 2) (set 3))
+        (subset? (set 1 2) (set 1 2 3))
+        (subset? (set 1 4) (set 1 2 3))
+        (= (set 1 2) (set 2 1))
+        (< (set 1 2) (set 1 3))
         ")
 		.unwrap(),
 		[
-			"foo",
-			"(1 2 () ())",
-			"(1 2 3 ())",
-			"(1 2 () 3)",
-			"(1 2 3 4)"
+			"()",
+			"#{1 2 3 5}",
+			"#{2 3}",
+			"#{1}",
+			"true",
+			"false",
+			"true",
+			"true"
 		]
 	);
 
+	// Elements follow the same rules as map keys: values of any type
+	// may be mixed, and only identical values are the same element.
 	assert_eq!(
 		run("
-        (define (foo a b :key (c 10) d) (list a b c d))
-        (foo 1 2)
-        (foo 1 2 :c 3)
-        (foo 1 2 :d 3)
-        (foo 1 2 :c 3 :d 4)
+        (use set :all)
+        (set \"a\" 1 :k 1)
+        (contains (set 1 \"a\") \"a\")
+        (contains (set 1) 1.0)
+        (len (union (set 1 2) (set 1.0 \"x\")))
+        (to-list (from-list (reverse (to-list (set 5 3 \"b\" 1 \"a\")))))
         ")
 		.unwrap(),
 		[
-			"foo",
-			"(1 2 10 ())",
-			"(1 2 3 ())",
-			"(1 2 10 3)",
-			"(1 2 3 4)"
+			"()",
+			r#"#{1 "a" :k}"#,
+			"true",
+			"false",
+			"4",
+			r#"(1 3 5 "a" "b")"#
 		]
 	);
-
-	assert_eq!(
+	assert_matches!(
 		run("
-        (define (foo a b :key c (d 10)) (list a b c d))
-        (foo 1 2)
-        (foo 1 2 :c 3)
-        (foo 1 2 :d 3)
-        (foo 1 2 :c 3 :d 4)
+        (use set (insert))
+        (insert '(1) 2)
         ")
-		.unwrap(),
-		[
-			"foo",
+		.unwrap_err(),
+		Error::ExecError(ExecError::TypeError {
+			expected: "set",
+			..
+		})
+	);
+}
+
+#[test]
+fn test_string_module() {
+	assert_eq!(
+		run(r#"
+        (use string :all)
+        (split "a,b,,c" ",")
+        (split "a,b,c" "," 2)
+        (split-whitespace "  a  b\tc ")
+        (lines "one\ntwo\r\nthree")
+        (trim "  x  ")
+        (trim-start "  x  ")
+        (trim-end "  x  ")
+        (trim "-+x-" "-+")
+        (starts-with "foobar" "foo")
+        (ends-with "foobar" "foo")
+        "#)
+		.unwrap(),
+		[
+			"()",
+			r#"("a" "b" "" "c")"#,
+			r#"("a" "b,c")"#,
+			r#"("a" "b" "c")"#,
+			r#"("one" "two" "three")"#,
+			r#""x""#,
+			r#""x  ""#,
+			r#""  x""#,
+			r#""x""#,
+			"true",
+			"false",
+		]
+	);
+
+	assert_eq!(
+		run(r#"
+        (use string :all)
+        (find "foobar" "o")
+        (rfind "foobar" "o")
+        (find "foobar" "z")
+        (replace "foo" "o" "0")
+        (replace "foo" "o" "0" 1)
+        (upper "straße")
+        (lower "ÀB")
+        (pad-start "7" 3 #'0')
+        (pad-end "ab" 4)
+        (pad-start "abcd" 2)
+        (repeat "ab" 3)
+        "#)
+		.unwrap(),
+		[
+			"()",
+			"1",
+			"2",
+			"()",
+			r#""f00""#,
+			r#""f0o""#,
+			r#""STRASSE""#,
+			r#""àb""#,
+			r#""007""#,
+			r#""ab  ""#,
+			r#""abcd""#,
+			r#""ababab""#,
+		]
+	);
+
+	assert_matches!(
+		run(r#"
+        (use string (split))
+        (split 'a ",")
+        "#)
+		.unwrap_err(),
+		Error::ExecError(ExecError::TypeError {
+			expected: "string",
+			..
+		})
+	);
+}
+
+#[test]
+fn test_json_module() {
+	assert_eq!(
+		run(r#"
+        (use json :all)
+        (decode "null")
+        (decode " [true, false, -12, 1.5, 2e2, [], {}] ")
+        (decode "{\"a\": {\"b\": \"x\\u00e9\\ud83d\\ude00\\n\"}}")
+        (decode "{\"a\": 1, \"b\": [2]}" :objects :plist)
+        (decode "123456789012345678901234567890")
+        (encode (decode "{\"a\": [1, 2.5, null], \"b\": \"\\\"\\u0001\"}"))
+        (encode '(:a (1 2) :b #'c') :objects :plist)
+        (encode '(:a 1))
+        (encode (hash-map "a" '(1 2)) :pretty true)
+        (encode (hash-map "a" {}) :indent 4)
+        "#)
+		.unwrap(),
+		[
+			"()",
+			"()",
+			"(true false -12 1.5 200.0 () {})",
+			r#"{"a" {"b" "xé😀\n"}}"#,
+			"(:a 1 :b (2))",
+			"123456789012345678901234567890",
+			r#""{\"a\":[1,2.5,null],\"b\":\"\\\"\\u0001\"}""#,
+			r#""{\"a\":[1,2],\"b\":\"c\"}""#,
+			r#""[\"a\",1]""#,
+			r#""{\n  \"a\": [\n    1,\n    2\n  ]\n}""#,
+			r#""{\n    \"a\": {}\n}""#,
+		]
+	);
+
+	let json_error = |code: &str| match run(code).unwrap_err() {
+		Error::Custom(e) => *e.downcast_ref::<JsonError>().unwrap(),
+		e => panic!("unexpected error: {:?}", e),
+	};
+
+	assert_eq!(
+		json_error(r#"(use json (decode)) (decode "[1, 2")"#),
+		JsonError::new(5, JsonErrorKind::UnexpectedEof)
+	);
+	assert_eq!(
+		json_error(r#"(use json (decode)) (decode "[1 2]")"#),
+		JsonError::new(3, JsonErrorKind::UnexpectedChar('2'))
+	);
+	assert_eq!(
+		json_error(r#"(use json (decode)) (decode "{1: 2}")"#),
+		JsonError::new(1, JsonErrorKind::KeyMustBeString)
+	);
+	assert_eq!(
+		json_error(r#"(use json (decode)) (decode "[01]")"#),
+		JsonError::new(1, JsonErrorKind::InvalidNumber)
+	);
+	assert_eq!(
+		json_error(r#"(use json (decode)) (decode "\"a\\x\"")"#),
+		JsonError::new(3, JsonErrorKind::InvalidEscape('x'))
+	);
+	assert_eq!(
+		json_error(r#"(use json (decode)) (decode "\"\\ud800\"")"#),
+		JsonError::new(1, JsonErrorKind::InvalidUnicodeEscape)
+	);
+	assert_eq!(
+		json_error(r#"(use json (decode)) (decode "1 2")"#),
+		JsonError::new(2, JsonErrorKind::UnexpectedChar('2'))
+	);
+
+	assert_matches!(
+		run(r#"
+        (use json (encode))
+        (encode (/ 0.0 0.0))
+        "#)
+		.unwrap_err(),
+		Error::ExecError(ExecError::TypeError {
+			expected: "finite float",
+			..
+		})
+	);
+
+	assert_matches!(
+		run(r#"
+        (use json (encode))
+        (encode (hash-map 1 2))
+        "#)
+		.unwrap_err(),
+		Error::ExecError(ExecError::TypeError {
+			expected: "string or keyword",
+			..
+		})
+	);
+
+	assert_matches!(
+		run(r#"
+        (use json (encode))
+        (encode () :pretty)
+        "#)
+		.unwrap_err(),
+		Error::ExecError(ExecError::OddKeywordParams)
+	);
+}
+
+#[test]
+fn test_time_module() {
+	assert_eq!(
+		run(r#"
+        (use time :all)
+        (duration 1.5)
+        (duration 250 :ms)
+        (add (duration 1) (duration 500 :ms))
+        (sub (duration 1) (duration 500 :ms))
+        (as-millis (mul (duration 1.5) 3))
+        (as-nanos (div (duration 1) 4))
+        (as-secs (duration 2 :min))
+        (< (duration 1) (duration 2))
+        (= (duration 1000 :ms) (duration 1))
+        (type-of (now))
+        "#)
+		.unwrap(),
+		[
+			"()",
+			"<duration 1.5s>",
+			"<duration 250ms>",
+			"<duration 1.5s>",
+			"<duration 500ms>",
+			"4500",
+			"250000000",
+			"120.0",
+			"true",
+			"true",
+			"instant",
+		]
+	);
+
+	assert_eq!(
+		run(r#"
+        (use time :all)
+        (format-time 0)
+        (format-time -1)
+        (format-time 951782400.25)
+        (format-time 1700000000 "%a %A %b %B %d %e %j %I %p %u %w %s %z %:z %Z %%")
+        (format-time 1.5 "%F %T%.3f")
+        (parse-time "1970-01-02T00:00:00Z")
+        (parse-time "2023-11-14T23:13:20.5+01:00")
+        (parse-time "02/01/70" "%d/%m/%y")
+        (parse-time "Tue, 14 Nov 2023 10:13:20 PM UTC" "%a, %d %b %Y %I:%M:%S %p %Z")
+        (format-time (parse-time "2024-060" "%Y-%j") "%D")
+        "#)
+		.unwrap(),
+		[
+			"()",
+			r#""1970-01-01T00:00:00Z""#,
+			r#""1969-12-31T23:59:59Z""#,
+			r#""2000-02-29T00:00:00.250Z""#,
+			r#""Tue Tuesday Nov November 14 14 318 10 PM 2 2 1700000000 +0000 +00:00 UTC %""#,
+			r#""1970-01-01 00:00:01.500""#,
+			"86400",
+			"1700000000.5",
+			"86400",
+			"1700000000",
+			r#""02/29/24""#,
+		]
+	);
+
+	let time_error = |code: &str| match run(code).unwrap_err() {
+		Error::Custom(e) => *e.downcast_ref::<TimeError>().unwrap(),
+		e => panic!("unexpected error: {:?}", e),
+	};
+
+	assert_eq!(
+		time_error(r#"(use time (parse-time)) (parse-time "1970-01-01X00:00:00Z")"#),
+		TimeError::ParseError(10)
+	);
+	assert_eq!(
+		time_error(r#"(use time (parse-time)) (parse-time "1970-02-30T00:00:00Z")"#),
+		TimeError::OutOfRange
+	);
+	assert_eq!(
+		time_error(r#"(use time (parse-time)) (parse-time "2020-01-01T00:00:00+99:99")"#),
+		TimeError::OutOfRange
+	);
+	assert_eq!(
+		time_error(r#"(use time (parse-time)) (parse-time "2020-01-01T00:00:00-00:60")"#),
+		TimeError::OutOfRange
+	);
+	assert_eq!(
+		run(r#"(use time (parse-time)) (parse-time "2020-01-01T00:00:00+23:59")"#).unwrap(),
+		["()", "1577750460"]
+	);
+	assert_eq!(
+		time_error(r#"(use time (format-time)) (format-time 0 "%Q")"#),
+		TimeError::InvalidFormat('Q')
+	);
+	assert_eq!(
+		time_error(r#"(use time (format-time)) (format-time 1e20)"#),
+		TimeError::OutOfRange
+	);
+
+	assert_matches!(
+		run(r#"
+        (use time (duration sub))
+        (sub (duration 1) (duration 2))
+        "#)
+		.unwrap_err(),
+		Error::ExecError(ExecError::Overflow)
+	);
+
+	assert_eq!(
+		run(r#"
+        (use time (as-nanos div duration))
+        (as-nanos (div (duration 10) 4000000000))
+        (as-nanos (div (duration 18446744073709551615) 18446744073709551616))
+        (as-nanos (div (duration 1) 100000000000000000000000000000))
+        "#)
+		.unwrap(),
+		["()", "2", "999999999", "0"]
+	);
+
+	assert_matches!(
+		run(r#"
+        (use time (div duration))
+        (div (duration 1) -2)
+        "#)
+		.unwrap_err(),
+		Error::ExecError(ExecError::TypeError { .. })
+	);
+}
+
+#[test]
+fn test_time_fake_clock() {
+	let clock = Rc::new(FakeClock::new(UNIX_EPOCH + Duration::from_secs(86400)));
+	let interp = Builder::new().clock(clock.clone()).finish();
+
+	let eval = |code: &str| {
+		let v = interp.run_code(code, None).unwrap();
+		interp.format_value(&v)
+	};
+
+	eval("(use time :all) (define start (now))");
+
+	assert_eq!(eval("(elapsed start)"), "<duration 0ns>");
+	assert_eq!(
+		eval("(format-time (unix-time))"),
+		r#""1970-01-02T00:00:00Z""#
+	);
+
+	clock.advance(Duration::from_secs(5));
+
+	assert_eq!(eval("(elapsed start)"), "<duration 5s>");
+	assert_eq!(eval("(sleep (duration 1 :h))"), "()");
+	assert_eq!(eval("(elapsed start)"), "<duration 3605s>");
+	assert_eq!(eval("(unix-time)"), "90005.0");
+	assert_eq!(eval("(sub (now) start)"), "<duration 3605s>");
+	assert_eq!(eval("(sub (now) (duration 5))"), "<instant 3600s>");
+}
+
+#[test]
+fn test_os_module() {
+	let interp = Interpreter::new();
+
+	let eval = |code: &str| interp.run_code(code, None).map(|v| interp.format_value(&v));
+
+	assert_eq!(eval("(use os :all)").unwrap(), "()");
+	assert_eq!(eval("(args)").unwrap(), "()");
+
+	interp.set_args(&["script.ket", "foo"]);
+
+	assert_eq!(eval("(args)").unwrap(), r#"("script.ket" "foo")"#);
+	assert_eq!(
+		eval(r#"(setenv "KETOS_TEST_OS_MODULE" "value")"#).unwrap(),
+		"()"
+	);
+	assert_eq!(
+		eval(r#"(getenv "KETOS_TEST_OS_MODULE")"#).unwrap(),
+		r#""value""#
+	);
+	assert_eq!(
+		eval(r#"(get (env-vars) "KETOS_TEST_OS_MODULE")"#).unwrap(),
+		r#""value""#
+	);
+	assert_eq!(eval(r#"(setenv "KETOS_TEST_OS_MODULE" ())"#).unwrap(), "()");
+	assert_eq!(eval(r#"(getenv "KETOS_TEST_OS_MODULE")"#).unwrap(), "()");
+	assert_eq!(eval("(pid)").unwrap(), process::id().to_string());
+	assert_eq!(eval("(type-of (cwd))").unwrap(), "path");
+	assert_eq!(eval("(type-of (hostname))").unwrap(), "string");
+
+	assert_matches!(
+		eval("(exit 2)").unwrap_err(),
+		Error::ExecError(ExecError::Exit(2))
+	);
+	assert_matches!(
+		eval("(try (exit) (catch e 1))").unwrap_err(),
+		Error::ExecError(ExecError::Exit(0))
+	);
+}
+
+#[test]
+fn test_bytes_module() {
+	assert_eq!(
+		run(r#"
+        (use bytes :all)
+        (hex-encode #b"\x01\xab\xff")
+        (hex-decode "01AbfF")
+        (base64-encode (bytes "hello"))
+        (base64-encode (bytes "hi"))
+        (base64-decode "aGVsbG8=")
+        (base64-decode "aGk")
+        (base64-decode "")
+        (utf8-decode #b"caf\xc3\xa9")
+        (pack :u8 1 :i16-le -2 :u32-be 3)
+        (pack :f32-le 1.5 :f64 2)
+        (unpack :u16-be #b"\x01\x02")
+        (unpack :u16-le #b"\x01\x02")
+        (unpack :i8 #b"\x00\xff" 1)
+        (unpack :i64-le (pack :i64-le -123456789012))
+        (unpack :u64 (pack :u64 18446744073709551615))
+        (unpack :f64-le (pack :f64-le 0.25))
+        (find #b"abcabc" #b"bc")
+        (find #b"abcabc" #b"bc" 2)
+        (find #b"abcabc" #b"x")
+        "#)
+		.unwrap(),
+		[
+			"()",
+			r#""01abff""#,
+			r#"#b"\x01\xab\xff""#,
+			r#""aGVsbG8=""#,
+			r#""aGk=""#,
+			r#"#b"hello""#,
+			r#"#b"hi""#,
+			r#"#b"""#,
+			r#""café""#,
+			r#"#b"\x01\xfe\xff\x00\x00\x00\x03""#,
+			r#"#b"\x00\x00\xc0?@\x00\x00\x00\x00\x00\x00\x00""#,
+			"258",
+			"513",
+			"-1",
+			"-123456789012",
+			"18446744073709551615",
+			"0.25",
+			"1",
+			"4",
+			"()",
+		]
+	);
+
+	let bytes_error = |code: &str| match run(code).unwrap_err() {
+		Error::Custom(e) => *e.downcast_ref::<BytesError>().unwrap(),
+		e => panic!("unexpected error: {:?}", e),
+	};
+
+	assert_eq!(
+		bytes_error(r#"(use bytes (hex-decode)) (hex-decode "abc")"#),
+		BytesError::new(3, BytesErrorKind::InvalidHex)
+	);
+	assert_eq!(
+		bytes_error(r#"(use bytes (hex-decode)) (hex-decode "0g")"#),
+		BytesError::new(1, BytesErrorKind::InvalidHex)
+	);
+	assert_eq!(
+		bytes_error(r#"(use bytes (base64-decode)) (base64-decode "aGk*")"#),
+		BytesError::new(3, BytesErrorKind::InvalidBase64)
+	);
+	assert_eq!(
+		bytes_error(r#"(use bytes (base64-decode)) (base64-decode "aGk==")"#),
+		BytesError::new(5, BytesErrorKind::InvalidBase64)
+	);
+	assert_eq!(
+		bytes_error(r#"(use bytes (utf8-decode)) (utf8-decode #b"ab\xffc")"#),
+		BytesError::new(2, BytesErrorKind::InvalidUtf8)
+	);
+
+	assert_matches!(
+		run("(use bytes (pack)) (pack :u8 256)").unwrap_err(),
+		Error::ExecError(ExecError::Overflow)
+	);
+	assert_matches!(
+		run("(use bytes (pack)) (pack :u24 1)").unwrap_err(),
+		Error::ExecError(ExecError::UnrecognizedKeyword(_))
+	);
+	assert_matches!(
+		run(r#"(use bytes (unpack)) (unpack :u32 #b"\x00\x00\x00\x00" 1)"#).unwrap_err(),
+		Error::ExecError(ExecError::OutOfBounds(5))
+	);
+}
+
+#[test]
+fn test_random_module() {
+	let sequence = |interp: &Interpreter| {
+		let v = interp
+			.run_code(
+				r#"
+                (use random :all)
+                (list (random) (random-int 1000000) (normal) (exponential 2)
+                    (random-bytes 4) (shuffle '(1 2 3 4 5 6 7 8)))
+                "#,
+				None,
+			)
+			.unwrap();
+		interp.format_value(&v)
+	};
+
+	let a = Builder::new().seed(42).finish();
+	let b = Builder::new().seed(42).finish();
+	let c = Builder::new().seed(43).finish();
+
+	let first = sequence(&a);
+	assert_eq!(first, sequence(&b));
+	assert_ne!(first, sequence(&c));
+
+	a.run_code("(seed 42)", None).unwrap();
+	assert_eq!(sequence(&a), first);
+
+	assert_eq!(
+		run("
+        (use random :all)
+        (<= 5 (random-int 5 6) 5)
+        (<= -3 (random-int -3 3) 2)
+        (let ((x (choice '(a b c)))) (or (= x 'a) (= x 'b) (= x 'c)))
+        (choice '(x))
+        (apply + (sample '(1 2 3 4) 4))
+        (len (sample '(1 2 3 4) 2))
+        (sample '(1 2 3) 0)
+        (weighted-choice '(a b c) '(0 1 0))
+        (weighted-choice '(a b) '(0.0 2.5))
+        (normal 3 0)
+        (<= 0.0 (exponential))
+        (len (random-bytes 16))
+        ")
+		.unwrap(),
+		["()", "true", "true", "true", "x", "10", "2", "()", "b", "b", "3.0", "true", "16",]
+	);
+
+	let random_error = |code: &str| match run(code).unwrap_err() {
+		Error::Custom(e) => *e.downcast_ref::<RandomError>().unwrap(),
+		e => panic!("unexpected error: {:?}", e),
+	};
+
+	assert_eq!(
+		random_error("(use random :all) (random-int 3 3)"),
+		RandomError::EmptyRange
+	);
+	assert_eq!(
+		random_error("(use random :all) (choice ())"),
+		RandomError::EmptyList
+	);
+	assert_eq!(
+		random_error("(use random :all) (sample '(1 2) 3)"),
+		RandomError::SampleTooLarge {
+			requested: 3,
+			available: 2
+		}
+	);
+	assert_eq!(
+		random_error("(use random :all) (weighted-choice '(a b) '(0 0))"),
+		RandomError::InvalidWeights
+	);
+	assert_eq!(
+		random_error("(use random :all) (weighted-choice '(a b) '(1))"),
+		RandomError::InvalidWeights
+	);
+	assert_eq!(
+		random_error("(use random :all) (weighted-choice '(a b) (list 1 (/ 1.0 0.0)))"),
+		RandomError::InvalidWeights
+	);
+	assert_eq!(
+		random_error("(use random :all) (weighted-choice '(a b) '(1e308 1e308))"),
+		RandomError::InvalidWeights
+	);
+	assert_matches!(
+		random_error("(use random :all) (exponential 0)"),
+		RandomError::InvalidParameter(_)
+	);
+	assert_matches!(
+		run("(use random :all) (seed -1)").unwrap_err(),
+		Error::ExecError(ExecError::Overflow)
+	);
+}
+
+#[test]
+fn test_math_integer() {
+	assert_eq!(
+		run("
+        (use math :all)
+        (gcd 12 -18 27)
+        (gcd 0)
+        (lcm 4 6 10)
+        (lcm 3 0)
+        (isqrt 17)
+        (isqrt 100000000000000000000000000000000000000)
+        (iroot 30 3)
+        (iroot -27 3)
+        (mod-pow 4 13 497)
+        (mod-pow 2 -1 7)
+        (mod-pow 3 200 -7)
+        (mod-inverse 3 7)
+        (mod-inverse -3 7)
+        (factorial 0)
+        (factorial 25)
+        (binomial 5 2)
+        (binomial 2 5)
+        (binomial 100000000000000000000 2)
+        (popcount 255)
+        (popcount -7)
+        (bit-length 0)
+        (bit-length 256)
+        (list (is-prime 1) (is-prime 2) (is-prime 91) (is-prime 97) (is-prime -7))
+        (is-prime 3825123056546413051)
+        (is-prime 170141183460469231731687303715884105727)
+        ")
+		.unwrap(),
+		[
+			"()",
+			"3",
+			"0",
+			"60",
+			"0",
+			"4",
+			"10000000000000000000",
+			"3",
+			"-3",
+			"445",
+			"4",
+			"-5",
+			"5",
+			"2",
+			"1",
+			"15511210043330985984000000",
+			"10",
+			"0",
+			"4999999999999999999950000000000000000000",
+			"8",
+			"3",
+			"0",
+			"9",
+			"(false true false true false)",
+			"false",
+			"true",
+		]
+	);
+
+	let math_error = |code: &str| match run(code).unwrap_err() {
+		Error::Custom(e) => *e.downcast_ref::<MathError>().unwrap(),
+		e => panic!("unexpected error: {:?}", e),
+	};
+
+	assert_eq!(
+		math_error("(use math :all) (isqrt -1)"),
+		MathError::DomainError("isqrt")
+	);
+	assert_eq!(
+		math_error("(use math :all) (iroot -4 2)"),
+		MathError::DomainError("iroot")
+	);
+	assert_eq!(
+		math_error("(use math :all) (factorial -1)"),
+		MathError::DomainError("factorial")
+	);
+	assert_eq!(
+		math_error("(use math :all) (mod-inverse 4 8)"),
+		MathError::NoInverse
+	);
+	assert_matches!(
+		run("(use math :all) (mod-pow 2 3 0)").unwrap_err(),
+		Error::ExecError(ExecError::DivideByZero)
+	);
+}
+
+#[test]
+fn test_path_module() {
+	assert_eq!(
+		run(r#"
+        (use path :self)
+        (path/join "foo" (path "bar") "baz.txt")
+        (path/join "foo" "/bar")
+        (path/parent "foo/bar.txt")
+        (path/parent "/")
+        (path/file-name (path "foo/bar.tar.gz"))
+        (path/file-name "/")
+        (path/stem "foo/bar.tar.gz")
+        (path/extension "foo/bar.tar.gz")
+        (path/extension "foo/bar")
+        (path/with-extension "foo/bar.txt" "md")
+        (path/with-extension "foo/bar.txt" "")
+        (path/components "/foo/../bar.txt")
+        (path/components "")
+        (path/is-absolute "/foo")
+        (path/is-absolute (path "foo"))
+        "#)
+		.unwrap(),
+		[
+			"()",
+			r#"#p"foo/bar/baz.txt""#,
+			r#"#p"/bar""#,
+			r#"#p"foo""#,
+			"()",
+			r#""bar.tar.gz""#,
+			"()",
+			r#""bar.tar""#,
+			r#""gz""#,
+			"()",
+			r#"#p"foo/bar.md""#,
+			r#"#p"foo/bar""#,
+			r#"("/" "foo" ".." "bar.txt")"#,
+			"()",
+			"true",
+			"false",
+		]
+	);
+
+	assert_matches!(
+		run(r#"(use path :self) (path/join "foo" 1)"#).unwrap_err(),
+		Error::ExecError(ExecError::TypeError {
+			expected: "path",
+			..
+		})
+	);
+
+	let dir = env::temp_dir().canonicalize().unwrap();
+	let interp = Builder::new().filesystem(FsAccess::Unrestricted).finish();
+	interp.scope().add_named_value("dir", dir.join(".").into());
+
+	let v = interp
+		.run_code("(use path (canonicalize)) (canonicalize dir)", None)
+		.unwrap();
+	assert_matches!(v, Value::Path(ref p) if *p == dir);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_process_module() {
+	let interp = Builder::new().allow_process(true).finish();
+
+	let eval = |code: &str| interp.run_code(code, None).map(|v| interp.format_value(&v));
+
+	assert_eq!(eval("(use process (run))").unwrap(), "()");
+	assert_eq!(
+		eval(r#"(run "echo" '("hello" "world"))"#).unwrap(),
+		r#"{:status 0 :success true :stdout "hello world\n" :stderr "" :timed-out false}"#
+	);
+	assert_eq!(
+		eval(
+			r#"(run "sh" '("-c" "cat; echo $KETOS_TEST_VAR >&2; exit 3")
+                :stdin "input" :env {"KETOS_TEST_VAR" "value"})"#
+		)
+		.unwrap(),
+		r#"{:status 3 :success false :stdout "input" :stderr "value\n" :timed-out false}"#
+	);
+	assert_eq!(
+		eval(r#"(get (run "printf" '("abc") :output :bytes) :stdout)"#).unwrap(),
+		r#"#b"abc""#
+	);
+	assert_eq!(
+		eval(r#"(get (run "pwd" () :cwd "/") :stdout)"#).unwrap(),
+		r#""/\n""#
+	);
+	assert_eq!(
+		eval(r#"(run "sleep" '("5") :timeout 0.1)"#).unwrap(),
+		r#"{:status () :success false :stdout "" :stderr "" :timed-out true}"#
+	);
+
+	// A process started by the program holds its output open
+	let start = Instant::now();
+	assert_eq!(
+		eval(r#"(get (run "sh" '("-c" "sleep 3; echo hi") :timeout 0.2) :timed-out)"#).unwrap(),
+		"true"
+	);
+	assert_eq!(
+		eval(r#"(get (run "sh" '("-c" "sleep 3 & echo hi") :timeout 0.2) :timed-out)"#).unwrap(),
+		"true"
+	);
+	assert!(start.elapsed() < Duration::from_secs(2));
+
+	assert_matches!(
+		eval(r#"(run "ketos-no-such-program")"#).unwrap_err(),
+		Error::IoError(IoError {
+			mode: IoMode::Execute,
+			..
+		})
+	);
+	assert_matches!(
+		eval(r#"(run "true" () :output :lines)"#).unwrap_err(),
+		Error::ExecError(ExecError::TypeError { .. })
+	);
+}
+
+#[test]
+fn test_read_stdin() {
+	let input = Rc::new(RefCell::new(Cursor::new(
+		"first line\r\nsecond\n\u{e9}x\nrest\nof input"
+			.as_bytes()
+			.to_vec(),
+	)));
+	let io = GlobalIo::null().with_stdin(input);
+	let interp = Builder::new().io(Rc::new(io)).finish();
+
+	let eval = |code: &str| {
+		let v = interp.run_code(code, None).unwrap();
+		interp.format_value(&v)
+	};
+
+	assert_eq!(eval("(read-line)"), r#""first line""#);
+	assert_eq!(eval("(read-line)"), r#""second""#);
+	assert_eq!(eval("(read-char)"), "#'\u{e9}'");
+	assert_eq!(eval("(read-char)"), "#'x'");
+	assert_eq!(eval("(read-char)"), r"#'\n'");
+	assert_eq!(eval("(read-all)"), r#""rest\nof input""#);
+	assert_eq!(eval("(read-line)"), "()");
+	assert_eq!(eval("(read-char)"), "()");
+	assert_eq!(eval("(read-all)"), r#""""#);
+
+	let interp = Builder::new().io(Rc::new(GlobalIo::null())).finish();
+
+	assert_matches!(interp.run_code("(read-line)", None), Ok(Value::Unit));
+}
+
+#[test]
+fn test_fs_module() {
+	let dir = env::temp_dir().join(format!("ketos-test-fs-{}", process::id()));
+	let _ = fs::remove_dir_all(&dir);
+	fs::create_dir(&dir).unwrap();
+
+	let interp = Builder::new()
+		.filesystem(FsAccess::Root(dir.clone()))
+		.finish();
+
+	let eval = |code: &str| interp.run_code(code, None).map(|v| interp.format_value(&v));
+
+	assert_eq!(eval("(use fs :self)").unwrap(), "()");
+	assert_eq!(eval(r#"(fs/exists "foo.txt")"#).unwrap(), "false");
+	assert_eq!(eval(r#"(fs/write "foo.txt" "hello")"#).unwrap(), "()");
+	assert_eq!(eval(r#"(fs/append "foo.txt" " world")"#).unwrap(), "()");
+	assert_eq!(eval(r#"(fs/exists "foo.txt")"#).unwrap(), "true");
+	assert_eq!(
+		eval(r#"(fs/read-string "foo.txt")"#).unwrap(),
+		r#""hello world""#
+	);
+	assert_eq!(
+		eval(r#"(fs/read-bytes "foo.txt")"#).unwrap(),
+		r#"#b"hello world""#
+	);
+	assert_eq!(
+		eval(r#"(get (fs/metadata "foo.txt") :size)"#).unwrap(),
+		"11"
+	);
+	assert_eq!(
+		eval(r#"(get (fs/metadata "foo.txt") :is-file)"#).unwrap(),
+		"true"
+	);
+	assert_eq!(eval(r#"(fs/mkdir "a/b" true)"#).unwrap(), "()");
+	assert_eq!(eval(r#"(get (fs/metadata "a") :is-dir)"#).unwrap(), "true");
+	assert_eq!(
+		eval(r#"(fs/list-dir ".")"#).unwrap(),
+		r#"(#p"./a" #p"./foo.txt")"#
+	);
+
+	assert_matches!(
+		eval(r#"(fs/remove "a")"#).unwrap_err(),
+		Error::IoError(IoError {
+			mode: IoMode::Remove,
+			..
+		})
+	);
+	assert_eq!(eval(r#"(fs/remove "a" true)"#).unwrap(), "()");
+	assert_eq!(eval(r#"(fs/remove "foo.txt")"#).unwrap(), "()");
+	assert_eq!(eval(r#"(fs/list-dir ".")"#).unwrap(), "()");
+
+	assert_matches!(
+		eval(r#"(fs/read-string "missing.txt")"#).unwrap_err(),
+		Error::IoError(IoError {
+			mode: IoMode::Stat,
+			..
+		})
+	);
+
+	fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_regex_module() {
+	assert_eq!(
+		run(r#"
+        (use regex :all)
+        (define re (regex "[0-9]+"))
+        re
+        (type-of re)
+        (match? re "abc123")
+        (match? "^[0-9]+$" "abc123")
+        (find re "abc123def456")
+        (find re "abc")
+        (find-all re "abc123def456")
+        (split "[, ]+" "a, b,c")
+        (split "[, ]+" "a, b,c" 2)
+        "#)
+		.unwrap(),
+		[
+			"()",
+			"re",
+			r#"<regex "[0-9]+">"#,
+			"regex",
+			"true",
+			"false",
+			r#""123""#,
+			"()",
+			r#"("123" "456")"#,
+			r#"("a" "b" "c")"#,
+			r#"("a" "b,c")"#,
+		]
+	);
+
+	assert_eq!(
+		run(r#"
+        (use regex :all)
+        (captures "(a)(b)?" "ac")
+        (captures "(a)" "xyz")
+        (captures "(?P<key>[a-z]+)=(?P<value>[0-9]+)?" "x foo= y")
+        (replace "[0-9]" "a1b2" "*")
+        (replace "([a-z])([0-9])" "a1b2" "$2$1")
+        (replace "[0-9]" "a1b2" "*" 1)
+        (replace "[a-z]" "a1b2" (lambda (caps) (concat (first caps) (first caps))))
+        (replace "(?P<n>[0-9])" "a1b2" (lambda (caps) (get caps :n)))
+        (replace "z" "a1b2" "*")
+        "#)
+		.unwrap(),
+		[
+			"()",
+			r#"("a" "a" ())"#,
+			"()",
+			r#"{:key "foo" :value ()}"#,
+			r#""a*b*""#,
+			r#""1a2b""#,
+			r#""a*b2""#,
+			r#""aa1bb2""#,
+			r#""a1b2""#,
+			r#""a1b2""#,
+		]
+	);
+
+	assert_matches!(
+		run(r#"
+        (use regex (regex))
+        (regex "(")
+        "#)
+		.unwrap_err(),
+		Error::Custom(_)
+	);
+
+	assert_matches!(
+		run(r#"
+        (use regex (replace))
+        (replace "a" "abc" (lambda (caps) 1))
+        "#)
+		.unwrap_err(),
+		Error::ExecError(ExecError::TypeError {
+			expected: "string",
+			..
+		})
+	);
+}
+
+#[test]
+fn test_list() {
+	assert_eq!(eval("(list 1 2 (+ 1 2))").unwrap(), "(1 2 3)");
+}
+
+#[test]
+fn test_reverse() {
+	assert_eq!(eval("(reverse ())").unwrap(), "()");
+	assert_eq!(eval("(reverse '(1 2 3))").unwrap(), "(3 2 1)");
+}
+
+/// Evaluates an expression with the `list` module imported.
+fn eval_list(s: &str) -> Result<String, Error> {
+	let mut res = run(&format!("(use list :all) {}", s))?;
+	Ok(res.pop().unwrap())
+}
+
+#[test]
+fn test_sort() {
+	assert_eq!(eval_list("(sort ())").unwrap(), "()");
+	assert_eq!(eval_list("(sort '(3 1 2))").unwrap(), "(1 2 3)");
+	assert_eq!(eval_list("(sort '(2 1/2 1.5))").unwrap(), "(1/2 1.5 2)");
+	assert_eq!(
+		eval_list(r#"(sort '("b" "c" "a"))"#).unwrap(),
+		r#"("a" "b" "c")"#
+	);
+	assert_eq!(
+		eval_list("(sort-by first '((2 a) (1 b) (2 c) (1 d)))").unwrap(),
+		"((1 b) (1 d) (2 a) (2 c))"
+	);
+	assert_eq!(
+		eval_list(r#"(sort-by len '("ccc" "a" "bb"))"#).unwrap(),
+		r#"("a" "bb" "ccc")"#
+	);
+	assert_eq!(eval_list("(sort-with > '(1 3 2))").unwrap(), "(3 2 1)");
+	assert_eq!(
+		eval_list("(sort-with (lambda (a b) (< (first a) (first b))) '((2 a) (1 b) (2 c) (1 d)))")
+			.unwrap(),
+		"((1 b) (1 d) (2 a) (2 c))"
+	);
+
+	assert_matches!(
+		eval_list(r#"(sort '(1 "a"))"#).unwrap_err(),
+		Error::ExecError(ExecError::CannotCompare(_))
+	);
+	assert_matches!(
+		eval_list(r#"(sort-by id '(1 "a" 2))"#).unwrap_err(),
+		Error::ExecError(ExecError::CannotCompare(_))
+	);
+	assert_matches!(
+		eval_list("(sort (list id id))").unwrap_err(),
+		Error::ExecError(ExecError::CannotCompare("function"))
+	);
+	assert_matches!(
+		eval_list("(sort-with (lambda (a b) 1) '(1 2))").unwrap_err(),
+		Error::ExecError(ExecError::TypeError {
+			expected: "bool",
+			..
+		})
+	);
+}
+
+#[test]
+fn test_higher_order() {
+	assert_eq!(eval_list("(map - '(1 2 3))").unwrap(), "(-1 -2 -3)");
+	assert_eq!(eval_list("(map id ())").unwrap(), "()");
+	assert_eq!(
+		eval_list("(filter (lambda (n) (> n 1)) '(1 2 3))").unwrap(),
+		"(2 3)"
+	);
+	assert_eq!(
+		eval_list("(filter (lambda (n) (> n 5)) '(1 2 3))").unwrap(),
+		"()"
+	);
+	assert_eq!(eval_list("(fold + 0 '(1 2 3))").unwrap(), "6");
+	assert_eq!(
+		eval_list("(fold list () '(a b c))").unwrap(),
+		"(((() a) b) c)"
+	);
+	assert_eq!(eval_list("(zip '(1 2 3) '(a b))").unwrap(), "((1 a) (2 b))");
+	assert_eq!(
+		eval_list("(zip '(1 2) '(a b) '(x y))").unwrap(),
+		"((1 a x) (2 b y))"
+	);
+	assert_eq!(eval_list("(zip () '(a b))").unwrap(), "()");
+	assert_eq!(
+		eval_list(r#"(group-by len '("a" "bb" "c"))"#).unwrap(),
+		r#"{1 ("a" "c") 2 ("bb")}"#
+	);
+	assert_eq!(
+		eval_list("(partition (lambda (n) (> n 1)) '(1 2 3))").unwrap(),
+		"((2 3) (1))"
+	);
+	assert_eq!(eval_list("(partition zero ())").unwrap(), "(() ())");
+	assert_eq!(eval_list("(dedup '(1 1 2 2 2 1))").unwrap(), "(1 2 1)");
+	assert_eq!(eval_list("(dedup (sort '(3 1 3 2 1)))").unwrap(), "(1 2 3)");
+
+	assert_matches!(
+		eval_list("(map 1 '(1))").unwrap_err(),
+		Error::ExecError(ExecError::TypeError {
+			expected: "function",
+			..
+		})
+	);
+	assert_matches!(
+		eval_list("(filter id '(1))").unwrap_err(),
+		Error::ExecError(ExecError::TypeError {
+			expected: "bool",
+			..
+		})
+	);
+}
+
+#[test]
+fn test_abs() {
+	assert_eq!(eval("(abs -1)").unwrap(), "1");
+	assert_eq!(eval("(abs -1/2)").unwrap(), "1/2");
+	assert_eq!(eval("(abs -1.5)").unwrap(), "1.5");
+	assert_eq!(eval("(abs (- (inf)))").unwrap(), "inf");
+}
+
+#[test]
+fn test_ceil() {
+	assert_eq!(eval("(ceil 1)").unwrap(), "1");
+	assert_eq!(eval("(ceil 1/2)").unwrap(), "1/1");
+	assert_eq!(eval("(ceil 1.3)").unwrap(), "2.0");
+}
+
+#[test]
+fn test_floor() {
+	assert_eq!(eval("(floor 1)").unwrap(), "1");
+	assert_eq!(eval("(floor 1/2)").unwrap(), "0/1");
+	assert_eq!(eval("(floor -1.3)").unwrap(), "-2.0");
+}
+
+#[test]
+fn test_trunc() {
+	assert_eq!(eval("(trunc 1)").unwrap(), "1");
+	assert_eq!(eval("(trunc -1.3)").unwrap(), "-1.0");
+	assert_eq!(eval("(trunc 5/2)").unwrap(), "2/1");
+}
+
+#[test]
+fn test_int() {
+	assert_eq!(eval("(int 123)").unwrap(), "123");
+	assert_eq!(eval("(int 123.0)").unwrap(), "123");
+	assert_eq!(eval("(int 123/1)").unwrap(), "123");
+
+	assert_matches!(
+		eval("(int (inf))").unwrap_err(),
+		Error::ExecError(ExecError::Overflow)
+	);
+	assert_matches!(
+		eval("(int (nan))").unwrap_err(),
+		Error::ExecError(ExecError::Overflow)
+	);
+}
+
+#[test]
+fn test_float() {
+	assert_eq!(eval("(float 123)").unwrap(), "123.0");
+	assert_eq!(eval("(float 123.0)").unwrap(), "123.0");
+	assert_eq!(eval("(float 123/1)").unwrap(), "123.0");
+
+	assert_matches!(
+		eval("(float (^ 10 309))").unwrap_err(),
+		Error::ExecError(ExecError::Overflow)
+	);
+}
+
+#[test]
+fn test_inf() {
+	assert_eq!(eval("(inf)").unwrap(), "inf");
+	assert_eq!(eval("(- (inf))").unwrap(), "-inf");
+	assert_eq!(eval("(inf (inf) (inf))").unwrap(), "true");
+	assert_eq!(eval("(inf (- (inf)))").unwrap(), "true");
+	assert_eq!(eval("(inf (inf) 1.0)").unwrap(), "false");
+}
+
+#[test]
+fn test_nan() {
+	assert_eq!(eval("(nan)").unwrap(), "NaN");
+	assert_eq!(eval("(nan (nan) (nan))").unwrap(), "true");
+	assert_eq!(eval("(nan (nan) 1.0)").unwrap(), "false");
+}
+
+#[test]
+fn test_fract() {
+	assert_eq!(eval("(fract 1.5)").unwrap(), "0.5");
+	assert_eq!(eval("(fract 5/2)").unwrap(), "1/2");
+}
+
+#[test]
+fn test_recip() {
+	assert_eq!(eval("(recip 3)").unwrap(), "1/3");
+	assert_eq!(eval("(recip 10.0)").unwrap(), "0.1");
+	assert_eq!(eval("(recip 2/3)").unwrap(), "3/2");
+
+	assert_matches!(
+		eval("(recip 0)").unwrap_err(),
+		Error::ExecError(ExecError::DivideByZero)
+	);
+	assert_matches!(
+		eval("(recip 0/1)").unwrap_err(),
+		Error::ExecError(ExecError::DivideByZero)
+	);
+}
+
+#[test]
+fn test_ratio() {
+	assert_eq!(eval("(denom 1/2)").unwrap(), "2");
+	assert_eq!(eval("(numer 1/2)").unwrap(), "1");
+
+	assert_eq!(eval("(denom 123)").unwrap(), "1");
+	assert_eq!(eval("(numer 123)").unwrap(), "123");
+
+	assert_eq!(eval("(rat 1.5)").unwrap(), "3/2");
+	assert_eq!(eval("(rat 1/3)").unwrap(), "1/3");
+	assert_eq!(eval("(rat 3)").unwrap(), "3/1");
+	assert_eq!(eval("(rat 1 2)").unwrap(), "1/2");
+	assert_matches!(
+		eval("(rat (inf))").unwrap_err(),
+		Error::ExecError(ExecError::Overflow)
+	);
+	assert_matches!(
+		eval("(rat (nan))").unwrap_err(),
+		Error::ExecError(ExecError::Overflow)
+	);
+}
+
+#[test]
+fn test_id() {
+	assert_eq!(eval("(id 1)").unwrap(), "1");
+	assert_eq!(eval("(id #'a')").unwrap(), "#'a'");
+	assert_eq!(eval("(id \"a\")").unwrap(), "\"a\"");
+	assert_eq!(eval("(id '(1 2 3))").unwrap(), "(1 2 3)");
+}
+
+#[test]
+fn test_is() {
+	assert_eq!(eval("(is 'integer 1)").unwrap(), "true");
+	assert_eq!(eval("(is 'ratio 1/1)").unwrap(), "true");
+	assert_eq!(eval("(is 'float 1.0)").unwrap(), "true");
+	assert_eq!(eval("(is 'list '(1))").unwrap(), "true");
+	assert_eq!(eval("(is 'list ())").unwrap(), "true");
+	assert_eq!(eval("(is 'unit ())").unwrap(), "true");
+	assert_eq!(eval("(is 'name 'a)").unwrap(), "true");
+	assert_eq!(eval("(is 'keyword :a)").unwrap(), "true");
+	assert_eq!(eval("(is 'string \"a\")").unwrap(), "true");
+	assert_eq!(eval("(is 'path #p\"a\")").unwrap(), "true");
+	assert_eq!(eval("(is 'bytes #b\"a\")").unwrap(), "true");
+	assert_eq!(eval("(is 'function is)").unwrap(), "true");
+	assert_eq!(eval("(is 'lambda (lambda () ()))").unwrap(), "true");
+
+	assert_eq!(eval("(is 'number 1)").unwrap(), "true");
+	assert_eq!(eval("(is 'number 1/1)").unwrap(), "true");
+	assert_eq!(eval("(is 'number 1.0)").unwrap(), "true");
+
+	assert_eq!(eval("(is 'integer 1.0)").unwrap(), "false");
+	assert_eq!(eval("(is 'list 'foo)").unwrap(), "false");
+	assert_eq!(eval("(is 'foo ())").unwrap(), "false");
+}
+
+#[test]
+fn test_null() {
+	assert_eq!(eval("(null ())").unwrap(), "true");
+	assert_eq!(eval("(null 'a)").unwrap(), "false");
+	assert_eq!(eval("(null 1)").unwrap(), "false");
+}
+
+#[test]
+fn test_type_of() {
+	assert_eq!(eval("(type-of ())").unwrap(), "unit");
+	assert_eq!(eval("(type-of true)").unwrap(), "bool");
+	assert_eq!(eval("(type-of 1)").unwrap(), "integer");
+	assert_eq!(eval("(type-of 1/1)").unwrap(), "ratio");
+	assert_eq!(eval("(type-of 1.0)").unwrap(), "float");
+	assert_eq!(eval("(type-of 'a)").unwrap(), "name");
+	assert_eq!(eval("(type-of :a)").unwrap(), "keyword");
+	assert_eq!(eval("(type-of #'a')").unwrap(), "char");
+	assert_eq!(eval("(type-of \"a\")").unwrap(), "string");
+	assert_eq!(eval("(type-of #p\"a\")").unwrap(), "path");
+	assert_eq!(eval("(type-of #b\"a\")").unwrap(), "bytes");
+	assert_eq!(eval("(type-of ''a)").unwrap(), "object");
+	assert_eq!(eval("(type-of '(1))").unwrap(), "list");
+	assert_eq!(eval("(type-of {})").unwrap(), "map");
+	assert_eq!(eval("(type-of id)").unwrap(), "function");
+	assert_eq!(eval("(type-of (lambda () ()))").unwrap(), "lambda");
+}
+
+#[test]
+fn test_define() {
+	assert_eq!(run("(define foo 123) foo").unwrap(), ["foo", "123"]);
+
+	// Standard names cannot be overriden in global scope
+	assert_matches!(run("(define (=) ())").unwrap_err(), Error::CompileError(_));
+	assert_matches!(run("(define (if) ())").unwrap_err(), Error::CompileError(_));
+
+	assert_eq!(
+		run("(define (foo n) (* n n)) (foo 3)").unwrap(),
+		["foo", "9"]
+	);
+	assert_eq!(
+		run("
+        (define (foo a b :optional c d) (list a b c d))
+        (foo 1 2)
+        (foo 1 2 3)
+        (foo 1 2 3 4)
+        ")
+		.unwrap(),
+		["foo", "(1 2 () ())", "(1 2 3 ())", "(1 2 3 4)"]
+	);
+
+	assert_eq!(
+		run("
+        (define (foo a b :key c d) (list a b c d))
+        (foo 1 2)
+        (foo 1 2 :c 3)
+        (foo 1 2 :d 3)
+        (foo 1 2 :c 3 :d 4)
+        ")
+		.unwrap(),
+		[
+			"foo",
+			"(1 2 () ())",
+			"(1 2 3 ())",
+			"(1 2 () 3)",
+			"(1 2 3 4)"
+		]
+	);
+
+	assert_eq!(
+		run("
+        (define (foo a b :key (c 10) d) (list a b c d))
+        (foo 1 2)
+        (foo 1 2 :c 3)
+        (foo 1 2 :d 3)
+        (foo 1 2 :c 3 :d 4)
+        ")
+		.unwrap(),
+		[
+			"foo",
+			"(1 2 10 ())",
+			"(1 2 3 ())",
+			"(1 2 10 3)",
+			"(1 2 3 4)"
+		]
+	);
+
+	assert_eq!(
+		run("
+        (define (foo a b :key c (d 10)) (list a b c d))
+        (foo 1 2)
+        (foo 1 2 :c 3)
+        (foo 1 2 :d 3)
+        (foo 1 2 :c 3 :d 4)
+        ")
+		.unwrap(),
+		[
+			"foo",
 			"(1 2 () 10)",
 			"(1 2 3 10)",
 			"(1 2 () 3)",
@@ -1867,6 +3147,105 @@ fn test_const_define() {
 	);
 }
 
+#[test]
+fn test_match() {
+	assert_eq!(
+		run("
+        (struct Point ((x integer) (y integer)))
+        (define (foo v)
+          (match v
+            (() 'empty)
+            ((list x) x)
+            ((list 1 _ 3) 'one-three)
+            ((list (list a b) c) (+ a b c))
+            ((Point :x 0 :y y) y)
+            ((Point :x x :y y) (+ x y))
+            ('foo 'quoted)
+            ('(a (b c)) 'quoted-list)
+            (\"foo\" 'string)
+            (:foo 'keyword)
+            (1 'one)
+            ((list x y :rest r) :when (= x y) r)
+            (_ 'other)))
+        (foo ())
+        (foo '(1))
+        (foo '(1 2 3))
+        (foo '(2 2 3 4))
+        (foo '(2 2))
+        (foo '((1 2) 3))
+        (foo (new Point :x 0 :y 1))
+        (foo (new Point :x 1 :y 2))
+        (foo 'foo)
+        (foo '(a (b c)))
+        (foo \"foo\")
+        (foo :foo)
+        (foo 1)
+        (foo 1.0)
+        (foo '(1 2))
+        ")
+		.unwrap(),
+		[
+			"Point",
+			"foo",
+			"empty",
+			"1",
+			"one-three",
+			"(3 4)",
+			"()",
+			"6",
+			"1",
+			"3",
+			"quoted",
+			"quoted-list",
+			"string",
+			"keyword",
+			"one",
+			"other",
+			"other"
+		]
+	);
+
+	assert_eq!(
+		run("
+        (define (count li n)
+          (match li
+            (() n)
+            ((list _ :rest tail) (count tail (+ n 1)))))
+        (count '(1 2 3) 0)
+        (define (adder v) (match v ((list a b) (lambda (c) (+ a b c)))))
+        ((adder '(1 2)) 3)
+        (match '(1 2) ((list :rest r) r))
+        (match () ((list :rest r) r))
+        ")
+		.unwrap(),
+		["count", "3", "adder", "6", "(1 2)", "()"]
+	);
+
+	assert_matches!(eval("(match 3 (1 'a) (2 'b))").unwrap_err(),
+        Error::ExecError(ExecError::NoMatch(Value::Integer(ref i)))
+            if i.to_u32() == Some(3));
+	assert_matches!(
+		eval("(match 1 (x :when false x))").unwrap_err(),
+		Error::ExecError(ExecError::NoMatch(_))
+	);
+	assert_matches!(
+		eval("(match 1 (x x) (y y))").unwrap_err(),
+		Error::CompileError(CompileError::SyntaxError(_))
+	);
+	assert_matches!(
+		eval("(match '(1 1) ((list x x) x))").unwrap_err(),
+		Error::CompileError(CompileError::SyntaxError(_))
+	);
+	assert_matches!(
+		eval("(match '(1 1) ((list :rest) 1))").unwrap_err(),
+		Error::CompileError(CompileError::SyntaxError(_))
+	);
+	assert_matches!(
+		eval("(match 1 (x :if true x))").unwrap_err(),
+		Error::CompileError(CompileError::SyntaxError(_))
+	);
+}
+
 #[test]
 fn test_lambda() {
 	assert_eq!(eval("((lambda (n) n) 1)").unwrap(), "1");
@@ -1874,6 +3253,38 @@ fn test_lambda() {
 		eval("((lambda (:rest rest) rest) 1 2 3)").unwrap(),
 		"(1 2 3)"
 	);
+	assert_eq!(
+		eval("((lambda ((a b) c) (list a b c)) '(1 2) 3)").unwrap(),
+		"(1 2 3)"
+	);
+	assert_eq!(
+		eval("((lambda ((a :rest b) :optional (c a)) (list b c)) '(1 2))").unwrap(),
+		"((2) 1)"
+	);
+	assert_matches!(
+		eval("((lambda ((a b)) a) '(1))").unwrap_err(),
+		Error::ExecError(ExecError::PatternError { .. })
+	);
+
+	// A name and a constant is a default value given before `:optional`.
+	assert_matches!(
+		eval("((lambda ((a 1)) a))").unwrap_err(),
+		Error::CompileError(CompileError::SyntaxError(
+			"default value before `:optional` or `:key`"
+		))
+	);
+	assert_matches!(
+		eval("((lambda (a (b \"x\")) b) 1)").unwrap_err(),
+		Error::CompileError(CompileError::SyntaxError(
+			"default value before `:optional` or `:key`"
+		))
+	);
+	// A name and another name or list is always a pattern.
+	assert_eq!(eval("((lambda ((a b)) b) '(1 2))").unwrap(), "2");
+	assert_eq!(
+		eval("((lambda ((a (b c))) (list a b c)) '(1 (2 3)))").unwrap(),
+		"(1 2 3)"
+	);
 }
 
 #[test]
@@ -1882,26 +3293,238 @@ fn test_do() {
 }
 
 #[test]
-fn test_macro() {
+fn test_macro() {
+	assert_eq!(
+		run("
+        (macro (quote a) `',a)
+        (quote foo)
+        ")
+		.unwrap(),
+		["quote", "foo"]
+	);
+
+	assert_matches!(
+		eval("(define (foo a) (macro (bar) a))").unwrap_err(),
+		Error::CompileError(_)
+	);
+
+	assert_matches!(
+		run("
+        (macro (foo) '(bar))
+        (macro (bar) '(foo))
+        (foo)
+        ")
+		.unwrap_err(),
+		Error::CompileError(CompileError::MacroRecursionExceeded)
+	);
+}
+
+#[test]
+fn test_gensym() {
+	assert_eq!(
+		run("
+        (define a (gensym))
+        (define b (gensym \"a\"))
+        (type-of a)
+        (= a b)
+        (= b (gensym 'a))
+        ")
+		.unwrap(),
+		["a", "b", "name", "false", "false"]
+	);
+
+	assert_eq!(
+		run("
+        (macro (swap a b)
+          (let ((tmp (gensym 'tmp)))
+            `(let ((,tmp ,a)) (list ,b ,tmp))))
+        (define tmp 1)
+        (swap tmp 2)
+        ")
+		.unwrap(),
+		["swap", "tmp", "(2 1)"]
+	);
+
+	assert_matches!(
+		eval("(gensym 1)").unwrap_err(),
+		Error::ExecError(ExecError::TypeError { .. })
+	);
+}
+
+#[test]
+fn test_syntax_rules() {
+	assert_eq!(
+		run("
+        (syntax-rules swap
+          ((_ a b) (let ((tmp a)) (list b tmp))))
+        (define tmp 1)
+        (swap tmp 2)
+        ")
+		.unwrap(),
+		["swap", "tmp", "(2 1)"]
+	);
+
+	assert_eq!(
+		run("
+        (syntax-rules my-or
+          \"Returns the first true value.\"
+          ((_) false)
+          ((_ e) e)
+          ((_ e rest ...)
+           (let ((t e))
+             (if t t (my-or rest ...)))))
+        (define t 5)
+        (my-or)
+        (my-or false t)
+        (my-or false false 3)
+        ")
+		.unwrap(),
+		["my-or", "t", "false", "5", "3"]
+	);
+
+	assert_eq!(
+		run("
+        (syntax-rules my-let
+          ((_ ((name value) ...) body ...)
+           ((lambda (name ...) body ...) value ...)))
+        (my-let ((a 1) (b 2)) (+ a b))
+        (my-let () 1)
+        ")
+		.unwrap(),
+		["my-let", "3", "1"]
+	);
+
+	assert_eq!(
+		run("
+        (syntax-rules repeat
+          ((_ n expr) (loop ((i n) (acc ())) (if (= i 0) acc (recur (- i 1) (concat acc (list expr)))))))
+        (define i 7)
+        (repeat 2 i)
+        ")
+		.unwrap(),
+		["repeat", "i", "(7 7)"]
+	);
+
+	assert_eq!(
+		run("
+        (syntax-rules pairs
+          ((_ (a b ...) ...) '((b ... a) ...)))
+        (pairs (1 2 3) (4) (5 6))
+        (syntax-rules tagged
+          ((_ :tag x) (list 'tag x))
+          ((_ x) (list 'plain x)))
+        (tagged :tag 1)
+        (tagged 2)
+        ")
+		.unwrap(),
+		[
+			"pairs",
+			"((2 3 1) (4) (6 5))",
+			"tagged",
+			"(tag 1)",
+			"(plain 2)"
+		]
+	);
+
+	assert_eq!(
+		run("
+        (use code (macroexpand))
+        (syntax-rules foo
+          ((_ a) (lambda (x) (+ x a))))
+        (= (macroexpand '(foo x)) '(lambda (x) (+ x x)))
+        ((foo 1) 2)
+        ")
+		.unwrap(),
+		["()", "foo", "false", "3"]
+	);
+
+	assert_matches!(
+		run("
+        (syntax-rules foo ((_ a) a))
+        (foo 1 2)
+        ")
+		.unwrap_err(),
+		Error::ExecError(ExecError::NoMatch(_))
+	);
+
+	assert_matches!(
+		eval("(syntax-rules foo ((_ a ...) a))").unwrap_err(),
+		Error::CompileError(CompileError::SyntaxError(_))
+	);
+	assert_matches!(
+		eval("(syntax-rules foo ((_ a) (a ...)))").unwrap_err(),
+		Error::CompileError(CompileError::SyntaxError(_))
+	);
+	assert_matches!(
+		eval("(syntax-rules foo ((_ a a) a))").unwrap_err(),
+		Error::CompileError(CompileError::SyntaxError(_))
+	);
+	assert_matches!(
+		eval("(syntax-rules foo (_ a))").unwrap_err(),
+		Error::CompileError(CompileError::SyntaxError(_))
+	);
+
+	assert_matches!(
+		eval("(syntax-expand '() '())").unwrap_err(),
+		Error::ExecError(ExecError::NameError(_))
+	);
+}
+
+#[test]
+fn test_eval() {
+	assert_eq!(
+		run("
+        (use code (eval))
+        (define a 1)
+        (eval '(+ a 2))
+        (eval (list '* 2 3))
+        (eval '(sqrt 4.0) 'math)
+        ")
+		.unwrap(),
+		["()", "a", "3", "6", "2.0"]
+	);
+
+	assert_matches!(
+		run("
+        (use code (eval))
+        (eval '(+ a 1))
+        ")
+		.unwrap_err(),
+		Error::ExecError(ExecError::NameError(_))
+	);
+
+	assert_matches!(
+		run("
+        (use code (eval))
+        (eval 1 \"math\")
+        ")
+		.unwrap_err(),
+		Error::ExecError(ExecError::TypeError { .. })
+	);
+}
+
+#[test]
+fn test_macroexpand() {
 	assert_eq!(
 		run("
-        (macro (quote a) `',a)
-        (quote foo)
+        (use code (macroexpand macroexpand-1))
+        (macro (foo a) `(bar ,a))
+        (macro (bar a) `(+ ,a 1))
+        (macroexpand-1 '(foo 1))
+        (macroexpand '(foo 1))
+        (macroexpand '(+ 1 2))
+        (macroexpand 1)
         ")
 		.unwrap(),
-		["quote", "foo"]
-	);
-
-	assert_matches!(
-		eval("(define (foo a) (macro (bar) a))").unwrap_err(),
-		Error::CompileError(_)
+		["()", "foo", "bar", "(bar 1)", "(+ 1 1)", "(+ 1 2)", "1"]
 	);
 
 	assert_matches!(
 		run("
+        (use code (macroexpand))
         (macro (foo) '(bar))
         (macro (bar) '(foo))
-        (foo)
+        (macroexpand '(foo))
         ")
 		.unwrap_err(),
 		Error::CompileError(CompileError::MacroRecursionExceeded)
@@ -1954,6 +3577,219 @@ fn test_call_self() {
 	);
 }
 
+#[test]
+fn test_tail_call() {
+	assert_eq!(
+		run("
+        (define (even? n) (if (= n 0) true (odd? (- n 1))))
+        (define (odd? n) (if (= n 0) false (even? (- n 1))))
+        (even? 101)
+        ")
+		.unwrap(),
+		["even?", "odd?", "false"]
+	);
+
+	assert_eq!(
+		run("
+        (define (foo a :rest rest) (apply list a rest))
+        (define (bar f) (f 1 2 3))
+        (bar foo)
+        (define (baz) (id 1))
+        (baz)
+        ")
+		.unwrap(),
+		["foo", "bar", "(1 2 3)", "baz", "1"]
+	);
+
+	assert_matches!(
+		run("
+        (define (foo n) n)
+        (define (bar) (foo 1 2))
+        (bar)
+        ")
+		.unwrap_err(),
+		Error::ExecError(ExecError::ArityError { .. })
+	);
+}
+
+#[test]
+fn test_loop() {
+	assert_eq!(
+		eval(
+			"
+        (loop ((n 10) (acc 0))
+          (if (= n 0)
+            acc
+            (recur (- n 1) (+ acc n))))
+        "
+		)
+		.unwrap(),
+		"55"
+	);
+
+	assert_eq!(
+		eval(
+			"
+        (let sum ((n 100000) (acc 0))
+          (if (= n 0)
+            acc
+            (sum (- n 1) (+ acc n))))
+        "
+		)
+		.unwrap(),
+		"5000050000"
+	);
+
+	assert_eq!(
+		eval(
+			"
+        (loop ((li '(1 2 3)) (acc ()))
+          (match li
+            (() acc)
+            ((list x :rest rest)
+              (let ((y (* x x)))
+                (recur rest (concat (list y) acc))))))
+        "
+		)
+		.unwrap(),
+		"(9 4 1)"
+	);
+
+	assert_eq!(
+		eval(
+			"
+        (let outer ((i 0) (acc ()))
+          (if (< i 2)
+            (loop ((j 0) (acc acc))
+              (cond
+                ((< j 2) (recur (+ j 1) (concat acc (list (list i j)))))
+                (else (outer (+ i 1) acc))))
+            acc))
+        "
+		)
+		.unwrap(),
+		"((0 0) (0 1) (1 0) (1 1))"
+	);
+
+	assert_eq!(eval("(let f ((f id)) (f 1))").unwrap(), "1");
+
+	assert_matches!(
+		eval("(loop ((n 0)) (+ 1 (recur n)))").unwrap_err(),
+		Error::CompileError(CompileError::SyntaxError(_))
+	);
+	assert_matches!(
+		eval("(loop ((n 0)) (id (recur n)))").unwrap_err(),
+		Error::CompileError(CompileError::SyntaxError(_))
+	);
+	assert_matches!(
+		eval("(loop ((n 0)) (loop ((m 0)) (recur 1 2)))").unwrap_err(),
+		Error::CompileError(CompileError::ArityError { .. })
+	);
+	assert_matches!(
+		eval("(recur)").unwrap_err(),
+		Error::CompileError(CompileError::SyntaxError(_))
+	);
+	assert_matches!(
+		eval("(loop ((n 0)) (recur))").unwrap_err(),
+		Error::CompileError(CompileError::ArityError { .. })
+	);
+}
+
+#[test]
+fn test_while() {
+	assert_eq!(
+		run("
+        (define n 0)
+        (while (< n 5) (define n (+ n 1)))
+        n
+        ")
+		.unwrap(),
+		["n", "()", "5"]
+	);
+}
+
+#[test]
+fn test_dotimes() {
+	assert_eq!(
+		run("
+        (define acc ())
+        (dotimes (i (+ 1 2)) (define acc (concat acc (list i))))
+        acc
+        (dotimes (i 0) (panic))
+        ")
+		.unwrap(),
+		["acc", "()", "(0 1 2)", "()"]
+	);
+}
+
+#[test]
+fn test_for() {
+	assert_eq!(
+		run("
+        (define acc ())
+        (for (x '(a b c)) (define acc (concat (list x) acc)))
+        acc
+        (for (x ()) (panic))
+        ")
+		.unwrap(),
+		["acc", "()", "(c b a)", "()"]
+	);
+}
+
+#[test]
+fn test_ref() {
+	assert_eq!(
+		run("
+        (define r (ref 0))
+        (deref r)
+        (set-ref! r 1)
+        (deref r)
+        (swap-ref! r + 2 3)
+        (deref r)
+        r
+        (type-of r)
+        ")
+		.unwrap(),
+		["r", "0", "0", "1", "6", "6", "<ref 6>", "ref"]
+	);
+
+	assert_eq!(
+		run("
+        (define (make-counter)
+          (let ((n (ref 0)))
+            (lambda () (swap-ref! n + 1))))
+        (define a (make-counter))
+        (define b (make-counter))
+        (a)
+        (a)
+        (b)
+        ")
+		.unwrap(),
+		["make-counter", "a", "b", "1", "2", "1"]
+	);
+
+	assert_eq!(
+		run("
+        (define r (ref ()))
+        (= r r)
+        (= r (ref ()))
+        (set-ref! r (list 1 r))
+        r
+        ")
+		.unwrap(),
+		["r", "true", "false", "()", "<ref (1 <ref ...>)>"]
+	);
+
+	assert_matches!(
+		eval("(< (ref 1) (ref 2))").unwrap_err(),
+		Error::ExecError(ExecError::CannotCompare("ref"))
+	);
+	assert_matches!(
+		eval("(deref 1)").unwrap_err(),
+		Error::ExecError(ExecError::TypeError { .. })
+	);
+}
+
 #[test]
 fn test_panic() {
 	assert_matches!(
@@ -1968,6 +3804,88 @@ fn test_panic() {
             if s == "foo");
 }
 
+#[test]
+fn test_try() {
+	assert_eq!(
+		run("
+        (try (/ 1 0) (catch e (type-of e)))
+        (try (/ 1 0) (catch e (format \"~a\" e)))
+        (try (panic 1) (catch e (+ e 1)))
+        (try (raise 'foo) (catch e e))
+        (try 1 (catch e 2))
+        (try (try (raise 1) (catch e (raise (+ e 1)))) (catch e e))
+        (let ((a 1)) (try (+ a (raise 2)) (catch e (+ a e))))
+        (define (foo n) (if (= n 0) (raise 'done) (foo (- n 1))))
+        (define (bar) (try (foo 10) (catch e (list 'caught e))))
+        (bar)
+        ")
+		.unwrap(),
+		[
+			"error",
+			r#""attempt to divide by zero""#,
+			"2",
+			"foo",
+			"1",
+			"2",
+			"3",
+			"foo",
+			"bar",
+			"(caught done)"
+		]
+	);
+
+	assert_matches!(
+		eval("(try (/ 1 0) (catch e (raise e)))").unwrap_err(),
+		Error::ExecError(ExecError::DivideByZero)
+	);
+	assert_matches!(
+		eval("(try (raise 1) (catch e (/ 1 0)))").unwrap_err(),
+		Error::ExecError(ExecError::DivideByZero)
+	);
+	assert_matches!(eval("(raise 123)").unwrap_err(),
+        Error::ExecError(ExecError::Panic(Some(Value::Integer(ref i))))
+            if i.to_u32() == Some(123));
+
+	assert_matches!(
+		eval("(try 1)").unwrap_err(),
+		Error::CompileError(CompileError::ArityError { .. })
+	);
+	assert_matches!(
+		eval("(try 1 2)").unwrap_err(),
+		Error::CompileError(CompileError::SyntaxError(_))
+	);
+	assert_matches!(
+		eval("(try 1 (finally) (catch e 2))").unwrap_err(),
+		Error::CompileError(CompileError::SyntaxError(_))
+	);
+	assert_matches!(
+		eval("(try 1 (catch e))").unwrap_err(),
+		Error::CompileError(CompileError::SyntaxError(_))
+	);
+}
+
+#[test]
+fn test_try_finally() {
+	assert_eq!(
+		run("
+        (define (test f) (try (f) (finally (raise 'finally))))
+        (try (test (lambda () 1)) (catch e e))
+        (try (test (lambda () (raise 1))) (catch e e))
+        (try (try 1 (finally 2)) (catch e e))
+        (try (try (raise 1) (finally 2)) (catch e e))
+        (try (try (raise 1) (catch e (+ e 1)) (finally 3)) (catch e e))
+        (try (try (raise 1) (catch e (raise 2)) (finally 3)) (catch e e))
+        ")
+		.unwrap(),
+		["test", "finally", "finally", "1", "1", "2", "2"]
+	);
+
+	assert_matches!(
+		eval("(try (/ 1 0) (finally 1))").unwrap_err(),
+		Error::ExecError(ExecError::DivideByZero)
+	);
+}
+
 #[test]
 fn test_use() {
 	assert_eq!(
@@ -2005,3 +3923,150 @@ fn test_use() {
 		Error::CompileError(CompileError::ImportError { .. })
 	);
 }
+
+#[test]
+fn test_use_builtin_source() {
+	assert_eq!(
+		run("
+        (use list (range take-while zip-with))
+        (range 5)
+        (take-while (lambda (n) (< n 3)) (range 10))
+        (zip-with + '(1 2) '(3 4))
+        ")
+		.unwrap(),
+		["()", "(0 1 2 3 4)", "(0 1 2)", "(4 6)"]
+	);
+
+	assert_eq!(
+		run("
+        (use list (map filter zip))
+        (map (lambda (n) (* n 2)) '(1 2 3))
+        (filter (lambda (n) (> n 1)) '(1 2 3))
+        (zip '(1 2) '(3 4))
+        (use list :self)
+        (list/map - '(1 2))
+        ")
+		.unwrap(),
+		["()", "(2 4 6)", "(2 3)", "((1 3) (2 4))", "()", "(-1 -2)"]
+	);
+
+	assert_eq!(
+		run("(use iter (map iter collect)) (collect (map - (iter '(1 2))))").unwrap(),
+		["()", "(-1 -2)"]
+	);
+
+	assert_eq!(
+		run("
+        (define (map f li) (len li))
+        (define (partition x) x)
+        (map id '(1 2))
+        ")
+		.unwrap(),
+		["map", "partition", "2"]
+	);
+
+	assert_eq!(
+		run("
+        (use test (assert-eq))
+        (assert-eq (+ 1 1) 2)
+        ")
+		.unwrap(),
+		["()", "()"]
+	);
+
+	assert_matches!(
+		run("
+        (use list (range-pos))
+        ")
+		.unwrap_err(),
+		Error::CompileError(CompileError::PrivacyError { .. })
+	);
+}
+
+#[test]
+fn test_iter_module() {
+	assert_eq!(
+		run("
+        (use iter :self)
+        (iter/collect (iter/range 5))
+        (iter/collect (iter/range 1 10 3))
+        (iter/collect (iter/range 5 0 -2))
+        (iter/collect (iter/take 3 (iter/range 10 ())))
+        (iter/collect (iter/map (lambda (n) (* n n)) (iter/range 4)))
+        (iter/collect (iter/filter (lambda (n) (= (rem n 3) 0)) '(1 3 5 6 9)))
+        (iter/collect (iter/take 4 (iter/iterate (lambda (n) (* n 2)) 1)))
+        (iter/collect ())
+        ")
+		.unwrap(),
+		[
+			"()",
+			"(0 1 2 3 4)",
+			"(1 4 7)",
+			"(5 3 1)",
+			"(10 11 12)",
+			"(0 1 4 9)",
+			"(3 6 9)",
+			"(1 2 4 8)",
+			"()"
+		]
+	);
+
+	assert_eq!(
+		run("
+        (use iter :self)
+        (define s (iter/iter '(a b)))
+        (iter/next s)
+        (iter/next s)
+        (iter/next s)
+        (iter/next s 'done)
+        (type-of s)
+        (iter/next '(1 2))
+        ")
+		.unwrap(),
+		["()", "s", "a", "b", "()", "done", "lazy-seq", "1"]
+	);
+
+	// Values consumed from a sequence are not produced again.
+	assert_eq!(
+		run("
+        (use iter :self)
+        (define s (iter/range 6))
+        (iter/collect (iter/take 2 s))
+        (iter/collect (iter/filter (lambda (n) (> n 3)) s))
+        (iter/collect s)
+        ")
+		.unwrap(),
+		["()", "s", "(0 1)", "(4 5)", "()"]
+	);
+
+	// `map` and `filter` do not call their functions until values are requested.
+	assert_eq!(
+		run("
+        (use iter :self)
+        (define s (iter/map (lambda (n) (/ 1 n)) '(2 1 0)))
+        (iter/next s)
+        (iter/next s)
+        ")
+		.unwrap(),
+		["()", "s", "1/2", "1"]
+	);
+
+	assert_matches!(
+		run("(use iter :self) (iter/collect (iter/range 1 5 0))").unwrap_err(),
+		Error::ExecError(ExecError::Panic(_))
+	);
+	assert_matches!(
+		run("(use iter :self) (iter/collect (iter/filter (lambda (n) n) '(1)))").unwrap_err(),
+		Error::ExecError(ExecError::TypeError {
+			expected: "bool",
+			..
+		})
+	);
+	assert_matches!(
+		run("(use iter :self) (iter/iter 1)").unwrap_err(),
+		Error::ExecError(ExecError::TypeError {
+			expected: "list or lazy-seq",
+			..
+		})
+	);
+}
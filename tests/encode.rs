@@ -5,11 +5,12 @@ extern crate ketos;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
-use ketos::encode::{read_bytecode, write_bytecode};
+use ketos::bytecode::code_flags;
+use ketos::encode::{read_bytecode, write_bytecode, EncodeError};
 use ketos::module::ModuleCode;
 use ketos::{
-	run_code, BuiltinModuleLoader, Context, Error, FileModuleLoader, Interpreter, ModuleLoader,
-	Value,
+	run_code, BuiltinModuleLoader, CompileError, Context, Error, FileModuleLoader, Interpreter,
+	ModuleLoader, Ref, Value,
 };
 
 fn new_interpreter() -> Interpreter {
@@ -70,6 +71,10 @@ fn test_encode() {
 
         (const b #b"y halo")
         (const p #p"thar")
+        (const m {:a 1 "b" '(2 3)})
+
+        (use set (set))
+        (define s (set 3 1 2))
         "#,
 		|ctx| {
 			assert_matches!(ctx.scope().get_named_constant("foo"),
@@ -80,11 +85,99 @@ fn test_encode() {
                 Some(Value::Bytes(ref b)) if b == b"y halo");
 			assert_matches!(ctx.scope().get_named_constant("p"),
                 Some(Value::Path(ref p)) if p == Path::new("thar"));
+			assert_matches!(ctx.scope().get_named_constant("m"),
+                Some(Value::Map(ref m)) if m.len() == 2);
+			assert_matches!(ctx.scope().get_named_value("s"),
+                Some(Value::Set(ref s)) if s.len() == 3);
+		},
+	)
+	.unwrap();
+}
+
+#[test]
+fn test_encode_ref() {
+	let interp = new_interpreter();
+	let name = interp.scope().add_name("r");
+
+	let mcode = ModuleCode {
+		constants: vec![(name, Value::Ref(Ref::new(Value::Unit)))],
+		..ModuleCode::default()
+	};
+
+	let scope = interp.scope();
+	let names = scope.borrow_names();
+	let mut buf = Vec::new();
+
+	assert_matches!(
+		write_bytecode(&mut buf, Path::new("<buffer>"), &mcode, &names),
+		Err(Error::EncodeError(EncodeError::UnencodableType("ref")))
+	);
+}
+
+#[test]
+fn test_encode_syntax_rules() {
+	run(
+		r#"
+        (syntax-rules swap
+          ((_ a b) (let ((tmp a)) (list b tmp))))
+        (define (f tmp) (swap tmp 2))
+        "#,
+		|ctx| {
+			run_code(
+				ctx,
+				r#"
+                (use test (assert-eq))
+
+                (assert-eq (f 1) '(2 1))
+                (define tmp 3)
+                (assert-eq (swap tmp 4) '(4 3))
+                "#,
+			)
+			.unwrap();
 		},
 	)
 	.unwrap();
 }
 
+#[test]
+fn test_decode_invalid_syntax_rules() {
+	let interp = new_interpreter();
+
+	let code: Vec<_> = interp
+		.compile_exprs("(macro (bad) '(((_ a ...) a)))")
+		.unwrap()
+		.into_iter()
+		.map(Rc::new)
+		.collect();
+
+	for code in &code {
+		interp.execute_code(code.clone()).unwrap();
+	}
+
+	let mut mcode = ModuleCode::new(code, interp.scope());
+
+	for (_, code) in &mut mcode.macros {
+		Rc::make_mut(code).flags |= code_flags::IS_SYNTAX_RULES;
+	}
+
+	let mut buf = Vec::new();
+	let path = Path::new("<buffer>");
+
+	{
+		let scope = interp.scope();
+		let names = scope.borrow_names();
+		write_bytecode(&mut buf, path, &mcode, &names).unwrap();
+	}
+
+	let sec_interp = new_interpreter();
+	let mcode = read_bytecode(&mut &buf[..], path, sec_interp.context()).unwrap();
+
+	assert_matches!(
+		mcode.load_in_context(sec_interp.context()),
+		Err(Error::CompileError(CompileError::SyntaxError(_)))
+	);
+}
+
 #[test]
 fn test_docs() {
 	run(
@@ -8,7 +8,9 @@ extern crate ketos_derive;
 
 use std::cmp::Ordering;
 
-use ketos::{Context, Error, ExecError, ForeignValue, Interpreter, Value};
+use ketos::{
+	Context, Error, ExecError, ForeignValue, FromValue, FromValueRef, Interpreter, LazySeq, Value,
+};
 
 #[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, FromValueRef, IntoValue)]
 pub struct MyType {
@@ -180,3 +182,53 @@ fn test_compare_foreign_value() {
 		"true"
 	);
 }
+
+#[test]
+fn test_lazy_seq() {
+	let interp = Interpreter::new();
+
+	interp
+		.scope()
+		.add_named_value("evens", LazySeq::new((0..).step_by(2)).into());
+	interp
+		.scope()
+		.add_named_value("words", LazySeq::new(vec!["foo", "bar"]).into());
+
+	let v = interp
+		.run_code(
+			"
+        (use iter :self)
+        (iter/collect (iter/take 3 evens))
+        ",
+			None,
+		)
+		.unwrap();
+	assert_eq!(interp.format_value(&v), "(0 2 4)");
+
+	assert_eq!(eval(&interp, "(type-of words)").unwrap(), "lazy-seq");
+	assert_eq!(eval(&interp, "words").unwrap(), "<lazy-seq>");
+
+	let v = interp
+		.run_code("(iter/map (lambda (s) (concat s \"!\")) words)", None)
+		.unwrap();
+	let seq = <&LazySeq>::from_value_ref(&v).unwrap();
+	let words = seq
+		.iter(interp.context())
+		.map(|v| String::from_value(v.unwrap()))
+		.collect::<Result<Vec<_>, _>>()
+		.unwrap();
+	assert_eq!(words, ["foo!", "bar!"]);
+	assert!(seq.next(interp.context()).unwrap().is_none());
+
+	let v = interp
+		.run_code("(iter/map (lambda (n) (/ 1 n)) '(1 0 2))", None)
+		.unwrap();
+	let seq = <&LazySeq>::from_value_ref(&v).unwrap();
+	let mut iter = seq.iter(interp.context());
+	assert_eq!(interp.format_value(&iter.next().unwrap().unwrap()), "1");
+	assert_matches!(
+		iter.next(),
+		Some(Err(Error::ExecError(ExecError::DivideByZero)))
+	);
+	assert!(iter.next().is_none());
+}
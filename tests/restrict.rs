@@ -2,9 +2,12 @@
 extern crate assert_matches;
 extern crate ketos;
 
+use std::env;
+use std::fs;
+use std::process;
 use std::time::Duration;
 
-use ketos::{Builder, Error, RestrictConfig, RestrictError};
+use ketos::{Builder, Error, FsAccess, RestrictConfig, RestrictError};
 
 fn run(restrict: RestrictConfig, code: &str) -> Result<(), Error> {
 	let interp = Builder::new().restrict(restrict).finish();
@@ -35,6 +38,95 @@ fn test_restrict_time() {
 		.unwrap_err(),
 		RestrictError::ExecutionTimeExceeded
 	);
+
+	assert_matches_re!(
+		run(
+			RestrictConfig {
+				execution_time: Some(Duration::from_millis(100)),
+				..RestrictConfig::permissive()
+			},
+			"(loop () (recur))"
+		)
+		.unwrap_err(),
+		RestrictError::ExecutionTimeExceeded
+	);
+
+	assert_matches_re!(
+		run(
+			RestrictConfig {
+				execution_time: Some(Duration::from_millis(100)),
+				..RestrictConfig::permissive()
+			},
+			"(while true)"
+		)
+		.unwrap_err(),
+		RestrictError::ExecutionTimeExceeded
+	);
+
+	assert_matches_re!(
+		run(
+			RestrictConfig {
+				execution_time: Some(Duration::from_millis(100)),
+				..RestrictConfig::permissive()
+			},
+			"
+        (use list (fold))
+        (define li '(1 2 3 4 5 6 7 8 9 10))
+        (define (deep n)
+          (if (= n 0) 0 (fold (lambda (acc x) (deep (- n 1))) 0 li)))
+        (deep 10)
+        "
+		)
+		.unwrap_err(),
+		RestrictError::ExecutionTimeExceeded
+	);
+}
+
+#[test]
+fn test_restrict_eval() {
+	assert_matches_re!(
+		run(
+			RestrictConfig {
+				execution_time: Some(Duration::from_millis(50)),
+				..RestrictConfig::permissive()
+			},
+			"
+        (use code (eval))
+        (define (foo) (foo))
+        (eval '(foo))
+        "
+		)
+		.unwrap_err(),
+		RestrictError::ExecutionTimeExceeded
+	);
+
+	assert_matches_re!(
+		run(
+			RestrictConfig {
+				max_integer_size: 100,
+				..RestrictConfig::permissive()
+			},
+			"
+        (use code (eval))
+        (eval '(<< 1 200))
+        "
+		)
+		.unwrap_err(),
+		RestrictError::IntegerLimitExceeded
+	);
+
+	assert_matches_re!(
+		run(
+			RestrictConfig::strict(),
+			"
+        (use code (eval))
+        (define (f n) (eval (list 'f (+ n 1))))
+        (f 0)
+        "
+		)
+		.unwrap_err(),
+		RestrictError::CallStackExceeded
+	);
 }
 
 #[test]
@@ -46,8 +138,8 @@ fn test_restrict_stack() {
 				..RestrictConfig::permissive()
 			},
 			"
-        (define (foo) (bar))
-        (define (bar) (foo))
+        (define (foo) (+ 1 (bar)))
+        (define (bar) (+ 1 (foo)))
         (foo)
         "
 		)
@@ -67,14 +159,75 @@ fn test_restrict_stack() {
                 (a ()) (a ()) (a ()) (a ()) (a ()) (a ()) (a ()) (a ())
                 (a ()) (a ()) (a ()) (a ()) (a ()) (a ()) (a ()) (a ())
                 (a ()) (a ()) (a ()) (a ()) (a ()) (a ()) (a ()) (a ()))
-            (bar)))
-        (define (bar) (foo))
+            (+ 1 (bar))))
+        (define (bar) (+ 1 (foo)))
         (foo)
         "
 		)
 		.unwrap_err(),
 		RestrictError::ValueStackExceeded
 	);
+
+	assert_matches_re!(
+		run(
+			RestrictConfig {
+				call_stack_size: 100,
+				..RestrictConfig::permissive()
+			},
+			"
+        (define (foo) (try (foo) (catch e e)))
+        (foo)
+        "
+		)
+		.unwrap_err(),
+		RestrictError::CallStackExceeded
+	);
+
+	for code in [
+		"(use list (map)) (define (f x) (map f (list x))) (f 1)",
+		"(use list (filter)) (define (f x) (filter f (list x))) (f 1)",
+		"(use list (sort-by)) (define (f x) (sort-by f (list x 1))) (f 1)",
+		"(use regex (regex replace)) (define (f x) (replace (regex \"a\") \"a\" f)) (f 1)",
+	] {
+		assert_matches_re!(
+			run(RestrictConfig::strict(), code).unwrap_err(),
+			RestrictError::CallStackExceeded
+		);
+	}
+
+	run(
+		RestrictConfig::strict(),
+		"(use list (map)) (map (lambda (x) (map (lambda (y) (map id (list x y))) (list x))) '(1 2))",
+	)
+	.unwrap();
+}
+
+#[test]
+fn test_restrict_tail_call() {
+	let config = RestrictConfig {
+		call_stack_size: 100,
+		..RestrictConfig::permissive()
+	};
+
+	run(
+		config.clone(),
+		"
+        (define (even? n) (if (= n 0) true (odd? (- n 1))))
+        (define (odd? n) (if (= n 0) false (even? (- n 1))))
+        (even? 10000)
+        ",
+	)
+	.unwrap();
+
+	run(
+		config,
+		"
+        (define (foo n) (if (= n 0) 'done (apply bar (list (- n 1)))))
+        (define (bar n) (let ((f foo)) (f n)))
+        (foo 10000)
+        ",
+	)
+	.unwrap();
 }
 
 #[test]
@@ -133,6 +286,76 @@ fn test_restrict_memory() {
 		.unwrap_err(),
 		RestrictError::MemoryLimitExceeded
 	);
+
+	assert_matches_re!(
+		run(
+			RestrictConfig {
+				memory_limit: 100,
+				..RestrictConfig::permissive()
+			},
+			"
+        (define refs
+          (loop ((i 0) (acc ()))
+            (if (< i 20)
+              (recur (+ i 1) (concat (list (ref ())) acc))
+              acc)))
+        (for (r refs) (set-ref! r '(1 2 3 4 5 6 7 8 9 10)))
+        "
+		)
+		.unwrap_err(),
+		RestrictError::MemoryLimitExceeded
+	);
+
+	run(
+		RestrictConfig {
+			memory_limit: 100,
+			..RestrictConfig::permissive()
+		},
+		"
+        (define r (ref ()))
+        (dotimes (i 100) (set-ref! r (list i)))
+        ",
+	)
+	.unwrap();
+
+	// Repeatedly storing into a ref held in several stack slots may leave
+	// memory held understated, but the limit must still be enforced.
+	assert_matches_re!(
+		run(
+			RestrictConfig {
+				memory_limit: 100,
+				..RestrictConfig::permissive()
+			},
+			"
+        (define r (ref ()))
+        (define (alias a b c)
+          (do
+            (set-ref! a '(1 2 3 4 5 6 7 8 9 10))
+            (set-ref! b ())
+            (set-ref! c '(1 2 3 4 5 6 7 8 9 10))))
+        (dotimes (i 100) (alias r r r))
+        (define (foo a) (foo (list () a)))
+        (foo ())
+        "
+		)
+		.unwrap_err(),
+		RestrictError::MemoryLimitExceeded
+	);
+
+	assert_matches_re!(
+		run(
+			RestrictConfig {
+				memory_limit: 100,
+				..RestrictConfig::permissive()
+			},
+			r#"
+        (use string (repeat))
+        (repeat "abc" 1000000000000)
+        "#
+		)
+		.unwrap_err(),
+		RestrictError::MemoryLimitExceeded
+	);
 }
 
 #[test]
@@ -198,6 +421,42 @@ fn test_restrict_integer() {
 		.unwrap_err(),
 		RestrictError::IntegerLimitExceeded
 	);
+
+	assert_matches_re!(
+		run(
+			cfg.clone(),
+			"
+        (use math (factorial))
+        (factorial 100)
+        "
+		)
+		.unwrap_err(),
+		RestrictError::IntegerLimitExceeded
+	);
+
+	assert_matches_re!(
+		run(
+			cfg.clone(),
+			"
+        (use math (binomial))
+        (binomial 200 100)
+        "
+		)
+		.unwrap_err(),
+		RestrictError::IntegerLimitExceeded
+	);
+
+	assert_matches_re!(
+		run(
+			cfg.clone(),
+			"
+        (use math (lcm))
+        (lcm 0xffffffffffffffffffffffff 0xfffffffffffffffffffffffe)
+        "
+		)
+		.unwrap_err(),
+		RestrictError::IntegerLimitExceeded
+	);
 }
 
 #[test]
@@ -219,3 +478,411 @@ fn test_restrict_syntax() {
 		RestrictError::MaxSyntaxNestingExceeded
 	);
 }
+
+#[test]
+fn test_restrict_regex() {
+	assert_matches_re!(
+		run(
+			RestrictConfig {
+				max_regex_size: 1 << 16,
+				..RestrictConfig::permissive()
+			},
+			r#"
+        (use regex (regex))
+        (regex "(a{1000}){1000}")
+        "#
+		)
+		.unwrap_err(),
+		RestrictError::RegexSizeExceeded
+	);
+
+	run(
+		RestrictConfig::strict(),
+		r#"
+        (use regex (match? replace))
+        (match? "^[0-9]+$" "123")
+        (replace "\\w+" "foo bar" "$0!")
+        "#,
+	)
+	.unwrap();
+}
+
+#[test]
+fn test_restrict_json() {
+	assert_matches_re!(
+		run(
+			RestrictConfig {
+				max_syntax_nesting: 50,
+				..RestrictConfig::permissive()
+			},
+			r#"
+        (use json (decode))
+        (use string (repeat))
+        (decode (concat (repeat "[" 100) (repeat "]" 100)))
+        "#
+		)
+		.unwrap_err(),
+		RestrictError::MaxSyntaxNestingExceeded
+	);
+
+	assert_matches_re!(
+		run(
+			RestrictConfig {
+				max_integer_size: 100,
+				..RestrictConfig::permissive()
+			},
+			r#"
+        (use json (decode))
+        (decode "[10000000000000000000000000000000000000000]")
+        "#
+		)
+		.unwrap_err(),
+		RestrictError::IntegerLimitExceeded
+	);
+
+	assert_matches_re!(
+		run(
+			RestrictConfig {
+				max_syntax_nesting: 50,
+				..RestrictConfig::permissive()
+			},
+			r#"
+        (use json (encode))
+        (define (nest n v) (if (= n 0) v (nest (- n 1) (list v))))
+        (encode (nest 100 1))
+        "#
+		)
+		.unwrap_err(),
+		RestrictError::MaxSyntaxNestingExceeded
+	);
+
+	assert_matches_re!(
+		run(
+			RestrictConfig {
+				memory_limit: 1000,
+				..RestrictConfig::permissive()
+			},
+			r#"
+        (use json (decode))
+        (use string (repeat))
+        (dotimes (i 100)
+          (decode (concat "{\"" (repeat "k" (+ i 1)) "\": 1}") :objects :plist))
+        "#
+		)
+		.unwrap_err(),
+		RestrictError::MemoryLimitExceeded
+	);
+}
+
+#[test]
+fn test_restrict_sleep() {
+	assert_matches_re!(
+		run(
+			RestrictConfig::strict(),
+			"
+        (use time (sleep))
+        (sleep 0)
+        "
+		)
+		.unwrap_err(),
+		RestrictError::SleepNotAllowed
+	);
+
+	assert_matches_re!(
+		run(
+			RestrictConfig {
+				execution_time: Some(Duration::from_millis(100)),
+				..RestrictConfig::permissive()
+			},
+			"
+        (use time (sleep))
+        (sleep 1e9)
+        "
+		)
+		.unwrap_err(),
+		RestrictError::ExecutionTimeExceeded
+	);
+
+	run(
+		RestrictConfig {
+			execution_time: Some(Duration::from_millis(100)),
+			..RestrictConfig::permissive()
+		},
+		"
+        (use time (sleep))
+        (sleep 0.01)
+        ",
+	)
+	.unwrap();
+}
+
+#[test]
+fn test_restrict_builtin_source() {
+	run(
+		RestrictConfig::strict(),
+		"
+        (use list (range foldr))
+        (foldr + 0 (range 10))
+        ",
+	)
+	.unwrap();
+}
+
+#[test]
+fn test_restrict_lazy_seq() {
+	run(
+		RestrictConfig::strict(),
+		"
+        (use iter :self)
+        (iter/collect (iter/take 10 (iter/range 1000000000)))
+        ",
+	)
+	.unwrap();
+
+	assert_matches_re!(
+		run(
+			RestrictConfig::strict(),
+			"
+        (use iter :self)
+        (iter/collect (iter/range ()))
+        "
+		)
+		.unwrap_err(),
+		RestrictError::MemoryLimitExceeded
+	);
+
+	assert_matches_re!(
+		run(
+			RestrictConfig {
+				execution_time: Some(Duration::from_millis(100)),
+				..RestrictConfig::permissive()
+			},
+			"
+        (use iter :self)
+        (iter/next (iter/filter (lambda (n) (< n 0)) (iter/range ())))
+        "
+		)
+		.unwrap_err(),
+		RestrictError::ExecutionTimeExceeded
+	);
+
+	for code in [
+		"(build (lambda (s) (iter/map id s)) (iter/range 10) 200000)",
+		"(build (lambda (s) (iter/filter id s)) (iter/range 10) 200000)",
+		"(build (lambda (s) (iter/take 1000000 s)) (iter/range 10) 200000)",
+	] {
+		assert_matches_re!(
+			run(
+				RestrictConfig::permissive(),
+				&format!(
+					"
+        (use iter :self)
+        (define (build f s n) (if (= n 0) s (build f (f s) (- n 1))))
+        {}
+        ",
+					code
+				)
+			)
+			.unwrap_err(),
+			RestrictError::CallStackExceeded
+		);
+	}
+
+	run(
+		RestrictConfig::strict(),
+		"
+        (use iter :self)
+        (define (build s n) (if (= n 0) s (build (iter/map id s) (- n 1))))
+        (iter/collect (build (iter/range 3) 50))
+        ",
+	)
+	.unwrap();
+
+	for code in [
+		"(define s (iterate (lambda (x) (next s)) 0)) (next s) (next s)",
+		"(define s (iter/map (lambda (x) (next s)) (iter/range ()))) (next s)",
+		"(define s (iter/filter (lambda (x) (next s)) (iter/range ()))) (next s)",
+	] {
+		assert_matches_re!(
+			run(
+				RestrictConfig::strict(),
+				&format!("(use iter :self) (use iter (next iterate)) {}", code)
+			)
+			.unwrap_err(),
+			RestrictError::CallStackExceeded
+		);
+	}
+}
+
+#[test]
+fn test_restrict_filesystem() {
+	assert_matches_re!(
+		run(
+			RestrictConfig::strict(),
+			r#"
+        (use fs (exists))
+        (exists "foo")
+        "#
+		)
+		.unwrap_err(),
+		RestrictError::FilesystemAccessDenied
+	);
+
+	let dir = env::temp_dir().join(format!("ketos-test-restrict-fs-{}", process::id()));
+	let _ = fs::remove_dir_all(&dir);
+	fs::create_dir(&dir).unwrap();
+
+	let run_root = |code: &str| {
+		let interp = Builder::new()
+			.restrict(RestrictConfig::strict())
+			.filesystem(FsAccess::Root(dir.clone()))
+			.finish();
+
+		interp.run_code(code, None).map(|_| ())
+	};
+
+	assert!(run_root(r#"(use fs (write)) (write "foo" "bar")"#).is_ok());
+
+	assert_matches_re!(
+		run_root(r#"(use fs (exists)) (exists "../foo")"#).unwrap_err(),
+		RestrictError::FilesystemAccessDenied
+	);
+	assert_matches_re!(
+		run_root(r#"(use fs (write)) (write "new/../../foo" "bar")"#).unwrap_err(),
+		RestrictError::FilesystemAccessDenied
+	);
+	assert_matches_re!(
+		run_root(r#"(use fs (read-string)) (read-string "/etc/passwd")"#).unwrap_err(),
+		RestrictError::FilesystemAccessDenied
+	);
+
+	#[cfg(unix)]
+	{
+		let outside = dir.with_extension("outside");
+		let _ = fs::remove_file(&outside);
+		std::os::unix::fs::symlink(&outside, dir.join("link")).unwrap();
+
+		assert_matches_re!(
+			run_root(r#"(use fs (write)) (write "link" "bar")"#).unwrap_err(),
+			RestrictError::FilesystemAccessDenied
+		);
+		assert!(!outside.exists());
+	}
+
+	assert_matches_re!(
+		run_root(r#"(use fs (remove)) (remove "." true)"#).unwrap_err(),
+		RestrictError::FilesystemAccessDenied
+	);
+	assert_matches_re!(
+		run_root(r#"(use fs (remove)) (remove "foo/.." true)"#).unwrap_err(),
+		RestrictError::FilesystemAccessDenied
+	);
+	assert!(dir.join("foo").exists());
+
+	#[cfg(unix)]
+	{
+		fs::create_dir(dir.join("sub")).unwrap();
+		fs::write(dir.join("sub/x"), "x").unwrap();
+		std::os::unix::fs::symlink(dir.join("foo"), dir.join("filelink")).unwrap();
+		std::os::unix::fs::symlink(dir.join("sub"), dir.join("dirlink")).unwrap();
+
+		assert!(run_root(
+			r#"
+            (use fs (metadata))
+            (if (not (get (metadata "filelink") :is-symlink))
+              (panic "expected symlink"))
+            "#
+		)
+		.is_ok());
+		assert!(run_root(r#"(use fs (read-string)) (read-string "filelink")"#).is_ok());
+
+		assert!(run_root(r#"(use fs (remove)) (remove "filelink")"#).is_ok());
+		assert!(run_root(r#"(use fs (remove)) (remove "dirlink" true)"#).is_ok());
+		assert!(fs::symlink_metadata(dir.join("filelink")).is_err());
+		assert!(fs::symlink_metadata(dir.join("dirlink")).is_err());
+		assert!(dir.join("foo").exists());
+		assert!(dir.join("sub/x").exists());
+	}
+
+	assert!(run_root(r#"(use fs (remove)) (remove "foo")"#).is_ok());
+
+	fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_restrict_os() {
+	assert_matches_re!(
+		run(
+			RestrictConfig::strict(),
+			r#"
+        (use os (getenv))
+        (getenv "HOME")
+        "#
+		)
+		.unwrap_err(),
+		RestrictError::OsNotAllowed
+	);
+
+	assert_matches_re!(
+		run(
+			RestrictConfig::strict(),
+			"
+        (use os (exit))
+        (exit 1)
+        "
+		)
+		.unwrap_err(),
+		RestrictError::OsNotAllowed
+	);
+}
+
+#[test]
+fn test_restrict_process() {
+	let code = r#"
+        (use process (run))
+        (run "true")
+        "#;
+
+	assert_matches_re!(
+		run(RestrictConfig::permissive(), code).unwrap_err(),
+		RestrictError::ProcessNotAllowed
+	);
+
+	assert_matches_re!(
+		run(RestrictConfig::strict(), code).unwrap_err(),
+		RestrictError::ProcessNotAllowed
+	);
+
+	let interp = Builder::new()
+		.restrict(RestrictConfig::strict())
+		.allow_process(true)
+		.finish();
+
+	if cfg!(unix) {
+		assert!(interp.run_code(code, None).is_ok());
+	}
+}
+
+#[test]
+fn test_restrict_path() {
+	assert!(run(
+		RestrictConfig::strict(),
+		r#"
+        (use path :self)
+        (path/parent (path/join "foo" "bar"))
+        "#
+	)
+	.is_ok());
+
+	assert_matches_re!(
+		run(
+			RestrictConfig::strict(),
+			r#"
+        (use path (canonicalize))
+        (canonicalize "/")
+        "#
+		)
+		.unwrap_err(),
+		RestrictError::FilesystemAccessDenied
+	);
+}